@@ -4,23 +4,72 @@ import { PreviewPanel } from './components/PreviewPanel';
 import { ResultPanel } from './components/ResultPanel';
 import { StatusFooter } from './components/StatusFooter';
 import { SettingsModal } from './components/SettingsModal';
+import { PdfImportModal } from './components/PdfImportModal';
 import type { HistoryItem } from './types';
-import { recognizeImage, getAvailableModels as fetchAvailableModels } from './services/tauriService';
-
-function getImageDimensions(dataUrl: string): Promise<{ width: number; height: number }> {
-  return new Promise((resolve, reject) => {
-    const img = new Image();
-    img.onload = () => resolve({ width: img.naturalWidth, height: img.naturalHeight });
-    img.onerror = reject;
-    img.src = dataUrl;
-  });
+import {
+  recognizeImage, recognizeRegions, RecognizeResult, RegionRecognition, getAvailableModels as fetchAvailableModels, SecondOpinion, ConsensusReport, LatexDiff, LatexDiagnostic,
+  HistoryEntry, HistorySearch, addHistory, searchHistory, getHistory, getHistoryImage, setHistoryFavorite, deleteHistory, clearHistory,
+} from './services/tauriService';
+
+const HISTORY_PAGE_SIZE = 50;
+
+function toHistoryItem(entry: HistoryEntry): HistoryItem {
+  const created = new Date(entry.created_at);
+  const preview = entry.text.trim().substring(0, 30);
+  return {
+    id: entry.id,
+    timestamp: created.toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' }),
+    formula: preview + (preview.length >= 30 ? '...' : ''),
+    isFavorite: entry.favorite,
+    dateLabel: `${created.getFullYear()}/${String(created.getMonth() + 1).padStart(2, '0')}/${String(created.getDate()).padStart(2, '0')}`,
+    mode: entry.mode,
+    needsReview: entry.needs_review,
+    content: entry.text,
+    model: entry.model,
+    hasImage: !!entry.image,
+  };
+}
+
+/** Shape of the entries older versions kept in `localStorage['history']`. */
+interface LegacyHistoryItem {
+  formula: string;
+  isFavorite: boolean;
+  mode: string;
+}
+
+let legacyMigration: Promise<number> | null = null;
+
+// Moves history saved by older versions into the history store, oldest first, then drops the key.
+// Shared so the effect running twice under StrictMode doesn't import everything twice.
+function migrateLegacyHistory(): Promise<number> {
+  legacyMigration ??= (async () => {
+    let items: LegacyHistoryItem[] = [];
+    try { items = JSON.parse(localStorage.getItem('history') || '[]'); }
+    catch {}
+    if (!Array.isArray(items)) items = [];
+    let migrated = 0;
+    while (items.length > 0) {
+      const item = items[items.length - 1];
+      if (item?.formula) {
+        const entry = await addHistory({ mode: item.mode || 'formula', text: item.formula, model: '' });
+        if (item.isFavorite) await setHistoryFavorite(entry.id, true);
+        migrated++;
+      }
+      // Keep what's left so a failure part way doesn't add the same entries again next time
+      items.pop();
+      localStorage.setItem('history', JSON.stringify(items));
+    }
+    localStorage.removeItem('history');
+    return migrated;
+  })();
+  return legacyMigration;
 }
 
 export default function App() {
-  const [history, setHistory] = useState<HistoryItem[]>(() => {
-    try { const s = localStorage.getItem('history'); return s ? JSON.parse(s) : []; }
-    catch { return []; }
-  });
+  const [history, setHistory] = useState<HistoryItem[]>([]);
+  const [historyTotal, setHistoryTotal] = useState(0);
+  const [favoritesOnly, setFavoritesOnly] = useState(false);
+  const [historyFilter, setHistoryFilter] = useState<HistorySearch>({});
   const [currentImage, setCurrentImage] = useState<string | null>(null);
   const [resultText, setResultText] = useState('');
   const [isProcessing, setIsProcessing] = useState(false);
@@ -33,16 +82,40 @@ export default function App() {
   const [originalText, setOriginalText] = useState<string | null>(null);
   const [correctedText, setCorrectedText] = useState<string | null>(null);
   const [showingOriginal, setShowingOriginal] = useState(false);
+  const [confidence, setConfidence] = useState<number | null>(null);
+  const [needsReview, setNeedsReview] = useState(false);
+  const [secondOpinion, setSecondOpinion] = useState<SecondOpinion | null>(null);
+  const [consensus, setConsensus] = useState<ConsensusReport | null>(null);
+  const [diff, setDiff] = useState<LatexDiff | null>(null);
+  const [syntaxIssues, setSyntaxIssues] = useState<LatexDiagnostic[]>([]);
+  const [cachedAt, setCachedAt] = useState<number | null>(null);
+  const [splitRegions, setSplitRegions] = useState(false);
+  const [regions, setRegions] = useState<RegionRecognition[] | null>(null);
+  const [regionImageSize, setRegionImageSize] = useState<{ width: number; height: number } | null>(null);
+  const [activeRegion, setActiveRegion] = useState<number | null>(null);
+  // Regions joined, shown when no single region is picked
+  const [combinedText, setCombinedText] = useState('');
   const [settingsOpen, setSettingsOpen] = useState(false);
+  const [pdfOpen, setPdfOpen] = useState(false);
   const [availableModels, setAvailableModels] = useState<any[]>([]);
   const [selectedModel, setSelectedModel] = useState<string>('');
   const [sfBalance, setSfBalance] = useState<string | undefined>();
 
-  // Persist history to localStorage
+  // Loads the first page again, or appends the next one
+  const loadHistory = (more = false) => {
+    const offset = more ? history.length : 0;
+    searchHistory({ ...historyFilter, offset, limit: HISTORY_PAGE_SIZE, favorites_only: favoritesOnly }).then(page => {
+      const items = page.items.map(toHistoryItem);
+      setHistory(prev => more ? [...prev, ...items] : items);
+      setHistoryTotal(page.total);
+    }).catch(e => console.error(e));
+  };
+
+  useEffect(() => { loadHistory(); }, [favoritesOnly, historyFilter]);
+
   useEffect(() => {
-    try { localStorage.setItem('history', JSON.stringify(history)); }
-    catch {}
-  }, [history]);
+    migrateLegacyHistory().then(migrated => { if (migrated > 0) loadHistory(); }).catch(e => console.error(e));
+  }, []);
 
   const fetchModels = () => {
     fetchAvailableModels().then(data => {
@@ -83,6 +156,85 @@ export default function App() {
     setOriginalText(null);
     setCorrectedText(null);
     setShowingOriginal(false);
+    setConfidence(null);
+    setNeedsReview(false);
+    setSecondOpinion(null);
+    setConsensus(null);
+    setDiff(null);
+    setSyntaxIssues([]);
+    setCachedAt(null);
+    setRegions(null);
+    setRegionImageSize(null);
+    setActiveRegion(null);
+    setCombinedText('');
+  };
+
+  // Shows one recognition with its verification details
+  const applyResult = (res: RecognizeResult) => {
+    setResultText(res.text);
+    setModel(res.model);
+    setVerified(res.verified ?? null);
+    setCorrected(res.corrected ?? false);
+    setOriginalText(res.originalText ?? null);
+    setCorrectedText(res.corrected ? res.text : null);
+    setShowingOriginal(false);
+    setConfidence(res.confidence ?? null);
+    setNeedsReview(res.needsReview ?? false);
+    setSecondOpinion(res.secondOpinion ?? null);
+    setConsensus(res.consensus ?? null);
+    setDiff(res.diff ?? null);
+    setSyntaxIssues(res.syntaxIssues);
+    setCachedAt(res.cachedAt ?? null);
+  };
+
+  const saveToHistory = (res: RecognizeResult, elapsed: number) => {
+    addHistory({
+      mode,
+      text: res.text,
+      model: res.model,
+      model_id: res.modelId,
+      confidence: res.confidence,
+      needs_review: res.needsReview,
+      verified: res.verified,
+      corrected: res.corrected,
+      original_text: res.originalText,
+      second_opinion: res.secondOpinion,
+      consensus: res.consensus,
+      latency_ms: elapsed,
+    }, currentImage).then(showNewEntry).catch(e => console.error(e));
+  };
+
+  const showNewEntry = (entry: HistoryEntry) => {
+    // A filtered list is reloaded so the new entry shows only if it matches
+    if (favoritesOnly) return;
+    if (Object.keys(historyFilter).length > 0) { loadHistory(); return; }
+    setHistory(prev => [toHistoryItem(entry), ...prev]);
+    setHistoryTotal(n => n + 1);
+  };
+
+  // Takes over a recognized PDF as a document-mode result; the history entry has no image
+  const handlePdfResult = (markdown: string, pdfModel: string | null) => {
+    resetState();
+    setCurrentImage(null);
+    setMode('document');
+    setResultText(markdown);
+    setModel(pdfModel);
+    addHistory({ mode: 'document', text: markdown, model: pdfModel ?? '' }, null)
+      .then(showNewEntry).catch(e => console.error(e));
+    fetchModels();
+  };
+
+  // Picks one region's result, or the joined results again with `null`
+  const handleSelectRegion = (index: number | null) => {
+    const result = index === null ? undefined : regions?.[index]?.result;
+    if (index !== null && !result) return;
+    setActiveRegion(index);
+    if (result) {
+      applyResult(result);
+    } else {
+      const first = regions?.find(r => r.result)?.result;
+      applyResult({ text: combinedText, model: first?.model ?? '', needsReview: regions?.some(r => r.result?.needsReview), failedAttempts: [], syntaxIssues: [] });
+    }
   };
 
   const handleImageUpload = (e: React.ChangeEvent<HTMLInputElement>) => {
@@ -108,27 +260,45 @@ export default function App() {
     resetState();
   };
 
-  const handleStartRecognition = async () => {
+  // `force` re-recognizes an image the cache already answered
+  const handleStartRecognition = async (force = false) => {
     if (!currentImage) return;
-
-    // Check image dimensions before sending to API (some models require min 28x28)
-    const isSimpleTex = selectedModel.startsWith('simpletex:');
-    if (!isSimpleTex) {
-      try {
-        const dims = await getImageDimensions(currentImage);
-        if (dims.width < 28 || dims.height < 28) {
-          setError(`图片尺寸太小 (${dims.width}×${dims.height})，硅基流动模型要求最小 28×28 像素，请使用更大的图片`);
-          return;
-        }
-      } catch {}
-    }
+    // Tiny images are enlarged by the backend's preprocessing; with that off, the provider's error is shown
 
     setIsProcessing(true);
     setError(null);
     const startTime = Date.now();
 
     try {
-      const res = await recognizeImage(currentImage, mode, selectedModel);
+      if (splitRegions) {
+        const split = await recognizeRegions(currentImage, mode, selectedModel, force);
+        const elapsed = Date.now() - startTime;
+        const done = split.regions.filter(r => r.result?.text.trim());
+        const failed = split.regions.filter(r => r.error);
+        // Formulas one per paragraph, so the joined text pastes as separate equations
+        const text = done.map(r => r.result!.text.trim()).join('\n\n');
+        resetState();
+        setRegions(split.regions);
+        setRegionImageSize({ width: split.width, height: split.height });
+        setCombinedText(text);
+        setResultText(text);
+        setLatency(elapsed);
+        setModel(done[0]?.result?.model ?? null);
+        setNeedsReview(done.some(r => r.result!.needsReview));
+        setCachedAt(done.length > 0 && done.every(r => r.result!.cachedAt) ? Math.min(...done.map(r => r.result!.cachedAt!)) : null);
+        if (failed.length > 0) {
+          setError(`${failed.length} 个区域识别失败：${failed[0].error!.message}`);
+        } else if (!text) {
+          setError('识别结果为空，可能图片中没有可识别的内容，请换一张图片试试');
+        }
+        if (text) {
+          saveToHistory({ ...done[0].result!, text, verified: undefined, corrected: undefined, originalText: undefined, secondOpinion: undefined, consensus: undefined }, elapsed);
+        }
+        fetchModels();
+        return;
+      }
+
+      const res = await recognizeImage(currentImage, mode, selectedModel, force);
       const elapsed = Date.now() - startTime;
       if (!res.text || !res.text.trim()) {
         setError('识别结果为空，可能图片中没有可识别的内容，请换一张图片试试');
@@ -138,27 +308,10 @@ export default function App() {
         setIsProcessing(false);
         return;
       }
-      setResultText(res.text);
+      resetState();
+      applyResult(res);
       setLatency(elapsed);
-      setModel(res.model);
-      setVerified(res.verified ?? null);
-      setCorrected(res.corrected ?? false);
-      setOriginalText(res.originalText ?? null);
-      setCorrectedText(res.corrected ? res.text : null);
-      setShowingOriginal(false);
-
-      const preview = res.text.trim().substring(0, 30);
-      const now = new Date();
-      const dateLabel = `${now.getFullYear()}/${String(now.getMonth() + 1).padStart(2, '0')}/${String(now.getDate()).padStart(2, '0')}`;
-      const newItem: HistoryItem = {
-        id: Date.now().toString(),
-        timestamp: new Date().toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' }),
-        formula: preview + (preview.length >= 30 ? '...' : ''),
-        isFavorite: false,
-        dateLabel,
-        mode,
-      };
-      setHistory(prev => [newItem, ...prev]);
+      saveToHistory(res, elapsed);
       fetchModels(); // refresh usage counts
     } catch (err: any) {
       console.error(err);
@@ -170,25 +323,61 @@ export default function App() {
     }
   };
 
-  const handleHistorySelect = (item: HistoryItem) => {
-    setResultText(item.formula);
+  // Restores the recognition as it was: result, verification details and the image
+  const handleHistorySelect = async (item: HistoryItem) => {
+    try {
+      const [entry] = await getHistory([item.id]);
+      if (!entry) return;
+      resetState();
+      setMode(entry.mode);
+      setResultText(entry.text);
+      setModel(entry.model);
+      setLatency(entry.latency_ms ?? null);
+      setVerified(entry.verified ?? null);
+      setCorrected(entry.corrected ?? false);
+      setOriginalText(entry.original_text ?? null);
+      setCorrectedText(entry.corrected ? entry.text : null);
+      setConfidence(entry.confidence ?? null);
+      setNeedsReview(entry.needs_review ?? false);
+      setSecondOpinion(entry.second_opinion ?? null);
+      setConsensus(entry.consensus ?? null);
+      if (entry.model_id && availableModels.some(m => m.id === entry.model_id)) setSelectedModel(entry.model_id);
+      setCurrentImage(entry.image ? await getHistoryImage(entry.id) : null);
+    } catch (e) {
+      console.error(e);
+    }
   };
 
   const handleToggleFavorite = (id: string) => {
-    setHistory(prev => prev.map(item =>
-      item.id === id ? { ...item, isFavorite: !item.isFavorite } : item
-    ));
+    const item = history.find(i => i.id === id);
+    if (!item) return;
+    setHistoryFavorite(id, !item.isFavorite).then(entry => {
+      if (favoritesOnly && !entry.favorite) {
+        setHistory(prev => prev.filter(i => i.id !== id));
+        setHistoryTotal(n => n - 1);
+      } else {
+        setHistory(prev => prev.map(i => i.id === id ? toHistoryItem(entry) : i));
+      }
+    }).catch(e => console.error(e));
   };
 
   const handleDeleteItems = (ids: string[]) => {
     const idSet = new Set(ids);
-    setHistory(prev => prev.filter(item => !idSet.has(item.id)));
+    deleteHistory(ids).then(removed => {
+      setHistory(prev => prev.filter(item => !idSet.has(item.id)));
+      setHistoryTotal(n => n - removed);
+    }).catch(e => console.error(e));
+  };
+
+  const handleClearHistory = (keepFavorites: boolean) => {
+    clearHistory(keepFavorites).then(() => loadHistory()).catch(e => console.error(e));
   };
 
   return (
     <div className="bg-background-light dark:bg-background-dark min-h-screen flex items-center justify-center p-4">
       <div className="flex h-[700px] w-full max-w-[960px] shadow-2xl shadow-slate-300/50 dark:shadow-black/30 rounded-2xl overflow-hidden border border-slate-200/80 dark:border-slate-800 bg-white dark:bg-slate-900">
-        <Sidebar history={history} onSelectItem={handleHistorySelect} onToggleFavorite={handleToggleFavorite} onDeleteItems={handleDeleteItems} />
+        <Sidebar history={history} total={historyTotal} onLoadMore={() => loadHistory(true)} onViewChange={v => setFavoritesOnly(v === 'favorites')} onSearch={setHistoryFilter}
+          onSelectItem={handleHistorySelect} onToggleFavorite={handleToggleFavorite} onDeleteItems={handleDeleteItems} onClearHistory={handleClearHistory} />
         <div className="flex-1 flex flex-col bg-white dark:bg-slate-900 relative z-10 min-w-0">
           {/* Header */}
           <div className="px-5 py-3 border-b border-slate-100 dark:border-slate-800 flex items-center justify-between bg-white dark:bg-slate-900 z-10 shrink-0">
@@ -201,13 +390,22 @@ export default function App() {
                 <span className="text-xs text-slate-400">AI OCR · 公式识别 · 导出Word</span>
               </div>
             </div>
-            <button
-              onClick={() => setSettingsOpen(true)}
-              className="text-slate-400 hover:text-indigo-500 transition-colors p-1.5 rounded-lg hover:bg-slate-50 dark:hover:bg-slate-800"
-              title="AI 设置"
-            >
-              <span className="material-icons text-[20px]">settings</span>
-            </button>
+            <div className="flex items-center gap-1">
+              <button
+                onClick={() => setPdfOpen(true)}
+                className="text-slate-400 hover:text-indigo-500 transition-colors p-1.5 rounded-lg hover:bg-slate-50 dark:hover:bg-slate-800"
+                title="导入 PDF"
+              >
+                <span className="material-icons text-[20px]">picture_as_pdf</span>
+              </button>
+              <button
+                onClick={() => setSettingsOpen(true)}
+                className="text-slate-400 hover:text-indigo-500 transition-colors p-1.5 rounded-lg hover:bg-slate-50 dark:hover:bg-slate-800"
+                title="AI 设置"
+              >
+                <span className="material-icons text-[20px]">settings</span>
+              </button>
+            </div>
           </div>
 
           <div className="flex-1 overflow-y-auto scrollbar-hide flex flex-col">
@@ -224,7 +422,13 @@ export default function App() {
                 onImageUpload={handleImageUpload}
                 onImagePaste={handleImagePaste}
                 onRemoveImage={handleRemoveImage}
-                onStartRecognition={handleStartRecognition}
+                onStartRecognition={() => handleStartRecognition()}
+                splitRegions={splitRegions}
+                setSplitRegions={setSplitRegions}
+                regions={regions}
+                regionImageSize={regionImageSize}
+                activeRegion={activeRegion}
+                onSelectRegion={handleSelectRegion}
               />
               {error && (
                 <div className="flex items-center gap-2 bg-red-50 dark:bg-red-950/20 border border-red-200/60 dark:border-red-800/30 text-red-600 dark:text-red-400 text-sm px-3 py-2 rounded-lg">
@@ -232,7 +436,15 @@ export default function App() {
                   {error}
                 </div>
               )}
+              {cachedAt !== null && !isProcessing && (
+                <div className="flex items-center gap-2 bg-slate-50 dark:bg-slate-800/50 border border-slate-200/60 dark:border-slate-700 text-slate-500 dark:text-slate-400 text-xs px-3 py-1.5 rounded-lg">
+                  <span className="material-icons text-[15px]">bolt</span>
+                  <span className="flex-1">结果来自缓存，首次识别于 {new Date(cachedAt).toLocaleString()}</span>
+                  <button onClick={() => handleStartRecognition(true)} className="text-indigo-600 dark:text-indigo-400 hover:underline">重新识别</button>
+                </div>
+              )}
               <ResultPanel resultText={resultText} mode={mode} verified={verified} corrected={corrected} originalText={originalText} showingOriginal={showingOriginal}
+                confidence={confidence} needsReview={needsReview} secondOpinion={secondOpinion} consensus={consensus} diff={diff} syntaxIssues={showingOriginal ? [] : syntaxIssues}
                 onUseOriginal={() => { if (originalText) { setResultText(originalText); setShowingOriginal(true); } }}
                 onUseCorrected={() => { if (correctedText) { setResultText(correctedText); setShowingOriginal(false); } }}
               />
@@ -242,6 +454,7 @@ export default function App() {
         </div>
       </div>
       <SettingsModal open={settingsOpen} onClose={() => { setSettingsOpen(false); fetchModels(); }} />
+      <PdfImportModal open={pdfOpen} onClose={() => setPdfOpen(false)} models={availableModels} onResult={handlePdfResult} />
     </div>
   );
 }
@@ -0,0 +1,216 @@
+import React, { useState, useEffect } from 'react';
+import type { PdfInfo, PdfProgress, PdfRecognition } from '../services/tauriService';
+import { pickPdf, recognizePdf, onPdfProgress } from '../services/tauriService';
+
+interface ModelOption {
+  id: string;
+  name: string;
+  modes: string[];
+  available: boolean;
+}
+
+interface PdfImportModalProps {
+  open: boolean;
+  onClose: () => void;
+  models: ModelOption[];
+  /** The combined Markdown, taken over as the current result */
+  onResult: (markdown: string, model: string | null) => void;
+}
+
+const DPI_OPTIONS = [100, 150, 200, 300];
+
+const STATUS_LABELS: Record<PdfProgress['status'], string> = {
+  rendering: '渲染中',
+  recognizing: '识别中',
+  done: '完成',
+  failed: '失败',
+};
+
+export const PdfImportModal: React.FC<PdfImportModalProps> = ({ open, onClose, models, onResult }) => {
+  const documentModels = models.filter(m => m.available && m.modes.includes('document'));
+  const [info, setInfo] = useState<PdfInfo | null>(null);
+  const [pages, setPages] = useState('');
+  const [dpi, setDpi] = useState(150);
+  const [modelId, setModelId] = useState('');
+  const [running, setRunning] = useState(false);
+  const [progress, setProgress] = useState<Record<number, PdfProgress>>({});
+  const [current, setCurrent] = useState<PdfProgress | null>(null);
+  const [result, setResult] = useState<PdfRecognition | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    if (!open) return;
+    const unlisten = onPdfProgress(p => {
+      setCurrent(p);
+      setProgress(prev => ({ ...prev, [p.page]: p }));
+    });
+    return () => { unlisten.then(f => f()); };
+  }, [open]);
+
+  useEffect(() => {
+    if (!documentModels.some(m => m.id === modelId)) setModelId(documentModels[0]?.id ?? '');
+  }, [models]);
+
+  const handlePick = async () => {
+    setError(null);
+    try {
+      const picked = await pickPdf();
+      if (!picked) return;
+      setInfo(picked);
+      setPages('');
+      setProgress({});
+      setCurrent(null);
+      setResult(null);
+    } catch (e: any) {
+      setError(e?.message || String(e));
+    }
+  };
+
+  const handleStart = async () => {
+    if (!info) return;
+    setRunning(true);
+    setError(null);
+    setProgress({});
+    setCurrent(null);
+    setResult(null);
+    try {
+      const res = await recognizePdf(info.path, pages, dpi, modelId);
+      setResult(res);
+      const failed = res.pages.filter(p => p.error);
+      if (failed.length > 0) setError(`${failed.length} 页识别失败：${failed[0].error!.message}`);
+    } catch (e: any) {
+      setError(e?.message || String(e));
+    } finally {
+      setRunning(false);
+    }
+  };
+
+  const handleUse = () => {
+    if (!result) return;
+    onResult(result.markdown, result.pages.find(p => p.model)?.model ?? null);
+    onClose();
+  };
+
+  if (!open) return null;
+
+  const fileName = info?.path.split(/[\\/]/).pop();
+  const pageStates = Object.values(progress).sort((a, b) => a.index - b.index);
+  const doneCount = pageStates.filter(p => p.status === 'done' || p.status === 'failed').length;
+
+  return (
+    <div className="fixed inset-0 bg-black/40 backdrop-blur-sm z-50 flex items-center justify-center p-4" onClick={() => { if (!running) onClose(); }}>
+      <div className="bg-white dark:bg-slate-900 rounded-2xl shadow-2xl w-full max-w-md p-6 space-y-4 max-h-[90vh] overflow-y-auto" onClick={e => e.stopPropagation()}>
+        <div className="flex items-center justify-between">
+          <h2 className="text-lg font-bold text-slate-800 dark:text-white flex items-center gap-2">
+            <span className="material-icons text-primary">picture_as_pdf</span>
+            PDF 识别
+          </h2>
+          <button onClick={onClose} disabled={running} className="text-slate-400 hover:text-slate-600 p-1 rounded-lg hover:bg-slate-100 disabled:opacity-40">
+            <span className="material-icons text-[20px]">close</span>
+          </button>
+        </div>
+
+        <p className="text-xs text-slate-400">逐页渲染 PDF 并以通用识别模式识别，合并为一份 Markdown 文档，适合讲义和论文</p>
+
+        <button
+          onClick={handlePick}
+          disabled={running}
+          className="w-full py-2 rounded-lg border border-dashed border-slate-300 dark:border-slate-600 text-sm text-slate-600 dark:text-slate-300 hover:border-indigo-300 hover:text-indigo-600 transition-colors flex items-center justify-center gap-1.5 disabled:opacity-50"
+        >
+          <span className="material-icons text-[16px]">folder_open</span>
+          {info ? `${fileName} · 共 ${info.pages} 页` : '选择 PDF 文件'}
+        </button>
+
+        {info && (
+          <>
+            <div className="grid grid-cols-2 gap-3">
+              <div className="space-y-1">
+                <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">页码</label>
+                <input
+                  type="text"
+                  value={pages}
+                  onChange={e => setPages(e.target.value)}
+                  placeholder={`全部（如 1-3, 5）`}
+                  disabled={running}
+                  className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+                />
+              </div>
+              <div className="space-y-1">
+                <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">分辨率</label>
+                <select
+                  value={dpi}
+                  onChange={e => setDpi(Number(e.target.value))}
+                  disabled={running}
+                  className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+                >
+                  {DPI_OPTIONS.map(d => <option key={d} value={d}>{d} DPI</option>)}
+                </select>
+              </div>
+            </div>
+            <div className="space-y-1">
+              <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">模型</label>
+              <select
+                value={modelId}
+                onChange={e => setModelId(e.target.value)}
+                disabled={running}
+                className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+              >
+                {documentModels.length === 0 && <option value="" disabled>请先在设置中配置 SimpleTex</option>}
+                {documentModels.map(m => <option key={m.id} value={m.id}>{m.name}</option>)}
+              </select>
+            </div>
+          </>
+        )}
+
+        {current && (
+          <div className="space-y-1.5">
+            <div className="flex justify-between text-xs text-slate-500">
+              <span>{running ? `第 ${current.page} 页${STATUS_LABELS[current.status]}` : '已结束'}</span>
+              <span>{doneCount}/{current.total}</span>
+            </div>
+            <div className="h-1.5 rounded-full bg-slate-100 dark:bg-slate-800 overflow-hidden">
+              <div className="h-full bg-indigo-500 transition-all" style={{ width: `${doneCount / current.total * 100}%` }} />
+            </div>
+            <div className="flex flex-wrap gap-1">
+              {pageStates.map(p => (
+                <span
+                  key={p.page}
+                  title={p.error ? p.error.message : STATUS_LABELS[p.status]}
+                  className={`text-[10px] px-1.5 py-0.5 rounded ${
+                    p.status === 'done' ? 'bg-green-50 text-green-600 dark:bg-green-950/30 dark:text-green-400'
+                      : p.status === 'failed' ? 'bg-red-50 text-red-600 dark:bg-red-950/30 dark:text-red-400'
+                      : 'bg-indigo-50 text-indigo-600 dark:bg-indigo-950/30 dark:text-indigo-300'
+                  }`}
+                >
+                  {p.page}
+                </span>
+              ))}
+            </div>
+          </div>
+        )}
+
+        {error && (
+          <div className="text-sm px-3 py-2 rounded-lg bg-red-50 text-red-600 dark:bg-red-950/20 dark:text-red-400">{error}</div>
+        )}
+
+        {result ? (
+          <button
+            onClick={handleUse}
+            className="w-full py-2.5 bg-indigo-600 hover:bg-indigo-700 text-white rounded-lg font-medium text-base transition-colors"
+          >
+            使用识别结果（{result.pages.filter(p => p.text).length} 页）
+          </button>
+        ) : (
+          <button
+            onClick={handleStart}
+            disabled={!info || running || !modelId}
+            className="w-full py-2.5 bg-indigo-600 hover:bg-indigo-700 text-white rounded-lg font-medium text-base transition-colors disabled:opacity-50 flex items-center justify-center gap-2"
+          >
+            {running && <span className="material-icons text-[18px] animate-spin">refresh</span>}
+            {running ? '识别中...' : '开始识别'}
+          </button>
+        )}
+      </div>
+    </div>
+  );
+};
@@ -1,4 +1,5 @@
 import React, { useRef, useEffect } from 'react';
+import type { RegionRecognition } from '../services/tauriService';
 
 interface ModelOption {
   id: string;
@@ -28,11 +29,19 @@ interface PreviewPanelProps {
   onImagePaste: (dataUrl: string) => void;
   onRemoveImage: () => void;
   onStartRecognition: () => void;
+  splitRegions: boolean;
+  setSplitRegions: (split: boolean) => void;
+  /** Regions of the last split recognition, over an image of `regionImageSize` */
+  regions?: RegionRecognition[] | null;
+  regionImageSize?: { width: number; height: number } | null;
+  activeRegion?: number | null;
+  onSelectRegion?: (index: number | null) => void;
 }
 
 export const PreviewPanel: React.FC<PreviewPanelProps> = ({
   imageSrc, isProcessing, mode, setMode, selectedModel, setSelectedModel,
-  models, sfBalance, onImageUpload, onImagePaste, onRemoveImage, onStartRecognition
+  models, sfBalance, onImageUpload, onImagePaste, onRemoveImage, onStartRecognition,
+  splitRegions, setSplitRegions, regions, regionImageSize, activeRegion = null, onSelectRegion
 }) => {
   const fileInputRef = useRef<HTMLInputElement>(null);
 
@@ -181,7 +190,33 @@ export const PreviewPanel: React.FC<PreviewPanelProps> = ({
                 className="absolute inset-0 w-full h-full opacity-0 cursor-pointer"
                 style={{ zIndex: 10 }}
               />
-              <img src={imageSrc} alt="Preview" className="object-contain max-h-40 max-w-full pointer-events-none" />
+              <div className="relative max-h-40 max-w-full">
+                <img src={imageSrc} alt="Preview" className="block object-contain max-h-40 max-w-full pointer-events-none" />
+                {regions && regionImageSize && regions.map((r, i) => (
+                  <button
+                    key={i}
+                    type="button"
+                    onClick={() => onSelectRegion?.(activeRegion === i ? null : i)}
+                    title={r.error ? `识别失败：${r.error.message}` : r.result?.text}
+                    className={`absolute border rounded-sm transition-colors ${
+                      r.error
+                        ? 'border-red-400 bg-red-400/10'
+                        : activeRegion === i
+                          ? 'border-indigo-600 bg-indigo-500/20'
+                          : 'border-indigo-400/70 bg-indigo-400/5 hover:bg-indigo-400/15'
+                    }`}
+                    style={{
+                      left: `${r.x / regionImageSize.width * 100}%`,
+                      top: `${r.y / regionImageSize.height * 100}%`,
+                      width: `${r.width / regionImageSize.width * 100}%`,
+                      height: `${r.height / regionImageSize.height * 100}%`,
+                      zIndex: 15,
+                    }}
+                  >
+                    <span className={`absolute -top-px -left-px text-[9px] leading-none px-0.5 rounded-br-sm text-white ${r.error ? 'bg-red-400' : 'bg-indigo-500'}`}>{i + 1}</span>
+                  </button>
+                ))}
+              </div>
             </div>
           </>
         ) : (
@@ -212,22 +247,37 @@ export const PreviewPanel: React.FC<PreviewPanelProps> = ({
       </div>
 
       {/* Action Button - more compact */}
-      <button
-        onClick={onStartRecognition}
-        disabled={!imageSrc || isProcessing}
-        className={`w-full py-2.5 rounded-xl font-medium text-base flex items-center justify-center gap-2 transition-all
-          ${!imageSrc || isProcessing
-            ? 'bg-slate-100 dark:bg-slate-800 cursor-not-allowed text-slate-400 dark:text-slate-600'
-            : 'bg-gradient-to-r from-indigo-600 to-violet-600 hover:from-indigo-700 hover:to-violet-700 active:scale-[0.99] text-white shadow-lg shadow-indigo-500/20'
+      <div className="flex gap-2">
+        <button
+          type="button"
+          onClick={() => setSplitRegions(!splitRegions)}
+          title="分区识别：把图片中的多个公式或段落分开识别，并标出各自的位置"
+          className={`px-3 rounded-xl border text-sm flex items-center gap-1 transition-colors shrink-0 ${
+            splitRegions
+              ? 'border-indigo-300 bg-indigo-50 text-indigo-600 dark:border-indigo-700 dark:bg-indigo-950/40 dark:text-indigo-300'
+              : 'border-slate-200 text-slate-400 hover:text-slate-600 dark:border-slate-700 dark:hover:text-slate-300'
           }`}
-      >
-        {isProcessing ? (
-          <span className="material-icons text-[18px] animate-spin">refresh</span>
-        ) : (
-          <span className="material-icons text-[18px]">bolt</span>
-        )}
-        {isProcessing ? '识别中...' : '开始识别'}
-      </button>
+        >
+          <span className="material-icons text-[16px]">view_agenda</span>
+          分区
+        </button>
+        <button
+          onClick={onStartRecognition}
+          disabled={!imageSrc || isProcessing}
+          className={`flex-1 py-2.5 rounded-xl font-medium text-base flex items-center justify-center gap-2 transition-all
+            ${!imageSrc || isProcessing
+              ? 'bg-slate-100 dark:bg-slate-800 cursor-not-allowed text-slate-400 dark:text-slate-600'
+              : 'bg-gradient-to-r from-indigo-600 to-violet-600 hover:from-indigo-700 hover:to-violet-700 active:scale-[0.99] text-white shadow-lg shadow-indigo-500/20'
+            }`}
+        >
+          {isProcessing ? (
+            <span className="material-icons text-[18px] animate-spin">refresh</span>
+          ) : (
+            <span className="material-icons text-[18px]">bolt</span>
+          )}
+          {isProcessing ? '识别中...' : '开始识别'}
+        </button>
+      </div>
     </div>
   );
 };
@@ -1,5 +1,6 @@
 import React, { useState, useMemo, useRef, useEffect } from 'react';
-import { exportToWord } from '../utils/exportWord';
+import { convertLatex, renderLatexImage, exportResult } from '../services/tauriService';
+import type { ExportFormat, SecondOpinion, ConsensusReport, LatexDiff, LatexDiagnostic } from '../services/tauriService';
 
 declare const katex: { renderToString: (tex: string, opts?: any) => string };
 
@@ -12,6 +13,12 @@ interface ResultPanelProps {
   showingOriginal?: boolean;
   onUseOriginal?: () => void;
   onUseCorrected?: () => void;
+  confidence?: number | null;
+  needsReview?: boolean;
+  secondOpinion?: SecondOpinion | null;
+  consensus?: ConsensusReport | null;
+  diff?: LatexDiff | null;
+  syntaxIssues?: LatexDiagnostic[];
 }
 
 function renderLatex(tex: string): string {
@@ -23,50 +30,6 @@ function renderLatex(tex: string): string {
   return `<code>${tex}</code>`;
 }
 
-function latexToMathML(tex: string): string {
-  try {
-    if (typeof katex !== 'undefined') {
-      const html = katex.renderToString(tex, { throwOnError: false, displayMode: true, output: 'mathml' });
-      const match = html.match(/<math[\s\S]*<\/math>/);
-      if (match) return match[0];
-    }
-  } catch {}
-  return '';
-}
-
-function latexToAsciiMath(tex: string): string {
-  let s = tex;
-  s = s.replace(/\\frac\{([^}]*)\}\{([^}]*)\}/g, '($1)/($2)');
-  s = s.replace(/\\sqrt\{([^}]*)\}/g, 'sqrt($1)');
-  s = s.replace(/\\left\(/g, '(').replace(/\\right\)/g, ')');
-  s = s.replace(/\\left\[/g, '[').replace(/\\right\]/g, ']');
-  s = s.replace(/\^{([^}]*)}/g, '^($1)');
-  s = s.replace(/_{([^}]*)}/g, '_($1)');
-  s = s.replace(/\\(alpha|beta|gamma|delta|epsilon|theta|lambda|mu|pi|sigma|omega|phi|psi|rho|tau|eta|xi|zeta|nu|kappa|iota|chi)/g, '$1');
-  s = s.replace(/\\(sum|prod|int|lim|inf|sin|cos|tan|log|ln|exp|max|min)/g, '$1');
-  s = s.replace(/\\cdot/g, '*').replace(/\\times/g, 'xx').replace(/\\div/g, '-:');
-  s = s.replace(/\\pm/g, '+-').replace(/\\mp/g, '-+');
-  s = s.replace(/\\leq/g, '<=').replace(/\\geq/g, '>=').replace(/\\neq/g, '!=');
-  s = s.replace(/\\infty/g, 'oo');
-  s = s.replace(/\\[a-zA-Z]+/g, '');
-  return s.trim();
-}
-
-function latexToTypst(tex: string): string {
-  let s = tex;
-  s = s.replace(/\\frac\{([^}]*)\}\{([^}]*)\}/g, '($1) / ($2)');
-  s = s.replace(/\\sqrt\{([^}]*)\}/g, 'sqrt($1)');
-  s = s.replace(/\^{([^}]*)}/g, '^($1)');
-  s = s.replace(/_{([^}]*)}/g, '_($1)');
-  s = s.replace(/\\(alpha|beta|gamma|delta|epsilon|theta|lambda|mu|pi|sigma|omega|phi|psi)/g, '$1');
-  s = s.replace(/\\cdot/g, 'dot').replace(/\\times/g, 'times');
-  s = s.replace(/\\infty/g, 'infinity');
-  s = s.replace(/\\leq/g, '<=').replace(/\\geq/g, '>=').replace(/\\neq/g, '!=');
-  s = s.replace(/\\left|\\right/g, '');
-  s = s.replace(/\\[a-zA-Z]+/g, '');
-  return `$${s.trim()}$`;
-}
-
 async function copyText(text: string): Promise<boolean> {
   try {
     await navigator.clipboard.writeText(text);
@@ -83,6 +46,17 @@ async function copyText(text: string): Promise<boolean> {
   }
 }
 
+async function copyPng(base64: string): Promise<boolean> {
+  try {
+    const bytes = Uint8Array.from(atob(base64), c => c.charCodeAt(0));
+    await navigator.clipboard.write([new ClipboardItem({ 'image/png': new Blob([bytes], { type: 'image/png' }) })]);
+    return true;
+  } catch (e) {
+    console.error(e);
+    return false;
+  }
+}
+
 
 interface DropdownItem {
   label: string;
@@ -172,7 +146,26 @@ function CopyButton({ label, icon, text }: { label: string; icon: string; text:
   );
 }
 
-export const ResultPanel: React.FC<ResultPanelProps> = ({ resultText, mode, verified, corrected, originalText, showingOriginal, onUseOriginal, onUseCorrected }) => {
+const DiffView: React.FC<{ diff: LatexDiff }> = ({ diff }) => (
+  <div className="font-mono text-[11px] leading-relaxed break-all text-slate-500 dark:text-slate-400">
+    {diff.ops.map((op, i) => {
+      switch (op.op) {
+        case 'equal': return <span key={i}>{op.text}</span>;
+        case 'insert': return <ins key={i} className="no-underline bg-emerald-100 text-emerald-700 dark:bg-emerald-900/30 dark:text-emerald-400 rounded px-0.5">{op.text}</ins>;
+        case 'delete': return <del key={i} className="bg-red-100 text-red-600 dark:bg-red-900/30 dark:text-red-400 rounded px-0.5">{op.text}</del>;
+        case 'replace': return (
+          <span key={i}>
+            <del className="bg-red-100 text-red-600 dark:bg-red-900/30 dark:text-red-400 rounded px-0.5">{op.from}</del>
+            <ins className="no-underline bg-emerald-100 text-emerald-700 dark:bg-emerald-900/30 dark:text-emerald-400 rounded px-0.5">{op.to}</ins>
+          </span>
+        );
+      }
+    })}
+    <div className="mt-0.5 font-sans text-slate-400">改动 {diff.commands} 个命令、{diff.symbols} 个符号</div>
+  </div>
+);
+
+export const ResultPanel: React.FC<ResultPanelProps> = ({ resultText, mode, verified, corrected, originalText, showingOriginal, onUseOriginal, onUseCorrected, confidence, needsReview, secondOpinion, consensus, diff, syntaxIssues = [] }) => {
   const latex = useMemo(() => {
     return resultText.trim();
   }, [resultText]);
@@ -218,11 +211,44 @@ export const ResultPanel: React.FC<ResultPanelProps> = ({ resultText, mode, veri
   ];
 
   const mathmlItems: DropdownItem[] = [
-    { label: '复制 MathML (Word)', icon: 'description', action: () => copyText(latexToMathML(latex)) },
-    { label: '复制 AsciiMath', icon: 'text_snippet', action: () => copyText(latexToAsciiMath(latex)) },
-    { label: '复制 Typst', icon: 'edit_note', action: () => copyText(latexToTypst(latex)) },
-    { label: '导出 Docx (Word/WPS)', icon: 'file_download', action: async () => {
-      try { await exportToWord(latex || resultText, mode); } catch(e) { console.error(e); }
+    { label: '复制 MathML', icon: 'code', action: async () => {
+      try { copyText((await convertLatex(latex, 'mathml')).output); } catch(e) { console.error(e); }
+    }},
+    { label: '复制 MathML (Word)', icon: 'description', action: async () => {
+      try { copyText((await convertLatex(latex, 'mathml', { wordNamespace: true })).output); } catch(e) { console.error(e); }
+    }},
+    { label: '复制 AsciiMath', icon: 'text_snippet', action: async () => {
+      try { copyText((await convertLatex(latex, 'asciimath')).output); } catch(e) { console.error(e); }
+    }},
+    { label: '复制 UnicodeMath (Word 线性格式)', icon: 'short_text', action: async () => {
+      try { copyText((await convertLatex(latex, 'unicodemath')).output); } catch(e) { console.error(e); }
+    }},
+    { label: '复制 Typst', icon: 'edit_note', action: async () => {
+      try { copyText((await convertLatex(latex, 'typst')).output); } catch(e) { console.error(e); }
+    }},
+  ];
+
+  const exportAs = async (format: ExportFormat) => {
+    try { await exportResult([{ content: mode === 'formula' ? latex : resultText, mode }], format); } catch(e) { console.error(e); }
+  };
+
+  const exportItems: DropdownItem[] = [
+    { label: '导出 Docx (Word/WPS)', icon: 'description', action: () => exportAs('docx') },
+    { label: '导出 PDF', icon: 'picture_as_pdf', action: () => exportAs('pdf') },
+    { label: '导出 LaTeX (.tex)', icon: 'code', action: () => exportAs('tex') },
+    { label: '导出 Markdown (.md)', icon: 'article', action: () => exportAs('md') },
+    { label: '导出 HTML (MathML)', icon: 'language', action: () => exportAs('html') },
+  ];
+
+  const imageItems: DropdownItem[] = [
+    { label: '复制 PNG 图片', icon: 'image', action: async () => {
+      try { copyPng((await renderLatexImage(latex, 'png')).data); } catch(e) { console.error(e); }
+    }},
+    { label: '复制 PNG 图片 (白色背景)', icon: 'wallpaper', action: async () => {
+      try { copyPng((await renderLatexImage(latex, 'png', { background: '#ffffff' })).data); } catch(e) { console.error(e); }
+    }},
+    { label: '复制 SVG', icon: 'polyline', action: async () => {
+      try { copyText((await renderLatexImage(latex, 'svg')).data); } catch(e) { console.error(e); }
     }},
   ];
 
@@ -248,6 +274,60 @@ export const ResultPanel: React.FC<ResultPanelProps> = ({ resultText, mode, veri
         )}
       </div>
 
+      {/* Syntax check */}
+      {syntaxIssues.length > 0 && (
+        <div className="text-xs text-amber-600 dark:text-amber-400 bg-amber-50 dark:bg-amber-950/20 border border-amber-200/50 dark:border-amber-800/30 rounded-lg px-2.5 py-1.5">
+          <div className="flex items-center gap-1.5">
+            <span className="material-icons text-[14px]">rule</span>
+            LaTeX 语法检查发现 {syntaxIssues.length} 个问题
+          </div>
+          <div className="mt-1 flex flex-col gap-0.5 pl-5">
+            {syntaxIssues.slice(0, 5).map((d, i) => (
+              <div key={i} className={d.severity === 'error' ? 'text-red-600 dark:text-red-400' : ''}>
+                {d.message}
+                <span className="font-mono text-slate-400 ml-1.5">{latex.slice(d.start, d.end)}</span>
+              </div>
+            ))}
+          </div>
+        </div>
+      )}
+
+      {/* Consensus report */}
+      {consensus && (
+        <div className={`text-xs rounded-lg px-2.5 py-1.5 border ${consensus.unanimous
+          ? 'text-emerald-600 dark:text-emerald-400 bg-emerald-50 dark:bg-emerald-950/20 border-emerald-200/50 dark:border-emerald-800/30'
+          : 'text-red-600 dark:text-red-400 bg-red-50 dark:bg-red-950/20 border-red-200/50 dark:border-red-800/30'}`}>
+          <div className="flex items-center gap-1.5">
+            <span className="material-icons text-[14px]">{consensus.unanimous ? 'how_to_vote' : 'report_problem'}</span>
+            {consensus.unanimous
+              ? `多模型投票：${consensus.responded} 个模型结果一致`
+              : `多模型投票：${consensus.votes}/${consensus.responded} 个模型一致，请人工检查`}
+          </div>
+          {!consensus.unanimous && (
+            <div className="mt-1 flex flex-col gap-0.5 pl-5">
+              {consensus.candidates.filter(c => !c.agrees).map(c => (
+                <div key={c.model_id} className="font-mono truncate" title={c.text}>{c.model}: {c.text}</div>
+              ))}
+            </div>
+          )}
+        </div>
+      )}
+
+      {/* Confidence badge */}
+      {needsReview && !consensus && (
+        <div className="flex items-center gap-1.5 text-xs text-red-600 dark:text-red-400 bg-red-50 dark:bg-red-950/20 border border-red-200/50 dark:border-red-800/30 rounded-lg px-2.5 py-1.5">
+          <span className="material-icons text-[14px]">report_problem</span>
+          置信度低{confidence != null && ` (${(confidence * 100).toFixed(0)}%)`}
+          {secondOpinion ? '，复核模型结果不一致，请人工检查' : '，请人工检查'}
+        </div>
+      )}
+      {!needsReview && secondOpinion?.agrees && (
+        <div className="flex items-center gap-1.5 text-xs text-emerald-600 dark:text-emerald-400 bg-emerald-50 dark:bg-emerald-950/20 border border-emerald-200/50 dark:border-emerald-800/30 rounded-lg px-2.5 py-1.5">
+          <span className="material-icons text-[14px]">verified</span>
+          置信度低{confidence != null && ` (${(confidence * 100).toFixed(0)}%)`}，复核模型结果一致
+        </div>
+      )}
+
       {/* Verification badge */}
       {verified === true && (
         <div className="flex items-center gap-1.5 text-xs text-emerald-600 dark:text-emerald-400 bg-emerald-50 dark:bg-emerald-950/20 border border-emerald-200/50 dark:border-emerald-800/30 rounded-lg px-2.5 py-1.5">
@@ -256,16 +336,19 @@ export const ResultPanel: React.FC<ResultPanelProps> = ({ resultText, mode, veri
         </div>
       )}
       {corrected && !showingOriginal && (
-        <div className="flex items-center justify-between text-xs bg-amber-50 dark:bg-amber-950/20 border border-amber-200/50 dark:border-amber-800/30 rounded-lg px-2.5 py-1.5">
-          <div className="flex items-center gap-1.5 text-amber-600 dark:text-amber-400">
-            <span className="material-icons text-[14px]">edit_note</span>
-            AI 校验后已修正结果
+        <div className="text-xs bg-amber-50 dark:bg-amber-950/20 border border-amber-200/50 dark:border-amber-800/30 rounded-lg px-2.5 py-1.5">
+          <div className="flex items-center justify-between">
+            <div className="flex items-center gap-1.5 text-amber-600 dark:text-amber-400">
+              <span className="material-icons text-[14px]">edit_note</span>
+              AI 校验后已修正结果
+            </div>
+            {originalText && onUseOriginal && (
+              <button onClick={onUseOriginal} className="text-amber-500 hover:text-amber-700 underline ml-2">
+                查看原始结果
+              </button>
+            )}
           </div>
-          {originalText && onUseOriginal && (
-            <button onClick={onUseOriginal} className="text-amber-500 hover:text-amber-700 underline ml-2">
-              查看原始结果
-            </button>
-          )}
+          {diff && <div className="mt-1 pl-5"><DiffView diff={diff} /></div>}
         </div>
       )}
       {corrected && showingOriginal && (
@@ -288,14 +371,20 @@ export const ResultPanel: React.FC<ResultPanelProps> = ({ resultText, mode, veri
           <>
             <DropdownButton icon="content_copy" label="LaTeX" items={latexItems} />
             <DropdownButton icon="functions" label="MathML" items={mathmlItems} />
+            <DropdownButton icon="image" label="图片" items={imageItems} />
+            <DropdownButton icon="file_download" label="导出" items={exportItems} />
           </>
         ) : mode === 'document' ? (
           <>
             <CopyButton label="复制 Markdown" icon="content_copy" text={resultText} />
             <CopyButton label="复制纯文本" icon="text_snippet" text={resultText.replace(/\$\$[\s\S]*?\$\$/g, '').replace(/\$[^\$\n]+?\$/g, '').replace(/[#*`]/g, '').trim()} />
+            <DropdownButton icon="file_download" label="导出" items={exportItems} />
           </>
         ) : (
-          <CopyButton label="复制文本" icon="content_copy" text={resultText} />
+          <>
+            <CopyButton label="复制文本" icon="content_copy" text={resultText} />
+            <DropdownButton icon="file_download" label="导出" items={exportItems} />
+          </>
         )}
       </div>
     </div>
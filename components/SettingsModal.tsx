@@ -1,5 +1,6 @@
 import React, { useState, useEffect } from 'react';
-import { getSettings, saveSettings, testSimpleTex, testSiliconFlow, getSfBalance, openExternalUrl } from '../services/tauriService';
+import type { PreprocessSettings } from '../services/tauriService';
+import { getSettings, saveSettings, testSimpleTex, testSiliconFlow, testProvider, getTesseractLanguages, getSfBalance, openExternalUrl, clearRecognitionCache } from '../services/tauriService';
 
 interface SimpleTexModel {
   id: string;
@@ -29,6 +30,28 @@ export const SettingsModal: React.FC<SettingsModalProps> = ({ open, onClose }) =
   const [sfTest, setSfTest] = useState<{ loading: boolean; result: null | { ok: boolean; error?: string; balance?: string } }>({ loading: false, result: null });
   const [voucherText, setVoucherText] = useState('');
   const [showVoucher, setShowVoucher] = useState(false);
+  const [cacheMaxEntries, setCacheMaxEntries] = useState(500);
+  const [cacheTtlDays, setCacheTtlDays] = useState(30);
+  const [cachePerceptual, setCachePerceptual] = useState(false);
+  const [cacheMessage, setCacheMessage] = useState('');
+  const [pdfiumPath, setPdfiumPath] = useState('');
+  const [openaiBaseUrl, setOpenaiBaseUrl] = useState('');
+  const [openaiModelsText, setOpenaiModelsText] = useState('');
+  const [openaiKey, setOpenaiKey] = useState('');
+  const [oaTest, setOaTest] = useState<{ loading: boolean; result: null | { ok: boolean; error?: string } }>({ loading: false, result: null });
+  const [localModelDir, setLocalModelDir] = useState('');
+  const [onnxruntimePath, setOnnxruntimePath] = useState('');
+  const [localTest, setLocalTest] = useState<{ loading: boolean; result: null | { ok: boolean; error?: string } }>({ loading: false, result: null });
+  const [tesseractPath, setTesseractPath] = useState('');
+  const [tesseractLangs, setTesseractLangs] = useState<string[]>([]);
+  const [installedLangs, setInstalledLangs] = useState<string[] | null>(null);
+  const [langsError, setLangsError] = useState('');
+  const [fallbackChainText, setFallbackChainText] = useState('');
+  const [maxRetries, setMaxRetries] = useState(2);
+  const [confidenceThreshold, setConfidenceThreshold] = useState(0.8);
+  const [secondOpinionModel, setSecondOpinionModel] = useState('');
+  const [consensusModelsText, setConsensusModelsText] = useState('');
+  const [preprocess, setPreprocess] = useState<PreprocessSettings>({ rotate: true, deskew: true, crop: true, upscale: true, downscale: true, grayscale: false, binarize: false });
 
   useEffect(() => {
     if (!open) return;
@@ -46,6 +69,27 @@ export const SettingsModal: React.FC<SettingsModalProps> = ({ open, onClose }) =
         setVoucherText(data.voucher_models.join('\n'));
         setShowVoucher(true);
       }
+      setCacheMaxEntries(data.cache_max_entries);
+      setCacheTtlDays(data.cache_ttl_days);
+      setCachePerceptual(data.cache_perceptual);
+      setCacheMessage('');
+      setPreprocess(data.preprocess);
+      setPdfiumPath(data.pdfium_path);
+      setOpenaiBaseUrl(data.openai_base_url);
+      setOpenaiModelsText(data.openai_models.join('\n'));
+      setOpenaiKey('');
+      setOaTest({ loading: false, result: null });
+      setLocalModelDir(data.local_model_dir);
+      setOnnxruntimePath(data.onnxruntime_path);
+      setLocalTest({ loading: false, result: null });
+      setFallbackChainText(data.fallback_chain.join('\n'));
+      setMaxRetries(data.max_retries);
+      setConfidenceThreshold(data.confidence_threshold);
+      setSecondOpinionModel(data.second_opinion_model);
+      setConsensusModelsText(data.consensus_models.join('\n'));
+      setTesseractPath(data.tesseract_path);
+      setTesseractLangs(data.tesseract_langs.length > 0 ? data.tesseract_langs : ['chi_sim', 'eng']);
+      loadTesseractLangs(data.tesseract_path);
     });
   }, [open]);
 
@@ -67,6 +111,25 @@ export const SettingsModal: React.FC<SettingsModalProps> = ({ open, onClose }) =
       if (apiKey) opts.siliconflow_key = apiKey;
       if (simpletexToken) opts.simpletex_token = simpletexToken;
       opts.voucher_models_text = voucherText;
+      opts.cache_max_entries = Math.max(0, Math.floor(cacheMaxEntries) || 0);
+      opts.cache_ttl_days = Math.max(0, Math.floor(cacheTtlDays) || 0);
+      opts.cache_perceptual = cachePerceptual;
+      opts.preprocess = preprocess;
+      opts.pdfium_path = pdfiumPath;
+      opts.onnxruntime_path = onnxruntimePath;
+      opts.fallback_chain_text = fallbackChainText;
+      opts.max_retries = Math.max(0, Math.floor(maxRetries) || 0);
+      opts.confidence_threshold = Math.min(1, Math.max(0, confidenceThreshold || 0));
+      opts.second_opinion_model = secondOpinionModel;
+      opts.consensus_models_text = consensusModelsText;
+      opts.openai_base_url = openaiBaseUrl;
+      opts.openai_models_text = openaiModelsText;
+      const credentials: Record<string, string> = {};
+      if (openaiKey) credentials.openai = openaiKey;
+      if (tesseractPath) credentials.tesseract = tesseractPath.trim();
+      opts.tesseract_langs_text = tesseractLangs.join('+');
+      if (localModelDir) credentials.local = localModelDir.trim();
+      opts.credentials = credentials;
       const result: any = await saveSettings(opts);
       if (result.ok) {
         setMessage('保存成功');
@@ -87,6 +150,15 @@ export const SettingsModal: React.FC<SettingsModalProps> = ({ open, onClose }) =
     }
   };
 
+  const handleClearCache = async () => {
+    try {
+      const n = await clearRecognitionCache();
+      setCacheMessage(`已清除 ${n} 条缓存`);
+    } catch {
+      setCacheMessage('清除失败');
+    }
+  };
+
   const currentSTModel = simpletexModels.find(m => m.id === simpletexModel);
   const displayLimit = currentSTModel?.freePerDay || 500;
   const currentUsage = simpletexUsageByModel[simpletexModel] || 0;
@@ -112,6 +184,44 @@ export const SettingsModal: React.FC<SettingsModalProps> = ({ open, onClose }) =
     }
   };
 
+  const handleTestOpenAi = async () => {
+    setOaTest({ loading: true, result: null });
+    try {
+      // The key is checked against the stored base URL
+      await saveSettings({ openai_base_url: openaiBaseUrl });
+      const data = await testProvider('openai', openaiKey || undefined);
+      setOaTest({ loading: false, result: data });
+    } catch {
+      setOaTest({ loading: false, result: { ok: false, error: '网络错误' } });
+    }
+  };
+
+  const loadTesseractLangs = async (path: string) => {
+    setLangsError('');
+    try {
+      setInstalledLangs(await getTesseractLanguages(path || undefined));
+    } catch (e) {
+      setInstalledLangs(null);
+      setLangsError(String(e));
+    }
+  };
+
+  const toggleTesseractLang = (lang: string, on: boolean) => {
+    setTesseractLangs(prev => on ? [...prev, lang] : prev.filter(l => l !== lang));
+  };
+
+  const handleTestLocal = async () => {
+    setLocalTest({ loading: true, result: null });
+    try {
+      // The runtime path is read from the store, so save it before loading the model
+      await saveSettings({ onnxruntime_path: onnxruntimePath });
+      const data = await testProvider('local', localModelDir || undefined);
+      setLocalTest({ loading: false, result: data });
+    } catch {
+      setLocalTest({ loading: false, result: { ok: false, error: '加载失败' } });
+    }
+  };
+
   if (!open) return null;
 
   return (
@@ -312,6 +422,289 @@ export const SettingsModal: React.FC<SettingsModalProps> = ({ open, onClose }) =
 
         </div>
 
+        {/* OpenAI-compatible endpoint */}
+        <div className="border border-slate-200 dark:border-slate-800 rounded-xl p-4 space-y-3">
+          <span className="text-base font-bold text-slate-700 dark:text-slate-300">OpenAI 兼容接口</span>
+          <div className="space-y-1.5">
+            <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">服务地址</label>
+            <input
+              type="text"
+              value={openaiBaseUrl}
+              onChange={e => setOpenaiBaseUrl(e.target.value)}
+              placeholder="如 http://localhost:11434/v1，留空则不启用"
+              className="w-full px-3 py-2 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-base focus:outline-none focus:ring-2 focus:ring-primary/50"
+            />
+          </div>
+          <div className="space-y-1.5">
+            <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">API Key</label>
+            <input
+              type="password"
+              value={openaiKey}
+              onChange={e => setOpenaiKey(e.target.value)}
+              placeholder="留空保持不变；自建服务可不填"
+              className="w-full px-3 py-2 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-base focus:outline-none focus:ring-2 focus:ring-primary/50"
+            />
+            <button
+              type="button"
+              onClick={handleTestOpenAi}
+              disabled={oaTest.loading || !openaiBaseUrl}
+              className="text-sm px-2.5 py-1 rounded-md border border-slate-200 dark:border-slate-700 text-slate-600 dark:text-slate-400 hover:bg-slate-50 dark:hover:bg-slate-800 transition-colors disabled:opacity-40"
+            >
+              {oaTest.loading ? '测试中...' : '测试连接'}
+            </button>
+            {oaTest.result && (
+              <span className={`text-sm ml-2 ${oaTest.result.ok ? 'text-green-600' : 'text-red-500'}`}>
+                {oaTest.result.ok ? '✓ 连接成功' : `✗ ${oaTest.result.error}`}
+              </span>
+            )}
+          </div>
+          <div className="space-y-1.5">
+            <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">模型列表</label>
+            <textarea
+              value={openaiModelsText}
+              onChange={e => setOpenaiModelsText(e.target.value)}
+              placeholder="每行一个模型 ID，如：&#10;qwen2.5vl:7b&#10;留空时从服务的 /models 接口获取"
+              className="w-full px-3 py-2 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-xs font-mono focus:outline-none focus:ring-2 focus:ring-primary/30 h-20 resize-y"
+            />
+          </div>
+        </div>
+
+        {/* Local ONNX model */}
+        <div className="border border-slate-200 dark:border-slate-700 rounded-xl p-4 space-y-3">
+          <span className="text-base font-bold text-slate-700 dark:text-slate-200">本地模型</span>
+          <p className="text-xs text-slate-400">离线识别公式，需要 pix2tex 导出的 ONNX 模型和 ONNX Runtime 动态库</p>
+          <div className="space-y-1.5">
+            <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">模型目录</label>
+            <input
+              type="text"
+              value={localModelDir}
+              onChange={e => setLocalModelDir(e.target.value)}
+              placeholder="包含 encoder.onnx、decoder.onnx、tokenizer.json 的目录"
+              className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+            />
+          </div>
+          <div className="space-y-1.5">
+            <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">ONNX Runtime</label>
+            <input
+              type="text"
+              value={onnxruntimePath}
+              onChange={e => setOnnxruntimePath(e.target.value)}
+              placeholder="onnxruntime.dll / libonnxruntime.dylib 的完整路径，留空时在程序目录查找"
+              className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+            />
+            <p className="text-xs text-slate-400">ONNX Runtime 加载成功后，更改路径需重启程序才能生效</p>
+          </div>
+          <button
+            type="button"
+            onClick={handleTestLocal}
+            disabled={localTest.loading || !localModelDir}
+            className="text-sm px-2.5 py-1 rounded-md border border-slate-200 dark:border-slate-700 text-slate-600 dark:text-slate-400 hover:bg-slate-50 dark:hover:bg-slate-800 transition-colors disabled:opacity-40"
+          >
+            {localTest.loading ? '加载中...' : '测试加载'}
+          </button>
+          {localTest.result && (
+            <span className={`text-sm ml-2 ${localTest.result.ok ? 'text-green-600' : 'text-red-500'}`}>
+              {localTest.result.ok ? '✓ 模型可用' : `✗ ${localTest.result.error}`}
+            </span>
+          )}
+        </div>
+
+        {/* Tesseract */}
+        <div className="border border-slate-200 dark:border-slate-700 rounded-xl p-4 space-y-3">
+          <span className="text-base font-bold text-slate-700 dark:text-slate-200">Tesseract 离线 OCR</span>
+          <div className="space-y-1.5">
+            <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">程序路径</label>
+            <div className="flex gap-2">
+              <input
+                type="text"
+                value={tesseractPath}
+                onChange={e => setTesseractPath(e.target.value)}
+                placeholder="tesseract 可执行文件，留空时在 PATH 中查找"
+                className="flex-1 min-w-0 px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+              />
+              <button
+                type="button"
+                onClick={() => loadTesseractLangs(tesseractPath)}
+                className="text-sm px-2.5 py-1 rounded-md border border-slate-200 dark:border-slate-700 text-slate-600 dark:text-slate-400 hover:bg-slate-50 dark:hover:bg-slate-800 transition-colors whitespace-nowrap"
+              >
+                读取语言包
+              </button>
+            </div>
+          </div>
+          <div className="space-y-1.5">
+            <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">识别语言</label>
+            {installedLangs ? (
+              <div className="flex flex-wrap gap-x-3 gap-y-1 max-h-28 overflow-y-auto">
+                {installedLangs.filter(l => l !== 'osd').map(lang => (
+                  <label key={lang} className="flex items-center gap-1 text-sm text-slate-600 dark:text-slate-300 cursor-pointer">
+                    <input
+                      type="checkbox"
+                      checked={tesseractLangs.includes(lang)}
+                      onChange={e => toggleTesseractLang(lang, e.target.checked)}
+                      className="accent-indigo-600"
+                    />
+                    <span className="font-mono">{lang}</span>
+                  </label>
+                ))}
+              </div>
+            ) : (
+              <p className="text-xs text-red-500">{langsError || '未读取到语言包'}</p>
+            )}
+            <p className="text-xs text-slate-400">当前：{tesseractLangs.join('+') || 'chi_sim+eng（默认）'}</p>
+          </div>
+        </div>
+
+        {/* Recognition strategy */}
+        <div className="border border-slate-200 dark:border-slate-700 rounded-xl p-4 space-y-3">
+          <span className="text-base font-bold text-slate-700 dark:text-slate-200">失败重试与备用模型</span>
+          <div className="space-y-1">
+            <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">重试次数</label>
+            <input
+              type="number"
+              min={0}
+              value={maxRetries}
+              onChange={e => setMaxRetries(Number(e.target.value))}
+              className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+            />
+            <p className="text-xs text-slate-400">网络超时、限流等临时错误时，同一模型最多重试的次数</p>
+          </div>
+          <div className="space-y-1">
+            <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">备用模型</label>
+            <textarea
+              value={fallbackChainText}
+              onChange={e => setFallbackChainText(e.target.value)}
+              placeholder="每行一个模型 ID，按顺序尝试，如：&#10;simpletex:latex_ocr_turbo&#10;siliconflow:Qwen/Qwen2.5-VL-7B-Instruct"
+              className="w-full px-3 py-2 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-xs font-mono focus:outline-none focus:ring-2 focus:ring-indigo-500/30 h-20 resize-y"
+            />
+            <p className="text-xs text-slate-400">所选模型重试后仍失败时依次改用这些模型</p>
+          </div>
+        </div>
+
+        {/* Low-confidence second opinion */}
+        <div className="border border-slate-200 dark:border-slate-700 rounded-xl p-4 space-y-3">
+          <span className="text-base font-bold text-slate-700 dark:text-slate-200">低置信度复核</span>
+          <p className="text-xs text-slate-400">SimpleTex 置信度低于阈值时，自动请另一个模型复核，结果不一致时标记为需人工检查</p>
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1">
+              <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">置信度阈值</label>
+              <input
+                type="number"
+                min={0}
+                max={1}
+                step={0.05}
+                value={confidenceThreshold}
+                onChange={e => setConfidenceThreshold(Number(e.target.value))}
+                className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+              />
+            </div>
+            <div className="space-y-1">
+              <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">复核模型</label>
+              <input
+                type="text"
+                value={secondOpinionModel}
+                onChange={e => setSecondOpinionModel(e.target.value)}
+                placeholder="留空用另一个 SimpleTex 模型"
+                className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm font-mono focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+              />
+            </div>
+          </div>
+          <p className="text-xs text-slate-400">阈值为 0 时不复核；复核模型填模型 ID，如 siliconflow:Qwen/Qwen2.5-VL-7B-Instruct</p>
+        </div>
+
+        {/* Consensus vote */}
+        <div className="border border-slate-200 dark:border-slate-700 rounded-xl p-4 space-y-2">
+          <span className="text-base font-bold text-slate-700 dark:text-slate-200">多模型投票</span>
+          <p className="text-xs text-slate-400">选择「多模型投票」时，同时用这些模型识别并按多数结果输出（至少两个）</p>
+          <textarea
+            value={consensusModelsText}
+            onChange={e => setConsensusModelsText(e.target.value)}
+            placeholder="每行一个模型 ID，如：&#10;simpletex:latex_ocr&#10;siliconflow:Qwen/Qwen2.5-VL-7B-Instruct&#10;siliconflow:Pro/Qwen/Qwen2.5-VL-7B-Instruct"
+            className="w-full px-3 py-2 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-xs font-mono focus:outline-none focus:ring-2 focus:ring-indigo-500/30 h-20 resize-y"
+          />
+        </div>
+
+        {/* Image preprocessing */}
+        <div className="border border-slate-200 dark:border-slate-700 rounded-xl p-4 space-y-2">
+          <span className="text-base font-bold text-slate-700 dark:text-slate-200">图片预处理</span>
+          <p className="text-xs text-slate-400">识别前在本地处理图片，并按真实格式上传</p>
+          {([
+            ['rotate', '自动旋转横拍或倒置的照片'],
+            ['deskew', '自动纠正倾斜（最多 10°）'],
+            ['crop', '自动裁掉空白边框'],
+            ['upscale', '放大过小的图片（硅基流动要求至少 28×28）'],
+            ['downscale', '缩小超大截图（长边超过 2048 像素）'],
+            ['grayscale', '转为灰度'],
+            ['binarize', '二值化（黑白），适合拍照或背景不均的图片'],
+          ] as [keyof PreprocessSettings, string][]).map(([key, label]) => (
+            <label key={key} className="flex items-center gap-2 text-sm text-slate-600 dark:text-slate-300 cursor-pointer">
+              <input
+                type="checkbox"
+                checked={preprocess[key]}
+                onChange={e => setPreprocess(p => ({ ...p, [key]: e.target.checked }))}
+                className="accent-indigo-600"
+              />
+              {label}
+            </label>
+          ))}
+        </div>
+
+        {/* Recognition cache */}
+        <div className="border border-slate-200 dark:border-slate-700 rounded-xl p-4 space-y-3">
+          <div className="flex items-center justify-between">
+            <span className="text-base font-bold text-slate-700 dark:text-slate-200">识别缓存</span>
+            <button
+              type="button"
+              onClick={handleClearCache}
+              className="text-xs px-2 py-1 rounded-lg text-slate-500 hover:text-red-500 hover:bg-red-50 dark:hover:bg-red-950/30 transition-colors flex items-center gap-0.5"
+            >
+              <span className="material-icons text-[14px]">delete_sweep</span>
+              清空缓存
+            </button>
+          </div>
+          <p className="text-xs text-slate-400">同一张图片以相同模式和模型再次识别时直接返回上次结果，不再调用接口、不计入用量</p>
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1">
+              <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">最多条数</label>
+              <input
+                type="number"
+                min={0}
+                value={cacheMaxEntries}
+                onChange={e => setCacheMaxEntries(Number(e.target.value))}
+                className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+              />
+            </div>
+            <div className="space-y-1">
+              <label className="text-xs font-semibold text-slate-500 uppercase tracking-wider">有效天数</label>
+              <input
+                type="number"
+                min={0}
+                value={cacheTtlDays}
+                onChange={e => setCacheTtlDays(Number(e.target.value))}
+                className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+              />
+            </div>
+          </div>
+          <p className="text-xs text-slate-400">条数为 0 时关闭缓存；天数为 0 时永不过期</p>
+          <label className="flex items-center gap-2 text-sm text-slate-600 dark:text-slate-300 cursor-pointer">
+            <input type="checkbox" checked={cachePerceptual} onChange={e => setCachePerceptual(e.target.checked)} className="accent-indigo-600" />
+            相似图片也视为相同（重新截图、压缩后的同一张图）
+          </label>
+          {cacheMessage && <p className="text-xs text-slate-500">{cacheMessage}</p>}
+        </div>
+
+        {/* PDF import */}
+        <div className="border border-slate-200 dark:border-slate-700 rounded-xl p-4 space-y-2">
+          <span className="text-base font-bold text-slate-700 dark:text-slate-200">PDF 导入</span>
+          <p className="text-xs text-slate-400">PDF 页面由 PDFium 渲染；留空时在程序目录和系统库中查找</p>
+          <input
+            type="text"
+            value={pdfiumPath}
+            onChange={e => setPdfiumPath(e.target.value)}
+            placeholder="PDFium 库文件或所在目录，如 C:\pdfium\bin\pdfium.dll"
+            className="w-full px-3 py-1.5 rounded-lg border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-800 text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/30"
+          />
+        </div>
+
         {message && (
           <div className={`text-base px-3 py-2 rounded-lg ${message.includes('成功') ? 'bg-green-50 text-green-600' : 'bg-red-50 text-red-600'}`}>
             {message}
@@ -1,14 +1,32 @@
-import React, { useState } from 'react';
+import React, { useState, useEffect } from 'react';
 import type { HistoryItem } from '../types';
+import type { HistorySearch } from '../services/tauriService';
 
 interface SidebarProps {
   history: HistoryItem[];
+  /** Entries in the current view, including those not loaded yet */
+  total: number;
+  onLoadMore: () => void;
+  onViewChange: (view: View) => void;
+  /** Called with only the filters that are set, so `{}` means no search */
+  onSearch: (filter: HistorySearch) => void;
   onSelectItem: (item: HistoryItem) => void;
   onToggleFavorite: (id: string) => void;
   onDeleteItems: (ids: string[]) => void;
+  onClearHistory: (keepFavorites: boolean) => void;
 }
 
 type View = 'history' | 'favorites';
+type DateRange = 'all' | 'today' | 'week' | 'month';
+
+function rangeStart(range: DateRange): number | undefined {
+  if (range === 'all') return undefined;
+  const start = new Date();
+  start.setHours(0, 0, 0, 0);
+  if (range === 'week') start.setDate(start.getDate() - 6);
+  if (range === 'month') start.setDate(start.getDate() - 29);
+  return start.getTime();
+}
 
 function groupByDate(items: HistoryItem[]): { label: string; items: HistoryItem[] }[] {
   const map = new Map<string, HistoryItem[]>();
@@ -20,12 +38,33 @@ function groupByDate(items: HistoryItem[]): { label: string; items: HistoryItem[
   return Array.from(map.entries()).map(([label, items]) => ({ label, items }));
 }
 
-export const Sidebar: React.FC<SidebarProps> = ({ history, onSelectItem, onToggleFavorite, onDeleteItems }) => {
+export const Sidebar: React.FC<SidebarProps> = ({ history, total, onLoadMore, onViewChange, onSearch, onSelectItem, onToggleFavorite, onDeleteItems, onClearHistory }) => {
   const [view, setView] = useState<View>('history');
   const [selectMode, setSelectMode] = useState(false);
   const [selected, setSelected] = useState<Set<string>>(new Set());
+  const [query, setQuery] = useState('');
+  const [latexSearch, setLatexSearch] = useState(false);
+  const [modeFilter, setModeFilter] = useState('');
+  const [range, setRange] = useState<DateRange>('all');
+
+  // Debounced, so typing doesn't search on every key
+  useEffect(() => {
+    const timer = setTimeout(() => {
+      const filter: HistorySearch = {};
+      const q = query.trim();
+      if (q) { if (latexSearch) filter.latex = q; else filter.text = q; }
+      if (modeFilter) filter.modes = [modeFilter];
+      const from = rangeStart(range);
+      if (from !== undefined) filter.from = from;
+      onSearch(filter);
+    }, 300);
+    return () => clearTimeout(timer);
+  }, [query, latexSearch, modeFilter, range]);
+
+  const searching = query.trim() !== '' || modeFilter !== '' || range !== 'all';
 
-  const items = view === 'favorites' ? history.filter(i => i.isFavorite) : history;
+  // The history comes filtered by view from the backend
+  const items = history;
   const groups = groupByDate(items);
 
   const toggleSelect = (id: string) => {
@@ -53,13 +92,28 @@ export const Sidebar: React.FC<SidebarProps> = ({ history, onSelectItem, onToggl
     setSelected(new Set());
   };
 
+  const switchView = (next: View) => {
+    if (next === view) return;
+    setView(next);
+    exitSelectMode();
+    onViewChange(next);
+  };
+
+  const handleClear = () => {
+    const keepFavorites = view === 'history';
+    const message = keepFavorites ? '清空全部历史记录？收藏的记录会保留。' : '清空全部收藏的记录？';
+    if (!window.confirm(message)) return;
+    onClearHistory(keepFavorites);
+    exitSelectMode();
+  };
+
   return (
     <div className="w-60 bg-slate-50/80 dark:bg-slate-950 border-r border-slate-100 dark:border-slate-800 flex flex-col hidden md:flex h-full">
       {/* Nav tabs */}
       <div className="p-3 pb-2">
         <div className="flex gap-1 bg-slate-100/80 dark:bg-slate-900 p-0.5 rounded-lg">
           <button
-            onClick={() => { setView('history'); exitSelectMode(); }}
+            onClick={() => switchView('history')}
             className={`flex-1 py-1.5 px-2 rounded-md text-sm font-medium flex items-center justify-center gap-1 transition-all ${
               view === 'history'
                 ? 'bg-white dark:bg-slate-800 text-slate-700 dark:text-slate-200 shadow-sm'
@@ -70,7 +124,7 @@ export const Sidebar: React.FC<SidebarProps> = ({ history, onSelectItem, onToggl
             历史
           </button>
           <button
-            onClick={() => { setView('favorites'); exitSelectMode(); }}
+            onClick={() => switchView('favorites')}
             className={`flex-1 py-1.5 px-2 rounded-md text-sm font-medium flex items-center justify-center gap-1 transition-all ${
               view === 'favorites'
                 ? 'bg-white dark:bg-slate-800 text-slate-700 dark:text-slate-200 shadow-sm'
@@ -83,6 +137,48 @@ export const Sidebar: React.FC<SidebarProps> = ({ history, onSelectItem, onToggl
         </div>
       </div>
 
+      {/* Search */}
+      <div className="px-3 pb-2 space-y-1.5">
+        <div className="flex items-center gap-1 bg-white dark:bg-slate-900 border border-slate-200 dark:border-slate-700 rounded-lg px-2 focus-within:ring-2 focus-within:ring-indigo-500/30">
+          <span className="material-icons text-[15px] text-slate-300">search</span>
+          <input
+            value={query}
+            onChange={e => setQuery(e.target.value)}
+            placeholder={latexSearch ? '搜索公式，如 \\frac{a}{b}' : '搜索识别结果'}
+            className={`flex-1 min-w-0 py-1 text-xs bg-transparent text-slate-600 dark:text-slate-300 focus:outline-none ${latexSearch ? 'font-mono' : ''}`}
+          />
+          <button
+            onClick={() => setLatexSearch(v => !v)}
+            title="按公式搜索：忽略空格、多余括号和 \le/\leq 等等价写法"
+            className={`p-0.5 rounded transition-colors ${latexSearch ? 'text-indigo-500' : 'text-slate-300 hover:text-slate-500'}`}
+          >
+            <span className="material-icons text-[15px]">functions</span>
+          </button>
+        </div>
+        <div className="flex gap-1">
+          <select
+            value={modeFilter}
+            onChange={e => setModeFilter(e.target.value)}
+            className="flex-1 min-w-0 px-1.5 py-0.5 rounded-md border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-900 text-xs text-slate-500 dark:text-slate-300 focus:outline-none cursor-pointer"
+          >
+            <option value="">全部类型</option>
+            <option value="formula">公式</option>
+            <option value="document">通用</option>
+            <option value="ocr">OCR</option>
+          </select>
+          <select
+            value={range}
+            onChange={e => setRange(e.target.value as DateRange)}
+            className="flex-1 min-w-0 px-1.5 py-0.5 rounded-md border border-slate-200 dark:border-slate-700 bg-white dark:bg-slate-900 text-xs text-slate-500 dark:text-slate-300 focus:outline-none cursor-pointer"
+          >
+            <option value="all">全部时间</option>
+            <option value="today">今天</option>
+            <option value="week">最近 7 天</option>
+            <option value="month">最近 30 天</option>
+          </select>
+        </div>
+      </div>
+
       {/* Batch action bar */}
       {items.length > 0 && (
         <div className="px-3 pb-1 flex items-center justify-between">
@@ -105,6 +201,13 @@ export const Sidebar: React.FC<SidebarProps> = ({ history, onSelectItem, onToggl
                 >
                   <span className="material-icons text-[16px]">delete_outline</span>
                 </button>
+                <button
+                  onClick={handleClear}
+                  title="清空"
+                  className="text-xs p-1 rounded text-red-500 hover:bg-red-50 dark:hover:bg-red-950/30 transition-colors"
+                >
+                  <span className="material-icons text-[16px]">delete_sweep</span>
+                </button>
                 <button
                   onClick={exitSelectMode}
                   className="text-xs px-1.5 py-0.5 rounded text-slate-400 hover:bg-slate-100 dark:hover:bg-slate-800 transition-colors"
@@ -129,8 +232,8 @@ export const Sidebar: React.FC<SidebarProps> = ({ history, onSelectItem, onToggl
       <div className="flex-1 overflow-y-auto scrollbar-hide px-3 pb-3 space-y-1">
         {items.length === 0 && (
           <div className="flex flex-col items-center justify-center text-slate-300 dark:text-slate-600 py-12 gap-2">
-            <span className="material-icons text-3xl">{view === 'favorites' ? 'bookmark_border' : 'inbox'}</span>
-            <span className="text-xs">{view === 'favorites' ? '还没有收藏' : '还没有记录'}</span>
+            <span className="material-icons text-3xl">{searching ? 'search_off' : view === 'favorites' ? 'bookmark_border' : 'inbox'}</span>
+            <span className="text-xs">{searching ? '没有匹配的记录' : view === 'favorites' ? '还没有收藏' : '还没有记录'}</span>
           </div>
         )}
 
@@ -152,6 +255,15 @@ export const Sidebar: React.FC<SidebarProps> = ({ history, onSelectItem, onToggl
             </div>
           </div>
         ))}
+
+        {items.length < total && (
+          <button
+            onClick={onLoadMore}
+            className="w-full mt-2 py-1.5 text-xs rounded-lg text-slate-400 hover:text-indigo-500 hover:bg-white dark:hover:bg-slate-900 transition-colors"
+          >
+            加载更多 ({items.length}/{total})
+          </button>
+        )}
       </div>
 
       {/* Footer */}
@@ -195,6 +307,9 @@ const HistoryItemRow: React.FC<{
         }`}>
           {item.mode === 'formula' ? '公式' : item.mode === 'document' ? '通用' : 'OCR'}
         </span>
+        {item.needsReview && (
+          <span className="material-icons text-[13px] text-red-400" title="置信度低，需人工检查">report_problem</span>
+        )}
       </div>
       {!selectMode && (
         <div className="flex items-center gap-0.5">
@@ -9,6 +9,10 @@ interface StatusFooterProps {
 export const StatusFooter: React.FC<StatusFooterProps> = ({ latency, model, selectedModelId }) => {
   const service = selectedModelId.startsWith('simpletex:') ? 'SimpleTex'
     : selectedModelId.startsWith('siliconflow:') ? '硅基流动'
+    : selectedModelId.startsWith('openai:') ? 'OpenAI 兼容'
+    : selectedModelId.startsWith('local:') ? '本地模型'
+    : selectedModelId.startsWith('tesseract:') ? 'Tesseract'
+    : selectedModelId === 'consensus' ? '多模型投票'
     : model?.includes('SimpleTex') ? 'SimpleTex'
     : model ? '硅基流动' : '-';
 
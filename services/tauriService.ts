@@ -1,4 +1,11 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
+
+export interface FailedAttempt {
+  model_id: string;
+  error: RecognizeError;
+  retries: number;
+}
 
 export interface RecognizeResponse {
   text: string;
@@ -6,26 +13,215 @@ export interface RecognizeResponse {
   verified?: boolean;
   corrected?: boolean;
   original_text?: string;
+  diff?: LatexDiff;
+  model_id?: string;
+  failed_attempts?: FailedAttempt[];
+  confidence?: number;
+  needs_review?: boolean;
+  second_opinion?: SecondOpinion;
+  consensus?: ConsensusReport;
+  syntax_issues?: LatexDiagnostic[];
+  /** Set when answered from the recognition cache: when it was first recognized (Unix ms) */
+  cached_at?: number;
+  preprocess?: PreprocessReport;
+}
+
+/** Image preprocessing steps, each toggled in settings. */
+export interface PreprocessSettings {
+  /** Turn pages photographed sideways or upside down upright */
+  rotate: boolean;
+  /** Straighten text lines tilted by up to 10° */
+  deskew: boolean;
+  crop: boolean;
+  upscale: boolean;
+  downscale: boolean;
+  grayscale: boolean;
+  /** Black and white; implies grayscale */
+  binarize: boolean;
+}
+
+/** What was done to the image before it was sent. */
+export interface PreprocessReport {
+  /** Format sniffed from the bytes */
+  format: string;
+  original_width: number;
+  original_height: number;
+  width: number;
+  height: number;
+  steps: ('orient' | 'rotate' | 'deskew' | 'crop' | 'downscale' | 'upscale' | 'pad' | 'grayscale' | 'binarize')[];
+  /** Degrees turned clockwise, by EXIF orientation and `rotate` together */
+  rotation: number;
+  /** Degrees the text was tilted clockwise before `deskew` */
+  skew: number;
+  reencoded: boolean;
+}
+
+/** A LaTeX syntax problem; `start`/`end` index into the formula string. */
+export interface LatexDiagnostic {
+  code: string;
+  severity: 'error' | 'warning';
+  message: string;
+  start: number;
+  end: number;
+}
+
+/** Another model's take on a low-confidence result. */
+export interface SecondOpinion {
+  model_id: string;
+  text: string;
+  confidence?: number;
+  agrees: boolean;
+  adopted: boolean;
+}
+
+export type DiffOp =
+  | { op: 'equal'; text: string }
+  | { op: 'insert'; text: string }
+  | { op: 'delete'; text: string }
+  | { op: 'replace'; from: string; to: string };
+
+/** Token-level changes from `original_text` to `text`. */
+export interface LatexDiff {
+  ops: DiffOp[];
+  inserted: number;
+  removed: number;
+  changed: number;
+  /** Inserted and removed tokens that are commands such as `\alpha` */
+  commands: number;
+  /** Inserted and removed tokens that are anything else */
+  symbols: number;
+}
+
+export interface ConsensusCandidate {
+  model_id: string;
+  model: string;
+  text: string;
+  normalized: string;
+  confidence?: number;
+  votes: number;
+  agrees: boolean;
+}
+
+/** How the models voted in consensus mode. */
+export interface ConsensusReport {
+  candidates: ConsensusCandidate[];
+  votes: number;
+  responded: number;
+  unanimous: boolean;
+  dissenters: string[];
 }
 
-export async function recognizeImage(base64Image: string, mode: string = 'formula', modelId?: string): Promise<{
+export type RecognizeErrorCode =
+  | 'unauthorized'
+  | 'quota_exhausted'
+  | 'image_too_small'
+  | 'network'
+  | 'rate_limited'
+  | 'provider_error'
+  | 'parse'
+  | 'not_configured'
+  | 'invalid_input'
+  | 'engine';
+
+/** Rejection value of `recognize`; branch on `code`, show `message`. */
+export interface RecognizeError {
+  code: RecognizeErrorCode;
+  message: string;
+  status?: number;
+  body?: string;
+  min_width?: number;
+  min_height?: number;
+}
+
+/** A recognition as the app uses it. */
+export interface RecognizeResult {
   text: string;
   model: string;
   verified?: boolean;
   corrected?: boolean;
   originalText?: string;
-}> {
-  const res = await invoke<RecognizeResponse>('recognize', {
-    image: base64Image,
-    mode,
-    model_id: modelId || '',
-  });
+  diff?: LatexDiff;
+  modelId?: string;
+  failedAttempts: FailedAttempt[];
+  confidence?: number;
+  needsReview?: boolean;
+  secondOpinion?: SecondOpinion;
+  consensus?: ConsensusReport;
+  syntaxIssues: LatexDiagnostic[];
+  cachedAt?: number;
+  preprocess?: PreprocessReport;
+}
+
+function toRecognizeResult(res: RecognizeResponse): RecognizeResult {
   return {
     text: res.text,
     model: res.model,
     verified: res.verified,
     corrected: res.corrected,
     originalText: res.original_text,
+    diff: res.diff,
+    modelId: res.model_id,
+    failedAttempts: res.failed_attempts || [],
+    confidence: res.confidence,
+    needsReview: res.needs_review,
+    secondOpinion: res.second_opinion,
+    consensus: res.consensus,
+    syntaxIssues: res.syntax_issues || [],
+    cachedAt: res.cached_at,
+    preprocess: res.preprocess,
+  };
+}
+
+/** `force` skips the recognition cache and replaces its entry with the new result. */
+export async function recognizeImage(base64Image: string, mode: string = 'formula', modelId?: string, force: boolean = false): Promise<RecognizeResult> {
+  const res = await invoke<RecognizeResponse>('recognize', {
+    image: base64Image,
+    mode,
+    model_id: modelId || '',
+    force,
+  });
+  return toRecognizeResult(res);
+}
+
+/** A formula or text region's bounding box, in pixels of the image as shown: EXIF orientation applied, not deskewed. */
+export interface ImageRegion {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/** A region and its recognition; exactly one of `result` and `error` is set. */
+export interface RegionRecognition extends ImageRegion {
+  result?: RecognizeResult;
+  error?: RecognizeError;
+}
+
+interface RegionsResponse {
+  width: number;
+  height: number;
+  regions: (ImageRegion & { result?: RecognizeResponse; error?: RecognizeError })[];
+}
+
+/**
+ * Splits the image into its formulas and paragraphs and recognizes each separately. Regions come
+ * top to bottom; rejects only when every region failed or none was found.
+ */
+export async function recognizeRegions(base64Image: string, mode: string = 'formula', modelId?: string, force: boolean = false): Promise<{
+  width: number;
+  height: number;
+  regions: RegionRecognition[];
+}> {
+  const res = await invoke<RegionsResponse>('recognize_regions', {
+    image: base64Image,
+    mode,
+    model_id: modelId || '',
+    force,
+  });
+  return {
+    width: res.width,
+    height: res.height,
+    regions: res.regions.map(({ result, ...r }) => ({ ...r, result: result && toRecognizeResult(result) })),
   };
 }
 
@@ -38,6 +234,23 @@ export interface SettingsData {
   sf_balance?: string;
   sf_charge_balance?: string;
   voucher_models: string[];
+  openai_base_url: string;
+  openai_models: string[];
+  local_model_dir: string;
+  onnxruntime_path: string;
+  pdfium_path: string;
+  tesseract_path: string;
+  tesseract_langs: string[];
+  fallback_chain: string[];
+  max_retries: number;
+  confidence_threshold: number;
+  second_opinion_model: string;
+  consensus_models: string[];
+  cache_max_entries: number;
+  cache_ttl_days: number;
+  cache_perceptual: boolean;
+  preprocess: PreprocessSettings;
+  providers: { id: string; name: string; configured: boolean }[];
 }
 
 export async function getSettings(): Promise<SettingsData> {
@@ -49,10 +262,34 @@ export async function saveSettings(opts: {
   siliconflow_key?: string;
   simpletex_model?: string;
   voucher_models_text?: string;
+  openai_base_url?: string;
+  openai_models_text?: string;
+  onnxruntime_path?: string;
+  pdfium_path?: string;
+  tesseract_langs_text?: string;
+  fallback_chain_text?: string;
+  max_retries?: number;
+  confidence_threshold?: number;
+  second_opinion_model?: string;
+  consensus_models_text?: string;
+  cache_max_entries?: number;
+  cache_ttl_days?: number;
+  cache_perceptual?: boolean;
+  preprocess?: PreprocessSettings;
+  credentials?: Record<string, string>;
 }): Promise<{ ok: boolean }> {
   return invoke('save_settings', opts);
 }
 
+/** Empties the recognition cache; resolves to how many results were dropped. */
+export async function clearRecognitionCache(): Promise<number> {
+  return invoke('clear_recognition_cache');
+}
+
+export async function testProvider(provider: string, credential?: string): Promise<{ ok: boolean; error?: string; balance?: string }> {
+  return invoke('test_provider', { provider, credential: credential || null });
+}
+
 export async function testSimpleTex(token?: string): Promise<{ ok: boolean; error?: string }> {
   return invoke('test_simpletex', { token: token || null });
 }
@@ -61,6 +298,280 @@ export async function testSiliconFlow(apiKey?: string): Promise<{ ok: boolean; e
   return invoke('test_siliconflow', { api_key: apiKey || null });
 }
 
+export async function checkLatex(latex: string): Promise<{ ok: boolean; diagnostics: LatexDiagnostic[] }> {
+  return invoke('check_latex', { latex });
+}
+
+export async function parseLatex(latex: string): Promise<{ nodes: any[]; diagnostics: LatexDiagnostic[] }> {
+  return invoke('parse_latex', { latex });
+}
+
+export type LatexFormat = 'mathml' | 'typst' | 'asciimath' | 'unicodemath';
+
+export async function convertLatex(
+  latex: string,
+  format: LatexFormat,
+  options: { display?: boolean; wordNamespace?: boolean } = {},
+): Promise<{ output: string; diagnostics: LatexDiagnostic[] }> {
+  return invoke('convert_latex', {
+    latex,
+    format,
+    display: options.display ?? null,
+    word_namespace: options.wordNamespace ?? null,
+  });
+}
+
+export async function renderLatexImage(
+  latex: string,
+  format: 'svg' | 'png',
+  options: { fontSize?: number; dpi?: number; color?: string; background?: string; display?: boolean } = {},
+): Promise<{ data: string; mime: string; width: number; height: number; diagnostics: LatexDiagnostic[] }> {
+  return invoke('render_latex', {
+    latex,
+    format,
+    font_size: options.fontSize ?? null,
+    dpi: options.dpi ?? null,
+    color: options.color ?? null,
+    background: options.background ?? null,
+    display: options.display ?? null,
+  });
+}
+
+export type ExportFormat = 'docx' | 'tex' | 'md' | 'html' | 'pdf';
+
+export interface ExportItem {
+  content: string;
+  mode: string;
+  /** Section heading when several items are exported together */
+  title?: string;
+}
+
+/** Saves results through a native save dialog; resolves to the path written, or null if cancelled. */
+export async function exportResult(items: ExportItem[], format: ExportFormat): Promise<string | null> {
+  return invoke('export_result', { items, format });
+}
+
+/** What a recognition produced, as saved to the history. */
+export interface HistoryRecord {
+  mode: string;
+  text: string;
+  model: string;
+  model_id?: string;
+  confidence?: number;
+  needs_review?: boolean;
+  verified?: boolean;
+  corrected?: boolean;
+  original_text?: string;
+  second_opinion?: SecondOpinion;
+  consensus?: ConsensusReport;
+  latency_ms?: number;
+}
+
+export interface HistoryEntry extends HistoryRecord {
+  id: string;
+  favorite: boolean;
+  /** File name of the stored image; fetch it with `getHistoryImage` */
+  image?: string;
+  /** Unix milliseconds */
+  created_at: number;
+  updated_at: number;
+}
+
+export interface HistoryQuery {
+  offset?: number;
+  limit?: number;
+  favorites_only?: boolean;
+}
+
+/** Filters for `searchHistory`; every one that is set must match. */
+export interface HistorySearch extends HistoryQuery {
+  /** Words that must all appear in the result, ignoring case */
+  text?: string;
+  /** Formula to find, ignoring whitespace, redundant braces and spellings like `\le` vs `\leq` */
+  latex?: string;
+  modes?: string[];
+  /** Display name or `provider:model` id */
+  model?: string;
+  /** Unix milliseconds, inclusive */
+  from?: number;
+  /** Unix milliseconds, exclusive */
+  to?: number;
+}
+
+export interface HistoryPage {
+  /** Newest first */
+  items: HistoryEntry[];
+  /** Entries matching the query, across all pages */
+  total: number;
+}
+
+export async function addHistory(record: HistoryRecord, image?: string | null): Promise<HistoryEntry> {
+  return invoke('add_history', { record, image: image || null });
+}
+
+export async function listHistory(query: HistoryQuery = {}): Promise<HistoryPage> {
+  return invoke('list_history', { query });
+}
+
+export async function searchHistory(query: HistorySearch): Promise<HistoryPage> {
+  return invoke('search_history', { query });
+}
+
+export async function getHistory(ids: string[]): Promise<HistoryEntry[]> {
+  return invoke('get_history', { ids });
+}
+
+/** The entry's image as a data URL, or null when it was saved without one. */
+export async function getHistoryImage(id: string): Promise<string | null> {
+  return invoke('get_history_image', { id });
+}
+
+export async function setHistoryFavorite(id: string, favorite: boolean): Promise<HistoryEntry> {
+  return invoke('set_history_favorite', { id, favorite });
+}
+
+export async function deleteHistory(ids: string[]): Promise<number> {
+  return invoke('delete_history', { ids });
+}
+
+export async function clearHistory(keepFavorites: boolean = false): Promise<number> {
+  return invoke('clear_history', { keep_favorites: keepFavorites });
+}
+
+export interface PdfInfo {
+  path: string;
+  pages: number;
+}
+
+/** Sent for every step while a PDF is recognized. */
+export interface PdfProgress {
+  /** Page number in the document, from 1 */
+  page: number;
+  /** Position among the selected pages, from 1 */
+  index: number;
+  total: number;
+  status: 'rendering' | 'recognizing' | 'done' | 'failed';
+  error?: RecognizeError;
+}
+
+export interface PdfPageResult {
+  page: number;
+  text?: string;
+  model?: string;
+  error?: RecognizeError;
+}
+
+export interface PdfRecognition {
+  /** Pages attempted; fewer than selected when a failure stopped the run */
+  pages: PdfPageResult[];
+  /** All pages as one Markdown document */
+  markdown: string;
+}
+
+/** Opens a file dialog; null when cancelled. */
+export async function pickPdf(): Promise<PdfInfo | null> {
+  return invoke('pick_pdf');
+}
+
+export async function getPdfInfo(path: string): Promise<PdfInfo> {
+  return invoke('get_pdf_info', { path });
+}
+
+/** `pages` such as `1-3, 5, 8-`, all when empty; progress arrives through `onPdfProgress`. */
+export async function recognizePdf(path: string, pages: string, dpi: number, modelId?: string): Promise<PdfRecognition> {
+  return invoke('recognize_pdf', { path, pages: pages.trim() || null, dpi, model_id: modelId || '' });
+}
+
+export function onPdfProgress(handler: (progress: PdfProgress) => void): Promise<UnlistenFn> {
+  return listen<PdfProgress>('pdf-progress', e => handler(e.payload));
+}
+
+export type BatchStatus = 'running' | 'paused' | 'cancelled' | 'completed';
+export type BatchItemStatus = 'pending' | 'running' | 'done' | 'failed';
+export type BatchExportFormat = 'csv' | 'jsonl' | 'md';
+
+export interface BatchItem {
+  path: string;
+  status: BatchItemStatus;
+  text?: string;
+  model?: string;
+  needs_review?: boolean;
+  error?: { code: string; message: string };
+  latency_ms?: number;
+}
+
+export interface BatchSummary {
+  id: string;
+  mode: string;
+  model_id: string;
+  status: BatchStatus;
+  total: number;
+  done: number;
+  failed: number;
+  /** Why the job paused by itself, e.g. an invalid token */
+  error?: { code: string; message: string };
+  created_at: number;
+  updated_at: number;
+}
+
+export interface BatchJob extends Omit<BatchSummary, 'total' | 'done' | 'failed'> {
+  concurrency: number;
+  items: BatchItem[];
+}
+
+/** Sent whenever an image or the job changes state; `index` and `item` name the image. */
+export interface BatchProgress extends BatchSummary {
+  index?: number;
+  item?: BatchItem;
+}
+
+/** Opens a folder or file dialog; empty when cancelled. */
+export async function pickBatchImages(folder: boolean): Promise<string[]> {
+  return invoke('pick_batch_images', { folder });
+}
+
+/** Queues image files and folders and starts the job; progress arrives through `onBatchProgress`. */
+export async function createBatch(sources: string[], mode: string, modelId?: string, concurrency?: number): Promise<BatchJob> {
+  return invoke('create_batch', { sources, mode, model_id: modelId || '', concurrency: concurrency ?? null });
+}
+
+export async function listBatches(): Promise<BatchSummary[]> {
+  return invoke('list_batches');
+}
+
+export async function getBatch(id: string): Promise<BatchJob> {
+  return invoke('get_batch', { id });
+}
+
+export async function pauseBatch(id: string): Promise<BatchSummary> {
+  return invoke('pause_batch', { id });
+}
+
+export async function resumeBatch(id: string): Promise<BatchSummary> {
+  return invoke('resume_batch', { id });
+}
+
+export async function cancelBatch(id: string): Promise<BatchSummary> {
+  return invoke('cancel_batch', { id });
+}
+
+export async function deleteBatch(id: string): Promise<void> {
+  return invoke('delete_batch', { id });
+}
+
+/** Saves the results through a save dialog; the path written, or null when cancelled. */
+export async function exportBatch(id: string, format: BatchExportFormat): Promise<string | null> {
+  return invoke('export_batch', { id, format });
+}
+
+export function onBatchProgress(handler: (progress: BatchProgress) => void): Promise<UnlistenFn> {
+  return listen<BatchProgress>('batch-progress', e => handler(e.payload));
+}
+
+export async function getTesseractLanguages(path?: string): Promise<string[]> {
+  return invoke('get_tesseract_languages', { path: path || null });
+}
+
 export interface AvailableModelsData {
   models: any[];
   sf_balance?: string;
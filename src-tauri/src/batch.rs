@@ -0,0 +1,726 @@
+//! Batch jobs: a folder or list of images recognized with one mode and model, a few at a time.
+//!
+//! Each job lives in `app_data_dir/batch/<id>.json` and is rewritten as every image starts and
+//! finishes, so a job cut short by quitting the app comes back paused where it stopped. Progress
+//! goes out as `batch-progress` events. Results stay with the job rather than in the history, and
+//! are exported from there as CSV, JSON Lines or Markdown.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::RecognizeError;
+use crate::export::{self, ExportItem};
+use crate::pipeline;
+
+// ── Constants ──
+
+pub const DEFAULT_CONCURRENCY: usize = 2;
+const MAX_CONCURRENCY: usize = 8;
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp", "gif"];
+
+/// Serializes every read-modify-write of a job file.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Jobs with workers running, by id. Taken before [`LOCK`] when both are needed.
+static ACTIVE: Mutex<Option<HashMap<String, Arc<Control>>>> = Mutex::new(None);
+
+const RUN: u8 = 0;
+const PAUSE: u8 = 1;
+const CANCEL: u8 = 2;
+
+/// What the workers of a running job are told: keep going, or stop after the image in hand.
+struct Control {
+    signal: AtomicU8,
+    workers: AtomicUsize,
+}
+
+// ── Types ──
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Paused,
+    Cancelled,
+    Completed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+/// A [`RecognizeError`] as stored with the job.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ItemError {
+    pub code: String,
+    pub message: String,
+}
+
+impl From<&RecognizeError> for ItemError {
+    fn from(e: &RecognizeError) -> Self {
+        ItemError { code: e.code().to_string(), message: e.to_string() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BatchItem {
+    pub path: String,
+    pub status: ItemStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub needs_review: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ItemError>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BatchJob {
+    pub id: String,
+    pub mode: String,
+    pub model_id: String,
+    pub concurrency: usize,
+    pub status: JobStatus,
+    /// In the order enqueued
+    pub items: Vec<BatchItem>,
+    /// Why the job paused by itself: a failure that every other image would hit too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ItemError>,
+    /// Unix milliseconds
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// A job without its items, for listing.
+#[derive(Serialize, Clone, Debug)]
+pub struct BatchSummary {
+    pub id: String,
+    pub mode: String,
+    pub model_id: String,
+    pub status: JobStatus,
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ItemError>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl BatchJob {
+    pub fn summary(&self) -> BatchSummary {
+        let count = |status| self.items.iter().filter(|i| i.status == status).count();
+        BatchSummary {
+            id: self.id.clone(),
+            mode: self.mode.clone(),
+            model_id: self.model_id.clone(),
+            status: self.status,
+            total: self.items.len(),
+            done: count(ItemStatus::Done),
+            failed: count(ItemStatus::Failed),
+            error: self.error.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    fn has_pending(&self) -> bool {
+        self.items.iter().any(|i| i.status == ItemStatus::Pending)
+    }
+
+    /// Puts a job left behind by a quit back in a resumable state: running becomes paused and
+    /// images in flight are queued again. Returns whether anything changed.
+    fn interrupt(&mut self) -> bool {
+        let mut changed = false;
+        for item in self.items.iter_mut().filter(|i| i.status == ItemStatus::Running) {
+            item.status = ItemStatus::Pending;
+            changed = true;
+        }
+        if self.status == JobStatus::Running {
+            self.status = JobStatus::Paused;
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Sent as `batch-progress` whenever an image or the job changes state.
+#[derive(Serialize, Clone)]
+pub struct BatchProgress {
+    #[serde(flatten)]
+    pub job: BatchSummary,
+    /// Position of the image that changed; absent when only the job's status did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<BatchItem>,
+}
+
+// ── Storage ──
+
+fn batch_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?.join("batch");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("无法创建批量任务目录: {}", e))?;
+    Ok(dir)
+}
+
+/// The job's file. Ids are millisecond timestamps; anything else could name a path outside `dir`.
+fn job_path(dir: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("批量任务不存在".to_string());
+    }
+    Ok(dir.join(format!("{}.json", id)))
+}
+
+fn load(dir: &Path, id: &str) -> Result<BatchJob, String> {
+    let data = std::fs::read_to_string(job_path(dir, id)?).map_err(|_| "批量任务不存在".to_string())?;
+    serde_json::from_str(&data).map_err(|e| format!("读取批量任务失败: {}", e))
+}
+
+/// Rewrites the file through a temporary one, so a crash leaves the previous state intact.
+fn save(dir: &Path, job: &BatchJob) -> Result<(), String> {
+    let data = serde_json::to_string(job).map_err(|e| e.to_string())?;
+    let tmp = dir.join(format!("{}.json.tmp", job.id));
+    std::fs::write(&tmp, data).map_err(|e| format!("保存批量任务失败: {}", e))?;
+    std::fs::rename(&tmp, dir.join(format!("{}.json", job.id))).map_err(|e| format!("保存批量任务失败: {}", e))
+}
+
+/// Applies `f` to the stored job and saves it; returns the job as saved and what `f` returned.
+fn update<R>(app: &AppHandle, id: &str, f: impl FnOnce(&mut BatchJob) -> R) -> Result<(BatchJob, R), String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = batch_dir(app)?;
+    let mut job = load(&dir, id)?;
+    let out = f(&mut job);
+    job.updated_at = chrono::Local::now().timestamp_millis();
+    save(&dir, &job)?;
+    Ok((job, out))
+}
+
+pub fn get(app: &AppHandle, id: &str) -> Result<BatchJob, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    load(&batch_dir(app)?, id)
+}
+
+/// Every job, newest first.
+pub fn list(app: &AppHandle) -> Result<Vec<BatchSummary>, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = batch_dir(app)?;
+    let mut jobs: Vec<BatchSummary> = std::fs::read_dir(&dir).map_err(|e| format!("读取批量任务失败: {}", e))?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            load(&dir, name.strip_suffix(".json")?).ok().map(|job| job.summary())
+        })
+        .collect();
+    jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+    Ok(jobs)
+}
+
+/// At startup: jobs that were running when the app quit are paused, and images that were being
+/// recognized go back in the queue.
+pub fn restore(app: &AppHandle) {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let Ok(dir) = batch_dir(app) else { return };
+    let Ok(entries) = std::fs::read_dir(&dir) else { return };
+    for name in entries.filter_map(|e| e.ok()?.file_name().into_string().ok()) {
+        let Some(id) = name.strip_suffix(".json") else { continue };
+        let Ok(mut job) = load(&dir, id) else { continue };
+        if !job.interrupt() { continue; }
+        if let Err(e) = save(&dir, &job) {
+            log::warn!("batch restore {}: {}", id, e);
+        }
+    }
+}
+
+// ── Queue ──
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// The images among `sources`: files as given, and the images directly inside folders, by name.
+fn collect_images(sources: &[String]) -> Result<Vec<String>, String> {
+    let mut paths: Vec<String> = Vec::new();
+    for source in sources {
+        let path = Path::new(source);
+        if path.is_dir() {
+            let mut found: Vec<PathBuf> = std::fs::read_dir(path)
+                .map_err(|e| format!("无法读取文件夹 {}: {}", source, e))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && is_image(p))
+                .collect();
+            found.sort();
+            paths.extend(found.into_iter().map(|p| p.to_string_lossy().into_owned()));
+        } else if path.is_file() {
+            paths.push(source.clone());
+        } else {
+            return Err(format!("文件不存在: {}", source));
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    paths.retain(|p| seen.insert(p.clone()));
+    if paths.is_empty() { return Err("没有找到图片文件".to_string()); }
+    Ok(paths)
+}
+
+/// Creates a job over the images of `sources` and starts it.
+pub fn create(app: &AppHandle, sources: &[String], mode: &str, model_id: &str, concurrency: usize) -> Result<BatchJob, String> {
+    let paths = collect_images(sources)?;
+    let job = {
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = batch_dir(app)?;
+        let now = chrono::Local::now().timestamp_millis();
+        // Millisecond ids, bumped past any taken by a job created in the same millisecond
+        let mut n = now;
+        while dir.join(format!("{}.json", n)).exists() { n += 1; }
+        let job = BatchJob {
+            id: n.to_string(),
+            mode: mode.to_string(),
+            model_id: model_id.to_string(),
+            concurrency: concurrency.clamp(1, MAX_CONCURRENCY),
+            status: JobStatus::Paused,
+            items: paths.into_iter().map(|path| BatchItem {
+                path,
+                status: ItemStatus::Pending,
+                text: None,
+                model: None,
+                needs_review: None,
+                error: None,
+                latency_ms: None,
+            }).collect(),
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        save(&dir, &job)?;
+        job
+    };
+    resume(app, &job.id)?;
+    get(app, &job.id)
+}
+
+fn emit(app: &AppHandle, job: &BatchJob, index: Option<usize>) {
+    let progress = BatchProgress { job: job.summary(), index, item: index.map(|i| job.items[i].clone()) };
+    if let Err(e) = app.emit("batch-progress", progress) {
+        log::warn!("batch-progress: {}", e);
+    }
+}
+
+fn active() -> std::sync::MutexGuard<'static, Option<HashMap<String, Arc<Control>>>> {
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Starts a paused job again, or keeps a job going that was told to pause but hasn't stopped yet.
+pub fn resume(app: &AppHandle, id: &str) -> Result<BatchSummary, String> {
+    let mut active = active();
+    let active = active.get_or_insert_with(HashMap::new);
+    if let Some(control) = active.get(id) {
+        let (job, cancelled) = update(app, id, |job| {
+            if job.status == JobStatus::Cancelled { return true; }
+            job.status = JobStatus::Running;
+            job.error = None;
+            false
+        })?;
+        if cancelled { return Err("任务已取消，无法继续".to_string()); }
+        control.signal.store(RUN, Ordering::SeqCst);
+        // Workers that retired during the pause are gone; bring the job back to its concurrency
+        let pending = job.items.iter().filter(|i| i.status == ItemStatus::Pending).count();
+        let running = control.workers.load(Ordering::SeqCst);
+        let missing = job.concurrency.clamp(1, MAX_CONCURRENCY).saturating_sub(running).min(pending);
+        control.workers.fetch_add(missing, Ordering::SeqCst);
+        for _ in 0..missing {
+            tauri::async_runtime::spawn(worker(app.clone(), id.to_string(), control.clone()));
+        }
+        emit(app, &job, None);
+        return Ok(job.summary());
+    }
+
+    let (job, _) = update(app, id, |job| {
+        if job.status == JobStatus::Paused {
+            job.status = if job.has_pending() { JobStatus::Running } else { JobStatus::Completed };
+            job.error = None;
+        }
+    })?;
+    match job.status {
+        JobStatus::Running => {}
+        JobStatus::Cancelled => return Err("任务已取消，无法继续".to_string()),
+        _ => return Ok(job.summary()),
+    }
+    let pending = job.items.iter().filter(|i| i.status == ItemStatus::Pending).count();
+    let workers = job.concurrency.clamp(1, MAX_CONCURRENCY).min(pending);
+    let control = Arc::new(Control { signal: AtomicU8::new(RUN), workers: AtomicUsize::new(workers) });
+    active.insert(id.to_string(), control.clone());
+    for _ in 0..workers {
+        tauri::async_runtime::spawn(worker(app.clone(), id.to_string(), control.clone()));
+    }
+    emit(app, &job, None);
+    Ok(job.summary())
+}
+
+/// Stops taking new images; those being recognized still finish.
+pub fn pause(app: &AppHandle, id: &str) -> Result<BatchSummary, String> {
+    stop(app, id, PAUSE, JobStatus::Paused)
+}
+
+/// Stops the job for good; images being recognized still finish and keep their results.
+pub fn cancel(app: &AppHandle, id: &str) -> Result<BatchSummary, String> {
+    stop(app, id, CANCEL, JobStatus::Cancelled)
+}
+
+fn stop(app: &AppHandle, id: &str, signal: u8, status: JobStatus) -> Result<BatchSummary, String> {
+    let active = active();
+    if let Some(control) = active.as_ref().and_then(|a| a.get(id)) {
+        control.signal.store(signal, Ordering::SeqCst);
+    }
+    let (job, _) = update(app, id, |job| {
+        // A paused job can still be cancelled; a finished one stays as it is
+        if matches!(job.status, JobStatus::Running | JobStatus::Paused) { job.status = status; }
+    })?;
+    emit(app, &job, None);
+    Ok(job.summary())
+}
+
+pub fn delete(app: &AppHandle, id: &str) -> Result<(), String> {
+    if active().as_ref().is_some_and(|a| a.contains_key(id)) {
+        return Err("任务正在运行，请先取消".to_string());
+    }
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    std::fs::remove_file(job_path(&batch_dir(app)?, id)?).map_err(|e| format!("删除批量任务失败: {}", e))
+}
+
+// ── Workers ──
+
+/// The file as a data URL, typed by its content rather than its extension.
+async fn load_image(path: &str) -> Result<String, RecognizeError> {
+    let bytes = tokio::fs::read(path).await
+        .map_err(|e| RecognizeError::InvalidInput(format!("读取图片失败: {}", e)))?;
+    let mime = image::guess_format(&bytes).map(|f| f.to_mime_type()).unwrap_or("image/png");
+    Ok(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(bytes)))
+}
+
+/// Takes pending images one at a time until there are none left or the job is told to stop.
+async fn worker(app: AppHandle, id: String, control: Arc<Control>) {
+    loop {
+        let claimed = if control.signal.load(Ordering::SeqCst) == RUN {
+            update(&app, &id, |job| {
+                let index = job.items.iter().position(|i| i.status == ItemStatus::Pending)?;
+                job.items[index].status = ItemStatus::Running;
+                Some((index, job.items[index].path.clone(), job.mode.clone(), job.model_id.clone()))
+            }).map(|(job, claim)| claim.map(|claim| (job, claim)))
+        } else {
+            Ok(None)
+        };
+        let (index, path, mode, model_id) = match claimed {
+            Ok(Some((job, claim))) => {
+                emit(&app, &job, Some(claim.0));
+                claim
+            }
+            Ok(None) => {
+                if retire(&app, &id, &control) { return; }
+                continue;
+            }
+            Err(e) => {
+                log::warn!("batch {}: {}", id, e);
+                control.signal.store(PAUSE, Ordering::SeqCst);
+                if retire(&app, &id, &control) { return; }
+                continue;
+            }
+        };
+
+        let started = Instant::now();
+        let result = match load_image(&path).await {
+            Ok(image) => pipeline::recognize(&app, image, &mode, &model_id, false).await,
+            Err(e) => Err(e),
+        };
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let updated = update(&app, &id, |job| {
+            let item = &mut job.items[index];
+            match result {
+                Ok(res) => {
+                    item.status = ItemStatus::Done;
+                    item.text = Some(res.text);
+                    item.model = Some(res.model);
+                    item.needs_review = res.needs_review;
+                    item.error = None;
+                    item.latency_ms = Some(latency_ms);
+                }
+                // Nothing else would get through either: the image goes back in the queue and the
+                // job pauses until the settings are fixed
+                Err(e) if e.affects_all() => {
+                    item.status = ItemStatus::Pending;
+                    job.error = Some(ItemError::from(&e));
+                    control.signal.store(PAUSE, Ordering::SeqCst);
+                }
+                Err(e) => {
+                    item.status = ItemStatus::Failed;
+                    item.error = Some(ItemError::from(&e));
+                    item.latency_ms = Some(latency_ms);
+                }
+            }
+        });
+        match updated {
+            Ok((job, _)) => emit(&app, &job, Some(index)),
+            Err(e) => log::warn!("batch {}: {}", id, e),
+        }
+    }
+}
+
+/// Called by a worker with nothing more to do. Returns `false` when the job was resumed in the
+/// meantime and still has images, so the worker carries on; the last worker out settles the job's
+/// status.
+fn retire(app: &AppHandle, id: &str, control: &Control) -> bool {
+    let mut active = active();
+    let signal = control.signal.load(Ordering::SeqCst);
+    if signal == RUN && get(app, id).is_ok_and(|job| job.has_pending()) {
+        return false;
+    }
+    if control.workers.fetch_sub(1, Ordering::SeqCst) > 1 {
+        return true;
+    }
+    if let Some(active) = active.as_mut() { active.remove(id); }
+    let settled = update(app, id, |job| {
+        job.status = match signal {
+            CANCEL => JobStatus::Cancelled,
+            _ if job.has_pending() => JobStatus::Paused,
+            _ => JobStatus::Completed,
+        };
+    });
+    match settled {
+        Ok((job, _)) => emit(app, &job, None),
+        Err(e) => log::warn!("batch {}: {}", id, e),
+    }
+    true
+}
+
+// ── Export ──
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn status_name(status: ItemStatus) -> &'static str {
+    match status {
+        ItemStatus::Pending => "pending",
+        ItemStatus::Running => "running",
+        ItemStatus::Done => "done",
+        ItemStatus::Failed => "failed",
+    }
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| path.to_string())
+}
+
+/// The job's results as `csv`, `jsonl` or `md`, one row, line or section per image in queue order.
+/// CSV starts with a byte order mark so Excel reads it as UTF-8.
+pub fn export(job: &BatchJob, format: &str) -> Result<Vec<u8>, String> {
+    match format {
+        "csv" => {
+            let mut out = String::from("\u{feff}path,status,model,text,error,latency_ms\r\n");
+            for item in &job.items {
+                let row = [
+                    csv_field(&item.path),
+                    status_name(item.status).to_string(),
+                    csv_field(item.model.as_deref().unwrap_or("")),
+                    csv_field(item.text.as_deref().unwrap_or("")),
+                    csv_field(item.error.as_ref().map(|e| e.message.as_str()).unwrap_or("")),
+                    item.latency_ms.map(|ms| ms.to_string()).unwrap_or_default(),
+                ];
+                out.push_str(&row.join(","));
+                out.push_str("\r\n");
+            }
+            Ok(out.into_bytes())
+        }
+        "jsonl" => {
+            let mut out = String::new();
+            for item in &job.items {
+                let line = serde_json::json!({
+                    "path": item.path,
+                    "status": item.status,
+                    "mode": job.mode,
+                    "model": item.model,
+                    "text": item.text,
+                    "needs_review": item.needs_review,
+                    "error": item.error,
+                    "latency_ms": item.latency_ms,
+                });
+                out.push_str(&line.to_string());
+                out.push('\n');
+            }
+            Ok(out.into_bytes())
+        }
+        "md" | "markdown" => {
+            // Images not reached yet are left out; failures stay in as a note under their file name
+            let items: Vec<ExportItem> = job.items.iter().filter_map(|item| {
+                let title = Some(file_name(&item.path));
+                match (&item.text, &item.error) {
+                    (Some(text), _) => Some(ExportItem { content: text.clone(), mode: job.mode.clone(), title }),
+                    (None, Some(e)) => Some(ExportItem { content: format!("> 识别失败：{}", e.message), mode: "document".into(), title }),
+                    (None, None) => None,
+                }
+            }).collect();
+            export::build(&items, export::Format::Markdown)
+        }
+        _ => Err(format!("不支持的导出格式: {}", format)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(path: &str, status: ItemStatus) -> BatchItem {
+        BatchItem { path: path.into(), status, text: None, model: None, needs_review: None, error: None, latency_ms: None }
+    }
+
+    fn job(status: JobStatus, items: Vec<BatchItem>) -> BatchJob {
+        BatchJob {
+            id: "1".into(),
+            mode: "formula".into(),
+            model_id: "simpletex:latex_ocr".into(),
+            concurrency: 2,
+            status,
+            items,
+            error: None,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    /// A fresh, empty directory under the system temp dir.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("formula-ocr-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn job_ids_are_digits_only() {
+        let dir = Path::new("batch");
+        assert_eq!(job_path(dir, "1718000000000").unwrap(), dir.join("1718000000000.json"));
+        for id in ["", "../x", "..", "12a", "1/2", "1.json", " 1"] {
+            assert!(job_path(dir, id).is_err(), "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn interrupted_jobs_come_back_paused() {
+        let mut running = job(JobStatus::Running, vec![item("a.png", ItemStatus::Done), item("b.png", ItemStatus::Running)]);
+        assert!(running.interrupt());
+        assert_eq!(running.status, JobStatus::Paused);
+        assert_eq!(running.items[0].status, ItemStatus::Done);
+        assert_eq!(running.items[1].status, ItemStatus::Pending);
+
+        // Paused while an image was still in flight
+        let mut paused = job(JobStatus::Paused, vec![item("a.png", ItemStatus::Running)]);
+        assert!(paused.interrupt());
+        assert_eq!(paused.status, JobStatus::Paused);
+        assert_eq!(paused.items[0].status, ItemStatus::Pending);
+
+        for status in [JobStatus::Paused, JobStatus::Cancelled, JobStatus::Completed] {
+            let mut settled = job(status, vec![item("a.png", ItemStatus::Done), item("b.png", ItemStatus::Pending)]);
+            assert!(!settled.interrupt());
+            assert_eq!(settled.status, status);
+        }
+    }
+
+    #[test]
+    fn collects_images_from_folders_and_files() {
+        let dir = temp_dir("batch-collect");
+        for name in ["b.png", "a.JPG", "notes.txt", "c.webp"] {
+            std::fs::write(dir.join(name), b"x").unwrap();
+        }
+        std::fs::create_dir(dir.join("sub.png")).unwrap();
+        std::fs::write(dir.join("sub.png").join("d.png"), b"x").unwrap();
+        let s = |p: PathBuf| p.to_string_lossy().into_owned();
+
+        let found = collect_images(&[s(dir.clone())]).unwrap();
+        assert_eq!(found, vec![s(dir.join("a.JPG")), s(dir.join("b.png")), s(dir.join("c.webp"))]);
+
+        // Files are taken as given, whatever the extension, and each path only once
+        let found = collect_images(&[s(dir.join("notes.txt")), s(dir.clone()), s(dir.join("b.png"))]).unwrap();
+        assert_eq!(found, vec![s(dir.join("notes.txt")), s(dir.join("a.JPG")), s(dir.join("b.png")), s(dir.join("c.webp"))]);
+
+        assert!(collect_images(&[s(dir.join("missing.png"))]).is_err());
+        let empty = temp_dir("batch-collect-empty");
+        assert!(collect_images(&[s(empty.clone())]).is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+        let _ = std::fs::remove_dir_all(empty);
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("x^2"), "x^2");
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("f(x,y)"), "\"f(x,y)\"");
+        assert_eq!(csv_field("\\text{\"a\"}"), "\"\\text{\"\"a\"\"}\"");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_field("a\rb"), "\"a\rb\"");
+    }
+
+    fn finished_job() -> BatchJob {
+        let mut done = item("/img/one.png", ItemStatus::Done);
+        done.text = Some("f(x,y) = \"z\"".into());
+        done.model = Some("SimpleTex".into());
+        done.latency_ms = Some(120);
+        let mut failed = item("/img/two.png", ItemStatus::Failed);
+        failed.error = Some(ItemError { code: "parse".into(), message: "识别结果为空".into() });
+        job(JobStatus::Completed, vec![done, failed, item("/img/three.png", ItemStatus::Pending)])
+    }
+
+    #[test]
+    fn exports_csv() {
+        let csv = String::from_utf8(export(&finished_job(), "csv").unwrap()).unwrap();
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(lines[0], "\u{feff}path,status,model,text,error,latency_ms");
+        assert_eq!(lines[1], "/img/one.png,done,SimpleTex,\"f(x,y) = \"\"z\"\"\",,120");
+        assert_eq!(lines[2], "/img/two.png,failed,,,识别结果为空,");
+        assert_eq!(lines[3], "/img/three.png,pending,,,,");
+        assert_eq!(lines[4], "");
+    }
+
+    #[test]
+    fn exports_jsonl() {
+        let jsonl = String::from_utf8(export(&finished_job(), "jsonl").unwrap()).unwrap();
+        let rows: Vec<serde_json::Value> = jsonl.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0]["status"], "done");
+        assert_eq!(rows[0]["mode"], "formula");
+        assert_eq!(rows[0]["text"], "f(x,y) = \"z\"");
+        assert_eq!(rows[0]["latency_ms"], 120);
+        assert_eq!(rows[1]["error"]["code"], "parse");
+        assert!(rows[2]["text"].is_null());
+    }
+
+    #[test]
+    fn exports_markdown() {
+        let md = String::from_utf8(export(&finished_job(), "md").unwrap()).unwrap();
+        assert!(md.contains("## one.png\n\n$$\nf(x,y) = \"z\"\n$$"), "{}", md);
+        assert!(md.contains("## two.png\n\n> 识别失败：识别结果为空"), "{}", md);
+        assert!(!md.contains("three.png"), "{}", md);
+
+        assert!(export(&finished_job(), "xlsx").is_err());
+    }
+}
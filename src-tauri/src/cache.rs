@@ -0,0 +1,390 @@
+//! Recognition cache in `app_data_dir/cache/recognitions.json`, so recognizing the same image
+//! again with the same mode, model and preprocessing settings returns the stored result instead of calling the provider
+//! (and spending its quota) a second time.
+//!
+//! Images match by the SHA-256 of their bytes. With `cache_perceptual` on, a re-encoded or
+//! re-captured copy of the same image also matches by a 64-bit difference hash.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::commands::{ConsensusReport, RecognizeResponse, SecondOpinion};
+use crate::latex;
+use crate::preprocess::{PreprocessReport, PreprocessSettings};
+use crate::store::get_store_u64;
+
+// ── Constants ──
+
+const DEFAULT_MAX_ENTRIES: usize = 500;
+const DEFAULT_TTL_DAYS: u64 = 30;
+/// Differing bits at which two difference hashes still count as the same image. Kept low:
+/// formulas that differ by one symbol can hash close together.
+const PHASH_MAX_DISTANCE: u32 = 3;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Serializes every read-modify-write of the cache file.
+static LOCK: Mutex<()> = Mutex::new(());
+
+// ── Settings ──
+
+/// Entries kept before the least recently used are dropped; 0 turns the cache off.
+pub fn max_entries(app: &AppHandle) -> usize {
+    get_store_u64(app, "cache_max_entries").map(|n| n as usize).unwrap_or(DEFAULT_MAX_ENTRIES)
+}
+
+/// Days a result stays valid; 0 keeps it until evicted.
+pub fn ttl_days(app: &AppHandle) -> u64 {
+    get_store_u64(app, "cache_ttl_days").unwrap_or(DEFAULT_TTL_DAYS)
+}
+
+pub fn perceptual(app: &AppHandle) -> bool {
+    app.store("config.json").ok()
+        .and_then(|store| store.get("cache_perceptual"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+// ── Types ──
+
+/// What identifies an image in the cache.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Fingerprint {
+    /// SHA-256 of the decoded image bytes, hex
+    pub sha256: String,
+    /// Difference hash of the 9×8 grayscale thumbnail; only computed when perceptual matching is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhash: Option<u64>,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+}
+
+/// The parts of a [`RecognizeResponse`] worth keeping; the rest is derived again on a hit.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct CachedResult {
+    text: String,
+    model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    corrected: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    original_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confidence: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    needs_review: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    second_opinion: Option<SecondOpinion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    consensus: Option<ConsensusReport>,
+    /// What preprocessing did to the image the provider saw
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preprocess: Option<PreprocessReport>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct CacheEntry {
+    fingerprint: Fingerprint,
+    mode: String,
+    /// As requested, so `auto` and `consensus` are cached under their own ids
+    model_id: String,
+    /// The preprocessing settings as JSON; the provider saw a different image under others
+    #[serde(default)]
+    preprocess: String,
+    result: CachedResult,
+    /// Unix milliseconds
+    created_at: i64,
+    last_used: i64,
+}
+
+impl CacheEntry {
+    fn into_response(self) -> RecognizeResponse {
+        let r = self.result;
+        let diff = match (&r.original_text, r.corrected) {
+            (Some(original), Some(true)) => Some(latex::diff(original, &r.text)),
+            _ => None,
+        };
+        RecognizeResponse {
+            text: r.text,
+            model: r.model,
+            verified: r.verified,
+            corrected: r.corrected,
+            original_text: r.original_text,
+            diff,
+            model_id: r.model_id,
+            confidence: r.confidence,
+            needs_review: r.needs_review,
+            second_opinion: r.second_opinion,
+            consensus: r.consensus,
+            preprocess: r.preprocess,
+            cached_at: Some(self.created_at),
+            ..Default::default()
+        }
+    }
+}
+
+// ── Fingerprinting ──
+
+/// Hashes a data URL or bare base64 image. `None` when it isn't valid base64; the provider
+/// reports that better than the cache could.
+pub fn fingerprint(image: &str, perceptual: bool) -> Option<Fingerprint> {
+    let data = image.split(',').next_back().unwrap_or(image);
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim()).ok()?;
+    let sha256 = Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect();
+    let (dhash, width, height) = match perceptual.then(|| image::load_from_memory(&bytes).ok()).flatten() {
+        Some(img) => (Some(dhash(&img)), img.width(), img.height()),
+        None => (None, 0, 0),
+    };
+    Some(Fingerprint { sha256, dhash, width, height })
+}
+
+/// One bit per horizontally adjacent pixel pair of a 9×8 grayscale thumbnail: brighter on the left.
+fn dhash(img: &image::DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, image::imageops::FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = hash << 1 | (small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0]) as u64;
+        }
+    }
+    hash
+}
+
+/// Byte-identical, or with perceptual hashes: the same size and hashes within
+/// [`PHASH_MAX_DISTANCE`] bits.
+fn same_image(a: &Fingerprint, b: &Fingerprint) -> bool {
+    if a.sha256 == b.sha256 { return true; }
+    match (a.dhash, b.dhash) {
+        (Some(x), Some(y)) => a.width == b.width && a.height == b.height && (x ^ y).count_ones() <= PHASH_MAX_DISTANCE,
+        _ => false,
+    }
+}
+
+fn settings_key(settings: &PreprocessSettings) -> String {
+    serde_json::to_string(settings).unwrap_or_default()
+}
+
+// ── Storage ──
+
+fn cache_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?.join("cache");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("无法创建缓存目录: {}", e))?;
+    Ok(dir.join("recognitions.json"))
+}
+
+/// Entries least recently used first. A corrupt file reads as an empty cache.
+fn load(path: &std::path::Path) -> Vec<CacheEntry> {
+    std::fs::read_to_string(path).ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save(path: &std::path::Path, entries: &[CacheEntry]) -> Result<(), String> {
+    let data = serde_json::to_string(entries).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, data).map_err(|e| format!("保存识别缓存失败: {}", e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("保存识别缓存失败: {}", e))
+}
+
+fn expired(entry: &CacheEntry, ttl_days: u64, now: i64) -> bool {
+    ttl_days > 0 && now - entry.created_at > ttl_days as i64 * DAY_MS
+}
+
+/// The stored result for this image, mode, model and preprocessing, if any. An exact match wins
+/// over a perceptual one.
+pub fn lookup(app: &AppHandle, fingerprint: &Fingerprint, mode: &str, model_id: &str, settings: &PreprocessSettings) -> Option<RecognizeResponse> {
+    if max_entries(app) == 0 { return None; }
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = cache_path(app).ok()?;
+    let now = chrono::Local::now().timestamp_millis();
+    let ttl = ttl_days(app);
+    let mut entries = load(&path);
+    let before = entries.len();
+    entries.retain(|e| !expired(e, ttl, now));
+
+    let preprocess = settings_key(settings);
+    let candidates = || entries.iter().enumerate().filter(|(_, e)| e.mode == mode && e.model_id == model_id && e.preprocess == preprocess);
+    let found = candidates().find(|(_, e)| e.fingerprint.sha256 == fingerprint.sha256)
+        .or_else(|| candidates().find(|(_, e)| same_image(&e.fingerprint, fingerprint)))
+        .map(|(i, _)| i);
+
+    let hit = found.map(|i| {
+        let mut entry = entries.remove(i);
+        entry.last_used = now;
+        entries.push(entry.clone());
+        entry
+    });
+    if hit.is_some() || entries.len() != before {
+        save(&path, &entries).ok();
+    }
+    hit.map(CacheEntry::into_response)
+}
+
+/// Remembers a result, replacing any for the same image, mode, model and preprocessing, and
+/// evicts the least recently used entries beyond the limit.
+pub fn insert(app: &AppHandle, fingerprint: Fingerprint, mode: &str, model_id: &str, settings: &PreprocessSettings, res: &RecognizeResponse) -> Result<(), String> {
+    let max = max_entries(app);
+    if max == 0 || res.text.trim().is_empty() { return Ok(()); }
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = cache_path(app)?;
+    let now = chrono::Local::now().timestamp_millis();
+    let ttl = ttl_days(app);
+    let mut entries = load(&path);
+    let preprocess = settings_key(settings);
+    let replaced = |e: &CacheEntry| e.mode == mode && e.model_id == model_id && e.preprocess == preprocess && e.fingerprint.sha256 == fingerprint.sha256;
+    entries.retain(|e| !(expired(e, ttl, now) || replaced(e)));
+
+    entries.push(CacheEntry {
+        fingerprint,
+        mode: mode.to_string(),
+        model_id: model_id.to_string(),
+        preprocess,
+        result: CachedResult {
+            text: res.text.clone(),
+            model: res.model.clone(),
+            model_id: res.model_id.clone(),
+            verified: res.verified,
+            corrected: res.corrected,
+            original_text: res.original_text.clone(),
+            confidence: res.confidence,
+            needs_review: res.needs_review,
+            second_opinion: res.second_opinion.clone(),
+            consensus: res.consensus.clone(),
+            preprocess: res.preprocess.clone(),
+        },
+        created_at: now,
+        last_used: now,
+    });
+    let excess = entries.len().saturating_sub(max);
+    entries.drain(..excess);
+    save(&path, &entries)
+}
+
+/// Empties the cache; returns how many entries were dropped.
+pub fn clear(app: &AppHandle) -> Result<usize, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = cache_path(app)?;
+    let count = load(&path).len();
+    save(&path, &[])?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+
+    /// A dark bar on a light background, with a soft gradient so the JPEG copy differs in bytes.
+    fn picture(width: u32, height: u32, bar: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let v = if (bar..bar + width / 4).contains(&x) && y > height / 4 { 30 } else { 200 + (x * 40 / width) as u8 };
+            image::Rgb([v, v, v])
+        }))
+    }
+
+    fn encode(img: &DynamicImage, format: ImageFormat) -> String {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut bytes, format).unwrap();
+        base64::engine::general_purpose::STANDARD.encode(bytes.into_inner())
+    }
+
+    fn entry(created_at: i64) -> CacheEntry {
+        CacheEntry {
+            fingerprint: Fingerprint { sha256: String::new(), dhash: None, width: 0, height: 0 },
+            mode: "formula".into(),
+            model_id: "auto".into(),
+            preprocess: String::new(),
+            result: CachedResult {
+                text: "x".into(),
+                model: "SimpleTex".into(),
+                model_id: None,
+                verified: None,
+                corrected: None,
+                original_text: None,
+                confidence: None,
+                needs_review: None,
+                second_opinion: None,
+                consensus: None,
+                preprocess: None,
+            },
+            created_at,
+            last_used: created_at,
+        }
+    }
+
+    #[test]
+    fn data_url_and_bare_base64_hash_alike() {
+        let png = encode(&picture(120, 60, 30), ImageFormat::Png);
+        let bare = fingerprint(&png, false).unwrap();
+        let url = fingerprint(&format!("data:image/png;base64,{}", png), false).unwrap();
+        assert_eq!(bare.sha256, url.sha256);
+        assert!(bare.dhash.is_none());
+        assert!(fingerprint("not base64!", false).is_none());
+    }
+
+    #[test]
+    fn reencoded_copy_matches_only_perceptually() {
+        let img = picture(120, 60, 30);
+        let png = encode(&img, ImageFormat::Png);
+        let jpeg = encode(&img, ImageFormat::Jpeg);
+        assert!(!same_image(&fingerprint(&png, false).unwrap(), &fingerprint(&jpeg, false).unwrap()));
+        assert!(same_image(&fingerprint(&png, true).unwrap(), &fingerprint(&jpeg, true).unwrap()));
+        // Byte-identical images match with or without perceptual hashes
+        assert!(same_image(&fingerprint(&png, true).unwrap(), &fingerprint(&png, false).unwrap()));
+    }
+
+    #[test]
+    fn different_images_do_not_match() {
+        let a = fingerprint(&encode(&picture(120, 60, 30), ImageFormat::Png), true).unwrap();
+        let moved = fingerprint(&encode(&picture(120, 60, 80), ImageFormat::Png), true).unwrap();
+        let resized = fingerprint(&encode(&picture(240, 120, 60), ImageFormat::Png), true).unwrap();
+        assert!(!same_image(&a, &moved));
+        // Same content at another size: the size check keeps it apart
+        assert!(!same_image(&a, &resized));
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let now = 100 * DAY_MS;
+        assert!(!expired(&entry(now - 30 * DAY_MS), 30, now));
+        assert!(expired(&entry(now - 30 * DAY_MS - 1), 30, now));
+        assert!(!expired(&entry(now - 1), 1, now));
+        // 0 days keeps entries forever
+        assert!(!expired(&entry(0), 0, now));
+    }
+
+    #[test]
+    fn hit_keeps_preprocess_report() {
+        let mut stored = entry(1);
+        stored.result.preprocess = Some(PreprocessReport {
+            format: "jpeg".into(),
+            original_width: 800,
+            original_height: 600,
+            width: 600,
+            height: 800,
+            steps: vec!["orient".into(), "deskew".into()],
+            rotation: 90,
+            skew: 1.5,
+            reencoded: true,
+        });
+        let json = serde_json::to_string(&stored).unwrap();
+        let res = serde_json::from_str::<CacheEntry>(&json).unwrap().into_response();
+        let report = res.preprocess.expect("report dropped");
+        assert_eq!((report.format.as_str(), report.width, report.height, report.rotation), ("jpeg", 600, 800, 90));
+        assert_eq!(report.steps, ["orient", "deskew"]);
+        assert_eq!(res.cached_at, Some(1));
+
+        // Entries written before reports were kept still load
+        let old = json.replace(&format!(",\"preprocess\":{}", serde_json::to_string(&stored.result.preprocess).unwrap()), "");
+        assert!(serde_json::from_str::<CacheEntry>(&old).unwrap().into_response().preprocess.is_none());
+    }
+}
@@ -1,12 +1,22 @@
 use base64::Engine;
-use chrono::Local;
-use open;
-use reqwest::multipart;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tauri::{AppHandle, Manager};
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
 use tauri_plugin_store::StoreExt;
 
+use crate::batch;
+use crate::cache;
+use crate::error::RecognizeError;
+use crate::export;
+use crate::history;
+use crate::latex::{self, Diagnostic, LatexDiff};
+use crate::pdf;
+use crate::pipeline;
+use crate::preprocess::{self, PreprocessReport, Region};
+use crate::providers::{self, SIMPLETEX_MODELS};
+use crate::store::{get_model_usage_today, get_store_string, get_store_vec};
+
 // ── Types ──
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -43,6 +53,13 @@ pub struct AvailableModel {
     pub total_balance: Option<String>,
 }
 
+#[derive(Serialize)]
+pub struct ProviderInfo {
+    pub id: String,
+    pub name: String,
+    pub configured: bool,
+}
+
 #[derive(Serialize)]
 pub struct SettingsResponse {
     pub has_key: bool,
@@ -53,6 +70,23 @@ pub struct SettingsResponse {
     pub sf_balance: Option<String>,
     pub sf_charge_balance: Option<String>,
     pub voucher_models: Vec<String>,
+    pub openai_base_url: String,
+    pub openai_models: Vec<String>,
+    pub local_model_dir: String,
+    pub onnxruntime_path: String,
+    pub pdfium_path: String,
+    pub tesseract_path: String,
+    pub tesseract_langs: Vec<String>,
+    pub fallback_chain: Vec<String>,
+    pub max_retries: u32,
+    pub confidence_threshold: f64,
+    pub second_opinion_model: String,
+    pub consensus_models: Vec<String>,
+    pub cache_max_entries: usize,
+    pub cache_ttl_days: u64,
+    pub cache_perceptual: bool,
+    pub preprocess: preprocess::PreprocessSettings,
+    pub providers: Vec<ProviderInfo>,
 }
 
 #[derive(Serialize, Clone)]
@@ -70,7 +104,7 @@ pub struct AvailableModelsResponse {
     pub voucher_balance: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Default)]
 pub struct RecognizeResponse {
     pub text: String,
     pub model: String,
@@ -80,286 +114,144 @@ pub struct RecognizeResponse {
     pub corrected: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub original_text: Option<String>,
+    /// What the verifier changed, from `original_text` to `text`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<LatexDiff>,
+    /// Full id (`provider:model`) of the model that produced `text`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_attempts: Vec<FailedAttempt>,
+    /// Provider-reported confidence in `[0, 1]` (SimpleTex only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Confidence was below the threshold and the second opinion didn't confirm the result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub needs_review: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub second_opinion: Option<SecondOpinion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consensus: Option<ConsensusReport>,
+    /// LaTeX syntax problems found in `text` (formula mode)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub syntax_issues: Vec<Diagnostic>,
+    /// Set when the result came from the recognition cache: when it was first recognized (Unix ms)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<i64>,
+    /// What was done to the image before it was sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preprocess: Option<PreprocessReport>,
 }
 
+/// One region of a split image and what it was recognized as. The box is in pixels of the image
+/// as received with its EXIF orientation applied, before any rotation or deskew, so it lines up
+/// with the image as a viewer shows it.
 #[derive(Serialize)]
-pub struct TestResult {
-    pub ok: bool,
+pub struct RegionResult {
+    #[serde(flatten)]
+    pub region: Region,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<String>,
+    pub result: Option<RecognizeResponse>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub balance: Option<String>,
+    pub error: Option<RecognizeError>,
 }
 
 #[derive(Serialize)]
-pub struct BalanceResponse {
-    pub charge_balance: Option<String>,
-    pub total_balance: Option<String>,
-    pub voucher_balance: Option<String>,
-}
-
-// ── Constants ──
-
-const SILICONFLOW_API_URL: &str = "https://api.siliconflow.cn/v1/chat/completions";
-
-const SIMPLETEX_MODELS: &[(&str, &str, u32, &str)] = &[
-    ("latex_ocr", "SimpleTex 标准模型", 500, "formula"),
-    ("latex_ocr_turbo", "SimpleTex 轻量模型", 2000, "formula"),
-    ("simpletex_ocr", "SimpleTex 通用识别", 50, "document"),
-];
-
-fn get_prompt(mode: &str) -> &'static str {
-    match mode {
-        "formula" => "请识别图片中的数学公式，只返回纯LaTeX代码，不要用markdown代码块包裹，不要加$符号，不要解释。",
-        "ocr" => "请识别图片中的所有文字内容，保持原始排版格式。只返回识别到的文字，不要解释。",
-        "document" => "请识别图片中的所有内容（包括文字、公式、表格等），以Markdown格式返回。公式用$...$（行内）或$$...$$（块级）包裹，表格用Markdown表格语法，保持原始排版结构。不要解释。",
-        _ => "请识别图片中的数学公式，只返回纯LaTeX代码，不要用markdown代码块包裹，不要加$符号，不要解释。",
-    }
-}
-
-// ── Vision model detection ──
-
-fn is_vision_model(id: &str) -> bool {
-    let upper = id.to_uppercase();
-    upper.contains("VL") || upper.contains("OCR") || upper.contains("PADDLEOCR")
-        || upper.contains("OMNI") || upper.contains("CAPTIONER") || id.contains("vl2")
-        || id.contains("Kimi-K2.5")
-        || {
-            // GLM-x.xV pattern
-            let parts: Vec<&str> = id.split('/').collect();
-            let last = parts.last().unwrap_or(&"");
-            last.contains("GLM-") && last.ends_with('V')
-        }
-}
-
-fn is_ocr_only_model(id: &str) -> bool {
-    let upper = id.to_uppercase();
-    upper.contains("PADDLEOCR") || upper.contains("DEEPSEEK-OCR") || upper.contains("CAPTIONER")
-}
-
-fn model_id_to_name(id: &str) -> String {
-    let is_pro = id.starts_with("Pro/");
-    let stripped = if is_pro { &id[4..] } else { id };
-    let parts: Vec<&str> = stripped.split('/').collect();
-    let name = parts.last().unwrap_or(&id)
-        .trim_end_matches("-Instruct");
-    if is_pro {
-        format!("{} (Pro)", name)
-    } else {
-        name.to_string()
-    }
-}
-
-// ── Store helpers ──
-
-fn get_store_string(app: &AppHandle, key: &str) -> String {
-    let store = app.store("config.json").unwrap();
-    store.get(key)
-        .and_then(|v| v.as_str().map(|s| s.to_string()))
-        .unwrap_or_default()
-}
-
-fn get_store_vec(app: &AppHandle, key: &str) -> Vec<String> {
-    let store = app.store("config.json").unwrap();
-    store.get(key)
-        .and_then(|v| serde_json::from_value::<Vec<String>>(v.clone()).ok())
-        .unwrap_or_default()
+pub struct RegionsResponse {
+    /// Size of the image the region boxes refer to: as received, EXIF orientation applied
+    pub width: u32,
+    pub height: u32,
+    /// Top to bottom
+    pub regions: Vec<RegionResult>,
+}
+
+/// Result of re-checking a low-confidence recognition with another model.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SecondOpinion {
+    pub model_id: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Same LaTeX as the first result, ignoring whitespace
+    pub agrees: bool,
+    /// The second opinion replaced the first result as `text`
+    pub adopted: bool,
+}
+
+/// How the models in consensus mode voted. Models that failed are in `failed_attempts`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ConsensusReport {
+    pub candidates: Vec<ConsensusCandidate>,
+    /// Models that agree with the winner, including itself
+    pub votes: u32,
+    /// Models that returned a result
+    pub responded: u32,
+    pub unanimous: bool,
+    /// Models whose normalized result differs from the winner
+    pub dissenters: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ConsensusCandidate {
+    pub model_id: String,
+    pub model: String,
+    pub text: String,
+    /// `text` after LaTeX normalization; candidates vote by equality of this
+    pub normalized: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    pub votes: u32,
+    /// Same normalized result as the winner
+    pub agrees: bool,
 }
 
-// ── Usage tracking ──
-
-fn get_usage_path(app: &AppHandle) -> std::path::PathBuf {
-    let dir = app.path().app_data_dir().unwrap();
-    std::fs::create_dir_all(&dir).ok();
-    dir.join("usage.json")
+/// A model in the fallback chain that gave up before the final result.
+#[derive(Serialize, Clone)]
+pub struct FailedAttempt {
+    pub model_id: String,
+    pub error: RecognizeError,
+    pub retries: u32,
 }
 
-fn load_usage(app: &AppHandle) -> (String, HashMap<String, u32>) {
-    let path = get_usage_path(app);
-    if let Ok(data) = std::fs::read_to_string(&path) {
-        if let Ok(val) = serde_json::from_str::<serde_json::Value>(&data) {
-            let date = val["date"].as_str().unwrap_or("").to_string();
-            let models: HashMap<String, u32> = val["models"].as_object()
-                .map(|m| m.iter().map(|(k, v)| (k.clone(), v.as_u64().unwrap_or(0) as u32)).collect())
-                .unwrap_or_default();
-            return (date, models);
-        }
-    }
-    (String::new(), HashMap::new())
+#[derive(Serialize)]
+pub struct TestResult {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<String>,
 }
 
-fn get_model_usage_today(app: &AppHandle, model_id: &str) -> u32 {
-    let today = Local::now().format("%Y-%m-%d").to_string();
-    let (date, models) = load_usage(app);
-    if date != today { return 0; }
-    *models.get(model_id).unwrap_or(&0)
+#[derive(Serialize)]
+pub struct LatexCheckResponse {
+    /// No errors; warnings such as unknown commands may remain
+    pub ok: bool,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
-fn increment_model_usage(app: &AppHandle, model_id: &str) {
-    let today = Local::now().format("%Y-%m-%d").to_string();
-    let (date, mut models) = load_usage(app);
-    if date != today {
-        models.clear();
-    }
-    let count = models.entry(model_id.to_string()).or_insert(0);
-    *count += 1;
-    let val = serde_json::json!({ "date": today, "models": models });
-    let path = get_usage_path(app);
-    std::fs::write(path, serde_json::to_string_pretty(&val).unwrap()).ok();
-}
-
-// ── API helpers ──
-
-async fn fetch_sf_balance(api_key: &str) -> Option<(String, String)> {
-    if api_key.is_empty() { return None; }
-    let client = reqwest::Client::new();
-    let res = client.get("https://api.siliconflow.cn/v1/user/info")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .send().await.ok()?;
-    if !res.status().is_success() { return None; }
-    let data: serde_json::Value = res.json().await.ok()?;
-    let charge = data["data"]["chargeBalance"].as_str().unwrap_or("0").to_string();
-    let total = data["data"]["totalBalance"].as_str()
-        .or_else(|| data["data"]["balance"].as_str())
-        .unwrap_or("0").to_string();
-    Some((charge, total))
-}
-
-async fn fetch_sf_vision_models(api_key: &str) -> Vec<SfModel> {
-    if api_key.is_empty() { return vec![]; }
-    let client = reqwest::Client::new();
-
-    // Fetch models
-    let models_res = client.get("https://api.siliconflow.cn/v1/models?sub_type=chat")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .send().await;
-    let all_models: Vec<String> = match models_res {
-        Ok(r) if r.status().is_success() => {
-            let data: serde_json::Value = r.json().await.unwrap_or_default();
-            data["data"].as_array()
-                .map(|arr| arr.iter().filter_map(|m| m["id"].as_str().map(|s| s.to_string())).collect())
-                .unwrap_or_default()
-        }
-        _ => return vec![],
-    };
-
-    // Fetch pricing
-    let pricing_map = fetch_pricing_map().await;
-
-    let mut result: Vec<SfModel> = all_models.iter()
-        .filter(|id| is_vision_model(id))
-        .map(|id| {
-            let price = pricing_map.get(id.as_str());
-            let input_price = price.map(|p| p.0).unwrap_or(-1.0);
-            let output_price = price.map(|p| p.1).unwrap_or(-1.0);
-            let is_free = input_price == 0.0 && output_price == 0.0;
-            let is_ocr_only = is_ocr_only_model(id);
-            let pricing = if input_price < 0.0 {
-                "价格未知".to_string()
-            } else if is_free {
-                "免费".to_string()
-            } else {
-                format!("入¥{}/出¥{}", input_price, output_price)
-            };
-            SfModel {
-                id: id.clone(),
-                name: model_id_to_name(id),
-                pricing,
-                modes: if is_ocr_only { vec!["ocr".into()] } else { vec!["formula".into(), "ocr".into()] },
-                input_price: input_price.max(0.0),
-                output_price: output_price.max(0.0),
-                free: is_free,
-            }
-        })
-        .collect();
-
-    result.sort_by(|a, b| {
-        if a.free && !b.free { return std::cmp::Ordering::Less; }
-        if !a.free && b.free { return std::cmp::Ordering::Greater; }
-        a.input_price.partial_cmp(&b.input_price).unwrap_or(std::cmp::Ordering::Equal)
-    });
-    result
+#[derive(Serialize)]
+pub struct RenderResponse {
+    /// SVG markup, or the PNG as base64
+    pub data: String,
+    pub mime: String,
+    /// In pixels of the returned image
+    pub width: u32,
+    pub height: u32,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
-async fn fetch_pricing_map() -> HashMap<String, (f64, f64)> {
-    let mut map = HashMap::new();
-    let client = reqwest::Client::new();
-    let res = match client.get("https://siliconflow.cn/pricing").send().await {
-        Ok(r) if r.status().is_success() => r,
-        _ => return map,
-    };
-    let html = res.text().await.unwrap_or_default();
-    let re = regex_lite::Regex::new(
-        r#"href="[^"]*?target=([^"]+)"[^>]*>([^<]+)</a></div><div[^>]*>(免费|[\d.]+)</div><div[^>]*>(免费|[\d.]+)</div>"#
-    );
-    if let Ok(re) = re {
-        for cap in re.captures_iter(&html) {
-            let id = cap[2].trim().to_string();
-            let inp = if &cap[3] == "免费" { 0.0 } else { cap[3].parse().unwrap_or(-1.0) };
-            let out = if &cap[4] == "免费" { 0.0 } else { cap[4].parse().unwrap_or(-1.0) };
-            if inp >= 0.0 && out >= 0.0 {
-                map.insert(id, (inp, out));
-            }
-        }
-    }
-    map
+#[derive(Serialize)]
+pub struct ConvertResponse {
+    pub output: String,
+    /// Parse problems; the output is still produced, best effort
+    pub diagnostics: Vec<Diagnostic>,
 }
 
-async fn recognize_simpletex(token: &str, image_base64: &str, model_id: &str, rec_mode: Option<&str>) -> Result<(String, f64), String> {
-    let base64_data = image_base64.split(",").last().unwrap_or(image_base64);
-    let image_bytes = base64::engine::general_purpose::STANDARD.decode(base64_data)
-        .map_err(|e| format!("Base64 解码失败: {}", e))?;
-
-    let part = multipart::Part::bytes(image_bytes)
-        .file_name("image.png")
-        .mime_str("image/png").unwrap();
-    let mut form = multipart::Form::new().part("file", part);
-    if let Some(rm) = rec_mode {
-        form = form.text("rec_mode", rm.to_string());
-    }
-
-    let client = reqwest::Client::new();
-    let res = client.post(format!("https://server.simpletex.net/api/{}", model_id))
-        .header("token", token)
-        .multipart(form)
-        .send().await
-        .map_err(|e| format!("SimpleTex 请求失败: {}", e))?;
-
-    if !res.status().is_success() {
-        return Err(format!("SimpleTex API 错误: {}", res.status()));
-    }
-
-    let data: serde_json::Value = res.json().await.map_err(|e| format!("解析响应失败: {}", e))?;
-    if !data["status"].as_bool().unwrap_or(false) {
-        let err_type = data["res"]["errType"].as_str()
-            .or_else(|| data["err_info"]["err_type"].as_str())
-            .or_else(|| data["errType"].as_str())
-            .unwrap_or("unknown");
-        if err_type == "req_unauthorized" {
-            return Err("SimpleTex Token 无效或已过期".into());
-        }
-        if err_type == "resource_no_valid" {
-            return Err("SimpleTex 额度已用完".into());
-        }
-        return Err(format!("SimpleTex 识别失败: {}", err_type));
-    }
-
-    let res_obj = &data["res"];
-    let text = if let Some(s) = res_obj["info"].as_str() {
-        s.to_string()
-    } else if res_obj["info"].is_object() {
-        res_obj["info"]["markdown"].as_str()
-            .or_else(|| res_obj["info"]["text"].as_str())
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| res_obj["info"].to_string())
-    } else {
-        res_obj["markdown"].as_str()
-            .or_else(|| res_obj["latex"].as_str())
-            .unwrap_or("").to_string()
-    };
-
-    let conf = res_obj["conf"].as_f64().unwrap_or(0.0);
-    Ok((text, conf))
+#[derive(Serialize)]
+pub struct BalanceResponse {
+    pub charge_balance: Option<String>,
+    pub total_balance: Option<String>,
+    pub voucher_balance: Option<String>,
 }
 
 // ══════════════════════════════════════════════════════════════
@@ -368,8 +260,6 @@ async fn recognize_simpletex(token: &str, image_base64: &str, model_id: &str, re
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_settings(app: AppHandle) -> Result<SettingsResponse, String> {
-    let st_token = get_store_string(&app, "simpletex_token");
-    let sf_key = get_store_string(&app, "siliconflow_key");
     let simpletex_model = get_store_string(&app, "simpletex_model");
     let simpletex_model = if simpletex_model.is_empty() { "latex_ocr".to_string() } else { simpletex_model };
     let voucher_models = get_store_vec(&app, "voucher_models");
@@ -379,18 +269,14 @@ pub async fn get_settings(app: AppHandle) -> Result<SettingsResponse, String> {
         usage_by_model.insert(id.to_string(), get_model_usage_today(&app, id));
     }
 
-    let (sf_balance, sf_charge_balance) = if !sf_key.is_empty() {
-        match fetch_sf_balance(&sf_key).await {
-            Some((charge, total)) => (Some(total), Some(charge)),
-            None => (None, None),
-        }
-    } else {
-        (None, None)
+    let sf_balance = match providers::find("siliconflow") {
+        Some(p) => p.balance(&app).await,
+        None => None,
     };
 
     Ok(SettingsResponse {
-        has_key: !sf_key.is_empty(),
-        has_simpletex: !st_token.is_empty(),
+        has_key: providers::find("siliconflow").is_some_and(|p| p.is_configured(&app)),
+        has_simpletex: providers::find("simpletex").is_some_and(|p| p.is_configured(&app)),
         simpletex_model,
         simpletex_models: SIMPLETEX_MODELS.iter().map(|&(id, name, free, _)| SimpleTexModelInfo {
             id: id.to_string(),
@@ -398,43 +284,138 @@ pub async fn get_settings(app: AppHandle) -> Result<SettingsResponse, String> {
             free_per_day: free,
         }).collect(),
         simpletex_usage_by_model: usage_by_model,
-        sf_balance,
-        sf_charge_balance,
+        sf_balance: sf_balance.as_ref().map(|b| b.total_balance.clone()),
+        sf_charge_balance: sf_balance.map(|b| b.charge_balance),
         voucher_models,
+        openai_base_url: get_store_string(&app, "openai_base_url"),
+        openai_models: get_store_vec(&app, "openai_models"),
+        local_model_dir: get_store_string(&app, "local_model_dir"),
+        onnxruntime_path: get_store_string(&app, "onnxruntime_path"),
+        pdfium_path: get_store_string(&app, "pdfium_path"),
+        tesseract_path: get_store_string(&app, "tesseract_path"),
+        tesseract_langs: get_store_vec(&app, "tesseract_langs"),
+        fallback_chain: get_store_vec(&app, "fallback_chain"),
+        max_retries: pipeline::max_retries(&app),
+        confidence_threshold: pipeline::confidence_threshold(&app),
+        second_opinion_model: get_store_string(&app, "second_opinion_model"),
+        consensus_models: pipeline::consensus_models(&app),
+        cache_max_entries: cache::max_entries(&app),
+        cache_ttl_days: cache::ttl_days(&app),
+        cache_perceptual: cache::perceptual(&app),
+        preprocess: preprocess::settings(&app),
+        providers: providers::registry().iter().map(|p| ProviderInfo {
+            id: p.id().to_string(),
+            name: p.name().to_string(),
+            configured: p.is_configured(&app),
+        }).collect(),
     })
 }
 
 #[tauri::command(rename_all = "snake_case")]
+#[allow(clippy::too_many_arguments)]
 pub async fn save_settings(
     app: AppHandle,
     simpletex_token: Option<String>,
     siliconflow_key: Option<String>,
     simpletex_model: Option<String>,
     voucher_models_text: Option<String>,
+    openai_base_url: Option<String>,
+    openai_models_text: Option<String>,
+    onnxruntime_path: Option<String>,
+    pdfium_path: Option<String>,
+    tesseract_langs_text: Option<String>,
+    fallback_chain_text: Option<String>,
+    max_retries: Option<u32>,
+    confidence_threshold: Option<f64>,
+    second_opinion_model: Option<String>,
+    consensus_models_text: Option<String>,
+    cache_max_entries: Option<u64>,
+    cache_ttl_days: Option<u64>,
+    cache_perceptual: Option<bool>,
+    preprocess: Option<preprocess::PreprocessSettings>,
+    credentials: Option<HashMap<String, String>>,
 ) -> Result<serde_json::Value, String> {
     let store = app.store("config.json").map_err(|e| e.to_string())?;
     let mut errors: Vec<String> = Vec::new();
 
-    // Validate & save SimpleTex token
-    if let Some(ref token) = simpletex_token {
-        if !token.is_empty() {
-            if validate_simpletex_token(token).await {
-                store.set("simpletex_token", serde_json::json!(token));
-            } else {
-                store.set("simpletex_token", serde_json::json!(""));
-                errors.push("SimpleTex Token 无效，已清除".into());
-            }
+    // Endpoint settings go first so the key is validated against the new URL
+    if let Some(ref url) = openai_base_url {
+        store.set("openai_base_url", serde_json::json!(url.trim().trim_end_matches('/')));
+    }
+    if let Some(ref text) = openai_models_text {
+        let ids: Vec<String> = text.lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect();
+        store.set("openai_models", serde_json::json!(ids));
+    }
+    if let Some(ref path) = onnxruntime_path {
+        store.set("onnxruntime_path", serde_json::json!(path.trim()));
+    }
+    if let Some(ref path) = pdfium_path {
+        store.set("pdfium_path", serde_json::json!(path.trim()));
+    }
+    if let Some(ref text) = tesseract_langs_text {
+        let langs: Vec<String> = text.split(|c: char| c.is_whitespace() || c == ',' || c == '+')
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_string())
+            .collect();
+        store.set("tesseract_langs", serde_json::json!(langs));
+    }
+    if let Some(ref text) = fallback_chain_text {
+        let ids: Vec<String> = text.lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty() && l.contains(':'))
+            .collect();
+        store.set("fallback_chain", serde_json::json!(ids));
+    }
+    if let Some(n) = max_retries {
+        store.set("max_retries", serde_json::json!(n));
+    }
+    if let Some(t) = confidence_threshold {
+        store.set("confidence_threshold", serde_json::json!(t.clamp(0.0, 1.0)));
+    }
+    if let Some(ref id) = second_opinion_model {
+        store.set("second_opinion_model", serde_json::json!(id.trim()));
+    }
+    if let Some(ref text) = consensus_models_text {
+        let mut ids: Vec<String> = Vec::new();
+        for id in text.lines().map(|l| l.trim()).filter(|l| l.contains(':')) {
+            if !ids.iter().any(|x| x == id) { ids.push(id.to_string()); }
         }
+        store.set("consensus_models", serde_json::json!(ids));
     }
-    // Validate & save SiliconFlow key
-    if let Some(ref key) = siliconflow_key {
-        if !key.is_empty() {
-            if validate_siliconflow_key(key).await {
-                store.set("siliconflow_key", serde_json::json!(key));
-            } else {
-                store.set("siliconflow_key", serde_json::json!(""));
-                errors.push("硅基流动 API Key 无效，已清除".into());
-            }
+    if let Some(n) = cache_max_entries {
+        store.set("cache_max_entries", serde_json::json!(n));
+    }
+    if let Some(days) = cache_ttl_days {
+        store.set("cache_ttl_days", serde_json::json!(days));
+    }
+    if let Some(on) = cache_perceptual {
+        store.set("cache_perceptual", serde_json::json!(on));
+    }
+    if let Some(ref steps) = preprocess {
+        store.set("preprocess", serde_json::json!(steps));
+    }
+
+    // Credentials keyed by provider id; the legacy per-provider fields still work
+    let mut credentials = credentials.unwrap_or_default();
+    if let Some(token) = simpletex_token {
+        credentials.insert("simpletex".into(), token);
+    }
+    if let Some(key) = siliconflow_key {
+        credentials.insert("siliconflow".into(), key);
+    }
+
+    // Validate & save each provider's credential
+    for provider in providers::registry() {
+        let Some(value) = credentials.get(provider.id()) else { continue };
+        if value.is_empty() { continue; }
+        if provider.validate_credentials(&app, value).await {
+            store.set(provider.credential_key(), serde_json::json!(value));
+        } else {
+            store.set(provider.credential_key(), serde_json::json!(""));
+            errors.push(format!("{} {} 无效，已清除", provider.name(), provider.credential_label()));
         }
     }
     if let Some(ref model) = simpletex_model {
@@ -455,231 +436,114 @@ pub async fn save_settings(
     }
 }
 
-async fn validate_simpletex_token(token: &str) -> bool {
-    let png_bytes = base64::engine::general_purpose::STANDARD
-        .decode("iVBORw0KGgoAAAANSUhEUgAAADIAAAAyCAIAAACRXR/mAAAASklEQVR4nO3OsQ3AIBAAsd9/abIAzSkFCNkTeNaV5nRgT6vQKrQKrUKr0CqeaM0/WlpaWlpaWlpaWlpaR2gVWoVWoVVoFVrFpa0PK6QKSH2kFl4AAAAASUVORK5CYII=")
-        .unwrap();
-    let part = multipart::Part::bytes(png_bytes).file_name("test.png").mime_str("image/png").unwrap();
-    let form = multipart::Form::new().part("file", part);
-    let client = reqwest::Client::new();
-    match client.post("https://server.simpletex.net/api/latex_ocr_turbo")
-        .header("token", token)
-        .multipart(form)
-        .send().await {
-        Ok(r) => {
-            if r.status().as_u16() == 401 { return false; }
-            let body = r.text().await.unwrap_or_default();
-            if let Ok(val) = serde_json::from_str::<serde_json::Value>(&body) {
-                let err_type = val["res"]["errType"].as_str()
-                    .or_else(|| val["err_info"]["err_type"].as_str())
-                    .unwrap_or("");
-                if err_type == "req_unauthorized" { return false; }
-            }
-            true // 200 or server error (not auth error) = token valid
-        }
-        Err(_) => false,
-    }
-}
-
-async fn validate_siliconflow_key(key: &str) -> bool {
-    let client = reqwest::Client::new();
-    match client.get("https://api.siliconflow.cn/v1/user/info")
-        .header("Authorization", format!("Bearer {}", key))
-        .send().await {
-        Ok(r) => r.status().is_success(),
-        Err(_) => false,
+#[tauri::command(rename_all = "snake_case")]
+pub async fn test_provider(app: AppHandle, provider: String, credential: Option<String>) -> Result<TestResult, String> {
+    let p = providers::find(&provider).ok_or_else(|| format!("未知的服务: {}", provider))?;
+    let stored = p.credential(&app);
+    let use_credential = credential.as_deref().filter(|s| !s.is_empty()).unwrap_or(&stored);
+    if use_credential.is_empty() && !p.is_configured(&app) {
+        return Ok(TestResult { ok: false, error: Some(format!("未填写 {}", p.credential_label())), balance: None });
     }
+    Ok(p.test_credentials(&app, use_credential).await)
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn test_simpletex(app: AppHandle, token: Option<String>) -> Result<TestResult, String> {
-    let stored_token = get_store_string(&app, "simpletex_token");
-    let use_token = token.as_deref().filter(|s| !s.is_empty()).unwrap_or(&stored_token);
-    if use_token.is_empty() {
-        return Ok(TestResult { ok: false, error: Some("未填写 Token".into()), balance: None });
-    }
-
-    // Send a 50x50 PNG (white bg, black square) to latex_ocr_turbo
-    let png_bytes = base64::engine::general_purpose::STANDARD
-        .decode("iVBORw0KGgoAAAANSUhEUgAAADIAAAAyCAIAAACRXR/mAAAASklEQVR4nO3OsQ3AIBAAsd9/abIAzSkFCNkTeNaV5nRgT6vQKrQKrUKr0CqeaM0/WlpaWlpaWlpaWlpaR2gVWoVWoVVoFVrFpa0PK6QKSH2kFl4AAAAASUVORK5CYII=")
-        .unwrap();
-
-    let part = multipart::Part::bytes(png_bytes).file_name("test.png").mime_str("image/png").unwrap();
-    let form = multipart::Form::new().part("file", part);
-
-    let client = reqwest::Client::new();
-    let res = match client.post("https://server.simpletex.net/api/latex_ocr_turbo")
-        .header("token", use_token)
-        .multipart(form)
-        .send().await {
-        Ok(r) => r,
-        Err(e) => return Ok(TestResult { ok: false, error: Some(format!("网络错误: {}", e)), balance: None }),
-    };
-
-    if res.status().as_u16() == 401 {
-        return Ok(TestResult { ok: false, error: Some("Token 无效或已过期".into()), balance: None });
-    }
-    if !res.status().is_success() {
-        let status = res.status().as_u16();
-        let body = res.text().await.unwrap_or_default();
-        // Check for specific error types in both res.errType and err_info.err_type paths
-        if let Ok(val) = serde_json::from_str::<serde_json::Value>(&body) {
-            let err_type = val["res"]["errType"].as_str()
-                .or_else(|| val["err_info"]["err_type"].as_str())
-                .or_else(|| val["errType"].as_str())
-                .unwrap_or("");
-            if err_type == "req_unauthorized" {
-                return Ok(TestResult { ok: false, error: Some("Token 无效或已过期".into()), balance: None });
-            }
-            if err_type == "resource_no_valid" {
-                return Ok(TestResult { ok: false, error: Some("无可用资源（额度已用完）".into()), balance: None });
-            }
-        }
-        return Ok(TestResult { ok: false, error: Some(format!("服务器错误 (HTTP {})", status)), balance: None });
-    }
-
-    let data: serde_json::Value = res.json().await.unwrap_or_default();
-    if !data["status"].as_bool().unwrap_or(false) {
-        // Check both res.errType and err_info.err_type paths
-        let err_type = data["res"]["errType"].as_str()
-            .or_else(|| data["err_info"]["err_type"].as_str())
-            .or_else(|| data["errType"].as_str())
-            .unwrap_or("未知错误");
-        if err_type == "req_unauthorized" {
-            return Ok(TestResult { ok: false, error: Some("Token 无效或已过期".into()), balance: None });
-        }
-        if err_type == "resource_no_valid" {
-            return Ok(TestResult { ok: false, error: Some("无可用资源（额度已用完）".into()), balance: None });
-        }
-        return Ok(TestResult { ok: false, error: Some(err_type.to_string()), balance: None });
-    }
-
-    Ok(TestResult { ok: true, error: None, balance: None })
+    test_provider(app, "simpletex".into(), token).await
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn test_siliconflow(app: AppHandle, api_key: Option<String>) -> Result<TestResult, String> {
-    let stored_key = get_store_string(&app, "siliconflow_key");
-    let use_key = api_key.as_deref().filter(|s| !s.is_empty()).unwrap_or(&stored_key);
-    if use_key.is_empty() {
-        return Ok(TestResult { ok: false, error: Some("未填写 API Key".into()), balance: None });
-    }
-
-    let client = reqwest::Client::new();
-    let res = match client.get("https://api.siliconflow.cn/v1/user/info")
-        .header("Authorization", format!("Bearer {}", use_key))
-        .send().await {
-        Ok(r) => r,
-        Err(e) => return Ok(TestResult { ok: false, error: Some(format!("网络错误: {}", e)), balance: None }),
-    };
-
-    if res.status().as_u16() == 401 {
-        return Ok(TestResult { ok: false, error: Some("API Key 无效".into()), balance: None });
-    }
-    if !res.status().is_success() {
-        return Ok(TestResult { ok: false, error: Some(format!("HTTP {}", res.status())), balance: None });
-    }
-
-    let data: serde_json::Value = res.json().await.unwrap_or_default();
-    let balance = data["data"]["totalBalance"].as_str()
-        .or_else(|| data["data"]["balance"].as_str())
-        .map(|s| s.to_string());
+    test_provider(app, "siliconflow".into(), api_key).await
+}
 
-    Ok(TestResult { ok: true, error: None, balance })
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_tesseract_languages(app: AppHandle, path: Option<String>) -> Result<Vec<String>, String> {
+    let stored = get_store_string(&app, "tesseract_path");
+    providers::tesseract_languages(path.as_deref().unwrap_or(&stored)).await
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_available_models(app: AppHandle) -> Result<AvailableModelsResponse, String> {
-    let st_token = get_store_string(&app, "simpletex_token");
-    let sf_key = get_store_string(&app, "siliconflow_key");
-    let voucher_models = get_store_vec(&app, "voucher_models");
-    let st_valid = !st_token.is_empty();
-    let sf_valid = !sf_key.is_empty();
-
     let mut models: Vec<AvailableModel> = Vec::new();
-
-    // SimpleTex models
-    for &(id, name, free_per_day, st_mode) in SIMPLETEX_MODELS {
-        let modes = if st_mode == "document" {
-            vec!["formula".into(), "ocr".into(), "document".into()]
-        } else {
-            vec![st_mode.to_string()]
-        };
-        models.push(AvailableModel {
-            id: format!("simpletex:{}", id),
-            name: name.to_string(),
-            provider: "SimpleTex".to_string(),
-            modes,
-            available: st_valid,
-            free_per_day: Some(free_per_day),
-            usage_today: Some(get_model_usage_today(&app, id)),
-            pricing: Some(format!("每日免费 {} 次", free_per_day)),
-            free: None,
-            voucher: None,
-            charge_balance: None,
-            total_balance: None,
-        });
+    let mut sf_balance = None;
+
+    for provider in providers::registry() {
+        let balance = if provider.is_configured(&app) { provider.balance(&app).await } else { None };
+        for mut m in provider.list_models(&app).await {
+            if let Some(b) = &balance {
+                m.charge_balance = Some(b.charge_balance.clone());
+                m.total_balance = Some(b.total_balance.clone());
+            }
+            models.push(m);
+        }
+        if provider.id() == "siliconflow" {
+            sf_balance = balance;
+        }
     }
 
-    // SiliconFlow vision models
-    let sf_balance = if sf_valid { fetch_sf_balance(&sf_key).await } else { None };
-    let sf_models = if sf_valid { fetch_sf_vision_models(&sf_key).await } else { vec![] };
+    models.push(AvailableModel {
+        id: pipeline::AUTO_MODEL_ID.to_string(),
+        name: "自动（优先免费额度）".to_string(),
+        provider: "自动".to_string(),
+        modes: vec!["formula".into(), "ocr".into(), "document".into()],
+        available: models.iter().any(|m| m.available),
+        free_per_day: None,
+        usage_today: None,
+        pricing: Some("仅使用免费额度 / 免费模型".to_string()),
+        free: Some(true),
+        voucher: None,
+        charge_balance: None,
+        total_balance: None,
+    });
 
-    for m in &sf_models {
-        let is_voucher = voucher_models.contains(&m.id);
+    let consensus = pipeline::consensus_models(&app);
+    if !consensus.is_empty() {
+        let available = consensus.iter().filter(|id| models.iter().any(|m| &m.id == *id && m.available)).count() >= 2;
         models.push(AvailableModel {
-            id: format!("siliconflow:{}", m.id),
-            name: m.name.clone(),
-            provider: "硅基流动".to_string(),
-            modes: m.modes.clone(),
-            available: sf_valid,
+            id: pipeline::CONSENSUS_MODEL_ID.to_string(),
+            name: format!("多模型投票（{} 个模型）", consensus.len()),
+            provider: "投票".to_string(),
+            modes: vec!["formula".into(), "ocr".into(), "document".into()],
+            available,
             free_per_day: None,
             usage_today: None,
-            pricing: Some(m.pricing.clone()),
-            free: Some(m.free),
-            voucher: Some(is_voucher),
-            charge_balance: sf_balance.as_ref().map(|(c, _)| c.clone()),
-            total_balance: sf_balance.as_ref().map(|(_, t)| t.clone()),
+            pricing: Some("按各模型计费".to_string()),
+            free: None,
+            voucher: None,
+            charge_balance: None,
+            total_balance: None,
         });
     }
 
-    let (sf_bal, sf_charge) = match &sf_balance {
-        Some((c, t)) => (Some(t.clone()), Some(c.clone())),
-        None => (None, None),
-    };
-    let voucher_balance = sf_balance.as_ref().map(|(c, t)| {
-        let total: f64 = t.parse().unwrap_or(0.0);
-        let charge: f64 = c.parse().unwrap_or(0.0);
-        format!("{:.4}", total - charge)
-    });
+    let voucher_balance = sf_balance.as_ref().map(voucher_balance_of);
 
     Ok(AvailableModelsResponse {
         models,
-        sf_balance: sf_bal,
-        sf_charge_balance: sf_charge,
+        sf_balance: sf_balance.as_ref().map(|b| b.total_balance.clone()),
+        sf_charge_balance: sf_balance.map(|b| b.charge_balance),
         voucher_balance,
     })
 }
 
+fn voucher_balance_of(b: &providers::Balance) -> String {
+    let total: f64 = b.total_balance.parse().unwrap_or(0.0);
+    let charge: f64 = b.charge_balance.parse().unwrap_or(0.0);
+    format!("{:.4}", total - charge)
+}
+
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_sf_balance(app: AppHandle) -> Result<BalanceResponse, String> {
-    let sf_key = get_store_string(&app, "siliconflow_key");
-    if sf_key.is_empty() {
-        return Ok(BalanceResponse { charge_balance: None, total_balance: None, voucher_balance: None });
-    }
-    match fetch_sf_balance(&sf_key).await {
-        Some((charge, total)) => {
-            let vc = {
-                let t: f64 = total.parse().unwrap_or(0.0);
-                let c: f64 = charge.parse().unwrap_or(0.0);
-                format!("{:.4}", t - c)
-            };
-            Ok(BalanceResponse {
-                charge_balance: Some(charge),
-                total_balance: Some(total),
-                voucher_balance: Some(vc),
-            })
-        }
+    let balance = match providers::find("siliconflow") {
+        Some(p) => p.balance(&app).await,
+        None => None,
+    };
+    match balance {
+        Some(b) => Ok(BalanceResponse {
+            voucher_balance: Some(voucher_balance_of(&b)),
+            charge_balance: Some(b.charge_balance),
+            total_balance: Some(b.total_balance),
+        }),
         None => Ok(BalanceResponse { charge_balance: None, total_balance: None, voucher_balance: None }),
     }
 }
@@ -695,178 +559,315 @@ pub async fn recognize(
     image: String,
     mode: String,
     model_id: String,
-) -> Result<RecognizeResponse, String> {
-    let (provider, actual_model) = if model_id.contains(':') {
-        let i = model_id.find(':').unwrap();
-        (model_id[..i].to_string(), model_id[i+1..].to_string())
-    } else {
-        // Fallback
-        let st_token = get_store_string(&app, "simpletex_token");
-        if !st_token.is_empty() && mode == "formula" {
-            ("simpletex".to_string(), "latex_ocr".to_string())
-        } else {
-            ("siliconflow".to_string(), String::new())
-        }
-    };
+    force: Option<bool>,
+) -> Result<RecognizeResponse, RecognizeError> {
+    pipeline::recognize(&app, image, &mode, &model_id, force.unwrap_or(false)).await
+}
 
-    if provider == "simpletex" {
-        let token = get_store_string(&app, "simpletex_token");
-        if token.is_empty() {
-            return Err("SimpleTex Token 未配置".into());
-        }
+/// Cuts the image into its formula and text regions and recognizes each one on its own, top to
+/// bottom. A region that fails keeps its error and the others go on, unless the failure would
+/// repeat for every region (missing credentials, quota used up).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn recognize_regions(
+    app: AppHandle,
+    image: String,
+    mode: String,
+    model_id: String,
+    force: Option<bool>,
+) -> Result<RegionsResponse, RecognizeError> {
+    let split = tokio::task::spawn_blocking(move || preprocess::split(&image))
+        .await
+        .map_err(|e| RecognizeError::Engine(e.to_string()))??;
+    if split.regions.is_empty() {
+        return Err(RecognizeError::InvalidInput("图片中没有找到可识别的内容".into()));
+    }
+
+    let mut regions = Vec::new();
+    for (region, crop) in split.regions {
+        let (result, error) = match pipeline::recognize(&app, crop, &mode, &model_id, force.unwrap_or(false)).await {
+            Ok(res) => (Some(res), None),
+            Err(e) if e.affects_all() => return Err(e),
+            Err(e) => (None, Some(e)),
+        };
+        regions.push(RegionResult { region, result, error });
+    }
+    if regions.iter().all(|r| r.result.is_none()) {
+        return Err(regions.swap_remove(0).error.unwrap_or_else(|| RecognizeError::Parse("识别结果为空".into())));
+    }
+    Ok(RegionsResponse { width: split.width, height: split.height, regions })
+}
+
+/// Asks for a PDF to recognize; `None` when the dialog was cancelled.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn pick_pdf(app: AppHandle) -> Result<Option<pdf::PdfInfo>, RecognizeError> {
+    // The dialog blocks until closed
+    tokio::task::spawn_blocking(move || {
+        let Some(path) = app.dialog().file().add_filter("PDF", &["pdf"]).blocking_pick_file() else {
+            return Ok(None);
+        };
+        let path = path.into_path().map_err(|e| RecognizeError::InvalidInput(format!("无效的文件路径: {}", e)))?;
+        pdf::info(&app, &path.to_string_lossy()).map(Some)
+    })
+    .await
+    .map_err(|e| RecognizeError::Engine(e.to_string()))?
+}
 
-        let rec_mode = if actual_model == "simpletex_ocr" {
-            Some(if mode == "formula" { "formula" } else { "document" })
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_pdf_info(app: AppHandle, path: String) -> Result<pdf::PdfInfo, RecognizeError> {
+    tokio::task::spawn_blocking(move || pdf::info(&app, &path))
+        .await
+        .map_err(|e| RecognizeError::Engine(e.to_string()))?
+}
+
+/// Recognizes the `pages` of a PDF (all when empty, e.g. `1-3, 5`) in document mode, rendered at
+/// `dpi`. Progress arrives as `pdf-progress` events.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn recognize_pdf(
+    app: AppHandle,
+    path: String,
+    pages: Option<String>,
+    dpi: Option<u32>,
+    model_id: String,
+) -> Result<pdf::PdfRecognition, RecognizeError> {
+    pdf::recognize(&app, &path, pages.as_deref().unwrap_or(""), dpi.unwrap_or(pdf::DEFAULT_DPI), &model_id).await
+}
+
+/// Asks for the images of a batch: a folder when `folder`, otherwise one or more image files.
+/// Empty when the dialog was cancelled.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn pick_batch_images(app: AppHandle, folder: Option<bool>) -> Result<Vec<String>, String> {
+    // The dialog blocks until closed
+    tokio::task::spawn_blocking(move || {
+        let dialog = app.dialog().file();
+        let picked = if folder.unwrap_or(false) {
+            dialog.blocking_pick_folder().map(|p| vec![p])
         } else {
-            None
+            dialog.add_filter("图片", &["png", "jpg", "jpeg", "webp", "bmp", "gif"]).blocking_pick_files()
         };
+        picked.unwrap_or_default().into_iter()
+            .map(|p| p.into_path().map(|p| p.to_string_lossy().into_owned()).map_err(|e| format!("无效的文件路径: {}", e)))
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-        let (text, _conf) = recognize_simpletex(&token, &image, &actual_model, rec_mode).await?;
-        increment_model_usage(&app, &actual_model);
+/// Queues the images in `sources` (files, or folders whose images are taken by name) and starts
+/// recognizing them, `concurrency` at a time. Progress arrives as `batch-progress` events.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn create_batch(
+    app: AppHandle,
+    sources: Vec<String>,
+    mode: String,
+    model_id: String,
+    concurrency: Option<usize>,
+) -> Result<batch::BatchJob, String> {
+    batch::create(&app, &sources, &mode, &model_id, concurrency.unwrap_or(batch::DEFAULT_CONCURRENCY))
+}
 
-        let model_name = SIMPLETEX_MODELS.iter()
-            .find(|&&(id, _, _, _)| id == actual_model)
-            .map(|&(_, name, _, _)| name)
-            .unwrap_or(&actual_model);
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_batches(app: AppHandle) -> Result<Vec<batch::BatchSummary>, String> {
+    batch::list(&app)
+}
 
-        return Ok(RecognizeResponse {
-            text,
-            model: format!("SimpleTex ({})", model_name),
-            verified: None,
-            corrected: None,
-            original_text: None,
-        });
-    }
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_batch(app: AppHandle, id: String) -> Result<batch::BatchJob, String> {
+    batch::get(&app, &id)
+}
 
-    // SiliconFlow path
-    let sf_key = get_store_string(&app, "siliconflow_key");
-    if sf_key.is_empty() {
-        return Err("请先在设置中配置硅基流动 API Key".into());
-    }
+#[tauri::command(rename_all = "snake_case")]
+pub async fn pause_batch(app: AppHandle, id: String) -> Result<batch::BatchSummary, String> {
+    batch::pause(&app, &id)
+}
 
-    let sf_model = if actual_model.is_empty() {
-        // No model specified, this shouldn't happen normally
-        return Err("未选择模型".into());
-    } else {
-        actual_model.clone()
+#[tauri::command(rename_all = "snake_case")]
+pub async fn resume_batch(app: AppHandle, id: String) -> Result<batch::BatchSummary, String> {
+    batch::resume(&app, &id)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn cancel_batch(app: AppHandle, id: String) -> Result<batch::BatchSummary, String> {
+    batch::cancel(&app, &id)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn delete_batch(app: AppHandle, id: String) -> Result<(), String> {
+    batch::delete(&app, &id)
+}
+
+/// Writes a batch's results to a file picked in a native save dialog: `csv`, `jsonl` or `md`.
+/// Returns the path written, or `None` when the dialog was cancelled.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_batch(app: AppHandle, id: String, format: String) -> Result<Option<String>, String> {
+    let job = batch::get(&app, &id)?;
+    let bytes = batch::export(&job, &format)?;
+    let extension = if format == "markdown" { "md" } else { format.as_str() };
+    let name = format!("批量识别_{}.{}", chrono::Local::now().format("%Y%m%d_%H%M%S"), extension);
+    let filter = match extension {
+        "csv" => "CSV 表格",
+        "jsonl" => "JSON Lines",
+        _ => "Markdown 文档",
     };
+    let extension = extension.to_string();
+    tokio::task::spawn_blocking(move || {
+        let Some(path) = app.dialog().file()
+            .add_filter(filter, &[extension.as_str()])
+            .set_file_name(name)
+            .blocking_save_file()
+        else {
+            return Ok(None);
+        };
+        let path = path.into_path().map_err(|e| format!("无效的保存路径: {}", e))?;
+        std::fs::write(&path, bytes).map_err(|e| format!("写入文件失败: {}", e))?;
+        Ok(Some(path.to_string_lossy().into_owned()))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    let image_url = if image.starts_with("data:") {
-        image.clone()
-    } else {
-        format!("data:image/png;base64,{}", image)
+/// Empties the recognition cache; returns how many results were dropped.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn clear_recognition_cache(app: AppHandle) -> Result<usize, String> {
+    cache::clear(&app)
+}
+
+/// AST of a formula, for tools that want more than the diagnostics.
+#[tauri::command(rename_all = "snake_case")]
+pub fn parse_latex(latex: String) -> latex::Parsed {
+    latex::parse(&latex)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn check_latex(latex: String) -> LatexCheckResponse {
+    let diagnostics = latex::check(&latex);
+    LatexCheckResponse {
+        ok: !diagnostics.iter().any(|d| d.severity == latex::parser::Severity::Error),
+        diagnostics,
+    }
+}
+
+/// Converts a formula to another markup. `format`: `mathml`, `typst`, `asciimath`, `unicodemath`.
+#[tauri::command(rename_all = "snake_case")]
+pub fn convert_latex(latex: String, format: String, display: Option<bool>, word_namespace: Option<bool>) -> Result<ConvertResponse, String> {
+    let parsed = latex::parse(&latex);
+    let output = match format.as_str() {
+        "mathml" => latex::mathml::to_mathml(&parsed.nodes, &latex::mathml::MathmlOptions {
+            display: display.unwrap_or(true),
+            word_namespace: word_namespace.unwrap_or(false),
+        }),
+        "typst" => latex::typst::to_typst(&parsed.nodes, display.unwrap_or(true)),
+        "asciimath" => latex::asciimath::to_asciimath(&parsed.nodes),
+        "unicodemath" => latex::unicodemath::to_unicodemath(&parsed.nodes),
+        _ => return Err(format!("不支持的格式: {}", format)),
     };
+    Ok(ConvertResponse { output, diagnostics: parsed.diagnostics })
+}
 
-    let prompt = get_prompt(&mode);
-
-    let client = reqwest::Client::new();
-
-    // Step 1: Recognize
-    let body = serde_json::json!({
-        "model": sf_model,
-        "messages": [{
-            "role": "user",
-            "content": [
-                { "type": "image_url", "image_url": { "url": image_url, "detail": "high" } },
-                { "type": "text", "text": prompt }
-            ]
-        }],
-        "max_tokens": 4096
-    });
+/// Writes results to a file picked in a native save dialog: `docx`, `tex`, `md`, `html` or `pdf`.
+/// Returns the path written, or `None` when the dialog was cancelled.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_result(app: AppHandle, items: Vec<export::ExportItem>, format: String) -> Result<Option<String>, String> {
+    let format = export::Format::parse(&format).ok_or_else(|| format!("不支持的导出格式: {}", format))?;
+    // The dialog blocks until closed, and PDF layout scans the system fonts on first use
+    tokio::task::spawn_blocking(move || {
+        let bytes = export::build(&items, format)?;
+        let name = format!("识别结果_{}.{}", chrono::Local::now().format("%Y%m%d_%H%M%S"), format.extension());
+        let Some(path) = app.dialog().file()
+            .add_filter(format.description(), &[format.extension()])
+            .set_file_name(name)
+            .blocking_save_file()
+        else {
+            return Ok(None);
+        };
+        let path = path.into_path().map_err(|e| format!("无效的保存路径: {}", e))?;
+        std::fs::write(&path, bytes).map_err(|e| format!("写入文件失败: {}", e))?;
+        Ok(Some(path.to_string_lossy().into_owned()))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    let res = client.post(SILICONFLOW_API_URL)
-        .header("Authorization", format!("Bearer {}", sf_key))
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send().await
-        .map_err(|e| format!("请求失败: {}", e))?;
-
-    if !res.status().is_success() {
-        let status = res.status().as_u16();
-        let err_text = res.text().await.unwrap_or_default();
-        let mut user_msg = format!("API 调用失败: {}", status);
-        if let Ok(err_json) = serde_json::from_str::<serde_json::Value>(&err_text) {
-            let msg = err_json["message"].as_str()
-                .or_else(|| err_json["error"]["message"].as_str())
-                .unwrap_or("");
-            if !msg.is_empty() {
-                if msg.to_lowercase().contains("height") && msg.to_lowercase().contains("width") && msg.to_lowercase().contains("must be larger") {
-                    user_msg = "图片尺寸太小，该模型要求最小 28×28 像素，请使用更大的图片".into();
-                } else {
-                    user_msg = msg.to_string();
-                }
+/// Renders a formula to an image. `format`: `svg` or `png`; `dpi` only applies to PNG, 96 being 1:1.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn render_latex(
+    latex: String,
+    format: String,
+    font_size: Option<f32>,
+    dpi: Option<f32>,
+    color: Option<String>,
+    background: Option<String>,
+    display: Option<bool>,
+) -> Result<RenderResponse, String> {
+    // The first render scans the system fonts
+    tokio::task::spawn_blocking(move || {
+        let parsed = latex::parse(&latex);
+        let svg = latex::svg::to_svg(&parsed.nodes, &latex::svg::RenderOptions {
+            font_size: font_size.unwrap_or(32.0).clamp(4.0, 512.0),
+            display: display.unwrap_or(true),
+            color: color.unwrap_or_else(|| "#000000".to_string()),
+            background,
+        })?;
+        let (data, mime, scale) = match format.as_str() {
+            "svg" => (svg.markup.clone(), "image/svg+xml", 1.0),
+            "png" => {
+                let scale = (dpi.unwrap_or(192.0) / 96.0).clamp(0.25, 8.0);
+                (base64::engine::general_purpose::STANDARD.encode(latex::svg::to_png(&svg, scale)?), "image/png", scale)
             }
-        }
-        return Err(user_msg);
-    }
+            _ => return Err(format!("不支持的格式: {}", format)),
+        };
+        Ok(RenderResponse {
+            data,
+            mime: mime.to_string(),
+            width: (svg.width * scale).ceil() as u32,
+            height: (svg.height * scale).ceil() as u32,
+            diagnostics: parsed.diagnostics,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
 
-    let data1: serde_json::Value = res.json().await.map_err(|e| format!("解析响应失败: {}", e))?;
-    let text1 = data1["choices"][0]["message"]["content"].as_str().unwrap_or("").trim().to_string();
+// ── History ──
 
-    if text1.is_empty() {
-        return Ok(RecognizeResponse {
-            text: String::new(),
-            model: sf_model,
-            verified: Some(false),
-            corrected: None,
-            original_text: None,
-        });
-    }
+/// Saves a recognition with its image (a data URL) and returns the stored entry.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn add_history(app: AppHandle, record: history::HistoryRecord, image: Option<String>) -> Result<history::HistoryEntry, String> {
+    history::add(&app, record, image.as_deref())
+}
 
-    // Step 2: Verify — only for formula mode
-    if mode == "formula" {
-        let verify_prompt = format!(
-            "请对照图片检查以下LaTeX公式是否正确。如果正确，原样返回该公式；如果有错误，返回修正后的公式。只返回最终的纯LaTeX代码，不要解释。\n\n识别结果：{}",
-            text1
-        );
-
-        let verify_body = serde_json::json!({
-            "model": sf_model,
-            "messages": [{
-                "role": "user",
-                "content": [
-                    { "type": "image_url", "image_url": { "url": image_url, "detail": "high" } },
-                    { "type": "text", "text": verify_prompt }
-                ]
-            }],
-            "max_tokens": 4096
-        });
+/// A page of the history, newest first, without the images.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_history(app: AppHandle, query: Option<history::HistoryQuery>) -> Result<history::HistoryPage, String> {
+    history::list(&app, query.unwrap_or_default())
+}
 
-        let verify_res = client.post(SILICONFLOW_API_URL)
-            .header("Authorization", format!("Bearer {}", sf_key))
-            .header("Content-Type", "application/json")
-            .json(&verify_body)
-            .send().await;
-
-        if let Ok(vr) = verify_res {
-            if vr.status().is_success() {
-                if let Ok(data2) = vr.json::<serde_json::Value>().await {
-                    let text2 = data2["choices"][0]["message"]["content"].as_str().unwrap_or("").trim().to_string();
-                    if !text2.is_empty() {
-                        let n1 = text1.split_whitespace().collect::<Vec<_>>().join(" ");
-                        let n2 = text2.split_whitespace().collect::<Vec<_>>().join(" ");
-                        let verified = n1 == n2;
-                        let corrected = !verified;
-                        let final_text = if corrected { text2.clone() } else { text1.clone() };
-                        return Ok(RecognizeResponse {
-                            text: final_text,
-                            model: sf_model,
-                            verified: Some(verified),
-                            corrected: Some(corrected),
-                            original_text: if corrected { Some(text1) } else { None },
-                        });
-                    }
-                }
-            }
-        }
-    }
+/// Like `list_history`, narrowed by text, formula, mode, model and date filters.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn search_history(app: AppHandle, query: history::HistorySearch) -> Result<history::HistoryPage, String> {
+    tokio::task::spawn_blocking(move || history::search(&app, &query)).await.map_err(|e| e.to_string())?
+}
 
-    // Non-formula mode or verify failed — return first result
-    Ok(RecognizeResponse {
-        text: text1,
-        model: sf_model,
-        verified: if mode == "formula" { Some(false) } else { None },
-        corrected: None,
-        original_text: None,
-    })
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_history(app: AppHandle, ids: Vec<String>) -> Result<Vec<history::HistoryEntry>, String> {
+    history::get(&app, &ids)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_history_image(app: AppHandle, id: String) -> Result<Option<String>, String> {
+    history::image(&app, &id)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_history_favorite(app: AppHandle, id: String, favorite: bool) -> Result<history::HistoryEntry, String> {
+    history::set_favorite(&app, &id, favorite)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn delete_history(app: AppHandle, ids: Vec<String>) -> Result<usize, String> {
+    history::delete(&app, &ids)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn clear_history(app: AppHandle, keep_favorites: Option<bool>) -> Result<usize, String> {
+    history::clear(&app, keep_favorites.unwrap_or(false))
 }
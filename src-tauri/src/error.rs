@@ -0,0 +1,119 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// Why a recognition call failed.
+///
+/// Serialized to the frontend as `{ code, message, ... }`: `code` is stable for scripts to branch on,
+/// `message` is the localized text shown to the user.
+#[derive(Debug, Clone)]
+pub enum RecognizeError {
+    /// Token / API key missing the provider's approval
+    Unauthorized(String),
+    /// Daily free quota or account balance used up
+    QuotaExhausted(String),
+    ImageTooSmall { min_width: u32, min_height: u32 },
+    Network(String),
+    RateLimited(String),
+    ProviderError { status: u16, body: String },
+    Parse(String),
+    /// Credential, endpoint or model missing in settings
+    NotConfigured(String),
+    /// The image or request could not be used as given
+    InvalidInput(String),
+    /// A local engine (ONNX Runtime, Tesseract) failed
+    Engine(String),
+}
+
+impl RecognizeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RecognizeError::Unauthorized(_) => "unauthorized",
+            RecognizeError::QuotaExhausted(_) => "quota_exhausted",
+            RecognizeError::ImageTooSmall { .. } => "image_too_small",
+            RecognizeError::Network(_) => "network",
+            RecognizeError::RateLimited(_) => "rate_limited",
+            RecognizeError::ProviderError { .. } => "provider_error",
+            RecognizeError::Parse(_) => "parse",
+            RecognizeError::NotConfigured(_) => "not_configured",
+            RecognizeError::InvalidInput(_) => "invalid_input",
+            RecognizeError::Engine(_) => "engine",
+        }
+    }
+
+    /// Worth retrying the same model after a short wait.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            RecognizeError::Network(_) | RecognizeError::RateLimited(_) => true,
+            RecognizeError::ProviderError { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Would fail the same way for any other image, so a job over several images stops.
+    pub fn affects_all(&self) -> bool {
+        matches!(self, RecognizeError::Unauthorized(_) | RecognizeError::QuotaExhausted(_) | RecognizeError::NotConfigured(_))
+    }
+
+    /// Maps a failed HTTP status to the matching variant; `body` is the provider's error text.
+    pub fn from_status(provider: &str, status: u16, body: String) -> Self {
+        match status {
+            401 => RecognizeError::Unauthorized(format!("{} 认证失败，请检查 Token / API Key", provider)),
+            429 => RecognizeError::RateLimited(format!("{} 请求过于频繁，请稍后再试", provider)),
+            _ => RecognizeError::ProviderError { status, body },
+        }
+    }
+}
+
+impl fmt::Display for RecognizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecognizeError::Unauthorized(msg)
+            | RecognizeError::QuotaExhausted(msg)
+            | RecognizeError::RateLimited(msg)
+            | RecognizeError::NotConfigured(msg)
+            | RecognizeError::InvalidInput(msg)
+            | RecognizeError::Engine(msg) => write!(f, "{}", msg),
+            RecognizeError::ImageTooSmall { min_width, min_height } => {
+                write!(f, "图片尺寸太小，该模型要求最小 {}×{} 像素，请使用更大的图片", min_width, min_height)
+            }
+            RecognizeError::Network(msg) => write!(f, "请求失败: {}", msg),
+            RecognizeError::ProviderError { status, body } => {
+                if body.is_empty() { write!(f, "API 调用失败: {}", status) } else { write!(f, "{}", body) }
+            }
+            RecognizeError::Parse(msg) => write!(f, "解析响应失败: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RecognizeError {}
+
+impl Serialize for RecognizeError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("RecognizeError", 4)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        match self {
+            RecognizeError::ProviderError { status, body } => {
+                s.serialize_field("status", status)?;
+                s.serialize_field("body", body)?;
+            }
+            RecognizeError::ImageTooSmall { min_width, min_height } => {
+                s.serialize_field("min_width", min_width)?;
+                s.serialize_field("min_height", min_height)?;
+            }
+            _ => {}
+        }
+        s.end()
+    }
+}
+
+impl From<reqwest::Error> for RecognizeError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            RecognizeError::Parse(e.to_string())
+        } else {
+            RecognizeError::Network(e.to_string())
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! `.docx` written directly: WordprocessingML with the formulas as OMML equations, so they stay
+//! editable in Word and WPS.
+
+use std::io::{Cursor, Write};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::markdown::{Block, Inline};
+use super::{blocks, item_heading, timestamp, title, ExportItem};
+use crate::latex;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/><Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/></Types>"#;
+
+const PACKAGE_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>"#;
+
+const DOCUMENT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+const STYLES_HEAD: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii="Times New Roman" w:hAnsi="Times New Roman" w:eastAsia="Microsoft YaHei" w:cs="Times New Roman"/><w:sz w:val="24"/><w:szCs w:val="24"/><w:lang w:val="en-US" w:eastAsia="zh-CN"/></w:rPr></w:rPrDefault><w:pPrDefault><w:pPr><w:spacing w:after="100" w:line="300" w:lineRule="auto"/></w:pPr></w:pPrDefault></w:docDefaults><w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/><w:qFormat/></w:style>"#;
+
+const DOCUMENT_HEAD: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:m="http://schemas.openxmlformats.org/officeDocument/2006/math" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><w:body>"#;
+
+/// A4 with 2.54 cm margins.
+const SECTION: &str = r#"<w:sectPr><w:pgSz w:w="11906" w:h="16838"/><w:pgMar w:top="1440" w:right="1440" w:bottom="1440" w:left="1440" w:header="851" w:footer="992" w:gutter="0"/></w:sectPr>"#;
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Heading 1–6, sized like Word's built-in ones.
+fn styles() -> String {
+    let mut out = STYLES_HEAD.to_string();
+    for (level, size) in [(1, 32), (2, 28), (3, 26), (4, 24), (5, 24), (6, 24)] {
+        out.push_str(&format!(
+            r#"<w:style w:type="paragraph" w:styleId="Heading{0}"><w:name w:val="heading {0}"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="240" w:after="120"/><w:outlineLvl w:val="{1}"/></w:pPr><w:rPr><w:b/><w:sz w:val="{2}"/><w:szCs w:val="{2}"/></w:rPr></w:style>"#,
+            level, level - 1, size,
+        ));
+    }
+    out.push_str("</w:styles>");
+    out
+}
+
+fn run(text: &str, props: &str) -> String {
+    let props = if props.is_empty() { String::new() } else { format!("<w:rPr>{}</w:rPr>", props) };
+    format!(r#"<w:r>{}<w:t xml:space="preserve">{}</w:t></w:r>"#, props, escape(text))
+}
+
+fn paragraph(props: &str, content: &str) -> String {
+    let props = if props.is_empty() { String::new() } else { format!("<w:pPr>{}</w:pPr>", props) };
+    format!("<w:p>{}{}</w:p>", props, content)
+}
+
+fn equation(latex: &str, display: bool) -> String {
+    latex::omml::to_omml(&latex::parse(latex).nodes, display)
+}
+
+fn inlines(items: &[Inline], bold: bool) -> String {
+    items.iter().map(|item| match item {
+        Inline::Text { text, bold: b } => run(text, if bold || *b { "<w:b/>" } else { "" }),
+        Inline::Math { latex, display } => equation(latex, *display),
+    }).collect()
+}
+
+/// One paragraph in a monospace font, the lines joined with breaks.
+fn code(text: &str) -> String {
+    let font = r#"<w:rFonts w:ascii="Consolas" w:hAnsi="Consolas" w:eastAsia="Microsoft YaHei" w:cs="Consolas"/><w:sz w:val="20"/><w:szCs w:val="20"/>"#;
+    let lines: Vec<String> = text.split('\n').map(|line| run(line, font)).collect();
+    paragraph(r#"<w:shd w:val="clear" w:color="auto" w:fill="F3F3F3"/><w:spacing w:after="200" w:line="240" w:lineRule="auto"/>"#, &lines.join("<w:r><w:br/></w:r>"))
+}
+
+fn table(rows: &[Vec<Vec<Inline>>], header: bool) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 { return String::new(); }
+    let border = |side: &str| format!(r#"<w:{} w:val="single" w:sz="4" w:space="0" w:color="auto"/>"#, side);
+    let mut out = format!(
+        r#"<w:tbl><w:tblPr><w:tblW w:w="0" w:type="auto"/><w:tblBorders>{}</w:tblBorders><w:tblCellMar><w:left w:w="100" w:type="dxa"/><w:right w:w="100" w:type="dxa"/></w:tblCellMar></w:tblPr><w:tblGrid>{}</w:tblGrid>"#,
+        ["top", "left", "bottom", "right", "insideH", "insideV"].map(border).concat(),
+        "<w:gridCol/>".repeat(columns),
+    );
+    for (i, row) in rows.iter().enumerate() {
+        let is_header = header && i == 0;
+        out.push_str("<w:tr>");
+        if is_header { out.push_str("<w:trPr><w:tblHeader/></w:trPr>"); }
+        // Word wants every row to fill the grid, and a paragraph in every cell
+        for j in 0..columns {
+            let content = row.get(j).map(|cell| inlines(cell, is_header)).unwrap_or_default();
+            out.push_str(&format!(
+                r#"<w:tc><w:tcPr><w:tcW w:w="0" w:type="auto"/></w:tcPr>{}</w:tc>"#,
+                paragraph(r#"<w:spacing w:after="0"/>"#, &content),
+            ));
+        }
+        out.push_str("</w:tr>");
+    }
+    out.push_str("</w:tbl>");
+    out
+}
+
+fn body(items: &[ExportItem]) -> String {
+    let mut out = paragraph(
+        r#"<w:jc w:val="center"/><w:spacing w:after="300"/>"#,
+        &run(title(items), r#"<w:b/><w:sz w:val="32"/><w:szCs w:val="32"/>"#),
+    );
+    out.push_str(&paragraph(
+        r#"<w:spacing w:after="200"/>"#,
+        &run(&timestamp(), r#"<w:color w:val="888888"/><w:sz w:val="18"/>"#),
+    ));
+
+    for item in items {
+        if let Some(heading) = item_heading(items, item) {
+            out.push_str(&paragraph(r#"<w:pStyle w:val="Heading1"/>"#, &run(&heading, "")));
+        }
+        for block in blocks(item) {
+            out.push_str(&match block {
+                Block::Heading(level, spans) => paragraph(&format!(r#"<w:pStyle w:val="Heading{}"/>"#, level), &inlines(&spans, false)),
+                Block::Paragraph(spans) => paragraph("", &inlines(&spans, false)),
+                Block::Math(latex) => paragraph("", &equation(&latex, true)),
+                Block::Code(text) => code(&text),
+                // The paragraph keeps the next table or heading from sticking to this one
+                Block::Table { rows, header } => table(&rows, header) + &paragraph("", ""),
+            });
+        }
+    }
+    out
+}
+
+pub fn build(items: &[ExportItem]) -> Result<Vec<u8>, String> {
+    let document = format!("{}{}{}</w:body></w:document>", DOCUMENT_HEAD, body(items), SECTION);
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES.to_string()),
+        ("_rels/.rels", PACKAGE_RELS.to_string()),
+        ("word/_rels/document.xml.rels", DOCUMENT_RELS.to_string()),
+        ("word/styles.xml", styles()),
+        ("word/document.xml", document),
+    ];
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, xml) in parts {
+        zip.start_file(name, options).map_err(|e| format!("写入 {} 失败: {}", name, e))?;
+        zip.write_all(xml.as_bytes()).map_err(|e| format!("写入 {} 失败: {}", name, e))?;
+    }
+    let cursor = zip.finish().map_err(|e| format!("生成 docx 失败: {}", e))?;
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(content: &str) -> String {
+        body(&[ExportItem { content: content.into(), mode: "document".into(), title: None }])
+    }
+
+    #[test]
+    fn inline_and_display_math() {
+        let out = document("Let $x$ be\n\n$$y$$");
+        let x = "<m:oMath><m:r><m:t xml:space=\"preserve\">x</m:t></m:r></m:oMath>";
+        let y = "<m:oMathPara><m:oMath><m:r><m:t xml:space=\"preserve\">y</m:t></m:r></m:oMath></m:oMathPara>";
+        assert!(out.contains(&format!("{}{}{}", run("Let ", ""), x, run(" be", ""))), "{}", out);
+        assert!(!out.contains(&format!("<m:oMathPara>{}", x)));
+        assert!(out.contains(&paragraph("", y)), "{}", out);
+    }
+
+    #[test]
+    fn formula_mode_is_display() {
+        let out = body(&[ExportItem { content: "x".into(), mode: "formula".into(), title: None }]);
+        assert!(out.contains("<w:p><m:oMathPara><m:oMath>"), "{}", out);
+    }
+
+    #[test]
+    fn table_rows_fill_the_grid() {
+        let out = document("| a | b | c |\n|---|---|---|\n| 1 |\n| 2 | 3 |");
+        let start = out.find("<w:tbl>").expect("no table");
+        let table = &out[start..out.find("</w:tbl>").unwrap()];
+        assert_eq!(table.matches("<w:gridCol/>").count(), 3);
+        assert_eq!(table.matches("<w:tr>").count(), 3);
+        assert_eq!(table.matches("<w:tc>").count(), 9);
+        assert_eq!(table.matches("<w:tblHeader/>").count(), 1);
+        // Every cell has a paragraph, the padded ones too
+        assert_eq!(table.matches("<w:p>").count(), 9);
+    }
+}
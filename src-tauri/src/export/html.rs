@@ -0,0 +1,107 @@
+//! A single HTML file with the formulas as MathML, which browsers render natively: no scripts,
+//! fonts or stylesheets to ship alongside it.
+
+use super::markdown::{Block, Inline};
+use super::{blocks, item_heading, timestamp, title, ExportItem};
+use crate::latex::{self, mathml::MathmlOptions};
+
+const STYLE: &str = "body{max-width:50em;margin:2em auto;padding:0 1.5em;font:16px/1.7 'Times New Roman','Microsoft YaHei',serif;color:#222}\
+h1.title{text-align:center}.time{color:#888;font-size:.8em}\
+math[display=block]{margin:.8em 0}\
+pre{background:#f6f6f6;padding:.6em .8em;overflow-x:auto;font:14px/1.5 Consolas,Menlo,monospace}\
+table{border-collapse:collapse;margin:1em 0}th,td{border:1px solid #999;padding:.3em .6em}th{background:#f3f3f3}";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn math(src: &str, display: bool) -> String {
+    latex::mathml::to_mathml(&latex::parse(src).nodes, &MathmlOptions { display, word_namespace: false })
+}
+
+fn inlines(items: &[Inline]) -> String {
+    items.iter().map(|item| match item {
+        Inline::Text { text, bold: true } => format!("<strong>{}</strong>", escape(text)),
+        Inline::Text { text, bold: false } => escape(text),
+        Inline::Math { latex, display } => math(latex, *display),
+    }).collect()
+}
+
+fn table(rows: &[Vec<Vec<Inline>>], header: bool) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 { return String::new(); }
+    let mut out = String::from("<table>\n");
+    for (i, row) in rows.iter().enumerate() {
+        let tag = if header && i == 0 { "th" } else { "td" };
+        out.push_str("<tr>");
+        // Short rows get empty cells so the borders run through
+        for j in 0..columns {
+            let content = row.get(j).map(|cell| inlines(cell)).unwrap_or_default();
+            out.push_str(&format!("<{0}>{1}</{0}>", tag, content));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+    out
+}
+
+pub fn build(items: &[ExportItem]) -> String {
+    let title = title(items);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n<style>{1}</style>\n</head>\n<body>\n<h1 class=\"title\">{0}</h1>\n<p class=\"time\">{2}</p>\n",
+        escape(title), STYLE, escape(&timestamp()),
+    );
+    for item in items {
+        if let Some(heading) = item_heading(items, item) {
+            out.push_str(&format!("<h2>{}</h2>\n", escape(&heading)));
+        }
+        for block in blocks(item) {
+            out.push_str(&match block {
+                // Below the document and item titles
+                Block::Heading(level, spans) => format!("<h{0}>{1}</h{0}>\n", (level + 1).min(6), inlines(&spans)),
+                Block::Paragraph(spans) => format!("<p>{}</p>\n", inlines(&spans)),
+                Block::Math(latex) => math(&latex, true) + "\n",
+                Block::Code(code) => format!("<pre><code>{}</code></pre>\n", escape(&code)),
+                Block::Table { rows, header } => table(&rows, header),
+            });
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(content: &str) -> String {
+        build(&[ExportItem { content: content.into(), mode: "document".into(), title: None }])
+    }
+
+    #[test]
+    fn formulas_are_mathml() {
+        let out = document("Let $x$ be\n\n$$y$$");
+        assert!(out.contains("<p>Let <math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"inline\"><mi>x</mi></math> be</p>\n"), "{}", out);
+        assert!(out.contains("<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\"><mi>y</mi></math>\n"), "{}", out);
+        assert!(!out.contains('$'));
+
+        let formula = build(&[ExportItem { content: "a<b".into(), mode: "formula".into(), title: None }]);
+        assert!(formula.contains("display=\"block\"><mrow><mi>a</mi><mo>&lt;</mo><mi>b</mi></mrow></math>"), "{}", formula);
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        let out = build(&[ExportItem { content: "<script>alert(\"x\")</script> & more".into(), mode: "ocr".into(), title: Some("a<b".into()) }]);
+        assert!(out.contains("<p>&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt; &amp; more</p>"), "{}", out);
+        assert!(!out.contains("<script>"));
+
+        let code = document("```\nif a < b && c\n```");
+        assert!(code.contains("<pre><code>if a &lt; b &amp;&amp; c</code></pre>"), "{}", code);
+    }
+
+    #[test]
+    fn table_rows_fill_the_columns() {
+        let out = document("| a | b | c |\n|---|---|---|\n| 1 |\n| 2 | 3 |");
+        assert!(out.contains("<tr><th>a</th><th>b</th><th>c</th></tr>\n<tr><td>1</td><td></td><td></td></tr>\n<tr><td>2</td><td>3</td><td></td></tr>\n"), "{}", out);
+    }
+}
@@ -0,0 +1,214 @@
+//! The Markdown subset the recognition models produce: headings, paragraphs, lists, pipe tables,
+//! fenced code blocks, and formulas between `$`/`$$` or `\(`/`\[`.
+
+pub enum Inline {
+    Text { text: String, bold: bool },
+    Math { latex: String, display: bool },
+}
+
+pub enum Block {
+    Heading(usize, Vec<Inline>),
+    Paragraph(Vec<Inline>),
+    /// A display formula on its own lines
+    Math(String),
+    /// The lines between ```` ``` ```` or `~~~` fences, as written
+    Code(String),
+    /// Rows of cells; `header` when the second line was a `|---|` separator
+    Table { rows: Vec<Vec<Vec<Inline>>>, header: bool },
+}
+
+/// Splits a line into text and formulas; `\$` is a literal dollar and `**` toggles bold.
+pub fn inlines(line: &str) -> Vec<Inline> {
+    let mut out = Vec::new();
+    let mut text = String::new();
+    let mut bold = false;
+    let mut rest = line;
+    let flush = |text: &mut String, out: &mut Vec<Inline>, bold: bool| {
+        if !text.is_empty() { out.push(Inline::Text { text: std::mem::take(text), bold }); }
+    };
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("\\$") {
+            text.push('$');
+            rest = after;
+            continue;
+        }
+        let math = [("$$", "$$", true), ("\\[", "\\]", true), ("$", "$", false), ("\\(", "\\)", false)]
+            .into_iter()
+            .find_map(|(open, close, display)| {
+                let body = rest.strip_prefix(open)?;
+                let end = find_closing(body, close)?;
+                Some((body[..end].trim().to_string(), display, &body[end + close.len()..]))
+            });
+        if let Some((latex, display, after)) = math {
+            if !latex.is_empty() {
+                flush(&mut text, &mut out, bold);
+                out.push(Inline::Math { latex, display });
+                rest = after;
+                continue;
+            }
+        }
+        if let Some(after) = rest.strip_prefix("**") {
+            flush(&mut text, &mut out, bold);
+            bold = !bold;
+            rest = after;
+            continue;
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    flush(&mut text, &mut out, bold);
+    out
+}
+
+/// Byte offset of `close` in `s`, skipping escaped dollars.
+fn find_closing(s: &str, close: &str) -> Option<usize> {
+    let mut i = 0;
+    while i < s.len() {
+        if s[i..].starts_with('\\') && s[i + 1..].starts_with('$') {
+            i += 2;
+            continue;
+        }
+        if s[i..].starts_with(close) { return Some(i); }
+        i += s[i..].chars().next().map_or(1, char::len_utf8);
+    }
+    None
+}
+
+/// Cells of a `| a | b |` row; pipes inside formulas do not split.
+fn cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    let mut out = Vec::new();
+    let mut cell = String::new();
+    let mut in_math = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some_and(|n| *n == '|' || *n == '$') => {
+                let n = chars.next().unwrap_or_default();
+                if n == '$' || in_math { cell.push('\\'); }
+                cell.push(n);
+            }
+            '$' => {
+                in_math = !in_math;
+                cell.push(c);
+            }
+            '|' if !in_math => out.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    out.push(cell.trim().to_string());
+    out
+}
+
+fn is_separator(line: &str) -> bool {
+    let cells = cells(line);
+    !cells.is_empty() && cells.iter().all(|c| {
+        let c = c.trim_matches(':');
+        !c.is_empty() && c.chars().all(|ch| ch == '-')
+    })
+}
+
+pub fn blocks(src: &str) -> Vec<Block> {
+    let lines: Vec<&str> = src.lines().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        i += 1;
+        if line.is_empty() { continue; }
+
+        // Fenced code, kept verbatim up to a closing fence at least as long
+        if let Some(marker) = ['`', '~'].into_iter().find(|m| line.starts_with(&m.to_string().repeat(3))) {
+            let fence = line.chars().take_while(|c| *c == marker).count();
+            let mut body = Vec::new();
+            while i < lines.len() {
+                let l = lines[i];
+                i += 1;
+                let t = l.trim();
+                if t.chars().take_while(|c| *c == marker).count() >= fence && t.trim_start_matches(marker).is_empty() { break; }
+                body.push(l);
+            }
+            out.push(Block::Code(body.join("\n")));
+            continue;
+        }
+
+        // Display math spanning lines: `$$` ... `$$` or `\[` ... `\]`
+        let fence = [("$$", "$$"), ("\\[", "\\]")].into_iter().find(|(open, _)| line.starts_with(open));
+        if let Some((open, close)) = fence {
+            let first = &line[open.len()..];
+            if let Some(end) = first.find(close) {
+                if first[end + close.len()..].trim().is_empty() {
+                    out.push(Block::Math(first[..end].trim().to_string()));
+                    continue;
+                }
+            } else {
+                let mut body = vec![first];
+                while i < lines.len() {
+                    let l = lines[i].trim();
+                    i += 1;
+                    if let Some(end) = l.find(close) {
+                        body.push(&l[..end]);
+                        break;
+                    }
+                    body.push(l);
+                }
+                out.push(Block::Math(body.join("\n").trim().to_string()));
+                continue;
+            }
+        }
+
+        if line.starts_with('|') {
+            let mut rows = vec![line];
+            while i < lines.len() && lines[i].trim().starts_with('|') {
+                rows.push(lines[i].trim());
+                i += 1;
+            }
+            let header = rows.len() > 1 && is_separator(rows[1]);
+            let rows = rows.into_iter()
+                .filter(|r| !is_separator(r))
+                .map(|r| cells(r).iter().map(|c| inlines(c)).collect())
+                .collect();
+            out.push(Block::Table { rows, header });
+            continue;
+        }
+
+        let level = line.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+            out.push(Block::Heading(level, inlines(line[level..].trim())));
+            continue;
+        }
+        let text = if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ ")) {
+            format!("• {}", item)
+        } else if let Some(quote) = line.strip_prefix('>') {
+            quote.trim().to_string()
+        } else {
+            line.to_string()
+        };
+        out.push(Block::Paragraph(inlines(&text)));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fenced_code_is_verbatim() {
+        let src = "Before $x$\n\n```python\ndef f(x):\n    return x ** 2  # $not math$\n```\n~~~~\n```\n~~~~\nAfter";
+        let blocks = blocks(src);
+        assert_eq!(blocks.len(), 4);
+        assert!(matches!(&blocks[1], Block::Code(code) if code == "def f(x):\n    return x ** 2  # $not math$"));
+        // A shorter or different fence inside does not close the block
+        assert!(matches!(&blocks[2], Block::Code(code) if code == "```"));
+        assert!(matches!(&blocks[3], Block::Paragraph(_)));
+    }
+
+    #[test]
+    fn unclosed_fence_runs_to_the_end() {
+        let blocks = blocks("```\n| a | b |\n# not a heading");
+        assert!(matches!(&blocks[..], [Block::Code(code)] if code == "| a | b |\n# not a heading"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Saving results as files. Formulas are converted here rather than in the webview, so the
+//! exported documents carry native equations instead of LaTeX source.
+
+pub mod docx;
+pub mod html;
+mod markdown;
+pub mod pdf;
+pub mod tex;
+
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::latex;
+use markdown::{Block, Inline};
+
+/// One result to export: the current one, or an entry picked from the history.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportItem {
+    pub content: String,
+    pub mode: String,
+    /// Section heading when several items go into one file; the mode's title by default
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Docx,
+    Tex,
+    Markdown,
+    Html,
+    Pdf,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Format> {
+        Some(match s {
+            "docx" => Format::Docx,
+            "tex" | "latex" => Format::Tex,
+            "md" | "markdown" => Format::Markdown,
+            "html" => Format::Html,
+            "pdf" => Format::Pdf,
+            _ => return None,
+        })
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Docx => "docx",
+            Format::Tex => "tex",
+            Format::Markdown => "md",
+            Format::Html => "html",
+            Format::Pdf => "pdf",
+        }
+    }
+
+    /// Name of the file type in the save dialog.
+    pub fn description(self) -> &'static str {
+        match self {
+            Format::Docx => "Word 文档",
+            Format::Tex => "LaTeX 文档",
+            Format::Markdown => "Markdown 文档",
+            Format::Html => "HTML 网页",
+            Format::Pdf => "PDF 文档",
+        }
+    }
+}
+
+fn mode_title(mode: &str) -> &'static str {
+    match mode {
+        "formula" => "公式识别结果",
+        "document" => "通用识别结果",
+        _ => "OCR识别结果",
+    }
+}
+
+/// The document title: the mode's title for one item, a generic one for several.
+fn title(items: &[ExportItem]) -> &'static str {
+    match items {
+        [item] => mode_title(&item.mode),
+        _ => "识别结果汇总",
+    }
+}
+
+/// The heading above an item; only files with several items have one.
+fn item_heading(items: &[ExportItem], item: &ExportItem) -> Option<String> {
+    (items.len() > 1).then(|| item.title.clone().unwrap_or_else(|| mode_title(&item.mode).to_string()))
+}
+
+fn timestamp() -> String {
+    format!("识别时间: {}", Local::now().format("%Y-%m-%d %H:%M:%S"))
+}
+
+/// The content of a result as blocks: one display formula in formula mode, Markdown in document
+/// mode, and a paragraph per line for plain text.
+fn blocks(item: &ExportItem) -> Vec<Block> {
+    match item.mode.as_str() {
+        "formula" => vec![Block::Math(item.content.trim().to_string())],
+        "document" => markdown::blocks(&item.content),
+        _ => item.content.lines()
+            .map(|line| Block::Paragraph(vec![Inline::Text { text: line.to_string(), bold: false }]))
+            .collect(),
+    }
+}
+
+/// `aligned` or `gathered` for a formula with several rows at the top level, which `$$` and
+/// `\[` do not accept bare.
+fn rows_environment(src: &str) -> Option<&'static str> {
+    let rows = latex::parser::split_rows(&latex::parse(src).nodes)?;
+    Some(if rows.iter().any(|row| row.len() > 1) { "aligned" } else { "gathered" })
+}
+
+/// Markdown as the models write it, so document results are copied through unchanged.
+fn to_markdown(items: &[ExportItem]) -> String {
+    let mut out = format!("# {}\n\n> {}\n", title(items), timestamp());
+    for item in items {
+        if let Some(heading) = item_heading(items, item) {
+            out.push_str(&format!("\n## {}\n", heading));
+        }
+        out.push('\n');
+        match item.mode.as_str() {
+            "formula" => {
+                let latex = item.content.trim();
+                match rows_environment(latex) {
+                    Some(env) => out.push_str(&format!("$$\n\\begin{{{0}}}\n{1}\n\\end{{{0}}}\n$$\n", env, latex)),
+                    None => out.push_str(&format!("$$\n{}\n$$\n", latex)),
+                }
+            }
+            "document" => {
+                out.push_str(item.content.trim_end());
+                out.push('\n');
+            }
+            // Hard line breaks, so the lines of plain text stay lines
+            _ => {
+                out.push_str(&item.content.trim_end().lines().collect::<Vec<_>>().join("  \n"));
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+pub fn build(items: &[ExportItem], format: Format) -> Result<Vec<u8>, String> {
+    if items.is_empty() { return Err("没有可导出的内容".to_string()); }
+    match format {
+        Format::Docx => docx::build(items),
+        Format::Tex => Ok(tex::build(items).into_bytes()),
+        Format::Markdown => Ok(to_markdown(items).into_bytes()),
+        Format::Html => Ok(html::build(items).into_bytes()),
+        Format::Pdf => pdf::build(items),
+    }
+}
@@ -0,0 +1,477 @@
+//! PDF typeset with the same layout engine as the formula images. Every glyph, in the text too,
+//! is drawn as its outline from a shared form XObject, so no font has to be embedded or subset.
+
+use std::collections::{BTreeSet, HashMap};
+
+use fontdb::ID;
+use pdf_writer::types::LineCapStyle;
+use pdf_writer::{Content, Name, Pdf, Rect, Ref, TextStr};
+use ttf_parser::{GlyphId, OutlineBuilder};
+
+use super::markdown::{Block, Inline};
+use super::{blocks, item_heading, timestamp, title, ExportItem};
+use crate::latex::{self, layout::{Item, Layout, Typesetter}};
+
+// A4 in points, with 2.54 cm margins
+const PAGE_WIDTH: f32 = 595.28;
+const PAGE_HEIGHT: f32 = 841.89;
+const MARGIN: f32 = 72.0;
+const TEXT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+const BODY_SIZE: f32 = 11.0;
+/// Baseline to baseline, in ems of the line's size
+const LEADING: f32 = 1.5;
+const PARAGRAPH_GAP: f32 = 0.6;
+const WORD_SPACE: f32 = 0.3;
+const CELL_PADDING: f32 = 4.0;
+const BORDER: f32 = 0.5;
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x2E80..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF)
+}
+
+/// Punctuation that must not start a line.
+fn is_closing(c: char) -> bool {
+    "，。、；：！？）》」』】〕…,.;:!?)]}%".contains(c)
+}
+
+/// A piece of a paragraph that never breaks across lines: a word, a CJK character or a formula.
+struct Atom {
+    layout: Layout,
+    /// There was a space before it
+    space: bool,
+    /// A line may start with it
+    breakable: bool,
+}
+
+fn tint(mut layout: Layout, rgb: &str) -> Layout {
+    for item in &mut layout.items {
+        let (Item::Glyph { color, .. } | Item::Rule { color, .. } | Item::Line { color, .. }) = item;
+        *color = Some(rgb.to_string());
+    }
+    layout
+}
+
+/// `#rrggbb`, `#rgb` or one of the basic color names, as PDF components.
+fn rgb(color: &str) -> Option<[f32; 3]> {
+    let hex = match color.trim().to_ascii_lowercase().as_str() {
+        "black" => "000000".to_string(),
+        "white" => "ffffff".to_string(),
+        "red" => "ff0000".to_string(),
+        "green" => "008000".to_string(),
+        "blue" => "0000ff".to_string(),
+        "gray" | "grey" => "808080".to_string(),
+        "orange" => "ffa500".to_string(),
+        "purple" => "800080".to_string(),
+        c => {
+            let c = c.strip_prefix('#')?;
+            if c.len() == 3 { c.chars().flat_map(|d| [d, d]).collect() } else { c.to_string() }
+        }
+    };
+    if hex.len() != 6 { return None; }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok().map(|v| v as f32 / 255.0);
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+struct Writer {
+    typesetter: Typesetter,
+    /// Finished pages; the origin is the top left corner and y grows downwards
+    pages: Vec<Layout>,
+    /// Top of the free space on the current page
+    y: f32,
+}
+
+impl Writer {
+    fn new_page(&mut self) {
+        self.pages.push(Layout::empty(0.0));
+        self.y = MARGIN;
+    }
+
+    fn page(&mut self) -> &mut Layout {
+        self.pages.last_mut().expect("a page is started before anything is placed")
+    }
+
+    /// Starts a new page unless `height` still fits on this one.
+    fn reserve(&mut self, height: f32) {
+        if self.y + height > PAGE_HEIGHT - MARGIN && self.y > MARGIN { self.new_page(); }
+    }
+
+    /// Places a line with its top at the current position and moves below it.
+    fn line(&mut self, line: Layout, x: f32, size: f32) {
+        let asc = line.asc.max(0.75 * size);
+        let desc = line.desc.max(0.25 * size);
+        let height = (asc + desc).max(LEADING * size);
+        self.reserve(height);
+        let baseline = self.y + (height - asc - desc) / 2.0 + asc;
+        self.page().place(line, MARGIN + x, -baseline);
+        self.y += height;
+    }
+
+    fn gap(&mut self, size: f32) {
+        self.y += PARAGRAPH_GAP * size;
+    }
+
+    fn formula(&mut self, src: &str, display: bool, size: f32) -> Layout {
+        let mut layout = self.typesetter.formula(&latex::parse(src).nodes, display);
+        layout.stretch(size, size);
+        layout
+    }
+
+    fn text(&mut self, s: &str, bold: bool, size: f32) -> Layout {
+        let mut layout = self.typesetter.text(s, bold);
+        layout.stretch(size, size);
+        layout
+    }
+
+    /// Words, CJK characters and inline formulas; display formulas end up in `displays` with the
+    /// number of atoms before them.
+    fn atoms(&mut self, spans: &[Inline], bold: bool, size: f32) -> (Vec<Atom>, Vec<(usize, Layout)>) {
+        let mut atoms = Vec::new();
+        let mut displays = Vec::new();
+        let mut space = false;
+        for span in spans {
+            match span {
+                Inline::Math { latex, display: true } => {
+                    let layout = self.formula(latex, true, size);
+                    displays.push((atoms.len(), layout));
+                    space = false;
+                }
+                Inline::Math { latex, display: false } => {
+                    let layout = self.formula(latex, false, size);
+                    atoms.push(Atom { layout, space, breakable: true });
+                    space = false;
+                }
+                Inline::Text { text, bold: b } => {
+                    let mut word = String::new();
+                    let flush = |this: &mut Self, word: &mut String, atoms: &mut Vec<Atom>, space: &mut bool| {
+                        if word.is_empty() { return; }
+                        let breakable = !word.starts_with(is_closing);
+                        let layout = this.text(word, bold || *b, size);
+                        atoms.push(Atom { layout, space: *space, breakable });
+                        word.clear();
+                        *space = false;
+                    };
+                    for c in text.chars() {
+                        if c.is_whitespace() {
+                            flush(self, &mut word, &mut atoms, &mut space);
+                            space = true;
+                        } else if is_cjk(c) {
+                            flush(self, &mut word, &mut atoms, &mut space);
+                            word.push(c);
+                            flush(self, &mut word, &mut atoms, &mut space);
+                        } else {
+                            word.push(c);
+                        }
+                    }
+                    flush(self, &mut word, &mut atoms, &mut space);
+                }
+            }
+        }
+        (atoms, displays)
+    }
+
+    /// Greedy line breaking; an atom wider than the line gets a line of its own.
+    fn break_lines(atoms: Vec<Atom>, width: f32, size: f32) -> Vec<Layout> {
+        let mut lines = Vec::new();
+        let mut line = Layout::empty(0.0);
+        let mut x = 0.0;
+        for atom in atoms {
+            let space = if atom.space && x > 0.0 { WORD_SPACE * size } else { 0.0 };
+            if x > 0.0 && atom.breakable && x + space + atom.layout.w > width {
+                lines.push(std::mem::replace(&mut line, Layout::empty(0.0)));
+                x = 0.0;
+            } else {
+                x += space;
+            }
+            let w = atom.layout.w;
+            line.place(atom.layout, x, 0.0);
+            x += w;
+        }
+        if x > 0.0 { lines.push(line); }
+        lines
+    }
+
+    fn paragraph(&mut self, spans: &[Inline], bold: bool, size: f32) {
+        let (mut atoms, displays) = self.atoms(spans, bold, size);
+        let mut done = 0;
+        for (at, display) in displays {
+            let rest = atoms.split_off(at - done);
+            done = at;
+            for line in Self::break_lines(std::mem::replace(&mut atoms, rest), TEXT_WIDTH, size) {
+                self.line(line, 0.0, size);
+            }
+            self.display(display, size);
+        }
+        for line in Self::break_lines(atoms, TEXT_WIDTH, size) {
+            self.line(line, 0.0, size);
+        }
+        self.gap(size);
+    }
+
+    /// Centered, and scaled down when wider than the text.
+    fn display(&mut self, mut layout: Layout, size: f32) {
+        if layout.w > TEXT_WIDTH {
+            let k = TEXT_WIDTH / layout.w;
+            layout.stretch(k, k);
+        }
+        self.gap(size / 2.0);
+        let x = (TEXT_WIDTH - layout.w) / 2.0;
+        self.line(layout, x, size);
+        self.gap(size / 2.0);
+    }
+
+    /// Code line by line as written, never wrapped; lines wider than the text are scaled down.
+    fn code(&mut self, text: &str, size: f32) {
+        let size = 0.9 * size;
+        for line in text.split('\n') {
+            let mut layout = self.typesetter.code(&line.replace('\t', "    "));
+            let k = if layout.w * size > TEXT_WIDTH { TEXT_WIDTH / layout.w } else { size };
+            layout.stretch(k, k);
+            self.line(layout, 0.0, size);
+        }
+        self.gap(size);
+    }
+
+    /// Equal column widths, rows kept whole, the header row in bold.
+    fn table(&mut self, rows: &[Vec<Vec<Inline>>], header: bool, size: f32) {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 { return; }
+        let column = TEXT_WIDTH / columns as f32;
+        for (i, row) in rows.iter().enumerate() {
+            let bold = header && i == 0;
+            let mut cells = Vec::new();
+            for j in 0..columns {
+                let spans = row.get(j).map(Vec::as_slice).unwrap_or_default();
+                let (mut atoms, displays) = self.atoms(spans, bold, size);
+                // No room to center a display formula in a cell: it goes inline
+                atoms.extend(displays.into_iter().map(|(_, layout)| Atom { layout, space: true, breakable: true }));
+                let mut cell = Layout::empty(0.0);
+                let mut y = 0.0;
+                for line in Self::break_lines(atoms, column - 2.0 * CELL_PADDING, size) {
+                    let asc = line.asc.max(0.75 * size);
+                    let height = (asc + line.desc.max(0.25 * size)).max(LEADING * size);
+                    cell.place(line, 0.0, -(y + asc));
+                    y += height;
+                }
+                cells.push((cell, y.max(LEADING * size)));
+            }
+            let height = cells.iter().map(|(_, h)| *h).fold(0.0, f32::max) + 2.0 * CELL_PADDING;
+            self.reserve(height);
+            let top = self.y;
+            let page = self.page();
+            for (j, (cell, _)) in cells.into_iter().enumerate() {
+                let x = MARGIN + j as f32 * column;
+                page.place(cell, x + CELL_PADDING, -(top + CELL_PADDING));
+                page.rule(x, -top, BORDER, height, None);
+            }
+            page.rule(MARGIN + TEXT_WIDTH, -top, BORDER, height, None);
+            page.rule(MARGIN, -top, TEXT_WIDTH + BORDER, BORDER, None);
+            page.rule(MARGIN, -(top + height), TEXT_WIDTH + BORDER, BORDER, None);
+            self.y += height;
+        }
+        self.gap(size);
+    }
+
+    fn heading(&mut self, spans: &[Inline], level: usize) {
+        let size = match level {
+            1 => 18.0,
+            2 => 15.0,
+            3 => 13.0,
+            _ => 12.0,
+        };
+        self.gap(size / 2.0);
+        // Keep the heading with the first line after it
+        self.reserve(LEADING * (size + BODY_SIZE));
+        self.paragraph(spans, true, size);
+    }
+}
+
+/// Glyph outlines as PDF path operators; quadratic curves become cubic ones.
+struct PathOps {
+    content: Content,
+    current: (f32, f32),
+    bbox: Option<[f32; 4]>,
+}
+
+impl PathOps {
+    fn extend(&mut self, x: f32, y: f32) {
+        let b = self.bbox.get_or_insert([x, y, x, y]);
+        *b = [b[0].min(x), b[1].min(y), b[2].max(x), b[3].max(y)];
+    }
+}
+
+impl OutlineBuilder for PathOps {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.content.move_to(x, y);
+        self.current = (x, y);
+        self.extend(x, y);
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.content.line_to(x, y);
+        self.current = (x, y);
+        self.extend(x, y);
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        let c1 = (x0 + 2.0 / 3.0 * (x1 - x0), y0 + 2.0 / 3.0 * (y1 - y0));
+        let c2 = (x + 2.0 / 3.0 * (x1 - x), y + 2.0 / 3.0 * (y1 - y));
+        self.curve_to(c1.0, c1.1, c2.0, c2.1, x, y);
+    }
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.content.cubic_to(x1, y1, x2, y2, x, y);
+        self.current = (x, y);
+        self.extend(x1, y1);
+        self.extend(x2, y2);
+        self.extend(x, y);
+    }
+    fn close(&mut self) {
+        self.content.close_path();
+    }
+}
+
+/// Writes the pages, each glyph once as a form XObject in font units.
+fn write(typesetter: &Typesetter, pages: &[Layout], document_title: &str) -> Vec<u8> {
+    let mut pdf = Pdf::new();
+    let mut next = Ref::new(1);
+    let mut alloc = || next.bump();
+    let (catalog, tree, info) = (alloc(), alloc(), alloc());
+    let mut forms: HashMap<(ID, GlyphId), Option<(String, Ref)>> = HashMap::new();
+    let mut page_ids = Vec::new();
+
+    for layout in pages {
+        let mut content = Content::new();
+        let mut used = BTreeSet::new();
+        let set_color = |content: &mut Content, color: &Option<String>, stroke: bool| {
+            let Some([r, g, b]) = color.as_deref().and_then(rgb) else { return };
+            if stroke { content.set_stroke_rgb(r, g, b); } else { content.set_fill_rgb(r, g, b); }
+        };
+        for item in &layout.items {
+            match item {
+                Item::Glyph { face, id, x, y, scale_x, scale_y, color } => {
+                    let form = forms.entry((*face, *id)).or_insert_with(|| {
+                        let mut path = PathOps { content: Content::new(), current: (0.0, 0.0), bbox: None };
+                        typesetter.outline(*face, *id, &mut path);
+                        let [x0, y0, x1, y1] = path.bbox?;
+                        path.content.fill_nonzero();
+                        let data = path.content.finish();
+                        let form_ref = alloc();
+                        pdf.form_xobject(form_ref, &data).bbox(Rect::new(x0, y0, x1, y1));
+                        Some((format!("G{}", form_ref.get()), form_ref))
+                    });
+                    let Some((name, form_ref)) = form else { continue };
+                    used.insert((name.clone(), *form_ref));
+                    content.save_state();
+                    set_color(&mut content, color, false);
+                    content.transform([*scale_x, 0.0, 0.0, *scale_y, *x, PAGE_HEIGHT - y]);
+                    content.x_object(Name(name.as_bytes()));
+                    content.restore_state();
+                }
+                Item::Rule { x, y, w, h, color } => {
+                    content.save_state();
+                    set_color(&mut content, color, false);
+                    content.rect(*x, PAGE_HEIGHT - y - h, *w, *h);
+                    content.fill_nonzero();
+                    content.restore_state();
+                }
+                Item::Line { x1, y1, x2, y2, width, color } => {
+                    content.save_state();
+                    set_color(&mut content, color, true);
+                    content.set_line_width(*width);
+                    content.set_line_cap(LineCapStyle::RoundCap);
+                    content.move_to(*x1, PAGE_HEIGHT - y1);
+                    content.line_to(*x2, PAGE_HEIGHT - y2);
+                    content.stroke();
+                    content.restore_state();
+                }
+            }
+        }
+        let (page_id, content_id) = (alloc(), alloc());
+        pdf.stream(content_id, &content.finish());
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT)).parent(tree).contents(content_id);
+        page.resources().x_objects().pairs(used.iter().map(|(name, form_ref)| (Name(name.as_bytes()), *form_ref)));
+        drop(page);
+        page_ids.push(page_id);
+    }
+
+    pdf.catalog(catalog).pages(tree);
+    pdf.pages(tree).count(page_ids.len() as i32).kids(page_ids);
+    pdf.document_info(info).title(TextStr(document_title)).creator(TextStr("公式识别助手"));
+    pdf.finish()
+}
+
+pub fn build(items: &[ExportItem]) -> Result<Vec<u8>, String> {
+    let mut writer = Writer { typesetter: Typesetter::new()?, pages: Vec::new(), y: 0.0 };
+    writer.new_page();
+
+    let title_size = 20.0;
+    let heading = writer.text(title(items), true, title_size);
+    let x = ((TEXT_WIDTH - heading.w) / 2.0).max(0.0);
+    writer.line(heading, x, title_size);
+    let time = writer.text(&timestamp(), false, 9.0);
+    writer.line(tint(time, "#888888"), 0.0, 9.0);
+    writer.gap(BODY_SIZE);
+
+    for item in items {
+        if let Some(h) = item_heading(items, item) {
+            writer.heading(&[Inline::Text { text: h, bold: true }], 1);
+        }
+        for block in blocks(item) {
+            match block {
+                Block::Heading(level, spans) => writer.heading(&spans, level),
+                Block::Paragraph(spans) => writer.paragraph(&spans, false, BODY_SIZE),
+                Block::Math(latex) => {
+                    let layout = writer.formula(&latex, true, BODY_SIZE);
+                    writer.display(layout, BODY_SIZE);
+                }
+                Block::Code(text) => writer.code(&text, BODY_SIZE),
+                Block::Table { rows, header } => writer.table(&rows, header, BODY_SIZE),
+            }
+        }
+    }
+    Ok(write(&writer.typesetter, &writer.pages, title(items)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(n: usize) -> ExportItem {
+        let content = (1..=n).map(|i| format!("第 {} 行", i)).collect::<Vec<_>>().join("\n");
+        ExportItem { content, mode: "ocr".into(), title: None }
+    }
+
+    /// Page objects in the file, and the count in the page tree.
+    fn pages(pdf: &[u8]) -> (usize, usize) {
+        let s = String::from_utf8_lossy(pdf);
+        let objects = s.matches("/Type /Page").count() - s.matches("/Type /Pages").count();
+        let count = s.split("/Count ").nth(1).and_then(|rest| rest.split_whitespace().next()).and_then(|n| n.parse().ok());
+        (objects, count.expect("no page tree"))
+    }
+
+    #[test]
+    fn header_and_single_page() {
+        let pdf = build(&[lines(3)]).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(pdf.trim_ascii_end().ends_with(b"%%EOF"));
+        assert_eq!(pages(&pdf), (1, 1));
+    }
+
+    #[test]
+    fn new_page_per_overflow() {
+        let mut writer = Writer { typesetter: Typesetter::new().unwrap(), pages: Vec::new(), y: 0.0 };
+        writer.new_page();
+        let height = LEADING * BODY_SIZE;
+        let per_page = ((PAGE_HEIGHT - 2.0 * MARGIN) / height) as usize;
+        for i in 1..=3 * per_page + 1 {
+            let line = writer.text("x", false, BODY_SIZE);
+            writer.line(line, 0.0, BODY_SIZE);
+            assert_eq!(writer.pages.len(), i.div_ceil(per_page), "line {}", i);
+        }
+
+        let (objects, count) = pages(&build(&[lines(200)]).unwrap());
+        assert!(objects > 1);
+        assert_eq!(objects, count);
+    }
+}
@@ -0,0 +1,147 @@
+//! A `.tex` file that compiles as is with XeLaTeX: `ctexart` for the Chinese titles and text, and the
+//! AMS packages the recognition models' LaTeX relies on.
+
+use super::markdown::{Block, Inline};
+use super::{blocks, item_heading, rows_environment, timestamp, title, ExportItem};
+use crate::latex;
+
+const PACKAGES: &str = r"\usepackage[a4paper,margin=2.54cm]{geometry}
+\usepackage{amsmath,amssymb,mathtools}
+\usepackage{bm}
+\usepackage{cancel}
+\usepackage{xcolor}
+\usepackage{array}
+";
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str(r"\textbackslash{}"),
+            '{' | '}' | '#' | '$' | '%' | '&' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str(r"\textasciitilde{}"),
+            '^' => out.push_str(r"\textasciicircum{}"),
+            '•' => out.push_str(r"\textbullet{}"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Display math, in `align*` or `gather*` when it has several rows; an environment the
+/// model wrote itself is kept.
+fn display(src: &str) -> String {
+    let src = latex::strip_delimiters(src).trim();
+    if src.starts_with(r"\begin{") && !src.starts_with(r"\begin{aligned}") && !src.starts_with(r"\begin{gathered}") {
+        let name = &src[7..src.find('}').unwrap_or(7)];
+        if matches!(name, "equation" | "equation*" | "align" | "align*" | "gather" | "gather*" | "multline" | "multline*" | "flalign" | "flalign*") {
+            return format!("{}\n", src);
+        }
+    }
+    match rows_environment(src) {
+        Some(env) => {
+            let env = if env == "aligned" { "align*" } else { "gather*" };
+            format!("\\begin{{{0}}}\n{1}\n\\end{{{0}}}\n", env, src)
+        }
+        None => format!("\\[\n{}\n\\]\n", src),
+    }
+}
+
+fn inlines(items: &[Inline]) -> String {
+    items.iter().map(|item| match item {
+        Inline::Text { text, bold: true } => format!(r"\textbf{{{}}}", escape(text)),
+        Inline::Text { text, bold: false } => escape(text),
+        Inline::Math { latex, display: true } => format!("\n{}", display(latex)),
+        Inline::Math { latex, display: false } => format!(r"\({}\)", latex),
+    }).collect()
+}
+
+fn table(rows: &[Vec<Vec<Inline>>], header: bool) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 { return String::new(); }
+    let mut out = format!("\\begin{{center}}\n\\begin{{tabular}}{{|{}}}\n\\hline\n", "l|".repeat(columns));
+    for (i, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = (0..columns).map(|j| {
+            let cell = row.get(j).map(|c| inlines(c)).unwrap_or_default();
+            if header && i == 0 && !cell.is_empty() { format!(r"\textbf{{{}}}", cell) } else { cell }
+        }).collect();
+        out.push_str(&cells.join(" & "));
+        out.push_str(" \\\\ \\hline\n");
+    }
+    out.push_str("\\end{tabular}\n\\end{center}\n");
+    out
+}
+
+fn heading(level: usize, text: &str) -> String {
+    let command = match level {
+        1 => "section",
+        2 => "subsection",
+        3 => "subsubsection",
+        _ => "paragraph",
+    };
+    format!("\\{}*{{{}}}\n", command, text)
+}
+
+pub fn build(items: &[ExportItem]) -> String {
+    let mut out = String::from("% 使用 XeLaTeX 编译\n\\documentclass[UTF8]{ctexart}\n");
+    out.push_str(PACKAGES);
+    out.push_str(&format!("\n\\title{{{}}}\n\\author{{}}\n\\date{{{}}}\n\n\\begin{{document}}\n\\maketitle\n", escape(title(items)), escape(&timestamp())));
+
+    for item in items {
+        if let Some(h) = item_heading(items, item) {
+            out.push('\n');
+            out.push_str(&heading(1, &escape(&h)));
+        }
+        for block in blocks(item) {
+            out.push('\n');
+            out.push_str(&match block {
+                Block::Heading(level, spans) => heading(level, &inlines(&spans)),
+                Block::Paragraph(spans) => inlines(&spans) + "\n",
+                Block::Math(latex) => display(&latex),
+                Block::Code(code) => format!("\\begin{{verbatim}}\n{}\n\\end{{verbatim}}\n", code),
+                Block::Table { rows, header } => table(&rows, header),
+            });
+        }
+    }
+    out.push_str("\n\\end{document}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_environments() {
+        assert_eq!(display("x^2"), "\\[\nx^2\n\\]\n");
+        assert_eq!(display("$$x^2$$"), "\\[\nx^2\n\\]\n");
+        assert_eq!(display("a &= b \\\\ c &= d"), "\\begin{align*}\na &= b \\\\ c &= d\n\\end{align*}\n");
+        assert_eq!(display("a + b \\\\ c"), "\\begin{gather*}\na + b \\\\ c\n\\end{gather*}\n");
+        // What the model wrapped itself is kept as is
+        assert_eq!(display("\\begin{equation}E=mc^2\\end{equation}"), "\\begin{equation}E=mc^2\\end{equation}\n");
+        assert_eq!(display("\\begin{align*}a&=b\\\\c&=d\\end{align*}"), "\\begin{align*}a&=b\\\\c&=d\\end{align*}\n");
+        // `aligned` can't stand alone in the document, so it goes inside display math
+        assert_eq!(
+            display("\\begin{aligned}a&=b\\end{aligned}"),
+            "\\[\n\\begin{aligned}a&=b\\end{aligned}\n\\]\n",
+        );
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(escape("a_b 50% R&D #1 $5"), "a\\_b 50\\% R\\&D \\#1 \\$5");
+        assert_eq!(escape("{x} \\ ~^"), "\\{x\\} \\textbackslash{} \\textasciitilde{}\\textasciicircum{}");
+
+        let out = build(&[ExportItem { content: "x_1 at 5% & #2 $y_1$".into(), mode: "document".into(), title: None }]);
+        assert!(out.contains("x\\_1 at 5\\% \\& \\#2 \\(y_1\\)\n"), "{}", out);
+    }
+
+    #[test]
+    fn table_rows_fill_the_columns() {
+        let out = build(&[ExportItem { content: "| a | b |\n|---|---|\n| 1 |".into(), mode: "document".into(), title: None }]);
+        assert!(out.contains("\\begin{tabular}{|l|l|}\n\\hline\n\\textbf{a} & \\textbf{b} \\\\ \\hline\n1 &  \\\\ \\hline\n"), "{}", out);
+    }
+}
@@ -0,0 +1,348 @@
+//! Recognition history in `app_data_dir/history`: one JSON object per line in `history.jsonl`,
+//! oldest first, and the recognized images next to it in `images/`.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::latex;
+
+/// Serializes every read-modify-write of the history file.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// What a recognition produced, as the frontend received it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct HistoryRecord {
+    pub mode: String,
+    pub text: String,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    #[serde(default)]
+    pub needs_review: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    #[serde(default)]
+    pub corrected: bool,
+    /// The result before the verifier corrected it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_opinion: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consensus: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    pub id: String,
+    #[serde(flatten)]
+    pub record: HistoryRecord,
+    #[serde(default)]
+    pub favorite: bool,
+    /// File name in `images/`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Unix milliseconds
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct HistoryQuery {
+    pub offset: usize,
+    /// Everything from `offset` when absent
+    pub limit: Option<usize>,
+    pub favorites_only: bool,
+}
+
+/// Filters for [`search`]; every one that is set must match.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct HistorySearch {
+    #[serde(flatten)]
+    pub page: HistoryQuery,
+    /// Words that must all appear in the result, ignoring case
+    pub text: Option<String>,
+    /// A formula or part of one, compared after LaTeX normalization: whitespace, redundant braces
+    /// and alternative spellings such as `\le` for `\leq` don't matter
+    pub latex: Option<String>,
+    /// Any of these modes; all when empty
+    pub modes: Vec<String>,
+    /// Display name or `provider:model` id
+    pub model: Option<String>,
+    /// Unix milliseconds, inclusive
+    pub from: Option<i64>,
+    /// Unix milliseconds, exclusive
+    pub to: Option<i64>,
+}
+
+impl HistorySearch {
+    /// The search as predicates over entries, with the query strings prepared once.
+    fn matcher(&self) -> impl Fn(&HistoryEntry) -> bool + '_ {
+        let words: Vec<String> = self.text.as_deref().unwrap_or("").split_whitespace().map(str::to_lowercase).collect();
+        let latex = self.latex.as_deref().map(latex::normalize).filter(|s| !s.is_empty());
+        let model = self.model.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        move |e: &HistoryEntry| {
+            let r = &e.record;
+            if self.page.favorites_only && !e.favorite { return false; }
+            if !self.modes.is_empty() && !self.modes.contains(&r.mode) { return false; }
+            if self.from.is_some_and(|from| e.created_at < from) { return false; }
+            if self.to.is_some_and(|to| e.created_at >= to) { return false; }
+            if let Some(model) = model {
+                if r.model != model && r.model_id.as_deref() != Some(model) { return false; }
+            }
+            if !words.is_empty() {
+                // The text before correction counts too, so a search for what the model first
+                // read still finds the entry
+                let haystack = match &r.original_text {
+                    Some(original) => format!("{}\n{}", r.text, original).to_lowercase(),
+                    None => r.text.to_lowercase(),
+                };
+                if !words.iter().all(|w| haystack.contains(w.as_str())) { return false; }
+            }
+            if let Some(latex) = &latex {
+                let found = std::iter::once(&r.text).chain(&r.original_text)
+                    .any(|text| latex::normalize(text).contains(latex.as_str()));
+                if !found { return false; }
+            }
+            true
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HistoryPage {
+    /// Newest first
+    pub items: Vec<HistoryEntry>,
+    /// Entries matching the query, across all pages
+    pub total: usize,
+}
+
+fn history_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?.join("history");
+    std::fs::create_dir_all(dir.join("images")).map_err(|e| format!("无法创建历史记录目录: {}", e))?;
+    Ok(dir)
+}
+
+/// Entries oldest first. A line that fails to parse (a write cut short) is skipped rather than
+/// losing the rest.
+fn load(dir: &std::path::Path) -> Vec<HistoryEntry> {
+    std::fs::read_to_string(dir.join("history.jsonl"))
+        .map(|data| data.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Rewrites the file through a temporary one, so a crash leaves the old history intact.
+fn save(dir: &std::path::Path, entries: &[HistoryEntry]) -> Result<(), String> {
+    let mut data = String::new();
+    for entry in entries {
+        data.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+        data.push('\n');
+    }
+    let tmp = dir.join("history.jsonl.tmp");
+    std::fs::write(&tmp, data).map_err(|e| format!("保存历史记录失败: {}", e))?;
+    std::fs::rename(&tmp, dir.join("history.jsonl")).map_err(|e| format!("保存历史记录失败: {}", e))
+}
+
+fn remove_image(dir: &std::path::Path, entry: &HistoryEntry) {
+    if let Some(name) = &entry.image {
+        std::fs::remove_file(dir.join("images").join(name)).ok();
+    }
+}
+
+/// Stores a data URL (or bare base64 PNG) as a file named after the entry.
+fn store_image(dir: &std::path::Path, id: &str, image: &str) -> Result<String, String> {
+    let (header, data) = image.split_once(',').unwrap_or(("data:image/png;base64", image));
+    let ext = match header.trim_start_matches("data:").split(';').next().unwrap_or("") {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/bmp" => "bmp",
+        _ => "png",
+    };
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim())
+        .map_err(|e| format!("Base64 解码失败: {}", e))?;
+    let name = format!("{}.{}", id, ext);
+    std::fs::write(dir.join("images").join(&name), bytes).map_err(|e| format!("保存图片失败: {}", e))?;
+    Ok(name)
+}
+
+pub fn add(app: &AppHandle, record: HistoryRecord, image: Option<&str>) -> Result<HistoryEntry, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = history_dir(app)?;
+    let entries = load(&dir);
+    let now = chrono::Local::now().timestamp_millis();
+    // Millisecond ids, bumped past any taken by an entry added in the same millisecond
+    let mut n = now;
+    while entries.iter().any(|e| e.id == n.to_string()) { n += 1; }
+    let id = n.to_string();
+
+    let image = match image.filter(|s| !s.is_empty()) {
+        Some(data) => Some(store_image(&dir, &id, data)?),
+        None => None,
+    };
+    let entry = HistoryEntry { id, record, favorite: false, image, created_at: now, updated_at: now };
+    let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(dir.join("history.jsonl"))
+        .map_err(|e| format!("保存历史记录失败: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("保存历史记录失败: {}", e))?;
+    Ok(entry)
+}
+
+pub fn list(app: &AppHandle, query: HistoryQuery) -> Result<HistoryPage, String> {
+    search(app, &HistorySearch { page: query, ..Default::default() })
+}
+
+/// The entries matching every filter, newest first, paged by `search.page`.
+pub fn search(app: &AppHandle, search: &HistorySearch) -> Result<HistoryPage, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = history_dir(app)?;
+    let matches = search.matcher();
+    let matching: Vec<HistoryEntry> = load(&dir).into_iter().rev().filter(|e| matches(e)).collect();
+    let total = matching.len();
+    let items = matching.into_iter()
+        .skip(search.page.offset)
+        .take(search.page.limit.unwrap_or(usize::MAX))
+        .collect();
+    Ok(HistoryPage { items, total })
+}
+
+/// The full entries for `ids`, in the order given; unknown ids are skipped.
+pub fn get(app: &AppHandle, ids: &[String]) -> Result<Vec<HistoryEntry>, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let entries = load(&history_dir(app)?);
+    Ok(ids.iter().filter_map(|id| entries.iter().find(|e| &e.id == id).cloned()).collect())
+}
+
+pub fn set_favorite(app: &AppHandle, id: &str, favorite: bool) -> Result<HistoryEntry, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = history_dir(app)?;
+    let mut entries = load(&dir);
+    let entry = entries.iter_mut().find(|e| e.id == id).ok_or_else(|| "历史记录不存在".to_string())?;
+    entry.favorite = favorite;
+    entry.updated_at = chrono::Local::now().timestamp_millis();
+    let updated = entry.clone();
+    save(&dir, &entries)?;
+    Ok(updated)
+}
+
+/// Deletes the entries and their images; returns how many were removed.
+pub fn delete(app: &AppHandle, ids: &[String]) -> Result<usize, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = history_dir(app)?;
+    let (removed, kept): (Vec<_>, Vec<_>) = load(&dir).into_iter().partition(|e| ids.contains(&e.id));
+    if removed.is_empty() { return Ok(0); }
+    save(&dir, &kept)?;
+    for entry in &removed { remove_image(&dir, entry); }
+    Ok(removed.len())
+}
+
+/// Deletes everything; favorites too unless `keep_favorites`.
+pub fn clear(app: &AppHandle, keep_favorites: bool) -> Result<usize, String> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = history_dir(app)?;
+    let (kept, removed): (Vec<_>, Vec<_>) = load(&dir).into_iter().partition(|e| keep_favorites && e.favorite);
+    save(&dir, &kept)?;
+    for entry in &removed { remove_image(&dir, entry); }
+    Ok(removed.len())
+}
+
+/// The entry's image as a data URL, `None` when it was saved without one.
+pub fn image(app: &AppHandle, id: &str) -> Result<Option<String>, String> {
+    let dir = history_dir(app)?;
+    let Some(name) = get(app, &[id.to_string()])?.into_iter().next().and_then(|e| e.image) else { return Ok(None) };
+    let bytes = std::fs::read(dir.join("images").join(&name)).map_err(|e| format!("读取图片失败: {}", e))?;
+    let mime = match name.rsplit('.').next() {
+        Some("jpg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("bmp") => "image/bmp",
+        _ => "image/png",
+    };
+    Ok(Some(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(bytes))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(mode: &str, text: &str, original_text: Option<&str>, created_at: i64) -> HistoryEntry {
+        HistoryEntry {
+            id: created_at.to_string(),
+            record: HistoryRecord {
+                mode: mode.into(),
+                text: text.into(),
+                model: "SimpleTex 标准".into(),
+                model_id: Some("simpletex:latex_ocr".into()),
+                original_text: original_text.map(Into::into),
+                ..Default::default()
+            },
+            favorite: false,
+            image: None,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    fn latex_matches(query: &str, text: &str) -> bool {
+        let search = HistorySearch { latex: Some(query.into()), ..Default::default() };
+        let matcher = search.matcher();
+        matcher(&entry("formula", text, None, 0))
+    }
+
+    #[test]
+    fn latex_search_ignores_spelling() {
+        let text = "\\frac{a}{b} \\le x^{2}";
+        for query in ["x^2", "x ^ { 2 }", "\\leq x", "\\frac {a} {b}", "$\\frac{a}{b}\\leq$", "b \\le x"] {
+            assert!(latex_matches(query, text), "{}", query);
+        }
+        for query in ["x^3", "\\geq", "\\frac{b}{a}"] {
+            assert!(!latex_matches(query, text), "{}", query);
+        }
+        // Nothing left after normalization filters nothing
+        assert!(latex_matches("  ", text));
+    }
+
+    #[test]
+    fn searches_text_before_correction() {
+        let corrected = entry("formula", "\\alpha + \\beta", Some("a + \\beta"), 0);
+        let search = HistorySearch { latex: Some("a+\\beta".into()), ..Default::default() };
+        assert!(search.matcher()(&corrected));
+        let search = HistorySearch { text: Some("A +".into()), ..Default::default() };
+        assert!(search.matcher()(&corrected));
+    }
+
+    #[test]
+    fn text_search_needs_every_word() {
+        let e = entry("document", "Fourier Transform of a Gaussian", None, 0);
+        let search = |text: &str| HistorySearch { text: Some(text.into()), ..Default::default() };
+        assert!(search("gaussian fourier").matcher()(&e));
+        assert!(!search("gaussian laplace").matcher()(&e));
+    }
+
+    #[test]
+    fn filters_combine() {
+        let e = entry("ocr", "hello", None, 1_000);
+        let search = HistorySearch { modes: vec!["formula".into(), "ocr".into()], from: Some(1_000), to: Some(2_000), ..Default::default() };
+        assert!(search.matcher()(&e));
+        let search = HistorySearch { from: Some(1_001), ..Default::default() };
+        assert!(!search.matcher()(&e));
+        let search = HistorySearch { to: Some(1_000), ..Default::default() };
+        assert!(!search.matcher()(&e));
+        let search = HistorySearch { modes: vec!["formula".into()], ..Default::default() };
+        assert!(!search.matcher()(&e));
+        let search = HistorySearch { model: Some("simpletex:latex_ocr".into()), ..Default::default() };
+        assert!(search.matcher()(&e));
+        let search = HistorySearch { model: Some("SimpleTex 轻量".into()), ..Default::default() };
+        assert!(!search.matcher()(&e));
+    }
+}
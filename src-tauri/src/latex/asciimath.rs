@@ -0,0 +1,458 @@
+//! AsciiMath from the LaTeX AST.
+//!
+//! Symbols without an AsciiMath name are written as the Unicode character.
+
+use super::parser::{split_primes, split_rows, Node};
+use super::symbols;
+
+/// `(unicode, AsciiMath)`
+const NAMES: &[(&str, &str)] = &[
+    // Greek
+    ("α", "alpha"), ("β", "beta"), ("γ", "gamma"), ("δ", "delta"), ("ϵ", "epsilon"), ("ε", "varepsilon"),
+    ("ζ", "zeta"), ("η", "eta"), ("θ", "theta"), ("ϑ", "vartheta"), ("ι", "iota"), ("κ", "kappa"),
+    ("λ", "lambda"), ("μ", "mu"), ("ν", "nu"), ("ξ", "xi"), ("π", "pi"), ("ρ", "rho"), ("σ", "sigma"),
+    ("τ", "tau"), ("υ", "upsilon"), ("ϕ", "phi"), ("φ", "varphi"), ("χ", "chi"), ("ψ", "psi"),
+    ("ω", "omega"), ("Γ", "Gamma"), ("Δ", "Delta"), ("Θ", "Theta"), ("Λ", "Lambda"), ("Ξ", "Xi"),
+    ("Π", "Pi"), ("Σ", "Sigma"), ("Φ", "Phi"), ("Ψ", "Psi"), ("Ω", "Omega"),
+    // Misc
+    ("∞", "oo"), ("∂", "del"), ("∇", "grad"), ("ℵ", "aleph"), ("∅", "O/"), ("∀", "AA"), ("∃", "EE"),
+    ("¬", "neg"), ("⊥", "_|_"), ("⊤", "TT"), ("∠", "/_"), ("△", "/_\\"), ("∴", ":."), ("∵", ":'"),
+    ("…", "..."), ("⋯", "cdots"), ("⋮", "vdots"), ("⋱", "ddots"), ("′", "'"), ("□", "square"),
+    ("⋄", "diamond"), ("mod", "mod"),
+    // Operators
+    ("±", "+-"), ("×", "xx"), ("÷", "-:"), ("⋅", "*"), ("∗", "**"), ("⋆", "***"), ("∘", "@"),
+    ("⊕", "o+"), ("⊗", "ox"), ("⊙", "o."), ("∧", "^^"), ("∨", "vv"), ("∩", "nn"), ("∪", "uu"),
+    ("∑", "sum"), ("∏", "prod"), ("⋀", "^^^"), ("⋁", "vvv"), ("⋂", "nnn"), ("⋃", "uuu"),
+    ("∫", "int"), ("∮", "oint"),
+    // Relations
+    ("≠", "!="), ("≤", "<="), ("≥", ">="), ("≪", "mlt"), ("≫", "mgt"), ("≺", "-<"), ("≻", ">-"),
+    ("⪯", "-<="), ("⪰", ">-="), ("∈", "in"), ("∉", "!in"), ("⊂", "sub"), ("⊃", "sup"), ("⊆", "sube"),
+    ("⊇", "supe"), ("≡", "-="), ("≅", "~="), ("≈", "~~"), ("∼", "~"), ("∝", "prop"), ("∣", "|"),
+    ("⊢", "|--"), ("⊨", "|=="),
+    // Arrows
+    ("↑", "uarr"), ("↓", "darr"), ("→", "->"), ("←", "larr"), ("↔", "harr"), ("⇒", "=>"),
+    ("⇐", "lArr"), ("⇔", "<=>"), ("↦", "|->"),
+    // Delimiters
+    ("⌊", "|__"), ("⌋", "__|"), ("⌈", "|~"), ("⌉", "~|"), ("⟨", "(:"), ("⟩", ":)"),
+];
+
+/// Operator names AsciiMath knows; other `\sin`-like names are written as text.
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "sec", "csc", "cot", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "sech", "csch", "coth", "exp", "log", "ln", "det", "dim", "mod", "gcd", "lcm", "lub", "glb",
+    "min", "max", "lim",
+];
+
+/// Names AsciiMath reads as taking the bracket after them, so `sqrt(x)` or `f(x)` stays one
+/// operand while `P(A)` is `P` times `(A)`.
+const UNARY: &[&str] = &[
+    "f", "g", "sqrt", "text", "bb", "bbb", "cc", "tt", "fr", "sf", "hat", "bar", "vec", "dot",
+    "ddot", "tilde", "ul", "ubrace", "obrace", "cancel", "abs", "norm", "floor", "ceil",
+];
+
+fn font(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "mathbf" | "boldsymbol" | "bm" => "bb",
+        "mathbb" => "bbb",
+        "mathcal" | "mathscr" => "cc",
+        "mathfrak" => "fr",
+        "mathsf" => "sf",
+        "mathtt" => "tt",
+        _ => return None,
+    })
+}
+
+/// AsciiMath's own accents; the rest are stacked with `overset`.
+fn accent(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "hat" | "widehat" => "hat",
+        "bar" | "overline" => "bar",
+        "vec" | "overrightarrow" => "vec",
+        "tilde" | "widetilde" => "tilde",
+        "dot" => "dot",
+        "ddot" => "ddot",
+        "underline" => "ul",
+        "overbrace" => "obrace",
+        "underbrace" => "ubrace",
+        _ => return None,
+    })
+}
+
+fn quote(s: &str) -> String {
+    if s.contains('"') { format!("text({})", s) } else { format!("\"{}\"", s) }
+}
+
+fn glyph(u: &str) -> String {
+    match NAMES.iter().find(|(c, _)| *c == u) {
+        Some(&(_, name)) => name.to_string(),
+        None => u.to_string(),
+    }
+}
+
+fn symbol(c: char) -> String {
+    match c {
+        // A single `/` builds a fraction and `*` is a dot
+        '/' => "//".to_string(),
+        '*' => "**".to_string(),
+        '~' => "\\ ".to_string(),
+        c => glyph(c.encode_utf8(&mut [0; 4])),
+    }
+}
+
+/// Whether `s` can take a script or be a fraction operand without brackets.
+fn atomic(s: &str) -> bool {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => return false,
+        (Some(c), None) => return c.is_alphanumeric() || !c.is_ascii(),
+        _ => {}
+    }
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') { return true; }
+    let name_end = s.find('(').unwrap_or(s.len());
+    let name = &s[..name_end];
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '.') { return false; }
+    if name_end == s.len() { return !s.ends_with('.'); }
+    if !FUNCTIONS.contains(&name) && !UNARY.contains(&name) { return false; }
+    // `sqrt(x)`: the bracket after the name has to close at the very end
+    let mut depth = 0;
+    for (i, c) in s.char_indices().skip_while(|&(i, _)| i < name_end) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 { return i == s.len() - 1; }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+fn bracket(s: String) -> String {
+    if atomic(&s) { s } else { format!("({})", s) }
+}
+
+fn spaced(prev: &str, next: &str) -> bool {
+    if matches!(prev, "(" | "[" | "{" | "{:" | "(:") || prev.ends_with(' ') { return false; }
+    if matches!(next, ")" | "]" | "}" | ":}" | ":)" | "," | ";" | "!" | "'") { return false; }
+    if matches!(next, "(" | "[") {
+        return !prev.ends_with(|c: char| c.is_alphanumeric() || c == '\'');
+    }
+    true
+}
+
+fn join(pieces: &[String]) -> String {
+    let mut out = String::new();
+    let mut prev: Option<&str> = None;
+    for piece in pieces.iter().filter(|p| !p.is_empty()) {
+        if prev.is_some_and(|p| spaced(p, piece)) { out.push(' '); }
+        out.push_str(piece);
+        prev = Some(piece);
+    }
+    out
+}
+
+fn list(nodes: &[Node]) -> String {
+    if let Some(rows) = split_rows(nodes) {
+        return table("{:", ":}", &rows);
+    }
+    let mut pieces = Vec::new();
+    for (i, n) in nodes.iter().enumerate() {
+        if let Node::Command { name, args, .. } = n {
+            if name == "color" {
+                let color = args.first().map(Node::plain_text).unwrap_or_default();
+                pieces.push(format!("color({})({})", color, list(&nodes[i + 1..])));
+                break;
+            }
+        }
+        pieces.push(node(n));
+    }
+    join(&pieces)
+}
+
+/// Argument content: groups are unwrapped.
+fn argument(n: Option<&Node>) -> String {
+    match n {
+        Some(Node::Group { body }) => list(body),
+        Some(n) => node(n),
+        None => String::new(),
+    }
+}
+
+fn node(n: &Node) -> String {
+    match n {
+        Node::Letter { value } => value.to_string(),
+        Node::Number { value } => value.clone(),
+        Node::Symbol { value } => symbol(*value),
+        Node::Group { body } => list(body),
+        Node::Command { name, optional, args } => command(name, optional.as_deref(), args),
+        Node::Text { command, value } => match command.as_str() {
+            "tag" | "tag*" | "label" => String::new(),
+            "textbf" => format!("bb({})", quote(value)),
+            "textsf" => format!("sf({})", quote(value)),
+            "texttt" => format!("tt({})", quote(value)),
+            _ => quote(value),
+        },
+        Node::Scripts { base, sub, sup } => scripts(base, sub.as_deref(), sup.as_deref()),
+        Node::Delimited { left, right, body } => delimited(left, right, body),
+        Node::Sized { delim, .. } => {
+            let d = symbols::delimiter(delim);
+            if d.is_empty() { String::new() } else { glyph(d) }
+        }
+        Node::Environment { name, rows, .. } => environment(name, rows),
+        Node::Align | Node::LineBreak => String::new(),
+    }
+}
+
+fn function(name: &str) -> String {
+    if FUNCTIONS.contains(&name) { name.to_string() } else { quote(name) }
+}
+
+fn command(name: &str, optional: Option<&Node>, args: &[Node]) -> String {
+    let arg0 = args.first();
+    let arg1 = args.get(1);
+
+    if name == "mathbb" {
+        // `RR`, `NN`, ... for the number sets
+        let set = arg0.map(Node::plain_text).unwrap_or_default();
+        if matches!(set.as_str(), "R" | "N" | "Z" | "Q" | "C") { return set.repeat(2); }
+    }
+    if name == "mathrm" {
+        return quote(&arg0.map(Node::plain_text).unwrap_or_default());
+    }
+    if let Some(f) = font(name) {
+        return format!("{}({})", f, argument(arg0));
+    }
+    if let Some(f) = accent(name) {
+        return format!("{}({})", f, argument(arg0));
+    }
+    if let Some((mark, under)) = symbols::accent(name) {
+        let stack = if under { "underset" } else { "overset" };
+        return format!("{}({})({})", stack, mark, argument(arg0));
+    }
+    if let Some(em) = symbols::space(name) {
+        return match name {
+            "quad" => "quad".to_string(),
+            "qquad" => "qquad".to_string(),
+            _ if em > 0.0 => "\\ ".to_string(),
+            _ => String::new(),
+        };
+    }
+    if symbols::function(name).is_some() {
+        return function(name);
+    }
+    if let Some((u, _)) = symbols::symbol(name) {
+        return glyph(u);
+    }
+
+    match name {
+        "frac" | "dfrac" | "tfrac" | "cfrac" => {
+            format!("{}/{}", bracket(argument(arg0)), bracket(argument(arg1)))
+        }
+        "binom" | "dbinom" | "tbinom" => format!("(({}),({}))", argument(arg0), argument(arg1)),
+        "sqrt" => match optional {
+            Some(index) => format!("root({})({})", argument(Some(index)), argument(arg0)),
+            None => format!("sqrt({})", argument(arg0)),
+        },
+        "overset" | "stackrel" => format!("overset({})({})", argument(arg0), argument(arg1)),
+        "underset" => format!("underset({})({})", argument(arg0), argument(arg1)),
+        "operatorname" | "operatorname*" => function(&arg0.map(Node::plain_text).unwrap_or_default()),
+        "cancel" | "bcancel" | "xcancel" => format!("cancel({})", argument(arg0)),
+        "textcolor" => format!("color({})({})", arg0.map(Node::plain_text).unwrap_or_default(), argument(arg1)),
+        "pmod" => format!("(mod {})", argument(arg0)),
+        "not" => glyph(&symbols::negated(&arg0.map(Node::plain_text).unwrap_or_default())),
+        "xrightarrow" | "xleftarrow" => {
+            let arrow = if name == "xrightarrow" { "->" } else { "larr" };
+            let over = format!("overset({})({})", argument(arg0), arrow);
+            match optional {
+                Some(below) => format!("underset({})({})", argument(Some(below)), over),
+                None => over,
+            }
+        }
+        "limits" | "nolimits" | "mathnormal" | "mathit" | "substack" | "boxed" => argument(arg0),
+        // Invisible in the output anyway
+        "phantom" | "hphantom" | "vphantom" => String::new(),
+        "displaystyle" | "textstyle" | "scriptstyle" | "scriptscriptstyle" | "color"
+        | "hline" | "nonumber" | "notag" => String::new(),
+        _ => quote(&format!("\\{}", name)),
+    }
+}
+
+/// Whether a subscript goes under `base`: operator names that take limits but that AsciiMath
+/// would otherwise write as a text subscript.
+fn under(base: &Node) -> bool {
+    match base {
+        Node::Command { name, args, .. } => match name.as_str() {
+            "operatorname*" => true,
+            "limits" => args.first().is_some_and(under),
+            n => symbols::function(n) == Some(true) && !FUNCTIONS.contains(&n),
+        },
+        _ => false,
+    }
+}
+
+fn scripts(base: &Node, sub: Option<&Node>, sup: Option<&Node>) -> String {
+    let base_text = match base {
+        Node::Group { body } if body.is_empty() => "\"\"".to_string(),
+        n => node(n),
+    };
+    let base_text = if atomic(&base_text) || matches!(base, Node::Delimited { .. }) {
+        base_text
+    } else {
+        format!("{{:{}:}}", base_text)
+    };
+    // AsciiMath reads `'` as a prime too
+    let (primes, rest) = split_primes(sup);
+    let (base_text, sup) = (base_text + &"'".repeat(primes), rest.as_ref());
+    if under(base) {
+        let mut out = base_text;
+        if let Some(s) = sub { out = format!("underset({})({})", argument(Some(s)), out); }
+        if let Some(s) = sup { out = format!("overset({})({})", argument(Some(s)), out); }
+        return out;
+    }
+    let sub = sub.map(|n| format!("_{}", bracket(argument(Some(n))))).unwrap_or_default();
+    let sup = sup.map(|n| format!("^{}", bracket(argument(Some(n))))).unwrap_or_default();
+    format!("{}{}{}", base_text, sub, sup)
+}
+
+fn delimited(left: &str, right: &str, body: &[Node]) -> String {
+    let (l, r) = (symbols::delimiter(left), symbols::delimiter(right));
+    let inner = list(body);
+    match (l, r) {
+        ("|", "|") => format!("abs({})", inner),
+        ("‖", "‖") => format!("norm({})", inner),
+        ("⌊", "⌋") => format!("floor({})", inner),
+        ("⌈", "⌉") => format!("ceil({})", inner),
+        _ => {
+            let l = if l.is_empty() { "{:".to_string() } else { glyph(l) };
+            let r = if r.is_empty() { ":}".to_string() } else { glyph(r) };
+            format!("{}{}{}", l, inner, r)
+        }
+    }
+}
+
+fn top_level_comma(nodes: &[Node]) -> bool {
+    let mut depth = 0usize;
+    for n in nodes {
+        match n {
+            Node::Symbol { value: '(' | '[' } => depth += 1,
+            Node::Symbol { value: ')' | ']' } => depth = depth.saturating_sub(1),
+            Node::Symbol { value: ',' } if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Rows as `(a,b),(c,d)` between the given brackets. A cell with a top-level comma is wrapped
+/// in `{: :}` so the comma doesn't start a new column.
+fn table(open: &str, close: &str, rows: &[Vec<Vec<Node>>]) -> String {
+    let rows: Vec<String> = rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = row.iter()
+                .map(|cell| {
+                    let text = list(cell);
+                    if top_level_comma(cell) { format!("{{:{}:}}", text) } else { text }
+                })
+                .collect();
+            format!("({})", cells.join(","))
+        })
+        .collect();
+    format!("{}{}{}", open, rows.join(","), close)
+}
+
+fn environment(name: &str, rows: &[Vec<Vec<Node>>]) -> String {
+    match name {
+        "pmatrix" => table("(", ")", rows),
+        "bmatrix" => table("[", "]", rows),
+        "Bmatrix" => table("{", "}", rows),
+        "vmatrix" => table("|", "|", rows),
+        "Vmatrix" => table("||", "||", rows),
+        "cases" | "dcases" => table("{", ":}", rows),
+        "rcases" => table("{:", "}", rows),
+        // matrix, array, aligned, gathered, ...: a grid without brackets
+        _ => table("{:", ":}", rows),
+    }
+}
+
+pub fn to_asciimath(nodes: &[Node]) -> String {
+    list(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latex::parse;
+
+    fn asciimath(src: &str) -> String {
+        to_asciimath(&parse(src).nodes)
+    }
+
+    /// `(LaTeX, AsciiMath)` pairs.
+    const CORPUS: &[(&str, &str)] = &[
+        ("\\frac{a^{2}}{b}", "(a^2)/b"),
+        ("\\frac{f(x,y)}{2}", "f(x, y)/2"),
+        ("\\sqrt{x^2+y^2}", "sqrt(x^2 + y^2)"),
+        ("\\sqrt[3]{x+1}", "root(3)(x + 1)"),
+        ("e^{-x^2}", "e^(- x^2)"),
+        ("x_{i,j}", "x_(i, j)"),
+        ("x'^2 + f'(x)", "x'^2 + f'(x)"),
+        ("\\sum_{i=1}^{n} i^2 = \\frac{n(n+1)(2n+1)}{6}", "sum_(i = 1)^n i^2 = (n(n + 1) (2 n + 1))/6"),
+        ("\\sum\\limits_{i=1}^n a_i", "sum_(i = 1)^n a_i"),
+        ("\\sum_i a_i + b", "sum_i a_i + b"),
+        ("\\prod_{k=1}^n k", "prod_(k = 1)^n k"),
+        ("\\int_0^1 x^2\\,dx", "int_0^1 x^2 \\ d x"),
+        ("\\int\\limits_0^1 x^2\\,dx", "int_0^1 x^2 \\ d x"),
+        ("\\int_0^1 x^2+1\\,dx = \\frac{4}{3}", "int_0^1 x^2 + 1 \\ d x = 4/3"),
+        ("\\int_{-\\infty}^{\\infty} e^{-x^2}\\,dx = \\sqrt{\\pi}", "int_(- oo)^oo e^(- x^2) \\ d x = sqrt(pi)"),
+        ("\\iint_D f(x,y)\\,dA", "∬_D f(x, y) \\ d A"),
+        ("\\oint_C \\mathbf{F}\\cdot d\\mathbf{r}", "oint_C bb(F) * d bb(r)"),
+        ("\\int \\int f \\, dx\\, dy", "int int f \\ d x \\ d y"),
+        ("\\lim_{x\\to 0} \\frac{\\sin x}{x} = 1", "lim_(x -> 0) (sin x)/x = 1"),
+        ("\\operatorname{rank} A", "\"rank\" A"),
+        ("\\begin{pmatrix}1&2\\\\3&4\\end{pmatrix}", "((1,2),(3,4))"),
+        ("\\begin{bmatrix}a&b\\\\c&d\\end{bmatrix}", "[(a,b),(c,d)]"),
+        ("\\begin{vmatrix}a&b\\\\c&d\\end{vmatrix}", "|(a,b),(c,d)|"),
+        ("\\begin{matrix}a&b\\end{matrix}", "{:(a,b):}"),
+        ("f(x)=\\begin{cases}0 & x<0\\\\ 1 & x\\ge 0\\end{cases}", "f(x) = {(0,x < 0),(1,x >= 0):}"),
+        ("\\begin{aligned}a &= b+c \\\\ &= d\\end{aligned}", "{:(a,= b + c),(,= d):}"),
+        ("\\hat{x} + \\bar{y} + \\vec{v} + \\dot{q}", "hat(x) + bar(y) + vec(v) + dot(q)"),
+        ("\\tilde{a} + \\ddot{x}", "tilde(a) + ddot(x)"),
+        ("\\hat{AB}", "hat(A B)"),
+        ("\\overline{AB}", "bar(A B)"),
+        ("\\underbrace{a+b}_{n}", "ubrace(a + b)_n"),
+        ("\\alpha\\beta\\Gamma", "alpha beta Gamma"),
+        ("a \\leq b \\neq c \\pm d \\times e", "a <= b != c +- d xx e"),
+        ("x \\in \\mathbb{R}", "x in RR"),
+        ("\\mathbf{v}", "bb(v)"),
+        ("\\left( \\frac{a}{b} \\right)", "(a/b)"),
+        ("\\left| x \\right|", "abs(x)"),
+        ("\\binom{n}{k}", "((n),(k))"),
+        ("\\text{if } x > 0", "\"if \" x > 0"),
+        ("\\sin^2 x + \\cos^2 x = 1", "sin^2 x + cos^2 x = 1"),
+        ("\\frac{\\partial f}{\\partial x}", "(del f)/(del x)"),
+        ("\\frac{P(A)}{P(B)}", "(P(A))/(P(B))"),
+        ("\\frac{\\sin(x)}{\\sqrt{2}}", "sin(x)/sqrt(2)"),
+    ];
+
+    #[test]
+    fn corpus() {
+        for (latex, expected) in CORPUS {
+            assert_eq!(asciimath(latex), *expected, "{}", latex);
+        }
+    }
+
+    /// Different spellings of the same formula convert to the same AsciiMath.
+    #[test]
+    fn equivalent_spellings() {
+        for (a, b) in [
+            ("x^{2}", "x^2"),
+            ("\\frac12", "\\frac{1}{2}"),
+            ("\\le", "\\leq"),
+            ("\\to", "\\rightarrow"),
+            ("\\left( x \\right)", "(x)"),
+        ] {
+            assert_eq!(asciimath(a), asciimath(b), "{} vs {}", a, b);
+        }
+    }
+}
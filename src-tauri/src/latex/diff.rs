@@ -0,0 +1,180 @@
+//! Token-level diff between two LaTeX strings, used to show what a verifier changed.
+
+use serde::Serialize;
+
+use super::{render, tokenize, Token, TokenKind};
+
+/// Beyond this many token pairs the LCS table gets too large; the whole text counts as replaced.
+const MAX_TABLE_CELLS: usize = 4_000_000;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum DiffOp {
+    Equal { text: String },
+    Insert { text: String },
+    Delete { text: String },
+    Replace { from: String, to: String },
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct LatexDiff {
+    pub ops: Vec<DiffOp>,
+    /// Number of tokens only in the new text
+    pub inserted: u32,
+    /// Number of tokens only in the old text
+    pub removed: u32,
+    /// Number of replaced runs
+    pub changed: u32,
+    /// Of the inserted and removed tokens, how many are commands such as `\alpha`
+    pub commands: u32,
+    /// Of the inserted and removed tokens, how many are anything else: letters, digits, operators
+    pub symbols: u32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Edit { Equal, Insert, Delete }
+
+/// Longest-common-subsequence edit script from `a` to `b`.
+fn edit_script(a: &[Token], b: &[Token]) -> Vec<Edit> {
+    let (n, m) = (a.len(), b.len());
+    if n.saturating_mul(m) > MAX_TABLE_CELLS {
+        let mut script = vec![Edit::Delete; n];
+        script.extend(vec![Edit::Insert; m]);
+        return script;
+    }
+
+    // lcs[i][j] = LCS length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i].text == b[j].text { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let mut script = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a[i].text == b[j].text {
+            script.push(Edit::Equal);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            script.push(Edit::Delete);
+            i += 1;
+        } else {
+            script.push(Edit::Insert);
+            j += 1;
+        }
+    }
+    script.extend(vec![Edit::Delete; n - i]);
+    script.extend(vec![Edit::Insert; m - j]);
+    script
+}
+
+/// Compares `old` and `new` token by token, ignoring whitespace and comments. Adjacent
+/// deletions and insertions are reported as one `Replace`.
+pub fn diff(old: &str, new: &str) -> LatexDiff {
+    let a: Vec<Token> = tokenize(old).into_iter().filter(|t| !t.is_trivia()).collect();
+    let b: Vec<Token> = tokenize(new).into_iter().filter(|t| !t.is_trivia()).collect();
+    let script = edit_script(&a, &b);
+
+    let mut out = LatexDiff::default();
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    while k < script.len() {
+        if script[k] == Edit::Equal {
+            let start = i;
+            while k < script.len() && script[k] == Edit::Equal {
+                i += 1;
+                j += 1;
+                k += 1;
+            }
+            out.ops.push(DiffOp::Equal { text: render(&a[start..i]) });
+            continue;
+        }
+
+        let (del_start, ins_start) = (i, j);
+        while k < script.len() && script[k] != Edit::Equal {
+            if script[k] == Edit::Delete { i += 1 } else { j += 1 }
+            k += 1;
+        }
+        let from = render(&a[del_start..i]);
+        let to = render(&b[ins_start..j]);
+        out.removed += (i - del_start) as u32;
+        out.inserted += (j - ins_start) as u32;
+        for t in a[del_start..i].iter().chain(&b[ins_start..j]) {
+            if t.kind == TokenKind::Command { out.commands += 1 } else { out.symbols += 1 }
+        }
+        out.ops.push(match (from.is_empty(), to.is_empty()) {
+            (false, true) => DiffOp::Delete { text: from },
+            (true, false) => DiffOp::Insert { text: to },
+            _ => {
+                out.changed += 1;
+                DiffOp::Replace { from, to }
+            }
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn equal(text: &str) -> DiffOp {
+        DiffOp::Equal { text: text.into() }
+    }
+
+    #[test]
+    fn insertion() {
+        let d = diff("a+b", "a+b+c");
+        assert_eq!(d.ops, vec![equal("a+b"), DiffOp::Insert { text: "+c".into() }]);
+        assert_eq!((d.inserted, d.removed, d.changed), (2, 0, 0));
+        assert_eq!((d.commands, d.symbols), (0, 2));
+    }
+
+    #[test]
+    fn deletion() {
+        let d = diff("\\alpha+\\beta", "\\alpha");
+        assert_eq!(d.ops, vec![equal("\\alpha"), DiffOp::Delete { text: "+\\beta".into() }]);
+        assert_eq!((d.inserted, d.removed, d.changed), (0, 2, 0));
+        assert_eq!((d.commands, d.symbols), (1, 1));
+    }
+
+    #[test]
+    fn adjacent_delete_and_insert_merge_into_replace() {
+        let d = diff("x^{2}+1", "x^{3}+1");
+        assert_eq!(d.ops, vec![
+            equal("x^{"),
+            DiffOp::Replace { from: "2".into(), to: "3".into() },
+            equal("}+1"),
+        ]);
+        assert_eq!((d.inserted, d.removed, d.changed), (1, 1, 1));
+
+        let d = diff("\\sin x", "\\cos y");
+        assert_eq!(d.ops, vec![DiffOp::Replace { from: "\\sin x".into(), to: "\\cos y".into() }]);
+        assert_eq!((d.commands, d.symbols), (2, 2));
+    }
+
+    #[test]
+    fn ignores_whitespace_and_comments() {
+        let d = diff("a + b", "a+b % sum\n");
+        assert_eq!(d.ops, vec![equal("a+b")]);
+        assert_eq!((d.inserted, d.removed, d.changed, d.commands, d.symbols), (0, 0, 0, 0, 0));
+    }
+
+    #[test]
+    fn identical_and_empty() {
+        assert!(diff("", "").ops.is_empty());
+        assert_eq!(diff("", "x").ops, vec![DiffOp::Insert { text: "x".into() }]);
+    }
+
+    #[test]
+    fn large_inputs_fall_back_to_one_replace() {
+        // 2001 × 2001 token pairs is just over MAX_TABLE_CELLS
+        let old = "a".repeat(2001);
+        let new = format!("{}b", "a".repeat(2000));
+        let d = diff(&old, &new);
+        assert_eq!(d.ops, vec![DiffOp::Replace { from: old, to: new }]);
+        assert_eq!((d.inserted, d.removed, d.changed), (2001, 2001, 1));
+    }
+}
@@ -0,0 +1,932 @@
+//! TeX-like formula layout with an installed math font: the boxes behind the SVG and PNG images
+//! and the formulas in exported PDFs.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use fontdb::{Database, Family, Query, Weight, ID};
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+use super::parser::{split_rows, Node};
+use super::symbols::{self, Class};
+
+/// Math fonts in order of preference; without any of them a serif font is used.
+const MATH_FONTS: &[&str] = &[
+    "Latin Modern Math", "STIX Two Math", "Cambria Math", "XITS Math", "Libertinus Math",
+    "TeX Gyre Termes Math", "TeX Gyre Pagella Math", "DejaVu Math TeX Gyre",
+];
+
+/// Serif text fonts for bold prose, in order of preference.
+const TEXT_FONTS: &[&str] = &["Times New Roman", "Liberation Serif", "Noto Serif", "DejaVu Serif"];
+
+// Dimensions in em of the current size
+const AXIS: f32 = 0.25;
+const RULE: f32 = 0.04;
+const THIN: f32 = 3.0 / 18.0;
+const MEDIUM: f32 = 4.0 / 18.0;
+const THICK: f32 = 5.0 / 18.0;
+const NULL_DELIMITER: f32 = 0.12;
+
+fn fonts() -> &'static Database {
+    static FONTS: OnceLock<Database> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut db = Database::new();
+        db.load_system_fonts();
+        db
+    })
+}
+
+// ── Boxes ──
+
+#[derive(Clone)]
+pub enum Item {
+    /// Baseline origin at `(x, y)`; scales are em per font unit.
+    Glyph { face: ID, id: GlyphId, x: f32, y: f32, scale_x: f32, scale_y: f32, color: Option<String> },
+    /// `y` is the top edge.
+    Rule { x: f32, y: f32, w: f32, h: f32, color: Option<String> },
+    Line { x1: f32, y1: f32, x2: f32, y2: f32, width: f32, color: Option<String> },
+}
+
+impl Item {
+    fn translate(&mut self, dx: f32, dy: f32) {
+        match self {
+            Item::Glyph { x, y, .. } | Item::Rule { x, y, .. } => { *x += dx; *y += dy; }
+            Item::Line { x1, y1, x2, y2, .. } => { *x1 += dx; *y1 += dy; *x2 += dx; *y2 += dy; }
+        }
+    }
+
+    fn scale(&mut self, fx: f32, fy: f32) {
+        match self {
+            Item::Glyph { x, y, scale_x, scale_y, .. } => { *x *= fx; *y *= fy; *scale_x *= fx; *scale_y *= fy; }
+            Item::Rule { x, y, w, h, .. } => { *x *= fx; *y *= fy; *w *= fx; *h *= fy; }
+            Item::Line { x1, y1, x2, y2, .. } => { *x1 *= fx; *y1 *= fy; *x2 *= fx; *y2 *= fy; }
+        }
+    }
+}
+
+/// A laid out box: `asc` above and `desc` below the baseline. y grows downwards, as in SVG.
+#[derive(Clone)]
+pub struct Layout {
+    pub w: f32,
+    pub asc: f32,
+    pub desc: f32,
+    pub items: Vec<Item>,
+    class: Class,
+}
+
+impl Layout {
+    pub fn empty(w: f32) -> Self {
+        Layout { w, asc: 0.0, desc: 0.0, items: Vec::new(), class: Class::Ordinary }
+    }
+
+    fn with_class(mut self, class: Class) -> Self {
+        self.class = class;
+        self
+    }
+
+    /// Moves the contents up by `d`.
+    fn raise(&mut self, d: f32) {
+        for item in &mut self.items { item.translate(0.0, -d); }
+        self.asc += d;
+        self.desc -= d;
+    }
+
+    /// Scales around the origin; stretches delimiters, radicals and wide accents.
+    pub fn stretch(&mut self, fx: f32, fy: f32) {
+        for item in &mut self.items { item.scale(fx, fy); }
+        self.w *= fx;
+        self.asc *= fy;
+        self.desc *= fy;
+    }
+
+    fn center_on_axis(&mut self, size: f32) {
+        let middle = (self.asc - self.desc) / 2.0;
+        self.raise(AXIS * size - middle);
+    }
+
+    /// Adds `child` with its origin at `x`, its baseline raised by `up`.
+    pub fn place(&mut self, child: Layout, x: f32, up: f32) {
+        self.asc = self.asc.max(child.asc + up);
+        self.desc = self.desc.max(child.desc - up);
+        self.w = self.w.max(x + child.w);
+        self.items.extend(child.items.into_iter().map(|mut item| {
+            item.translate(x, -up);
+            item
+        }));
+    }
+
+    /// A filled rectangle whose top edge is `top` above the baseline.
+    pub fn rule(&mut self, x: f32, top: f32, w: f32, h: f32, color: Option<String>) {
+        self.asc = self.asc.max(top);
+        self.desc = self.desc.max(h - top);
+        self.w = self.w.max(x + w);
+        self.items.push(Item::Rule { x, y: -top, w, h, color });
+    }
+}
+
+#[derive(Clone)]
+struct Style {
+    /// Relative to the base font size: 1, 0.7 for scripts, 0.5 for scripts of scripts
+    size: f32,
+    display: bool,
+    variant: Option<&'static str>,
+    color: Option<String>,
+}
+
+impl Style {
+    fn script(&self) -> Style {
+        Style { size: if self.size > 0.75 { 0.7 } else { 0.5 }, display: false, ..self.clone() }
+    }
+
+    /// Numerator and denominator.
+    fn fraction(&self) -> Style {
+        if self.display { Style { display: false, ..self.clone() } } else { self.script() }
+    }
+
+    fn inline(&self) -> Style {
+        Style { display: false, ..self.clone() }
+    }
+
+    fn upright(&self) -> Style {
+        Style { variant: None, ..self.clone() }
+    }
+}
+
+/// TeX's spacing between neighbouring atoms; none in scripts except around operators.
+fn spacing(left: Class, right: Class, style: &Style) -> f32 {
+    use Class::*;
+    let script = style.size < 0.75;
+    let em = match (left, right) {
+        (Open, _) | (_, Close) | (_, Punct) | (Relation, Relation) => 0.0,
+        (Binary, _) | (_, Binary) if !script => MEDIUM,
+        (Relation, _) | (_, Relation) if !script => THICK,
+        (Punct, _) if !script => THIN,
+        (LargeOp, Ordinary | LargeOp) | (Ordinary | Close, LargeOp) => THIN,
+        _ => 0.0,
+    };
+    em * style.size
+}
+
+fn font_variant(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "mathrm" | "mathup" => "normal",
+        "mathbf" => "bold",
+        "mathit" | "mathnormal" => "italic",
+        "mathbb" => "double-struck",
+        "mathcal" | "mathscr" => "script",
+        "mathfrak" => "fraktur",
+        "mathsf" => "sans-serif",
+        "mathtt" => "monospace",
+        "boldsymbol" | "bm" => "bold-italic",
+        _ => return None,
+    })
+}
+
+/// Spacing forms of the accent marks; the combining ones have no width to center with.
+fn accent_glyph(command: &str, mark: &str) -> char {
+    match command {
+        "hat" | "widehat" => 'ˆ',
+        "tilde" | "widetilde" => '˜',
+        "grave" => 'ˋ',
+        "dddot" => '⋯',
+        _ => mark.chars().next().unwrap_or(' '),
+    }
+}
+
+/// `(class, glyph)` of a character typed directly.
+fn symbol_char(c: char) -> (Class, char) {
+    match c {
+        '-' => (Class::Binary, '−'),
+        '*' => (Class::Binary, '∗'),
+        '+' => (Class::Binary, c),
+        '=' | '<' | '>' | ':' => (Class::Relation, c),
+        '(' | '[' => (Class::Open, c),
+        ')' | ']' | '!' | '?' => (Class::Close, c),
+        ',' | ';' => (Class::Punct, c),
+        '\'' => (Class::Ordinary, '′'),
+        _ => (Class::Ordinary, c),
+    }
+}
+
+// ── Layout ──
+
+#[derive(Clone, Copy)]
+struct Metrics {
+    units_per_em: f32,
+    advance: f32,
+    y_min: f32,
+    y_max: f32,
+}
+
+pub struct Typesetter {
+    db: &'static Database,
+    primary: ID,
+    /// A bold text face for bold prose; the math font's bold letters are spaced too wide for it
+    bold: Option<ID>,
+    glyphs: HashMap<char, Option<(ID, GlyphId, Metrics)>>,
+}
+
+impl Typesetter {
+    pub fn new() -> Result<Self, String> {
+        let db = fonts();
+        let query = |family: Family| db.query(&Query { families: &[family], ..Query::default() });
+        let primary = MATH_FONTS.iter().find_map(|name| query(Family::Name(name)))
+            .or_else(|| query(Family::Serif))
+            .or_else(|| db.faces().next().map(|f| f.id))
+            .ok_or_else(|| "未找到可用的字体，无法渲染公式".to_string())?;
+        let bold = TEXT_FONTS.iter().map(|name| Family::Name(name)).chain([Family::Serif])
+            .filter_map(|family| db.query(&Query { families: &[family], weight: Weight::BOLD, ..Query::default() }))
+            .find(|id| db.face(*id).is_some_and(|f| f.weight >= Weight::SEMIBOLD));
+        Ok(Typesetter { db, primary, bold, glyphs: HashMap::new() })
+    }
+
+    /// A formula at 1em; all dimensions are in em.
+    pub fn formula(&mut self, nodes: &[Node], display: bool) -> Layout {
+        self.list(nodes, &Style { size: 1.0, display, variant: None, color: None })
+    }
+
+    /// Running text at 1em, for the prose around formulas in exported documents.
+    pub fn text(&mut self, s: &str, bold: bool) -> Layout {
+        let style = Style { size: 1.0, display: false, variant: bold.then_some("bold"), color: None };
+        let Some(face) = self.bold.filter(|_| bold) else { return self.word(s, &style) };
+        let mut out = Layout::empty(0.0);
+        for c in s.chars() {
+            let g = match face_glyph(self.db, face, c) {
+                _ if c == ' ' => Layout::empty(0.25),
+                Some(found) => glyph_box(found, &style),
+                None => self.styled_glyph(c, &style, "normal"),
+            };
+            let x = out.w;
+            out.place(g, x, 0.0);
+        }
+        out
+    }
+
+    /// A line of code at 1em in the math font's monospace letters, every character at least one
+    /// column wide so indentation lines up.
+    pub fn code(&mut self, s: &str) -> Layout {
+        let style = Style { size: 1.0, display: false, variant: Some("monospace"), color: None };
+        let column = self.styled_glyph('0', &style, "normal").w;
+        let mut out = Layout::empty(0.0);
+        let mut x = 0.0;
+        for c in s.chars() {
+            if c == ' ' {
+                x += column;
+                continue;
+            }
+            let g = self.styled_glyph(c, &style, "normal");
+            let w = g.w;
+            out.place(g, x, 0.0);
+            x += w.max(column);
+        }
+        out.w = x;
+        out
+    }
+
+    /// Feeds the outline of a glyph in font units, y up, to `builder`.
+    pub fn outline(&self, face: ID, id: GlyphId, builder: &mut impl OutlineBuilder) {
+        self.db.with_face_data(face, |data, index| {
+            Face::parse(data, index).ok().and_then(|parsed| parsed.outline_glyph(id, builder))
+        });
+    }
+
+    /// The math font first, then whichever installed font has the character (CJK in `\text`).
+    fn lookup(&mut self, c: char) -> Option<(ID, GlyphId, Metrics)> {
+        if let Some(hit) = self.glyphs.get(&c) { return *hit; }
+        let db = self.db;
+        let faces = std::iter::once(self.primary).chain(db.faces().map(|f| f.id).filter(|id| *id != self.primary));
+        let found = faces.into_iter().find_map(|face| face_glyph(db, face, c));
+        self.glyphs.insert(c, found);
+        found
+    }
+
+    fn glyph(&mut self, c: char, style: &Style) -> Layout {
+        match self.lookup(c) {
+            Some(found) => glyph_box(found, style),
+            None => Layout::empty(0.5 * style.size),
+        }
+    }
+
+    /// `c` in the style's variant when the font has that form, letters italic by default.
+    fn styled_glyph(&mut self, c: char, style: &Style, default: &str) -> Layout {
+        let styled = symbols::styled(c, style.variant.unwrap_or(default));
+        if styled != c && self.lookup(styled).is_some() {
+            self.glyph(styled, style)
+        } else {
+            self.glyph(c, style)
+        }
+    }
+
+    /// Upright characters side by side, e.g. operator names and `\text`.
+    fn word(&mut self, s: &str, style: &Style) -> Layout {
+        let mut out = Layout::empty(0.0);
+        let mut x = 0.0;
+        for c in s.chars() {
+            if c == ' ' || c == '\u{00A0}' {
+                x += 0.25 * style.size;
+                continue;
+            }
+            let g = self.styled_glyph(c, style, "normal");
+            let w = g.w;
+            out.place(g, x, 0.0);
+            x += w;
+        }
+        out.w = x;
+        out
+    }
+
+    fn list(&mut self, nodes: &[Node], style: &Style) -> Layout {
+        self.list_after(nodes, style, None)
+    }
+
+    /// `after`: class of an invisible atom before the list, so `&= b` spaces its `=`.
+    fn list_after(&mut self, nodes: &[Node], style: &Style, after: Option<Class>) -> Layout {
+        if let Some(rows) = split_rows(nodes) {
+            return self.aligned(&rows, style);
+        }
+        let mut style = style.clone();
+        let mut atoms = Vec::new();
+        for n in nodes {
+            if let Node::Command { name, args, .. } = n {
+                match name.as_str() {
+                    "displaystyle" => { style.display = true; continue; }
+                    "textstyle" => { style.display = false; continue; }
+                    "scriptstyle" | "scriptscriptstyle" => { style = style.script(); continue; }
+                    "color" => { style.color = args.first().map(Node::plain_text); continue; }
+                    _ => {}
+                }
+            }
+            atoms.push(self.node(n, &style));
+        }
+        hpack(atoms, &style, after)
+    }
+
+    fn argument(&mut self, n: Option<&Node>, style: &Style) -> Layout {
+        match n {
+            Some(Node::Group { body }) => self.list(body, style),
+            Some(n) => self.node(n, style),
+            None => Layout::empty(0.0),
+        }
+    }
+
+    fn node(&mut self, n: &Node, style: &Style) -> Layout {
+        match n {
+            Node::Letter { value } => self.styled_glyph(*value, style, "italic"),
+            Node::Number { value } => self.word(value, style),
+            Node::Symbol { value } => {
+                if *value == '~' { return Layout::empty(0.25 * style.size); }
+                let (class, c) = symbol_char(*value);
+                self.glyph(c, style).with_class(class)
+            }
+            Node::Group { body } => self.list(body, style).with_class(Class::Ordinary),
+            Node::Command { name, optional, args } => self.command(name, optional.as_deref(), args, style),
+            Node::Text { command, value } => {
+                let variant = match command.as_str() {
+                    "tag" | "tag*" | "label" => return Layout::empty(0.0),
+                    "textbf" => Some("bold"),
+                    "textit" => Some("italic"),
+                    "textsf" => Some("sans-serif"),
+                    "texttt" => Some("monospace"),
+                    _ => None,
+                };
+                self.word(value, &Style { variant, ..style.clone() })
+            }
+            Node::Scripts { base, sub, sup } => self.scripts(base, sub.as_deref(), sup.as_deref(), style),
+            Node::Delimited { left, right, body } => {
+                let inner = self.list(body, style);
+                self.fence(symbols::delimiter(left), symbols::delimiter(right), inner, style)
+            }
+            Node::Sized { size, delim } => {
+                let height = match size.trim_end_matches(['l', 'r', 'm']) {
+                    "big" => 1.2,
+                    "Big" => 1.8,
+                    "bigg" => 2.4,
+                    "Bigg" => 3.0,
+                    _ => 1.2,
+                } * style.size;
+                let class = match size.chars().last() {
+                    Some('l') => Class::Open,
+                    Some('r') => Class::Close,
+                    Some('m') => Class::Relation,
+                    _ => Class::Ordinary,
+                };
+                self.delimiter(symbols::delimiter(delim), height, style).with_class(class)
+            }
+            Node::Environment { name, args, rows } => self.environment(name, args, rows, style),
+            Node::Align | Node::LineBreak => Layout::empty(0.0),
+        }
+    }
+
+    fn command(&mut self, name: &str, optional: Option<&Node>, args: &[Node], style: &Style) -> Layout {
+        let arg0 = args.first();
+        let arg1 = args.get(1);
+        let s = style.size;
+
+        if let Some(v) = font_variant(name) {
+            return self.argument(arg0, &Style { variant: Some(v), ..style.clone() });
+        }
+        if let Some((mark, under)) = symbols::accent(name) {
+            return self.accent(name, mark, under, arg0, style);
+        }
+        if let Some(em) = symbols::space(name) {
+            return Layout::empty(em * s);
+        }
+        if symbols::function(name).is_some() {
+            return self.word(name, &style.upright()).with_class(Class::LargeOp);
+        }
+        if let Some((u, class)) = symbols::symbol(name) {
+            return match class {
+                Class::LargeOp => self.large_operator(name, u, style),
+                Class::Ordinary => self.word(u, style),
+                _ => self.word(u, &style.upright()).with_class(class),
+            };
+        }
+
+        match name {
+            "frac" | "cfrac" => self.fraction(arg0, arg1, style, true),
+            "dfrac" => self.fraction(arg0, arg1, &Style { display: true, ..style.clone() }, true),
+            "tfrac" => self.fraction(arg0, arg1, &style.inline(), true),
+            "binom" | "dbinom" | "tbinom" => {
+                let style = match name {
+                    "dbinom" => Style { display: true, ..style.clone() },
+                    "tbinom" => style.inline(),
+                    _ => style.clone(),
+                };
+                let inner = self.fraction(arg0, arg1, &style, false);
+                self.fence("(", ")", inner, &style)
+            }
+            "sqrt" => self.radical(optional, arg0, style),
+            "overset" | "stackrel" | "underset" => {
+                let base = self.argument(arg1, style);
+                let note = Some(self.argument(arg0, &style.script()));
+                if name == "underset" { stack(base, None, note, s) } else { stack(base, note, None, s) }
+            }
+            "operatorname" | "operatorname*" => {
+                let text = arg0.map(Node::plain_text).unwrap_or_default();
+                self.word(&text, &style.upright()).with_class(Class::LargeOp)
+            }
+            "boxed" => {
+                let body = self.argument(arg0, style);
+                let (pad, t) = (0.25 * s, RULE * s);
+                let (w, top, bottom) = (body.w + 2.0 * (pad + t), body.asc + pad + t, body.desc + pad + t);
+                let mut out = Layout::empty(0.0);
+                out.place(body, pad + t, 0.0);
+                let color = style.color.clone();
+                out.rule(0.0, top, w, t, color.clone());
+                out.rule(0.0, t - bottom, w, t, color.clone());
+                out.rule(0.0, top, t, top + bottom, color.clone());
+                out.rule(w - t, top, t, top + bottom, color);
+                out
+            }
+            "cancel" | "bcancel" | "xcancel" => {
+                let mut out = self.argument(arg0, style);
+                let (w, asc, desc) = (out.w, out.asc, out.desc);
+                let line = |rising: bool| Item::Line {
+                    x1: 0.0, y1: if rising { desc } else { -asc },
+                    x2: w, y2: if rising { -asc } else { desc },
+                    width: RULE * s, color: style.color.clone(),
+                };
+                if name != "bcancel" { out.items.push(line(true)); }
+                if name != "cancel" { out.items.push(line(false)); }
+                out
+            }
+            "phantom" | "hphantom" | "vphantom" => {
+                let mut out = self.argument(arg0, style);
+                out.items.clear();
+                if name == "hphantom" { out.asc = 0.0; out.desc = 0.0; }
+                if name == "vphantom" { out.w = 0.0; }
+                out
+            }
+            "textcolor" => {
+                let color = arg0.map(Node::plain_text);
+                self.argument(arg1, &Style { color, ..style.clone() })
+            }
+            "pmod" => {
+                let upright = style.upright();
+                let atoms = vec![
+                    Layout::empty(s),
+                    self.glyph('(', style).with_class(Class::Open),
+                    self.word("mod", &upright),
+                    Layout::empty(THIN * 2.0 * s),
+                    self.argument(arg0, style),
+                    self.glyph(')', style).with_class(Class::Close),
+                ];
+                hpack(atoms, style, None)
+            }
+            "not" => {
+                let op = arg0.map(Node::plain_text).unwrap_or_default();
+                let negated = symbols::negated(&op);
+                if !negated.ends_with('\u{0338}') {
+                    return self.word(&negated, &style.upright()).with_class(Class::Relation);
+                }
+                let mut out = self.word(&op, &style.upright()).with_class(Class::Relation);
+                out.items.push(Item::Line {
+                    x1: out.w * 0.25, y1: 0.2 * s, x2: out.w * 0.75, y2: -0.75 * s,
+                    width: RULE * s, color: style.color.clone(),
+                });
+                out
+            }
+            "xrightarrow" | "xleftarrow" => {
+                let script = style.script();
+                let over = self.argument(arg0, &script);
+                let under = optional.map(|n| self.argument(Some(n), &script));
+                let mut arrow = self.glyph(if name == "xrightarrow" { '→' } else { '←' }, &style.upright());
+                let target = over.w.max(under.as_ref().map_or(0.0, |u| u.w)) + 0.6 * s;
+                if arrow.w > 0.0 && arrow.w < target { arrow.stretch(target / arrow.w, 1.0); }
+                stack(arrow, Some(over), under, s).with_class(Class::Relation)
+            }
+            "limits" | "nolimits" | "mathnormal" | "substack" => self.argument(arg0, style),
+            "displaystyle" | "textstyle" | "scriptstyle" | "scriptscriptstyle" | "color"
+            | "hline" | "nonumber" | "notag" => Layout::empty(0.0),
+            _ => {
+                let red = Style { color: Some("#cc0000".to_string()), ..style.upright() };
+                self.word(&format!("\\{}", name), &red)
+            }
+        }
+    }
+
+    /// Larger in display style, centered on the math axis.
+    fn large_operator(&mut self, name: &str, u: &str, style: &Style) -> Layout {
+        let mut op = self.word(u, &style.upright());
+        let integral = matches!(name, "int" | "iint" | "iiint" | "oint");
+        if style.display {
+            if integral { op.stretch(1.3, 2.0) } else { op.stretch(1.4, 1.4) }
+        }
+        op.center_on_axis(style.size);
+        op.with_class(Class::LargeOp)
+    }
+
+    fn fraction(&mut self, num: Option<&Node>, den: Option<&Node>, style: &Style, bar: bool) -> Layout {
+        let inner = style.fraction();
+        let n = self.argument(num, &inner);
+        let d = self.argument(den, &inner);
+        let s = style.size;
+        let axis = AXIS * s;
+        let t = if bar { RULE * s } else { 0.0 };
+        let gap = if style.display { 0.15 } else { 0.08 } * s;
+        let w = n.w.max(d.w) + 2.0 * NULL_DELIMITER * s;
+        let num_up = (axis + t / 2.0 + gap + n.desc).max(if style.display { 0.68 } else { 0.4 } * s);
+        let den_down = (d.asc + gap + t / 2.0 - axis).max(if style.display { 0.69 } else { 0.35 } * s);
+
+        let mut out = Layout::empty(w);
+        let (nw, dw) = (n.w, d.w);
+        out.place(n, (w - nw) / 2.0, num_up);
+        out.place(d, (w - dw) / 2.0, -den_down);
+        if bar {
+            out.rule(NULL_DELIMITER * s, axis + t / 2.0, w - 2.0 * NULL_DELIMITER * s, t, style.color.clone());
+        }
+        out
+    }
+
+    fn radical(&mut self, index: Option<&Node>, body: Option<&Node>, style: &Style) -> Layout {
+        let b = self.argument(body, style);
+        let s = style.size;
+        let t = RULE * s;
+        let gap = if style.display { 0.15 } else { 0.1 } * s;
+        let top = b.asc + gap + t;
+        let mut sign = self.glyph('√', &style.upright());
+        let natural = sign.asc + sign.desc;
+        if natural > 0.0 && top + b.desc > natural { sign.stretch(1.0, (top + b.desc) / natural); }
+        // The vinculum continues from the sign's top right corner
+        sign.raise(top - sign.asc);
+
+        let mut out = Layout::empty(0.0);
+        let mut x = 0.0;
+        if let Some(index) = index {
+            let small = Style { size: 0.5 * s.max(0.7), display: false, ..style.clone() };
+            let i = self.argument(Some(index), &small);
+            x = (i.w - 0.5 * sign.w).max(0.0);
+            let up = -sign.desc + 0.6 * (sign.asc + sign.desc) + i.desc;
+            let iw = i.w;
+            out.place(i, (x + 0.55 * sign.w - iw).max(0.0), up);
+        }
+        let (sign_w, body_w) = (sign.w, b.w);
+        out.place(sign, x, 0.0);
+        out.rule(x + sign_w, top, body_w + 0.05 * s, t, style.color.clone());
+        out.place(b, x + sign_w, 0.0);
+        out.w = x + sign_w + body_w + 0.05 * s;
+        out
+    }
+
+    fn scripts(&mut self, base: &Node, sub: Option<&Node>, sup: Option<&Node>, style: &Style) -> Layout {
+        let b = self.node(base, style);
+        let script = style.script();
+        let sub = sub.map(|n| self.argument(Some(n), &script));
+        let sup = sup.map(|n| self.argument(Some(n), &script));
+        let s = style.size;
+        if takes_limits(base, style.display) {
+            return stack(b, sup, sub, s);
+        }
+
+        let class = b.class;
+        let (bw, basc, bdesc) = (b.w, b.asc, b.desc);
+        let mut out = Layout::empty(0.0);
+        out.place(b, 0.0, 0.0);
+        let mut sup_up = sup.as_ref().map_or(0.0, |l| (basc - 0.25 * s).max(0.4 * s).max(l.desc + 0.1 * s));
+        let mut sub_down = sub.as_ref().map_or(0.0, |l| (bdesc + 0.05 * s).max(0.2 * s).max(l.asc - 0.36 * s));
+        if let (Some(p), Some(b)) = (&sup, &sub) {
+            let clearance = (sup_up - p.desc) - (b.asc - sub_down);
+            if clearance < 0.15 * s {
+                sub_down += (0.15 * s - clearance) / 2.0;
+                sup_up += (0.15 * s - clearance) / 2.0;
+            }
+        }
+        let mut w = bw;
+        if let Some(p) = sup {
+            w = w.max(bw + p.w);
+            out.place(p, bw, sup_up);
+        }
+        if let Some(b) = sub {
+            w = w.max(bw + b.w);
+            out.place(b, bw, -sub_down);
+        }
+        out.w = w + 0.05 * s;
+        out.with_class(class)
+    }
+
+    fn accent(&mut self, name: &str, mark: &str, under: bool, arg: Option<&Node>, style: &Style) -> Layout {
+        let body = self.argument(arg, style);
+        let s = style.size;
+        let (bw, basc, bdesc) = (body.w, body.asc, body.desc);
+        let mut out = Layout::empty(0.0);
+        out.place(body, 0.0, 0.0);
+        match name {
+            "overline" => out.rule(0.0, basc + 0.12 * s + RULE * s, bw, RULE * s, style.color.clone()),
+            "underline" => out.rule(0.0, -(bdesc + 0.12 * s), bw, RULE * s, style.color.clone()),
+            _ => {
+                let small = if name == "vec" { style.script() } else { style.clone() };
+                let mut a = self.glyph(accent_glyph(name, mark), &small.upright());
+                let wide = name.starts_with("wide") || name.starts_with("over") || name.starts_with("under");
+                if wide && a.w > 0.0 && a.w < bw { a.stretch(bw / a.w, 1.0); }
+                let gap = 0.05 * s;
+                // Marks sit high in the font, so place them by their outline rather than their box
+                let up = if under { -(bdesc + gap + a.asc) } else { basc + gap + a.desc };
+                let aw = a.w;
+                out.place(a, (bw - aw) / 2.0, up);
+            }
+        }
+        out.w = out.w.max(bw);
+        out
+    }
+
+    /// A delimiter at least `height` tall, centered on the axis.
+    fn delimiter(&mut self, d: &str, height: f32, style: &Style) -> Layout {
+        if d.is_empty() { return Layout::empty(NULL_DELIMITER * style.size); }
+        let mut g = self.word(d, &style.upright());
+        let natural = g.asc + g.desc;
+        if natural > 0.0 && height > natural { g.stretch(1.0, height / natural); }
+        g.center_on_axis(style.size);
+        g
+    }
+
+    /// `inner` between delimiters that grow to cover it.
+    fn fence(&mut self, left: &str, right: &str, inner: Layout, style: &Style) -> Layout {
+        let axis = AXIS * style.size;
+        let height = 2.0 * (inner.asc - axis).max(inner.desc + axis);
+        let atoms = vec![
+            self.delimiter(left, height, style).with_class(Class::Open),
+            inner,
+            self.delimiter(right, height, style).with_class(Class::Close),
+        ];
+        hpack(atoms, style, None)
+    }
+
+    fn environment(&mut self, name: &str, args: &[String], rows: &[Vec<Vec<Node>>], style: &Style) -> Layout {
+        let s = style.size;
+        let inline = style.inline();
+        let matrix = |r: &mut Typesetter| r.table(rows, &inline, |_| 'c', |_| s, 0.2 * s, false);
+        let (left, right, body) = match name {
+            "pmatrix" => ("(", ")", matrix(self)),
+            "bmatrix" => ("[", "]", matrix(self)),
+            "Bmatrix" => ("{", "}", matrix(self)),
+            "vmatrix" => ("|", "|", matrix(self)),
+            "Vmatrix" => ("‖", "‖", matrix(self)),
+            "cases" | "dcases" | "rcases" => {
+                let cells = if name == "dcases" { style.clone() } else { inline };
+                let body = self.table(rows, &cells, |_| 'l', |_| s, 0.3 * s, false);
+                if name == "rcases" { ("", "}", body) } else { ("{", "", body) }
+            }
+            "array" | "subarray" => {
+                let spec: Vec<char> = args.first().map_or(Vec::new(), |a| a.chars().filter(|c| "lcr".contains(*c)).collect());
+                ("", "", self.table(rows, &inline, |j| spec.get(j).copied().unwrap_or('c'), |_| s, 0.2 * s, false))
+            }
+            "aligned" | "alignedat" | "align" | "align*" | "split" | "eqnarray" => return self.aligned(rows, style),
+            "gathered" | "gather" | "gather*" | "multline" | "multline*" => {
+                ("", "", self.table(rows, &Style { display: true, ..style.clone() }, |_| 'c', |_| s, 0.3 * s, false))
+            }
+            _ => ("", "", matrix(self)),
+        };
+        if left.is_empty() && right.is_empty() { return body; }
+        self.fence(left, right, body, style)
+    }
+
+    /// Columns alternate right and left aligned, pairs apart, as `aligned` lines up at `&`.
+    fn aligned(&mut self, rows: &[Vec<Vec<Node>>], style: &Style) -> Layout {
+        let s = style.size;
+        let display = Style { display: true, ..style.clone() };
+        self.table(
+            rows, &display,
+            |j| if j % 2 == 0 { 'r' } else { 'l' },
+            |j| if j % 2 == 0 { 2.0 * s } else { 0.0 },
+            0.3 * s, true,
+        )
+    }
+
+    /// A grid centered on the axis. `gap(j)` is the space before column `j`.
+    fn table(
+        &mut self,
+        rows: &[Vec<Vec<Node>>],
+        style: &Style,
+        align: impl Fn(usize) -> char,
+        gap: impl Fn(usize) -> f32,
+        row_gap: f32,
+        relation_cells: bool,
+    ) -> Layout {
+        let s = style.size;
+        let cells: Vec<Vec<Layout>> = rows.iter().map(|row| {
+            row.iter().enumerate().map(|(j, cell)| {
+                let after = if relation_cells && j % 2 == 1 { Some(Class::Ordinary) } else { None };
+                self.list_after(cell, style, after)
+            }).collect()
+        }).collect();
+        let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+        let mut widths = vec![0.0f32; columns];
+        for row in &cells {
+            for (j, cell) in row.iter().enumerate() { widths[j] = widths[j].max(cell.w); }
+        }
+        let mut xs = Vec::with_capacity(columns);
+        let mut x = 0.0;
+        for (j, w) in widths.iter().enumerate() {
+            if j > 0 { x += gap(j); }
+            xs.push(x);
+            x += w;
+        }
+
+        let mut out = Layout::empty(x);
+        let mut baseline = 0.0;
+        for (i, row) in cells.into_iter().enumerate() {
+            let asc = row.iter().fold(0.7 * s, |m, c| m.max(c.asc));
+            let desc = row.iter().fold(0.3 * s, |m, c| m.max(c.desc));
+            if i > 0 { baseline += row_gap + asc; }
+            for (j, cell) in row.into_iter().enumerate() {
+                let offset = match align(j) {
+                    'l' => 0.0,
+                    'r' => widths[j] - cell.w,
+                    _ => (widths[j] - cell.w) / 2.0,
+                };
+                out.place(cell, xs[j] + offset, -baseline);
+            }
+            baseline += desc;
+        }
+        out.center_on_axis(s);
+        out
+    }
+}
+
+fn face_glyph(db: &Database, face: ID, c: char) -> Option<(ID, GlyphId, Metrics)> {
+    db.with_face_data(face, |data, index| {
+        let parsed = Face::parse(data, index).ok()?;
+        let id = parsed.glyph_index(c)?;
+        let bbox = parsed.glyph_bounding_box(id);
+        Some((face, id, Metrics {
+            units_per_em: parsed.units_per_em() as f32,
+            advance: parsed.glyph_hor_advance(id).unwrap_or(0) as f32,
+            y_min: bbox.map_or(0.0, |b| b.y_min as f32),
+            y_max: bbox.map_or(0.0, |b| b.y_max as f32),
+        }))
+    })
+    .flatten()
+}
+
+fn glyph_box((face, id, m): (ID, GlyphId, Metrics), style: &Style) -> Layout {
+    let k = style.size / m.units_per_em;
+    Layout {
+        w: m.advance * k,
+        asc: m.y_max * k,
+        desc: -m.y_min * k,
+        items: vec![Item::Glyph { face, id, x: 0.0, y: 0.0, scale_x: k, scale_y: k, color: style.color.clone() }],
+        class: Class::Ordinary,
+    }
+}
+
+/// Atoms side by side with TeX's spacing between them.
+fn hpack(mut atoms: Vec<Layout>, style: &Style, after: Option<Class>) -> Layout {
+    // A binary operator without an operand on either side is ordinary: the `-` in `-x`
+    for i in 0..atoms.len() {
+        if atoms[i].class != Class::Binary { continue; }
+        let before = if i == 0 { after } else { Some(atoms[i - 1].class) };
+        let next = atoms.get(i + 1).map(|a| a.class);
+        if matches!(before, None | Some(Class::Binary | Class::Relation | Class::Open | Class::Punct | Class::LargeOp))
+            || matches!(next, None | Some(Class::Relation | Class::Close | Class::Punct))
+        {
+            atoms[i].class = Class::Ordinary;
+        }
+    }
+    let class = if atoms.len() == 1 { atoms[0].class } else { Class::Ordinary };
+    let mut out = Layout::empty(0.0);
+    let mut x = 0.0;
+    let mut prev = after;
+    for atom in atoms {
+        if let Some(p) = prev { x += spacing(p, atom.class, style); }
+        prev = Some(atom.class);
+        let w = atom.w;
+        out.place(atom, x, 0.0);
+        x += w;
+    }
+    out.w = x;
+    out.with_class(class)
+}
+
+/// `over` and `under` centered on `base`: limits, `\overset`, `\xrightarrow`.
+fn stack(base: Layout, over: Option<Layout>, under: Option<Layout>, s: f32) -> Layout {
+    let w = [Some(&base), over.as_ref(), under.as_ref()].into_iter().flatten().fold(0.0f32, |m, l| m.max(l.w));
+    let gap = 0.12 * s;
+    let (class, basc, bdesc, bw) = (base.class, base.asc, base.desc, base.w);
+    let mut out = Layout::empty(w);
+    out.place(base, (w - bw) / 2.0, 0.0);
+    if let Some(o) = over {
+        let (up, ow) = (basc + gap + o.desc, o.w);
+        out.place(o, (w - ow) / 2.0, up);
+    }
+    if let Some(u) = under {
+        let (up, uw) = (-(bdesc + gap + u.asc), u.w);
+        out.place(u, (w - uw) / 2.0, up);
+    }
+    out.with_class(class)
+}
+
+fn takes_limits(base: &Node, display: bool) -> bool {
+    let Node::Command { name, .. } = base else { return false };
+    match name.as_str() {
+        "limits" => true,
+        "nolimits" => false,
+        "operatorname*" | "overbrace" | "underbrace" => true,
+        n if symbols::function(n) == Some(true) => display,
+        n => matches!(symbols::symbol(n), Some((_, Class::LargeOp)))
+            && !matches!(n, "int" | "iint" | "iiint" | "oint")
+            && display,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latex::parse;
+
+    /// Nested scripts, fractions and roots, as recognition results nest them.
+    const NESTED: &[&str] = &[
+        "x^{a^{b^{c}}}",
+        "x_{i_{j}}^{2}",
+        "\\frac{\\frac{a}{b}}{\\frac{c}{d}}",
+        "\\frac{1}{1+\\frac{1}{1+\\frac{1}{x}}}",
+        "\\sqrt{\\sqrt{\\sqrt{x}}}",
+        "\\sqrt[n]{\\frac{a^2}{b_1}}",
+        "e^{\\frac{-x^2}{2\\sigma^2}}",
+        "\\sum_{i=1}^{n} \\frac{\\sqrt{i}}{i^{2}}",
+        "\\left(\\frac{a}{b}\\right)^{\\sqrt{2}}",
+    ];
+
+    #[test]
+    fn nested_boxes_are_positive() {
+        let mut typesetter = Typesetter::new().expect("no font to lay out with");
+        for display in [false, true] {
+            for src in NESTED {
+                let layout = typesetter.formula(&parse(src).nodes, display);
+                assert!(layout.w > 0.0 && layout.asc > 0.0 && layout.asc + layout.desc > 0.0, "{}", src);
+                assert!(!layout.items.is_empty(), "{}", src);
+                for item in &layout.items {
+                    match item {
+                        Item::Glyph { x, y, scale_x, scale_y, .. } => {
+                            assert!(*scale_x > 0.0 && *scale_y > 0.0, "{}", src);
+                            assert!(*x >= -0.01 && *x <= layout.w, "{}", src);
+                            assert!(-*y <= layout.asc + 0.01 && *y <= layout.desc + 0.01, "{}", src);
+                        }
+                        Item::Rule { x, y, w, h, .. } => {
+                            assert!(*w > 0.0 && *h > 0.0, "{}", src);
+                            assert!(*x >= -0.01 && x + w <= layout.w + 0.01, "{}", src);
+                            assert!(-*y <= layout.asc + 0.01 && y + h <= layout.desc + 0.01, "{}", src);
+                        }
+                        Item::Line { width, .. } => assert!(*width > 0.0, "{}", src),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scripts_and_fractions_shrink_and_stack() {
+        let mut typesetter = Typesetter::new().expect("no font to lay out with");
+        let mut lay = |src: &str| typesetter.formula(&parse(src).nodes, false);
+        let x = lay("x");
+        let squared = lay("x^2");
+        assert!(squared.w > x.w && squared.asc > x.asc);
+        // A script of a script is smaller than the script
+        let nested = lay("x^{2^{2}}");
+        assert!(nested.w > squared.w && nested.w - squared.w < squared.w - x.w);
+        // A fraction stands taller than its parts, over and under the baseline
+        let frac = lay("\\frac{a}{b}");
+        assert!(frac.asc > x.asc && frac.desc > 0.0);
+        let root = lay("\\sqrt{x}");
+        assert!(root.w > x.w && root.asc > x.asc);
+    }
+}
@@ -0,0 +1,595 @@
+//! Presentation MathML from the LaTeX AST.
+
+use super::parser::{split_rows, Node};
+use super::symbols::{self, Class};
+
+pub struct MathmlOptions {
+    /// `display="block"` instead of inline
+    pub display: bool,
+    /// Prefix every element with `mml:`, the form Word copies and pastes
+    pub word_namespace: bool,
+}
+
+const MATHML_NS: &str = "http://www.w3.org/1998/Math/MathML";
+
+/// Function application, invisible: tells readers `sin x` is a call.
+const APPLY_FUNCTION: &str = "\u{2061}";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn font_variant(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "mathbf" | "textbf" => "bold",
+        "mathrm" | "textrm" | "text" | "textnormal" | "mbox" | "hbox" => "normal",
+        "mathit" | "textit" => "italic",
+        "mathbb" => "double-struck",
+        "mathcal" | "mathscr" => "script",
+        "mathfrak" => "fraktur",
+        "mathsf" | "textsf" => "sans-serif",
+        "mathtt" | "texttt" => "monospace",
+        "boldsymbol" | "bm" => "bold-italic",
+        _ => return None,
+    })
+}
+
+/// Fences for the matrix environments.
+fn matrix_fences(env: &str) -> Option<(&'static str, &'static str)> {
+    Some(match env {
+        "matrix" | "smallmatrix" => ("", ""),
+        "pmatrix" => ("(", ")"),
+        "bmatrix" => ("[", "]"),
+        "Bmatrix" => ("{", "}"),
+        "vmatrix" => ("|", "|"),
+        "Vmatrix" => ("‖", "‖"),
+        _ => return None,
+    })
+}
+
+/// `columnalign` for `array`'s column spec, e.g. `c|cl` → `center center left`.
+fn array_columns(spec: &str) -> String {
+    spec.chars().filter_map(|c| match c {
+        'l' => Some("left"),
+        'c' => Some("center"),
+        'r' => Some("right"),
+        _ => None,
+    }).collect::<Vec<_>>().join(" ")
+}
+
+struct Emitter {
+    out: String,
+    prefix: &'static str,
+    display: bool,
+}
+
+impl Emitter {
+    fn start(&mut self, name: &str, attrs: &[(&str, &str)]) {
+        self.out.push('<');
+        self.out.push_str(self.prefix);
+        self.out.push_str(name);
+        for (k, v) in attrs {
+            self.out.push_str(&format!(" {}=\"{}\"", k, escape(v)));
+        }
+        self.out.push('>');
+    }
+
+    fn end(&mut self, name: &str) {
+        self.out.push_str(&format!("</{}{}>", self.prefix, name));
+    }
+
+    fn token(&mut self, name: &str, attrs: &[(&str, &str)], text: &str) {
+        self.start(name, attrs);
+        self.out.push_str(&escape(text));
+        self.end(name);
+    }
+
+    fn empty(&mut self, name: &str, attrs: &[(&str, &str)]) {
+        self.start(name, attrs);
+        self.end(name);
+    }
+
+    fn mi(&mut self, text: &str, variant: Option<&str>) {
+        match variant {
+            Some(v) => self.token("mi", &[("mathvariant", v)], text),
+            None => self.token("mi", &[], text),
+        }
+    }
+
+    /// An operator name: `<mo>` for those taking limits like `lim`, `<mi>` for `sin` and the like.
+    fn function(&mut self, text: &str, limits: bool) {
+        self.token(if limits { "mo" } else { "mi" }, &[], text);
+    }
+
+    fn mo(&mut self, text: &str, class: Class) {
+        match class {
+            Class::Open | Class::Close => self.token("mo", &[("stretchy", "false")], text),
+            _ => self.token("mo", &[], text),
+        }
+    }
+
+    /// `<mrow>` around `nodes`, skipped for a single node.
+    fn row(&mut self, nodes: &[Node], variant: Option<&str>) {
+        if nodes.len() == 1 && !matches!(nodes[0], Node::Command { .. }) {
+            self.node(&nodes[0], variant);
+            return;
+        }
+        self.start("mrow", &[]);
+        self.list(nodes, variant);
+        self.end("mrow");
+    }
+
+    /// A required argument: groups are unwrapped into one `<mrow>`.
+    fn arg(&mut self, node: Option<&Node>, variant: Option<&str>) {
+        match node {
+            Some(Node::Group { body }) => self.row(body, variant),
+            Some(n) => self.node(n, variant),
+            None => self.empty("mrow", &[]),
+        }
+    }
+
+    fn list(&mut self, nodes: &[Node], variant: Option<&str>) {
+        if let Some(rows) = split_rows(nodes) {
+            self.table(&rows, Some("right left"), variant);
+            return;
+        }
+        for (i, node) in nodes.iter().enumerate() {
+            // Switches apply to the rest of the list
+            if let Node::Command { name, args, .. } = node {
+                let rest = &nodes[i + 1..];
+                match name.as_str() {
+                    "color" => {
+                        let color = args.first().map(Node::plain_text).unwrap_or_default();
+                        self.start("mstyle", &[("mathcolor", &color)]);
+                        self.list(rest, variant);
+                        self.end("mstyle");
+                        return;
+                    }
+                    "displaystyle" | "textstyle" => {
+                        let on = if name == "displaystyle" { "true" } else { "false" };
+                        self.start("mstyle", &[("displaystyle", on), ("scriptlevel", "0")]);
+                        self.list(rest, variant);
+                        self.end("mstyle");
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+            self.node(node, variant);
+        }
+    }
+
+    fn node(&mut self, node: &Node, variant: Option<&str>) {
+        match node {
+            Node::Letter { value } => self.mi(&value.to_string(), variant),
+            Node::Number { value } => match variant {
+                Some(v) => self.token("mn", &[("mathvariant", v)], value),
+                None => self.token("mn", &[], value),
+            },
+            Node::Symbol { value } => self.symbol(*value),
+            Node::Group { body } => self.row(body, variant),
+            Node::Command { name, optional, args } => self.command(name, optional.as_deref(), args, variant),
+            Node::Text { command, value } => match command.as_str() {
+                "tag" | "tag*" | "label" => {}
+                _ => match font_variant(command).filter(|v| *v != "normal") {
+                    Some(v) => self.token("mtext", &[("mathvariant", v)], value),
+                    None => self.token("mtext", &[], value),
+                },
+            },
+            Node::Scripts { base, sub, sup } => self.scripts(base, sub.as_deref(), sup.as_deref(), variant),
+            Node::Delimited { left, right, body } => {
+                self.start("mrow", &[]);
+                let l = symbols::delimiter(left);
+                if !l.is_empty() { self.token("mo", &[("fence", "true")], l); }
+                self.list(body, variant);
+                let r = symbols::delimiter(right);
+                if !r.is_empty() { self.token("mo", &[("fence", "true")], r); }
+                self.end("mrow");
+            }
+            Node::Sized { size, delim } => {
+                let d = symbols::delimiter(delim);
+                if d.is_empty() { return; }
+                let em = match size.trim_end_matches(['l', 'r', 'm']) {
+                    "big" => "1.2em",
+                    "Big" => "1.623em",
+                    "bigg" => "2.047em",
+                    "Bigg" => "2.470em",
+                    _ => "",
+                };
+                if em.is_empty() {
+                    self.token("mo", &[("stretchy", "true")], d);
+                } else {
+                    self.token("mo", &[("minsize", em), ("maxsize", em)], d);
+                }
+            }
+            Node::Environment { name, args, rows } => self.environment(name, args, rows, variant),
+            // Only reachable inside a single-node row; lists turn these into tables
+            Node::Align | Node::LineBreak => {}
+        }
+    }
+
+    fn symbol(&mut self, c: char) {
+        match c {
+            '-' => self.mo("−", Class::Binary),
+            '*' => self.mo("∗", Class::Binary),
+            '\'' => self.mo("′", Class::Ordinary),
+            '+' | '=' | '<' | '>' | ',' | ';' | ':' | '!' | '/' | '|' | '.' | '?' => self.mo(&c.to_string(), Class::Ordinary),
+            '(' | '[' => self.mo(&c.to_string(), Class::Open),
+            ')' | ']' => self.mo(&c.to_string(), Class::Close),
+            c if c.is_alphanumeric() => self.mi(&c.to_string(), None),
+            c => self.mo(&c.to_string(), Class::Ordinary),
+        }
+    }
+
+    fn command(&mut self, name: &str, optional: Option<&Node>, args: &[Node], variant: Option<&str>) {
+        let arg0 = args.first();
+        let arg1 = args.get(1);
+
+        if let Some(v) = font_variant(name) {
+            self.arg(arg0, Some(v));
+            return;
+        }
+        if let Some((mark, under)) = symbols::accent(name) {
+            let stretchy = if name.starts_with("wide") || name.starts_with("over") || name.starts_with("under") { "true" } else { "false" };
+            let (el, attr) = if under { ("munder", "accentunder") } else { ("mover", "accent") };
+            self.start(el, &[(attr, "true")]);
+            self.arg(arg0, variant);
+            self.token("mo", &[("stretchy", stretchy)], mark);
+            self.end(el);
+            return;
+        }
+        if let Some(width) = symbols::space(name) {
+            self.empty("mspace", &[("width", &format!("{}em", width))]);
+            return;
+        }
+        if let Some(limits) = symbols::function(name) {
+            self.function(name, limits);
+            self.token("mo", &[], APPLY_FUNCTION);
+            return;
+        }
+        if name == "prime" {
+            self.mo("′", Class::Ordinary);
+            return;
+        }
+        if let Some((u, class)) = symbols::symbol(name) {
+            match class {
+                Class::Ordinary if !matches!(name, "ldots" | "dots" | "dotsc" | "cdots" | "dotsb" | "vdots" | "ddots") => {
+                    // Lowercase Greek stays italic like other identifiers
+                    let lower_greek = u.chars().all(|c| ('α'..='ω').contains(&c) || "ϵϑϰϖϱϕ".contains(c));
+                    self.mi(u, if lower_greek { variant } else { Some(variant.unwrap_or("normal")) });
+                }
+                _ => self.mo(u, class),
+            }
+            return;
+        }
+
+        match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let style = match name {
+                    "dfrac" | "cfrac" => Some("true"),
+                    "tfrac" => Some("false"),
+                    _ => None,
+                };
+                if let Some(s) = style { self.start("mstyle", &[("displaystyle", s), ("scriptlevel", "0")]); }
+                self.start("mfrac", &[]);
+                self.arg(arg0, variant);
+                self.arg(arg1, variant);
+                self.end("mfrac");
+                if style.is_some() { self.end("mstyle"); }
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                self.start("mrow", &[]);
+                self.mo("(", Class::Open);
+                self.start("mfrac", &[("linethickness", "0")]);
+                self.arg(arg0, variant);
+                self.arg(arg1, variant);
+                self.end("mfrac");
+                self.mo(")", Class::Close);
+                self.end("mrow");
+            }
+            "sqrt" => match optional {
+                Some(index) => {
+                    self.start("mroot", &[]);
+                    self.arg(arg0, variant);
+                    self.arg(Some(index), variant);
+                    self.end("mroot");
+                }
+                None => {
+                    self.start("msqrt", &[]);
+                    self.arg(arg0, variant);
+                    self.end("msqrt");
+                }
+            },
+            "overset" | "stackrel" | "underset" => {
+                let el = if name == "underset" { "munder" } else { "mover" };
+                self.start(el, &[]);
+                self.arg(arg1, variant);
+                self.arg(arg0, variant);
+                self.end(el);
+            }
+            "operatorname" | "operatorname*" => {
+                let text = arg0.map(Node::plain_text).unwrap_or_default();
+                self.function(&text, name == "operatorname*");
+                self.token("mo", &[], APPLY_FUNCTION);
+            }
+            "boxed" | "cancel" | "bcancel" | "xcancel" => {
+                let notation = match name {
+                    "boxed" => "box",
+                    "cancel" => "updiagonalstrike",
+                    "bcancel" => "downdiagonalstrike",
+                    _ => "updiagonalstrike downdiagonalstrike",
+                };
+                self.start("menclose", &[("notation", notation)]);
+                self.arg(arg0, variant);
+                self.end("menclose");
+            }
+            "phantom" | "hphantom" | "vphantom" => {
+                self.start("mphantom", &[]);
+                self.arg(arg0, variant);
+                self.end("mphantom");
+            }
+            "textcolor" => {
+                let color = arg0.map(Node::plain_text).unwrap_or_default();
+                self.start("mstyle", &[("mathcolor", &color)]);
+                self.arg(arg1, variant);
+                self.end("mstyle");
+            }
+            "pmod" => {
+                self.empty("mspace", &[("width", "1em")]);
+                self.mo("(", Class::Open);
+                self.token("mi", &[], "mod");
+                self.empty("mspace", &[("width", "0.333em")]);
+                self.arg(arg0, variant);
+                self.mo(")", Class::Close);
+            }
+            "not" => {
+                let op = arg0.map(Node::plain_text).unwrap_or_default();
+                self.mo(&symbols::negated(&op), Class::Relation);
+            }
+            "xrightarrow" | "xleftarrow" => {
+                let arrow = if name == "xrightarrow" { "→" } else { "←" };
+                let el = if optional.is_some() { "munderover" } else { "mover" };
+                self.start(el, &[]);
+                self.token("mo", &[("stretchy", "true"), ("minsize", "2em")], arrow);
+                if let Some(below) = optional { self.arg(Some(below), variant); }
+                self.arg(arg0, variant);
+                self.end(el);
+            }
+            "substack" | "mathnormal" => self.arg(arg0, variant),
+            // Wrapper from the parser: the operator with explicit limits
+            "limits" | "nolimits" => self.arg(arg0, variant),
+            "displaystyle" | "textstyle" | "scriptstyle" | "scriptscriptstyle" | "color"
+            | "hline" | "nonumber" | "notag" => {}
+            _ => {
+                self.start("merror", &[]);
+                self.token("mtext", &[], &format!("\\{}", name));
+                self.end("merror");
+            }
+        }
+    }
+
+    /// Whether scripts on `base` go above and below rather than to the right.
+    fn takes_limits(&self, base: &Node) -> bool {
+        let Node::Command { name, .. } = base else { return false };
+        match name.as_str() {
+            "limits" => true,
+            "nolimits" => false,
+            "operatorname*" | "overbrace" | "underbrace" => true,
+            n if symbols::function(n) == Some(true) => self.display,
+            n => matches!(symbols::symbol(n), Some((_, Class::LargeOp)))
+                && !matches!(n, "int" | "iint" | "iiint" | "oint")
+                && self.display,
+        }
+    }
+
+    fn scripts(&mut self, base: &Node, sub: Option<&Node>, sup: Option<&Node>, variant: Option<&str>) {
+        let limits = self.takes_limits(base);
+        let el = match (limits, sub.is_some(), sup.is_some()) {
+            (true, true, true) => "munderover",
+            (true, true, false) => "munder",
+            (true, false, true) => "mover",
+            (false, true, true) => "msubsup",
+            (false, true, false) => "msub",
+            _ => "msup",
+        };
+        // Operator names carry their scripts, and function application follows the whole: `sin^2⁡x`
+        let function = match base {
+            Node::Command { name, args, .. } if name.starts_with("operatorname") => {
+                Some((args.first().map(Node::plain_text).unwrap_or_default(), name == "operatorname*"))
+            }
+            Node::Command { name, .. } => symbols::function(name).map(|limits| (name.clone(), limits)),
+            _ => None,
+        };
+        self.start(el, &[]);
+        match &function {
+            Some((text, limits)) => self.function(text, *limits),
+            None => self.arg(Some(base), variant),
+        }
+        if let Some(s) = sub { self.arg(Some(s), variant); }
+        if let Some(s) = sup { self.arg(Some(s), variant); }
+        self.end(el);
+        if function.is_some() { self.token("mo", &[], APPLY_FUNCTION); }
+    }
+
+    fn table(&mut self, rows: &[Vec<Vec<Node>>], columnalign: Option<&str>, variant: Option<&str>) {
+        let mut attrs: Vec<(&str, &str)> = Vec::new();
+        if let Some(align) = columnalign { attrs.push(("columnalign", align)); }
+        self.start("mtable", &attrs);
+        for row in rows {
+            self.start("mtr", &[]);
+            for cell in row {
+                self.start("mtd", &[]);
+                self.row(cell, variant);
+                self.end("mtd");
+            }
+            self.end("mtr");
+        }
+        self.end("mtable");
+    }
+
+    fn environment(&mut self, name: &str, args: &[String], rows: &[Vec<Vec<Node>>], variant: Option<&str>) {
+        if let Some((open, close)) = matrix_fences(name) {
+            self.start("mrow", &[]);
+            if !open.is_empty() { self.token("mo", &[("fence", "true")], open); }
+            self.table(rows, None, variant);
+            if !close.is_empty() { self.token("mo", &[("fence", "true")], close); }
+            self.end("mrow");
+            return;
+        }
+        match name {
+            "cases" | "dcases" => {
+                self.start("mrow", &[]);
+                self.token("mo", &[("fence", "true")], "{");
+                self.table(rows, Some("left left"), variant);
+                self.end("mrow");
+            }
+            "rcases" => {
+                self.start("mrow", &[]);
+                self.table(rows, Some("left left"), variant);
+                self.token("mo", &[("fence", "true")], "}");
+                self.end("mrow");
+            }
+            "array" | "subarray" => {
+                let columns = args.first().map(|s| array_columns(s)).unwrap_or_default();
+                self.table(rows, (!columns.is_empty()).then_some(columns.as_str()), variant);
+            }
+            "gathered" | "gather" | "gather*" | "equation" | "equation*" | "multline" | "multline*" => {
+                self.table(rows, None, variant)
+            }
+            // aligned, align, split, alignat, eqnarray: right/left pairs
+            _ => self.table(rows, Some("right left"), variant),
+        }
+    }
+}
+
+pub fn to_mathml(nodes: &[Node], opts: &MathmlOptions) -> String {
+    let mut e = Emitter {
+        out: String::new(),
+        prefix: if opts.word_namespace { "mml:" } else { "" },
+        display: opts.display,
+    };
+    let ns_attr = if opts.word_namespace { "xmlns:mml" } else { "xmlns" };
+    let display = if opts.display { "block" } else { "inline" };
+    e.start("math", &[(ns_attr, MATHML_NS), ("display", display)]);
+    e.row(nodes, None);
+    e.end("math");
+    e.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latex::parse;
+
+    /// The content of `<math>`, without the wrapper element.
+    fn mathml(src: &str, display: bool) -> String {
+        let mut e = Emitter { out: String::new(), prefix: "", display };
+        e.row(&parse(src).nodes, None);
+        e.out
+    }
+
+    /// `(LaTeX, inline MathML)` pairs taken from recognition results.
+    const CORPUS: &[(&str, &str)] = &[
+        ("x_i^2", "<msubsup><mi>x</mi><mi>i</mi><mn>2</mn></msubsup>"),
+        ("\\frac{a}{b}", "<mrow><mfrac><mi>a</mi><mi>b</mi></mfrac></mrow>"),
+        ("\\sqrt[3]{x}", "<mrow><mroot><mi>x</mi><mn>3</mn></mroot></mrow>"),
+        ("12.5 - x", "<mrow><mn>12.5</mn><mo>\u{2212}</mo><mi>x</mi></mrow>"),
+        ("a \\leq b \\neq c", "<mrow><mi>a</mi><mo>\u{2264}</mo><mi>b</mi><mo>\u{2260}</mo><mi>c</mi></mrow>"),
+        ("\\alpha + \\Gamma", "<mrow><mi>\u{3b1}</mi><mo>+</mo><mi mathvariant=\"normal\">\u{393}</mi></mrow>"),
+        (
+            "\\mathbf{v} \\cdot \\mathbb{R}",
+            "<mrow><mi mathvariant=\"bold\">v</mi><mo>\u{22c5}</mo><mi mathvariant=\"double-struck\">R</mi></mrow>",
+        ),
+        ("\\sin x", "<mrow><mi>sin</mi><mo>\u{2061}</mo><mi>x</mi></mrow>"),
+        ("\\sin^2 x", "<mrow><msup><mi>sin</mi><mn>2</mn></msup><mo>\u{2061}</mo><mi>x</mi></mrow>"),
+        (
+            "\\lim_{x\\to0} f(x)",
+            "<mrow><msub><mo>lim</mo><mrow><mi>x</mi><mo>\u{2192}</mo><mn>0</mn></mrow></msub><mo>\u{2061}</mo>\
+             <mi>f</mi><mo stretchy=\"false\">(</mo><mi>x</mi><mo stretchy=\"false\">)</mo></mrow>",
+        ),
+        ("\\operatorname{rank} A", "<mrow><mi>rank</mi><mo>\u{2061}</mo><mi>A</mi></mrow>"),
+        (
+            "\\operatorname*{arg\\,max}_x f(x)",
+            "<mrow><munder><mo>argmax</mo><mi>x</mi></munder><mo>\u{2061}</mo>\
+             <mi>f</mi><mo stretchy=\"false\">(</mo><mi>x</mi><mo stretchy=\"false\">)</mo></mrow>",
+        ),
+        (
+            "\\sum_{i=1}^n i",
+            "<mrow><msubsup><mo>\u{2211}</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi></msubsup><mi>i</mi></mrow>",
+        ),
+        (
+            "\\int_0^1 f\\,dx",
+            "<mrow><msubsup><mo>\u{222b}</mo><mn>0</mn><mn>1</mn></msubsup><mi>f</mi>\
+             <mspace width=\"0.1667em\"></mspace><mi>d</mi><mi>x</mi></mrow>",
+        ),
+        (
+            "\\hat{x} + \\overline{AB}",
+            "<mrow><mover accent=\"true\"><mi>x</mi><mo stretchy=\"false\">^</mo></mover><mo>+</mo>\
+             <mover accent=\"true\"><mrow><mi>A</mi><mi>B</mi></mrow><mo stretchy=\"true\">\u{203e}</mo></mover></mrow>",
+        ),
+        ("\\left( x \\right)", "<mrow><mo fence=\"true\">(</mo><mi>x</mi><mo fence=\"true\">)</mo></mrow>"),
+        (
+            "\\binom{n}{k}",
+            "<mrow><mrow><mo stretchy=\"false\">(</mo><mfrac linethickness=\"0\"><mi>n</mi><mi>k</mi></mfrac>\
+             <mo stretchy=\"false\">)</mo></mrow></mrow>",
+        ),
+        (
+            "\\begin{pmatrix}1&2\\\\3&4\\end{pmatrix}",
+            "<mrow><mo fence=\"true\">(</mo><mtable><mtr><mtd><mn>1</mn></mtd><mtd><mn>2</mn></mtd></mtr>\
+             <mtr><mtd><mn>3</mn></mtd><mtd><mn>4</mn></mtd></mtr></mtable><mo fence=\"true\">)</mo></mrow>",
+        ),
+        (
+            "f(x)=\\begin{cases}0 & x<0\\\\ 1 & x\\ge0\\end{cases}",
+            "<mrow><mi>f</mi><mo stretchy=\"false\">(</mo><mi>x</mi><mo stretchy=\"false\">)</mo><mo>=</mo>\
+             <mrow><mo fence=\"true\">{</mo><mtable columnalign=\"left left\">\
+             <mtr><mtd><mn>0</mn></mtd><mtd><mrow><mi>x</mi><mo>&lt;</mo><mn>0</mn></mrow></mtd></mtr>\
+             <mtr><mtd><mn>1</mn></mtd><mtd><mrow><mi>x</mi><mo>\u{2265}</mo><mn>0</mn></mrow></mtd></mtr>\
+             </mtable></mrow></mrow>",
+        ),
+        ("\\text{if } x", "<mrow><mtext>if </mtext><mi>x</mi></mrow>"),
+        ("\\unknowncmd", "<mrow><merror><mtext>\\unknowncmd</mtext></merror></mrow>"),
+    ];
+
+    #[test]
+    fn corpus() {
+        for (latex, expected) in CORPUS {
+            assert_eq!(mathml(latex, false), *expected, "{}", latex);
+        }
+    }
+
+    #[test]
+    fn display_limits() {
+        for (latex, expected) in [
+            (
+                "\\lim_{x\\to0} f",
+                "<mrow><munder><mo>lim</mo><mrow><mi>x</mi><mo>\u{2192}</mo><mn>0</mn></mrow></munder><mo>\u{2061}</mo><mi>f</mi></mrow>",
+            ),
+            (
+                "\\max_{i} a_i",
+                "<mrow><munder><mo>max</mo><mi>i</mi></munder><mo>\u{2061}</mo><msub><mi>a</mi><mi>i</mi></msub></mrow>",
+            ),
+            (
+                "\\sum_{i=1}^n i",
+                "<mrow><munderover><mo>\u{2211}</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi></munderover><mi>i</mi></mrow>",
+            ),
+            // Integrals keep their limits at the side even in display style
+            ("\\int_0^1 f", "<mrow><msubsup><mo>\u{222b}</mo><mn>0</mn><mn>1</mn></msubsup><mi>f</mi></mrow>"),
+            ("\\sin^2 x", "<mrow><msup><mi>sin</mi><mn>2</mn></msup><mo>\u{2061}</mo><mi>x</mi></mrow>"),
+        ] {
+            assert_eq!(mathml(latex, true), expected, "{}", latex);
+        }
+    }
+
+    #[test]
+    fn wrapper() {
+        let nodes = parse("x").nodes;
+        assert_eq!(
+            to_mathml(&nodes, &MathmlOptions { display: true, word_namespace: false }),
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\"><mi>x</mi></math>"
+        );
+        assert_eq!(
+            to_mathml(&nodes, &MathmlOptions { display: false, word_namespace: true }),
+            "<mml:math xmlns:mml=\"http://www.w3.org/1998/Math/MathML\" display=\"inline\"><mml:mi>x</mml:mi></mml:math>"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! LaTeX helpers shared by the recognition pipeline: tokenizing and normalizing model output
+//! so results from different providers can be compared.
+
+pub mod asciimath;
+mod diff;
+pub mod layout;
+pub mod mathml;
+pub mod omml;
+pub mod parser;
+pub mod symbols;
+pub mod svg;
+pub mod typst;
+pub mod unicodemath;
+
+pub use diff::{diff, LatexDiff};
+pub use parser::{check, parse, Diagnostic, Parsed};
+
+use serde::Serialize;
+
+// ── Tokens ──
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    /// `\alpha`, `\\`, `\,` (text includes the backslash)
+    Command,
+    Letter,
+    Digit,
+    /// Any other single character: operators, punctuation, `(`, `|`, ...
+    Symbol,
+    BeginGroup,
+    EndGroup,
+    Superscript,
+    Subscript,
+    /// `&` column separator
+    Align,
+    Space,
+    /// `% ...` up to the end of the line
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    /// Byte offset in the source
+    pub start: usize,
+}
+
+impl Token {
+    fn new(kind: TokenKind, text: &str, start: usize) -> Self {
+        Token { kind, text: text.to_string(), start }
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Space | TokenKind::Comment)
+    }
+}
+
+pub fn tokenize(src: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            '\\' => {
+                let mut end = start + 1;
+                match chars.peek().copied() {
+                    Some((_, n)) if n.is_ascii_alphabetic() => {
+                        while let Some(&(i, n)) = chars.peek() {
+                            if !n.is_ascii_alphabetic() { break; }
+                            end = i + n.len_utf8();
+                            chars.next();
+                        }
+                    }
+                    // Control symbol: `\\`, `\{`, `\,`, ...
+                    Some((i, n)) => {
+                        end = i + n.len_utf8();
+                        chars.next();
+                    }
+                    None => {}
+                }
+                tokens.push(Token::new(TokenKind::Command, &src[start..end], start));
+                continue;
+            }
+            '%' => {
+                let mut end = src.len();
+                for (i, n) in chars.by_ref() {
+                    if n == '\n' { end = i; break; }
+                }
+                tokens.push(Token::new(TokenKind::Comment, &src[start..end], start));
+                continue;
+            }
+            c if c.is_whitespace() => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, n)) = chars.peek() {
+                    if !n.is_whitespace() { break; }
+                    end = i + n.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::new(TokenKind::Space, &src[start..end], start));
+                continue;
+            }
+            '{' => TokenKind::BeginGroup,
+            '}' => TokenKind::EndGroup,
+            '^' => TokenKind::Superscript,
+            '_' => TokenKind::Subscript,
+            '&' => TokenKind::Align,
+            c if c.is_alphabetic() => TokenKind::Letter,
+            c if c.is_ascii_digit() => TokenKind::Digit,
+            _ => TokenKind::Symbol,
+        };
+        tokens.push(Token::new(kind, &src[start..start + c.len_utf8()], start));
+    }
+    tokens
+}
+
+/// Concatenates tokens back into source, keeping a space only where a command name would
+/// otherwise run into a following letter.
+pub fn render(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    let mut prev: Option<&Token> = None;
+    for t in tokens.iter().filter(|t| !t.is_trivia()) {
+        let needs_space = prev.is_some_and(|p| p.kind == TokenKind::Command && p.text.ends_with(|c: char| c.is_ascii_alphabetic()))
+            && t.kind == TokenKind::Letter;
+        if needs_space { out.push(' '); }
+        out.push_str(&t.text);
+        prev = Some(t);
+    }
+    out
+}
+
+// ── Normalization ──
+
+/// Sizing and spacing commands that don't change what the formula says.
+const IGNORED_COMMANDS: &[&str] = &[
+    "\\displaystyle", "\\textstyle", "\\scriptstyle", "\\left", "\\right",
+    "\\big", "\\Big", "\\bigg", "\\Bigg", "\\bigl", "\\bigr", "\\Bigl", "\\Bigr",
+    "\\biggl", "\\biggr", "\\Biggl", "\\Biggr", "\\limits", "\\nolimits",
+    "\\,", "\\;", "\\:", "\\!", "\\ ", "\\quad", "\\qquad",
+];
+
+/// Spellings that render the same; the right-hand side is canonical.
+const COMMAND_ALIASES: &[(&str, &str)] = &[
+    ("\\le", "\\leq"), ("\\ge", "\\geq"), ("\\ne", "\\neq"),
+    ("\\to", "\\rightarrow"), ("\\gets", "\\leftarrow"),
+    ("\\dfrac", "\\frac"), ("\\tfrac", "\\frac"),
+    ("\\lbrace", "\\{"), ("\\rbrace", "\\}"),
+    ("\\vert", "|"), ("\\lvert", "|"), ("\\rvert", "|"),
+    ("\\lt", "<"), ("\\gt", ">"), ("\\land", "\\wedge"), ("\\lor", "\\vee"),
+];
+
+/// Removes surrounding `$...$`, `$$...$$`, `\[...\]` or `\(...\)`.
+pub fn strip_delimiters(src: &str) -> &str {
+    let s = src.trim();
+    for (open, close) in [("$$", "$$"), ("\\[", "\\]"), ("\\(", "\\)"), ("$", "$")] {
+        if s.len() >= open.len() + close.len() && s.starts_with(open) && s.ends_with(close) {
+            return s[open.len()..s.len() - close.len()].trim();
+        }
+    }
+    s
+}
+
+/// Canonical token stream: no whitespace, comments, sizing or spacing commands, aliases
+/// resolved, and braces around a single token dropped (`x^{2}` → `x^2`).
+pub fn normalize_tokens(src: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = tokenize(strip_delimiters(src)).into_iter()
+        .filter(|t| !t.is_trivia())
+        .filter(|t| !(t.kind == TokenKind::Command && IGNORED_COMMANDS.contains(&t.text.as_str())))
+        .map(|mut t| {
+            if let Some(&(_, canonical)) = COMMAND_ALIASES.iter().find(|(alias, _)| *alias == t.text) {
+                t.kind = if canonical.starts_with('\\') { TokenKind::Command } else { TokenKind::Symbol };
+                t.text = canonical.to_string();
+            }
+            t
+        })
+        .collect();
+
+    loop {
+        let single = tokens.windows(3).position(|w| {
+            w[0].kind == TokenKind::BeginGroup
+                && w[2].kind == TokenKind::EndGroup
+                && !matches!(w[1].kind, TokenKind::BeginGroup | TokenKind::EndGroup)
+        });
+        let Some(i) = single else { break };
+        tokens.remove(i + 2);
+        tokens.remove(i);
+    }
+    tokens
+}
+
+/// [`normalize_tokens`] rendered back to a string, for comparing results.
+pub fn normalize(src: &str) -> String {
+    render(&normalize_tokens(src))
+}
@@ -0,0 +1,712 @@
+//! Office Math (OMML), Word's native equations, from the LaTeX AST.
+//! The output uses the `m:` prefix; the enclosing document declares the namespace.
+
+use super::parser::{split_rows, Node};
+use super::symbols::{self, Class};
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn font_variant(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "mathrm" | "mathup" => "normal",
+        "mathbf" => "bold",
+        "mathit" | "mathnormal" => "italic",
+        "mathbb" => "double-struck",
+        "mathcal" | "mathscr" => "script",
+        "mathfrak" => "fraktur",
+        "mathsf" => "sans-serif",
+        "mathtt" => "monospace",
+        "boldsymbol" | "bm" => "bold-italic",
+        _ => return None,
+    })
+}
+
+fn matrix_fences(env: &str) -> Option<(&'static str, &'static str)> {
+    Some(match env {
+        "matrix" | "smallmatrix" => ("", ""),
+        "pmatrix" => ("(", ")"),
+        "bmatrix" => ("[", "]"),
+        "Bmatrix" => ("{", "}"),
+        "vmatrix" => ("|", "|"),
+        "Vmatrix" => ("‖", "‖"),
+        _ => return None,
+    })
+}
+
+fn glyph(c: char) -> String {
+    match c {
+        '-' => "−".to_string(),
+        '*' => "∗".to_string(),
+        '\'' => "′".to_string(),
+        '~' => "\u{00A0}".to_string(),
+        c => c.to_string(),
+    }
+}
+
+/// Where an n-ary operator's or a function's operand ends.
+fn boundary(n: &Node) -> bool {
+    match n {
+        Node::Symbol { value } => "=<>+-,;:".contains(*value),
+        Node::Command { name, .. } => match symbols::symbol(name) {
+            Some((_, Class::Relation | Class::Punct)) => true,
+            _ => matches!(name.as_str(), "pm" | "mp" | "quad" | "qquad"),
+        },
+        Node::Align | Node::LineBreak => true,
+        _ => false,
+    }
+}
+
+enum Operator {
+    /// `\sum`, `\int`, ...: the character
+    Nary(&'static str),
+    /// `\sin`, `\operatorname{..}`: the name
+    Function(String),
+}
+
+struct Emitter {
+    out: String,
+    display: bool,
+    /// Where the last unstyled run ends, so the next one can join it as Word writes them
+    plain_run_end: usize,
+}
+
+impl Emitter {
+    fn start(&mut self, name: &str) {
+        self.out.push_str(&format!("<m:{}>", name));
+    }
+
+    fn end(&mut self, name: &str) {
+        self.out.push_str(&format!("</m:{}>", name));
+    }
+
+    /// A property element, e.g. `<m:chr m:val="∑"/>`.
+    fn prop(&mut self, name: &str, val: &str) {
+        self.out.push_str(&format!("<m:{} m:val=\"{}\"/>", name, escape(val)));
+    }
+
+    /// A math run; `sty` `p` is upright, otherwise Word italicizes letters.
+    fn run(&mut self, text: &str, sty: Option<&str>) {
+        if text.is_empty() { return; }
+        const CLOSE: &str = "</m:t></m:r>";
+        if sty.is_none() && self.plain_run_end == self.out.len() && self.out.ends_with(CLOSE) {
+            self.out.insert_str(self.out.len() - CLOSE.len(), &escape(text));
+            self.plain_run_end = self.out.len();
+            return;
+        }
+        self.out.push_str("<m:r>");
+        if let Some(s) = sty { self.out.push_str(&format!("<m:rPr><m:sty m:val=\"{}\"/></m:rPr>", s)); }
+        self.out.push_str(&format!("<m:t xml:space=\"preserve\">{}</m:t></m:r>", escape(text)));
+        if sty.is_none() { self.plain_run_end = self.out.len(); }
+    }
+
+    /// Ordinary text inside the equation, as `\text` is.
+    fn text(&mut self, text: &str, color: Option<&str>) {
+        if text.is_empty() { return; }
+        self.out.push_str("<m:r><m:rPr><m:nor/></m:rPr>");
+        if let Some(c) = color { self.out.push_str(&format!("<w:rPr><w:color w:val=\"{}\"/></w:rPr>", c)); }
+        self.out.push_str(&format!("<m:t xml:space=\"preserve\">{}</m:t></m:r>", escape(text)));
+    }
+
+    /// Letters and digits in a font style: the styled characters, upright so Word leaves them be.
+    fn letters(&mut self, text: &str, variant: Option<&str>) {
+        match variant {
+            Some(v) => self.run(&text.chars().map(|c| symbols::styled(c, v)).collect::<String>(), Some("p")),
+            None => self.run(text, None),
+        }
+    }
+
+    /// `<m:name>` around an argument.
+    fn element(&mut self, name: &str, node: Option<&Node>, variant: Option<&str>) {
+        self.start(name);
+        self.arg(node, variant);
+        self.end(name);
+    }
+
+    fn arg(&mut self, node: Option<&Node>, variant: Option<&str>) {
+        match node {
+            Some(Node::Group { body }) => self.list(body, variant),
+            Some(n) => self.node(n, variant),
+            None => {}
+        }
+    }
+
+    fn list(&mut self, nodes: &[Node], variant: Option<&str>) {
+        if let Some(rows) = split_rows(nodes) {
+            self.equation_array(&rows, variant);
+            return;
+        }
+        let mut i = 0;
+        while i < nodes.len() {
+            let node = &nodes[i];
+            i += 1;
+            // `\sum` and `\sin` take what follows as their operand, as Word builds them
+            if let Some((op, base, sub, sup)) = operator(node) {
+                let end = operand_end(nodes, i, matches!(op, Operator::Nary(_)));
+                if end > i {
+                    let limits = takes_limits(base, self.display);
+                    match op {
+                        Operator::Nary(chr) => self.nary(chr, sub, sup, limits, &nodes[i..end], variant),
+                        Operator::Function(name) => self.function(&name, sub, sup, limits, &nodes[i..end], variant),
+                    }
+                    i = end;
+                    continue;
+                }
+            }
+            self.node(node, variant);
+        }
+    }
+
+    fn node(&mut self, node: &Node, variant: Option<&str>) {
+        match node {
+            Node::Letter { value } => self.letters(&value.to_string(), variant),
+            Node::Number { value } => self.letters(value, variant),
+            Node::Symbol { value } => self.run(&glyph(*value), None),
+            Node::Group { body } => self.list(body, variant),
+            Node::Command { name, optional, args } => self.command(name, optional.as_deref(), args, variant),
+            Node::Text { command, value } => match command.as_str() {
+                "tag" | "tag*" | "label" => {}
+                _ => self.text(value, None),
+            },
+            Node::Scripts { base, sub, sup } => {
+                let limits = takes_limits(base, self.display);
+                self.attach(|e| e.arg(Some(base), variant), sub.as_deref(), sup.as_deref(), limits, variant);
+            }
+            Node::Delimited { left, right, body } => {
+                // `\middle` splits the body into separated parts
+                let parts: Vec<&[Node]> = body.split(|n| matches!(n, Node::Sized { size, .. } if size == "middle")).collect();
+                let separator = body.iter().find_map(|n| match n {
+                    Node::Sized { size, delim } if size == "middle" => Some(symbols::delimiter(delim)),
+                    _ => None,
+                });
+                self.start("d");
+                self.start("dPr");
+                self.prop("begChr", symbols::delimiter(left));
+                if let Some(s) = separator { self.prop("sepChr", s); }
+                self.prop("endChr", symbols::delimiter(right));
+                self.end("dPr");
+                for part in parts {
+                    self.start("e");
+                    self.list(part, variant);
+                    self.end("e");
+                }
+                self.end("d");
+            }
+            Node::Sized { delim, .. } => self.run(symbols::delimiter(delim), None),
+            Node::Environment { name, args, rows } => self.environment(name, args, rows, variant),
+            Node::Align | Node::LineBreak => {}
+        }
+    }
+
+    fn command(&mut self, name: &str, optional: Option<&Node>, args: &[Node], variant: Option<&str>) {
+        let arg0 = args.first();
+        let arg1 = args.get(1);
+
+        if let Some(v) = font_variant(name) {
+            self.arg(arg0, Some(v));
+            return;
+        }
+        if let Some(mark) = symbols::combining(name) {
+            self.start("acc");
+            self.start("accPr");
+            self.prop("chr", &mark.to_string());
+            self.end("accPr");
+            self.element("e", arg0, variant);
+            self.end("acc");
+            return;
+        }
+        if let Some((mark, under)) = symbols::accent(name) {
+            match name {
+                "overline" | "underline" => {
+                    self.start("bar");
+                    self.start("barPr");
+                    self.prop("pos", if under { "bot" } else { "top" });
+                    self.end("barPr");
+                    self.element("e", arg0, variant);
+                    self.end("bar");
+                }
+                _ => {
+                    // Braces and arrows stretched over or under the operand
+                    self.start("groupChr");
+                    self.start("groupChrPr");
+                    self.prop("chr", mark);
+                    self.prop("pos", if under { "bot" } else { "top" });
+                    self.prop("vertJc", if under { "top" } else { "bot" });
+                    self.end("groupChrPr");
+                    self.element("e", arg0, variant);
+                    self.end("groupChr");
+                }
+            }
+            return;
+        }
+        if let Some(em) = symbols::space(name) {
+            let space = match name {
+                "quad" => "\u{2003}",
+                "qquad" => "\u{2003}\u{2003}",
+                _ if em >= 0.27 => "\u{2004}",
+                _ if em >= 0.2 => "\u{205F}",
+                _ if em > 0.0 => "\u{2009}",
+                _ => "",
+            };
+            self.run(space, None);
+            return;
+        }
+        if symbols::function(name).is_some() {
+            self.run(name, Some("p"));
+            return;
+        }
+        if let Some((u, class)) = symbols::symbol(name) {
+            match (class, variant) {
+                (Class::Ordinary, Some(_)) => self.letters(u, variant),
+                _ => self.run(u, None),
+            }
+            return;
+        }
+
+        match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => self.fraction(arg0, arg1, true, variant),
+            "binom" | "dbinom" | "tbinom" => {
+                self.fenced("(", ")", |e| e.fraction(arg0, arg1, false, variant));
+            }
+            "sqrt" => {
+                self.start("rad");
+                if optional.is_none() {
+                    self.start("radPr");
+                    self.prop("degHide", "1");
+                    self.end("radPr");
+                }
+                self.element("deg", optional, variant);
+                self.element("e", arg0, variant);
+                self.end("rad");
+            }
+            "overset" | "stackrel" | "underset" => {
+                let el = if name == "underset" { "limLow" } else { "limUpp" };
+                self.start(el);
+                self.element("e", arg1, variant);
+                self.element("lim", arg0, variant);
+                self.end(el);
+            }
+            "operatorname" | "operatorname*" => {
+                self.run(&arg0.map(Node::plain_text).unwrap_or_default(), Some("p"));
+            }
+            "boxed" | "cancel" | "bcancel" | "xcancel" => {
+                self.start("borderBox");
+                if name != "boxed" {
+                    self.start("borderBoxPr");
+                    for side in ["hideTop", "hideBot", "hideLeft", "hideRight"] { self.prop(side, "1"); }
+                    if name != "bcancel" { self.prop("strikeBLTR", "1"); }
+                    if name != "cancel" { self.prop("strikeTLBR", "1"); }
+                    self.end("borderBoxPr");
+                }
+                self.element("e", arg0, variant);
+                self.end("borderBox");
+            }
+            "phantom" | "hphantom" | "vphantom" => {
+                self.start("phant");
+                self.start("phantPr");
+                self.prop("show", "0");
+                if name == "vphantom" { self.prop("zeroWid", "1"); }
+                if name == "hphantom" {
+                    self.prop("zeroAsc", "1");
+                    self.prop("zeroDesc", "1");
+                }
+                self.end("phantPr");
+                self.element("e", arg0, variant);
+                self.end("phant");
+            }
+            "textcolor" => self.arg(arg1, variant),
+            "pmod" => {
+                self.run("\u{2003}(", None);
+                self.run("mod\u{2004}", Some("p"));
+                self.arg(arg0, variant);
+                self.run(")", None);
+            }
+            "not" => {
+                let op = arg0.map(Node::plain_text).unwrap_or_default();
+                self.run(&symbols::negated(&op), None);
+            }
+            "xrightarrow" | "xleftarrow" => {
+                let arrow = if name == "xrightarrow" { "→" } else { "←" };
+                if optional.is_some() { self.start("limLow"); self.start("e"); }
+                // The arrow stretches under its label
+                self.start("groupChr");
+                self.start("groupChrPr");
+                self.prop("chr", arrow);
+                self.prop("vertJc", "bot");
+                self.end("groupChrPr");
+                self.element("e", arg0, variant);
+                self.end("groupChr");
+                if let Some(below) = optional {
+                    self.end("e");
+                    self.element("lim", Some(below), variant);
+                    self.end("limLow");
+                }
+            }
+            "substack" | "mathnormal" | "limits" | "nolimits" => self.arg(arg0, variant),
+            "displaystyle" | "textstyle" | "scriptstyle" | "scriptscriptstyle" | "color"
+            | "hline" | "nonumber" | "notag" => {}
+            _ => self.text(&format!("\\{}", name), Some("FF0000")),
+        }
+    }
+
+    fn fraction(&mut self, num: Option<&Node>, den: Option<&Node>, bar: bool, variant: Option<&str>) {
+        self.start("f");
+        if !bar {
+            self.start("fPr");
+            self.prop("type", "noBar");
+            self.end("fPr");
+        }
+        self.element("num", num, variant);
+        self.element("den", den, variant);
+        self.end("f");
+    }
+
+    fn fenced(&mut self, open: &str, close: &str, body: impl FnOnce(&mut Self)) {
+        self.start("d");
+        self.start("dPr");
+        self.prop("begChr", open);
+        self.prop("endChr", close);
+        self.end("dPr");
+        self.start("e");
+        body(self);
+        self.end("e");
+        self.end("d");
+    }
+
+    /// Scripts to the right, or below and above with `limits`.
+    fn attach(&mut self, base: impl FnOnce(&mut Self), sub: Option<&Node>, sup: Option<&Node>, limits: bool, variant: Option<&str>) {
+        if limits {
+            if sup.is_some() { self.start("limUpp"); self.start("e"); }
+            if sub.is_some() { self.start("limLow"); self.start("e"); }
+            base(self);
+            if let Some(s) = sub {
+                self.end("e");
+                self.element("lim", Some(s), variant);
+                self.end("limLow");
+            }
+            if let Some(s) = sup {
+                self.end("e");
+                self.element("lim", Some(s), variant);
+                self.end("limUpp");
+            }
+            return;
+        }
+        let el = match (sub.is_some(), sup.is_some()) {
+            (true, true) => "sSubSup",
+            (true, false) => "sSub",
+            (false, true) => "sSup",
+            _ => return base(self),
+        };
+        self.start(el);
+        self.start("e");
+        base(self);
+        self.end("e");
+        if sub.is_some() { self.element("sub", sub, variant); }
+        if sup.is_some() { self.element("sup", sup, variant); }
+        self.end(el);
+    }
+
+    fn nary(&mut self, chr: &str, sub: Option<&Node>, sup: Option<&Node>, limits: bool, operand: &[Node], variant: Option<&str>) {
+        self.start("nary");
+        self.start("naryPr");
+        self.prop("chr", chr);
+        self.prop("limLoc", if limits { "undOvr" } else { "subSup" });
+        if sub.is_none() { self.prop("subHide", "1"); }
+        if sup.is_none() { self.prop("supHide", "1"); }
+        self.end("naryPr");
+        self.element("sub", sub, variant);
+        self.element("sup", sup, variant);
+        self.start("e");
+        self.list(operand, variant);
+        self.end("e");
+        self.end("nary");
+    }
+
+    fn function(&mut self, name: &str, sub: Option<&Node>, sup: Option<&Node>, limits: bool, operand: &[Node], variant: Option<&str>) {
+        self.start("func");
+        self.start("fName");
+        self.attach(|e| e.run(name, Some("p")), sub, sup, limits, variant);
+        self.end("fName");
+        self.start("e");
+        self.list(operand, variant);
+        self.end("e");
+        self.end("func");
+    }
+
+    /// A grid; rows are padded so every row has all columns, as Word requires.
+    fn matrix(&mut self, rows: &[Vec<Vec<Node>>], align: impl Fn(usize) -> &'static str, variant: Option<&str>) {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        self.start("m");
+        self.start("mPr");
+        self.start("mcs");
+        for j in 0..columns {
+            self.start("mc");
+            self.start("mcPr");
+            self.prop("count", "1");
+            self.prop("mcJc", align(j));
+            self.end("mcPr");
+            self.end("mc");
+        }
+        self.end("mcs");
+        self.end("mPr");
+        for row in rows {
+            self.start("mr");
+            for j in 0..columns {
+                self.start("e");
+                if let Some(cell) = row.get(j) { self.list(cell, variant); }
+                self.end("e");
+            }
+            self.end("mr");
+        }
+        self.end("m");
+    }
+
+    /// Lines aligned at `&`, which Word reads as alignment points inside `m:eqArr`.
+    fn equation_array(&mut self, rows: &[Vec<Vec<Node>>], variant: Option<&str>) {
+        self.start("eqArr");
+        for row in rows {
+            self.start("e");
+            for (j, cell) in row.iter().enumerate() {
+                if j > 0 { self.run("&", None); }
+                self.list(cell, variant);
+            }
+            self.end("e");
+        }
+        self.end("eqArr");
+    }
+
+    fn environment(&mut self, name: &str, args: &[String], rows: &[Vec<Vec<Node>>], variant: Option<&str>) {
+        if let Some((open, close)) = matrix_fences(name) {
+            if open.is_empty() {
+                self.matrix(rows, |_| "center", variant);
+            } else {
+                self.fenced(open, close, |e| e.matrix(rows, |_| "center", variant));
+            }
+            return;
+        }
+        match name {
+            "cases" | "dcases" => self.fenced("{", "", |e| e.matrix(rows, |_| "left", variant)),
+            "rcases" => self.fenced("", "}", |e| e.matrix(rows, |_| "left", variant)),
+            "array" | "subarray" => {
+                let spec: Vec<&'static str> = args.first().map_or(Vec::new(), |s| {
+                    s.chars().filter_map(|c| match c {
+                        'l' => Some("left"),
+                        'c' => Some("center"),
+                        'r' => Some("right"),
+                        _ => None,
+                    }).collect()
+                });
+                self.matrix(rows, |j| spec.get(j).copied().unwrap_or("center"), variant);
+            }
+            "gathered" | "gather" | "gather*" | "equation" | "equation*" | "multline" | "multline*" => {
+                self.start("eqArr");
+                for row in rows {
+                    self.start("e");
+                    for cell in row { self.list(cell, variant); }
+                    self.end("e");
+                }
+                self.end("eqArr");
+            }
+            _ => self.equation_array(rows, variant),
+        }
+    }
+}
+
+/// An n-ary operator or function name, with its scripts.
+fn operator(node: &Node) -> Option<(Operator, &Node, Option<&Node>, Option<&Node>)> {
+    let (base, sub, sup) = match node {
+        Node::Scripts { base, sub, sup } => (base.as_ref(), sub.as_deref(), sup.as_deref()),
+        n => (n, None, None),
+    };
+    let Node::Command { name, args, .. } = base else { return None };
+    let name = match name.as_str() {
+        // `\limits` wraps the operator itself
+        "limits" | "nolimits" => match args.first() {
+            Some(Node::Command { name, .. }) => name.as_str(),
+            _ => return None,
+        },
+        n => n,
+    };
+    let op = match symbols::symbol(name) {
+        Some((u, Class::LargeOp)) => Operator::Nary(u),
+        _ if symbols::function(name).is_some() => Operator::Function(name.to_string()),
+        _ if name.starts_with("operatorname") => Operator::Function(args.first().map(Node::plain_text).unwrap_or_default()),
+        _ => return None,
+    };
+    Some((op, base, sub, sup))
+}
+
+/// End of the operand starting at `start`: up to the next relation or `+` for an n-ary
+/// operator, one atom or a parenthesized group for a function.
+fn operand_end(nodes: &[Node], start: usize, nary: bool) -> usize {
+    let open = |n: &Node| matches!(n, Node::Symbol { value: '(' | '[' });
+    let close = |n: &Node| matches!(n, Node::Symbol { value: ')' | ']' });
+    if nary {
+        let mut depth = 0usize;
+        let mut j = start;
+        while j < nodes.len() {
+            let n = &nodes[j];
+            if open(n) { depth += 1; }
+            if close(n) {
+                if depth == 0 { break; }
+                depth -= 1;
+            }
+            if depth == 0 && boundary(n) { break; }
+            j += 1;
+        }
+        return j;
+    }
+    match nodes.get(start) {
+        Some(n) if open(n) => {
+            let mut depth = 0usize;
+            for (j, n) in nodes.iter().enumerate().skip(start) {
+                if open(n) { depth += 1; }
+                if close(n) {
+                    depth -= 1;
+                    if depth == 0 { return j + 1; }
+                }
+            }
+            start
+        }
+        Some(n) if !boundary(n) && !close(n) => start + 1,
+        _ => start,
+    }
+}
+
+fn takes_limits(base: &Node, display: bool) -> bool {
+    let Node::Command { name, .. } = base else { return false };
+    match name.as_str() {
+        "limits" => true,
+        "nolimits" => false,
+        "operatorname*" | "overbrace" | "underbrace" => true,
+        n if symbols::function(n) == Some(true) => display,
+        n => matches!(symbols::symbol(n), Some((_, Class::LargeOp)))
+            && !matches!(n, "int" | "iint" | "iiint" | "oint")
+            && display,
+    }
+}
+
+/// `<m:oMath>`, inside `<m:oMathPara>` for a display equation.
+pub fn to_omml(nodes: &[Node], display: bool) -> String {
+    let mut e = Emitter { out: String::new(), display, plain_run_end: 0 };
+    if display { e.start("oMathPara"); }
+    e.start("oMath");
+    e.list(nodes, None);
+    e.end("oMath");
+    if display { e.end("oMathPara"); }
+    e.out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latex::parse;
+
+    /// The content of `<m:oMath>`, with runs written as `<m:t>` to keep the expectations short.
+    fn omml(src: &str, display: bool) -> String {
+        let mut e = Emitter { out: String::new(), display, plain_run_end: 0 };
+        e.list(&parse(src).nodes, None);
+        e.out.replace("<m:t xml:space=\"preserve\">", "<m:t>")
+    }
+
+    /// `(LaTeX, inline OMML)` pairs taken from recognition results.
+    const CORPUS: &[(&str, &str)] = &[
+        ("x_i^2", "<m:sSubSup><m:e><m:r><m:t>x</m:t></m:r></m:e><m:sub><m:r><m:t>i</m:t></m:r></m:sub><m:sup><m:r><m:t>2</m:t></m:r></m:sup></m:sSubSup>"),
+        ("\\frac{a}{b}", "<m:f><m:num><m:r><m:t>a</m:t></m:r></m:num><m:den><m:r><m:t>b</m:t></m:r></m:den></m:f>"),
+        (
+            "\\binom{n}{k}",
+            "<m:d><m:dPr><m:begChr m:val=\"(\"/><m:endChr m:val=\")\"/></m:dPr><m:e>\
+             <m:f><m:fPr><m:type m:val=\"noBar\"/></m:fPr><m:num><m:r><m:t>n</m:t></m:r></m:num><m:den><m:r><m:t>k</m:t></m:r></m:den></m:f>\
+             </m:e></m:d>",
+        ),
+        ("\\sqrt{x}", "<m:rad><m:radPr><m:degHide m:val=\"1\"/></m:radPr><m:deg></m:deg><m:e><m:r><m:t>x</m:t></m:r></m:e></m:rad>"),
+        ("\\sqrt[3]{x}", "<m:rad><m:deg><m:r><m:t>3</m:t></m:r></m:deg><m:e><m:r><m:t>x</m:t></m:r></m:e></m:rad>"),
+        (
+            "\\sin x",
+            "<m:func><m:fName><m:r><m:rPr><m:sty m:val=\"p\"/></m:rPr><m:t>sin</m:t></m:r></m:fName><m:e><m:r><m:t>x</m:t></m:r></m:e></m:func>",
+        ),
+        (
+            "\\sum_{i=1}^n i",
+            "<m:nary><m:naryPr><m:chr m:val=\"\u{2211}\"/><m:limLoc m:val=\"subSup\"/></m:naryPr>\
+             <m:sub><m:r><m:t>i=1</m:t></m:r></m:sub><m:sup><m:r><m:t>n</m:t></m:r></m:sup><m:e><m:r><m:t>i</m:t></m:r></m:e></m:nary>",
+        ),
+        (
+            "\\int_0^1 f\\,dx = 1",
+            "<m:nary><m:naryPr><m:chr m:val=\"\u{222b}\"/><m:limLoc m:val=\"subSup\"/></m:naryPr>\
+             <m:sub><m:r><m:t>0</m:t></m:r></m:sub><m:sup><m:r><m:t>1</m:t></m:r></m:sup><m:e><m:r><m:t>f\u{2009}dx</m:t></m:r></m:e></m:nary>\
+             <m:r><m:t>=1</m:t></m:r>",
+        ),
+        (
+            "\\prod_{k} a_k",
+            "<m:nary><m:naryPr><m:chr m:val=\"\u{220f}\"/><m:limLoc m:val=\"subSup\"/><m:supHide m:val=\"1\"/></m:naryPr>\
+             <m:sub><m:r><m:t>k</m:t></m:r></m:sub><m:sup></m:sup>\
+             <m:e><m:sSub><m:e><m:r><m:t>a</m:t></m:r></m:e><m:sub><m:r><m:t>k</m:t></m:r></m:sub></m:sSub></m:e></m:nary>",
+        ),
+        (
+            "\\begin{pmatrix}1&2\\\\3&4\\end{pmatrix}",
+            "<m:d><m:dPr><m:begChr m:val=\"(\"/><m:endChr m:val=\")\"/></m:dPr><m:e><m:m><m:mPr><m:mcs>\
+             <m:mc><m:mcPr><m:count m:val=\"1\"/><m:mcJc m:val=\"center\"/></m:mcPr></m:mc>\
+             <m:mc><m:mcPr><m:count m:val=\"1\"/><m:mcJc m:val=\"center\"/></m:mcPr></m:mc></m:mcs></m:mPr>\
+             <m:mr><m:e><m:r><m:t>1</m:t></m:r></m:e><m:e><m:r><m:t>2</m:t></m:r></m:e></m:mr>\
+             <m:mr><m:e><m:r><m:t>3</m:t></m:r></m:e><m:e><m:r><m:t>4</m:t></m:r></m:e></m:mr></m:m></m:e></m:d>",
+        ),
+        // Short rows are padded with empty cells
+        (
+            "\\begin{matrix}1&2\\\\3\\end{matrix}",
+            "<m:m><m:mPr><m:mcs>\
+             <m:mc><m:mcPr><m:count m:val=\"1\"/><m:mcJc m:val=\"center\"/></m:mcPr></m:mc>\
+             <m:mc><m:mcPr><m:count m:val=\"1\"/><m:mcJc m:val=\"center\"/></m:mcPr></m:mc></m:mcs></m:mPr>\
+             <m:mr><m:e><m:r><m:t>1</m:t></m:r></m:e><m:e><m:r><m:t>2</m:t></m:r></m:e></m:mr>\
+             <m:mr><m:e><m:r><m:t>3</m:t></m:r></m:e><m:e></m:e></m:mr></m:m>",
+        ),
+        (
+            "f(x)=\\begin{cases}0 & x<0\\\\ 1 & x\\ge0\\end{cases}",
+            "<m:r><m:t>f(x)=</m:t></m:r><m:d><m:dPr><m:begChr m:val=\"{\"/><m:endChr m:val=\"\"/></m:dPr><m:e><m:m><m:mPr><m:mcs>\
+             <m:mc><m:mcPr><m:count m:val=\"1\"/><m:mcJc m:val=\"left\"/></m:mcPr></m:mc>\
+             <m:mc><m:mcPr><m:count m:val=\"1\"/><m:mcJc m:val=\"left\"/></m:mcPr></m:mc></m:mcs></m:mPr>\
+             <m:mr><m:e><m:r><m:t>0</m:t></m:r></m:e><m:e><m:r><m:t>x&lt;0</m:t></m:r></m:e></m:mr>\
+             <m:mr><m:e><m:r><m:t>1</m:t></m:r></m:e><m:e><m:r><m:t>x\u{2265}0</m:t></m:r></m:e></m:mr></m:m></m:e></m:d>",
+        ),
+    ];
+
+    #[test]
+    fn corpus() {
+        for (latex, expected) in CORPUS {
+            assert_eq!(omml(latex, false), *expected, "{}", latex);
+        }
+    }
+
+    #[test]
+    fn display_limits() {
+        for (latex, expected) in [
+            (
+                "\\sum_{i=1}^n i",
+                "<m:nary><m:naryPr><m:chr m:val=\"\u{2211}\"/><m:limLoc m:val=\"undOvr\"/></m:naryPr>\
+                 <m:sub><m:r><m:t>i=1</m:t></m:r></m:sub><m:sup><m:r><m:t>n</m:t></m:r></m:sup><m:e><m:r><m:t>i</m:t></m:r></m:e></m:nary>",
+            ),
+            // Integrals keep their limits at the side even in display style
+            (
+                "\\int_0^1 f",
+                "<m:nary><m:naryPr><m:chr m:val=\"\u{222b}\"/><m:limLoc m:val=\"subSup\"/></m:naryPr>\
+                 <m:sub><m:r><m:t>0</m:t></m:r></m:sub><m:sup><m:r><m:t>1</m:t></m:r></m:sup><m:e><m:r><m:t>f</m:t></m:r></m:e></m:nary>",
+            ),
+            (
+                "\\lim_{x\\to0} f",
+                "<m:func><m:fName><m:limLow><m:e><m:r><m:rPr><m:sty m:val=\"p\"/></m:rPr><m:t>lim</m:t></m:r></m:e>\
+                 <m:lim><m:r><m:t>x\u{2192}0</m:t></m:r></m:lim></m:limLow></m:fName><m:e><m:r><m:t>f</m:t></m:r></m:e></m:func>",
+            ),
+        ] {
+            assert_eq!(omml(latex, true), expected, "{}", latex);
+        }
+    }
+
+    #[test]
+    fn wrapper() {
+        let nodes = parse("x").nodes;
+        let run = "<m:r><m:t xml:space=\"preserve\">x</m:t></m:r>";
+        assert_eq!(to_omml(&nodes, false), format!("<m:oMath>{}</m:oMath>", run));
+        assert_eq!(to_omml(&nodes, true), format!("<m:oMathPara><m:oMath>{}</m:oMath></m:oMathPara>", run));
+    }
+}
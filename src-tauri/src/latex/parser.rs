@@ -0,0 +1,683 @@
+//! Error-tolerant parser from LaTeX math to an AST.
+//!
+//! Parsing never fails: problems are collected as [`Diagnostic`]s and the parser recovers the way
+//! a reader would guess (a missing `}` closes at the end, a stray `}` is skipped, a `\left`
+//! without `\right` closes with an invisible delimiter, ...).
+
+use serde::Serialize;
+
+use super::{strip_delimiters, symbols, tokenize, Token, TokenKind};
+
+// ── AST ──
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Node {
+    Letter { value: char },
+    Number { value: String },
+    /// Any other character written directly: `+`, `(`, `|`, ...
+    Symbol { value: char },
+    /// `{...}`
+    Group { body: Vec<Node> },
+    /// `\name[optional]{arg}...`; symbols like `\alpha` have no arguments.
+    /// `\limits` / `\nolimits` wrap the operator before them as their argument.
+    Command { name: String, optional: Option<Box<Node>>, args: Vec<Node> },
+    /// Argument of `\text` and friends, kept verbatim
+    Text { command: String, value: String },
+    Scripts { base: Box<Node>, sub: Option<Box<Node>>, sup: Option<Box<Node>> },
+    /// `\left( ... \right)`; delimiters are kept as written (`(`, `\langle`, `.` for none)
+    Delimited { left: String, right: String, body: Vec<Node> },
+    /// `\big(`, `\middle|`
+    Sized { size: String, delim: String },
+    /// `\begin{name}{args}...\end{name}` split into rows of cells
+    Environment { name: String, args: Vec<String>, rows: Vec<Vec<Vec<Node>>> },
+    /// `&` outside an environment
+    Align,
+    /// `\\` outside an environment
+    LineBreak,
+}
+
+impl Node {
+    pub fn group(body: Vec<Node>) -> Node {
+        Node::Group { body }
+    }
+
+    /// Flattened text content, e.g. the name in `\operatorname{arg\,max}`.
+    pub fn plain_text(&self) -> String {
+        match self {
+            Node::Letter { value } | Node::Symbol { value } => value.to_string(),
+            Node::Number { value } | Node::Text { value, .. } => value.clone(),
+            Node::Group { body } => body.iter().map(Node::plain_text).collect(),
+            Node::Command { name, args, .. } => match symbols::symbol(name) {
+                Some((u, _)) => u.to_string(),
+                None => args.iter().map(Node::plain_text).collect(),
+            },
+            Node::Scripts { base, .. } => base.plain_text(),
+            Node::Delimited { body, .. } => body.iter().map(Node::plain_text).collect(),
+            _ => String::new(),
+        }
+    }
+
+    fn is_primes(&self) -> bool {
+        matches!(self, Node::Group { body } if body.iter().all(|n| matches!(n, Node::Command { name, .. } if name == "prime")))
+    }
+}
+
+/// Splits a list at `&` and `\\` into rows of cells; `None` if it has neither.
+pub fn split_rows(nodes: &[Node]) -> Option<Vec<Vec<Vec<Node>>>> {
+    if !nodes.iter().any(|n| matches!(n, Node::Align | Node::LineBreak)) { return None; }
+    let mut rows = vec![vec![vec![]]];
+    for node in nodes {
+        match node {
+            Node::Align => rows.last_mut()?.push(vec![]),
+            Node::LineBreak => rows.push(vec![vec![]]),
+            n => rows.last_mut()?.last_mut()?.push(n.clone()),
+        }
+    }
+    if rows.len() > 1 && rows.last().is_some_and(|r| r.len() == 1 && r[0].is_empty()) { rows.pop(); }
+    Some(rows)
+}
+
+/// Leading primes of a superscript and what follows them: `x'^2` has one prime, then `2`.
+pub fn split_primes(sup: Option<&Node>) -> (usize, Option<Node>) {
+    let Some(Node::Group { body }) = sup else { return (0, sup.cloned()) };
+    let primes = body.iter().take_while(|n| matches!(n, Node::Command { name, .. } if name == "prime")).count();
+    if primes == 0 { return (0, sup.cloned()); }
+    (primes, (body.len() > primes).then(|| Node::group(body[primes..].to_vec())))
+}
+
+// ── Diagnostics ──
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    /// Stable identifier, e.g. `unclosed_group`
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// UTF-16 offsets into the source, the way JavaScript indexes strings
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize)]
+pub struct Parsed {
+    pub nodes: Vec<Node>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// ── Command tables ──
+
+/// `(command, required arguments, takes an [optional] argument)`
+const COMMANDS_WITH_ARGS: &[(&str, usize, bool)] = &[
+    ("frac", 2, false), ("dfrac", 2, false), ("tfrac", 2, false), ("cfrac", 2, false),
+    ("binom", 2, false), ("dbinom", 2, false), ("tbinom", 2, false), ("sqrt", 1, true),
+    ("overset", 2, false), ("underset", 2, false), ("stackrel", 2, false),
+    ("mathbf", 1, false), ("mathrm", 1, false), ("mathit", 1, false), ("mathbb", 1, false),
+    ("mathcal", 1, false), ("mathscr", 1, false), ("mathfrak", 1, false), ("mathsf", 1, false),
+    ("mathtt", 1, false), ("mathnormal", 1, false), ("boldsymbol", 1, false), ("bm", 1, false),
+    ("operatorname", 1, false), ("operatorname*", 1, false), ("boxed", 1, false),
+    ("phantom", 1, false), ("hphantom", 1, false), ("vphantom", 1, false), ("cancel", 1, false),
+    ("bcancel", 1, false), ("xcancel", 1, false), ("pmod", 1, false), ("not", 1, false),
+    ("xrightarrow", 1, true), ("xleftarrow", 1, true), ("substack", 1, false),
+    ("color", 1, false), ("textcolor", 2, false),
+];
+
+/// Commands whose argument is text rather than math.
+const TEXT_COMMANDS: &[&str] = &[
+    "text", "textrm", "textbf", "textit", "textsf", "texttt", "textnormal", "mbox", "hbox",
+    "tag", "tag*", "label",
+];
+
+/// Size prefixes that take a single delimiter.
+const SIZES: &[&str] = &[
+    "big", "Big", "bigg", "Bigg", "bigl", "bigr", "Bigl", "Bigr", "biggl", "biggr", "Biggl", "Biggr",
+    "bigm", "Bigm", "biggm", "Biggm", "middle",
+];
+
+/// Switches and structural commands handled by the parser itself.
+const DECLARATIONS: &[&str] = &[
+    "displaystyle", "textstyle", "scriptstyle", "scriptscriptstyle", "limits", "nolimits",
+    "nonumber", "notag", "hline", "left", "right", "begin", "end",
+];
+
+const ENVIRONMENTS: &[&str] = &[
+    "matrix", "pmatrix", "bmatrix", "Bmatrix", "vmatrix", "Vmatrix", "smallmatrix",
+    "cases", "dcases", "rcases", "aligned", "alignedat", "gathered", "split", "array", "subarray",
+    "align", "align*", "alignat", "alignat*", "gather", "gather*", "equation", "equation*",
+    "eqnarray", "eqnarray*", "multline", "multline*",
+];
+
+/// Environments with a column spec or count argument after `\begin{name}`.
+const ENVIRONMENTS_WITH_ARG: &[&str] = &["array", "subarray", "alignedat", "alignat", "alignat*"];
+
+/// Delimiters allowed after `\left`, `\right` and the size prefixes.
+const DELIMITER_CHARS: &str = "()[]|./<>";
+
+fn arity(name: &str) -> Option<(usize, bool)> {
+    if let Some(&(_, n, opt)) = COMMANDS_WITH_ARGS.iter().find(|(c, _, _)| *c == name) {
+        return Some((n, opt));
+    }
+    symbols::accent(name).map(|_| (1, false))
+}
+
+pub fn is_known_command(name: &str) -> bool {
+    symbols::symbol(name).is_some()
+        || symbols::function(name).is_some()
+        || symbols::space(name).is_some()
+        || arity(name).is_some()
+        || TEXT_COMMANDS.contains(&name)
+        || SIZES.contains(&name)
+        || DECLARATIONS.contains(&name)
+        || name == "\\"
+}
+
+fn is_delimiter(tok: &Token) -> bool {
+    match tok.kind {
+        TokenKind::Symbol => DELIMITER_CHARS.contains(tok.text.as_str()),
+        TokenKind::Command => {
+            let name = &tok.text[1..];
+            matches!(symbols::symbol(name), Some((_, symbols::Class::Open | symbols::Class::Close)))
+                || matches!(name, "|" | "vert" | "Vert" | "uparrow" | "downarrow" | "updownarrow" | "Uparrow" | "Downarrow" | "backslash")
+        }
+        _ => false,
+    }
+}
+
+// ── Parser ──
+
+/// Tokens that end the list being parsed, inherited by nested lists.
+#[derive(Clone, Copy, Default)]
+struct Stops {
+    group: bool,
+    left: bool,
+    env: bool,
+    optional: bool,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn end_of(tok: &Token) -> usize {
+    tok.start + tok.text.len()
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_is(&self, kind: TokenKind, text: &str) -> bool {
+        self.peek().is_some_and(|t| t.kind == kind && t.text == text)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        if tok.is_some() { self.pos += 1; }
+        tok
+    }
+
+    fn report(&mut self, severity: Severity, code: &'static str, message: String, start: usize, end: usize) {
+        self.diagnostics.push(Diagnostic { code, severity, message, start, end });
+    }
+
+    fn error(&mut self, code: &'static str, message: String, tok: &Token) {
+        self.report(Severity::Error, code, message, tok.start, end_of(tok));
+    }
+
+    fn is_stop(tok: &Token, stops: Stops) -> bool {
+        match tok.kind {
+            TokenKind::EndGroup => stops.group,
+            TokenKind::Align => stops.env,
+            TokenKind::Symbol => stops.optional && tok.text == "]",
+            TokenKind::Command => match tok.text.as_str() {
+                "\\right" => stops.left,
+                "\\end" | "\\\\" => stops.env,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn parse_list(&mut self, stops: Stops) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        while let Some(tok) = self.peek().cloned() {
+            if Self::is_stop(&tok, stops) { break; }
+            match (tok.kind, tok.text.as_str()) {
+                (TokenKind::EndGroup, _) => {
+                    self.next();
+                    self.error("unexpected_close", "多余的 }，没有与之匹配的 {".into(), &tok);
+                }
+                (TokenKind::Align, _) => {
+                    self.next();
+                    // Top-level rows are read as an aligned block; inside braces or \left...\right it's a slip
+                    if stops.group || stops.left || stops.optional {
+                        self.report(Severity::Warning, "stray_align", "& 不在对齐环境中".into(), tok.start, end_of(&tok));
+                    }
+                    nodes.push(Node::Align);
+                }
+                (TokenKind::Superscript | TokenKind::Subscript, _) => self.parse_script(&mut nodes, stops),
+                (TokenKind::Symbol, "'") => self.parse_primes(&mut nodes),
+                (TokenKind::Command, "\\\\") => {
+                    self.next();
+                    nodes.push(Node::LineBreak);
+                }
+                // Wrap the operator so scripts attach to it: \sum\limits_i
+                (TokenKind::Command, "\\limits" | "\\nolimits") => {
+                    self.next();
+                    let Some(op) = nodes.pop() else { continue };
+                    nodes.push(Node::Command { name: tok.text[1..].to_string(), optional: None, args: vec![op] });
+                }
+                (TokenKind::Command, "\\right") => {
+                    self.next();
+                    self.error("unmatched_right", "\\right 没有对应的 \\left".into(), &tok);
+                    self.parse_delimiter(&tok, stops);
+                }
+                (TokenKind::Command, "\\end") => {
+                    self.next();
+                    let name = self.read_raw_group(&tok).unwrap_or_default();
+                    self.error("unmatched_end", format!("\\end{{{}}} 没有对应的 \\begin", name), &tok);
+                }
+                _ => {
+                    if let Some(node) = self.parse_atom(stops, false) { nodes.push(node); }
+                }
+            }
+        }
+        nodes
+    }
+
+    /// One node. `single` limits numbers to one digit, as TeX does for arguments (`\frac12`).
+    fn parse_atom(&mut self, stops: Stops, single: bool) -> Option<Node> {
+        let tok = self.next()?;
+        let node = match tok.kind {
+            TokenKind::BeginGroup => {
+                let body = self.parse_list(Stops { group: true, optional: false, ..stops });
+                if self.peek().is_some_and(|t| t.kind == TokenKind::EndGroup) {
+                    self.next();
+                } else {
+                    self.error("unclosed_group", "缺少与 { 匹配的 }".into(), &tok);
+                }
+                Node::Group { body }
+            }
+            TokenKind::Letter => Node::Letter { value: tok.text.chars().next().unwrap_or(' ') },
+            TokenKind::Digit => {
+                let mut value = tok.text.clone();
+                if !single {
+                    loop {
+                        match self.peek() {
+                            Some(t) if t.kind == TokenKind::Digit => value.push_str(&t.text),
+                            Some(t) if t.text == "." && self.tokens.get(self.pos + 1).is_some_and(|n| n.kind == TokenKind::Digit) => value.push('.'),
+                            _ => break,
+                        }
+                        self.pos += 1;
+                    }
+                }
+                Node::Number { value }
+            }
+            TokenKind::Command => self.parse_command(tok, stops),
+            _ => Node::Symbol { value: tok.text.chars().next().unwrap_or(' ') },
+        };
+        Some(node)
+    }
+
+    /// A required argument of `owner`; reports and yields an empty group if there is none.
+    fn parse_argument(&mut self, owner: &Token, stops: Stops) -> Node {
+        let missing = match self.peek() {
+            None => true,
+            Some(t) => Self::is_stop(t, stops)
+                || matches!(t.kind, TokenKind::EndGroup | TokenKind::Superscript | TokenKind::Subscript | TokenKind::Align)
+                || matches!(t.text.as_str(), "\\\\" | "\\right" | "\\end"),
+        };
+        if missing {
+            self.error("missing_argument", format!("{} 缺少参数", owner.text), owner);
+            return Node::group(vec![]);
+        }
+        self.parse_atom(stops, true).unwrap_or_else(|| Node::group(vec![]))
+    }
+
+    fn parse_script(&mut self, nodes: &mut Vec<Node>, stops: Stops) {
+        let Some(tok) = self.next() else { return };
+        let is_sup = tok.kind == TokenKind::Superscript;
+        let base = match nodes.pop() {
+            Some(n) if !matches!(n, Node::Align | Node::LineBreak) => n,
+            popped => {
+                nodes.extend(popped);
+                self.report(Severity::Warning, "missing_base", format!("{} 前面没有内容", tok.text), tok.start, end_of(&tok));
+                Node::group(vec![])
+            }
+        };
+        let arg = self.parse_argument(&tok, stops);
+
+        let node = match base {
+            Node::Scripts { base, mut sub, mut sup } => {
+                let slot = if is_sup { &mut sup } else { &mut sub };
+                match slot.take() {
+                    // x'^2 is x^{\prime 2}
+                    Some(existing) if is_sup && existing.is_primes() => {
+                        let Node::Group { mut body } = *existing else { unreachable!() };
+                        body.push(arg);
+                        *slot = Some(Box::new(Node::group(body)));
+                    }
+                    Some(_) => {
+                        let (code, what) = if is_sup { ("double_superscript", "上标") } else { ("double_subscript", "下标") };
+                        self.error(code, format!("重复的{}", what), &tok);
+                        *slot = Some(Box::new(arg));
+                    }
+                    None => *slot = Some(Box::new(arg)),
+                }
+                Node::Scripts { base, sub, sup }
+            }
+            base => {
+                let arg = Some(Box::new(arg));
+                let (sub, sup) = if is_sup { (None, arg) } else { (arg, None) };
+                Node::Scripts { base: Box::new(base), sub, sup }
+            }
+        };
+        nodes.push(node);
+    }
+
+    fn parse_primes(&mut self, nodes: &mut Vec<Node>) {
+        let mut primes = Vec::new();
+        while self.peek_is(TokenKind::Symbol, "'") {
+            self.next();
+            primes.push(Node::Command { name: "prime".into(), optional: None, args: vec![] });
+        }
+        let base = nodes.pop().unwrap_or_else(|| Node::group(vec![]));
+        let node = match base {
+            Node::Scripts { base, sub, sup: None } => Node::Scripts { base, sub, sup: Some(Box::new(Node::group(primes))) },
+            base => Node::Scripts { base: Box::new(base), sub: None, sup: Some(Box::new(Node::group(primes))) },
+        };
+        nodes.push(node);
+    }
+
+    fn parse_command(&mut self, tok: Token, stops: Stops) -> Node {
+        let mut name = tok.text[1..].to_string();
+        // \operatorname*, \tag*
+        if self.peek().is_some_and(|t| t.text == "*" && t.start == end_of(&tok)) && is_known_command(&format!("{}*", name)) {
+            self.next();
+            name.push('*');
+        }
+
+        match name.as_str() {
+            "begin" => return self.parse_environment(tok, stops),
+            "left" => return self.parse_left(tok, stops),
+            "\\" | "right" | "end" => {
+                self.error("missing_argument", format!("此处不能使用 {}", tok.text), &tok);
+                return Node::group(vec![]);
+            }
+            _ => {}
+        }
+
+        if SIZES.contains(&name.as_str()) {
+            let delim = self.parse_delimiter(&tok, stops);
+            return Node::Sized { size: name, delim };
+        }
+        if TEXT_COMMANDS.contains(&name.as_str()) {
+            let value = self.read_raw_group(&tok).unwrap_or_else(|| {
+                self.error("missing_argument", format!("{} 缺少参数", tok.text), &tok);
+                String::new()
+            });
+            return Node::Text { command: name, value };
+        }
+        if let Some((n, takes_optional)) = arity(&name) {
+            let mut optional = None;
+            if takes_optional && self.peek_is(TokenKind::Symbol, "[") {
+                let open = self.next().unwrap_or_else(|| tok.clone());
+                let body = self.parse_list(Stops { optional: true, ..stops });
+                if self.peek_is(TokenKind::Symbol, "]") {
+                    self.next();
+                } else {
+                    self.error("unclosed_optional", "缺少与 [ 匹配的 ]".into(), &open);
+                }
+                optional = Some(Box::new(Node::group(body)));
+            }
+            let args = (0..n).map(|_| self.parse_argument(&tok, stops)).collect();
+            return Node::Command { name, optional, args };
+        }
+
+        if !is_known_command(&name) {
+            self.report(Severity::Warning, "unknown_command", format!("未知命令 {}", tok.text), tok.start, end_of(&tok));
+        }
+        Node::Command { name, optional: None, args: vec![] }
+    }
+
+    /// The delimiter after `\left`, `\right`, `\big`...; `.` if it is missing or invalid.
+    fn parse_delimiter(&mut self, owner: &Token, stops: Stops) -> String {
+        match self.peek().cloned() {
+            Some(t) if is_delimiter(&t) => {
+                self.next();
+                t.text
+            }
+            Some(t) if !Self::is_stop(&t, stops) && !matches!(t.kind, TokenKind::EndGroup) => {
+                self.next();
+                self.error("invalid_delimiter", format!("{} 不能作为 {} 的定界符", t.text, owner.text), &t);
+                ".".into()
+            }
+            _ => {
+                self.error("missing_delimiter", format!("{} 后缺少定界符", owner.text), owner);
+                ".".into()
+            }
+        }
+    }
+
+    fn parse_left(&mut self, tok: Token, stops: Stops) -> Node {
+        let left = self.parse_delimiter(&tok, stops);
+        let body = self.parse_list(Stops { left: true, optional: false, ..stops });
+        if self.peek_is(TokenKind::Command, "\\right") {
+            let right_tok = self.next().unwrap_or_else(|| tok.clone());
+            let right = self.parse_delimiter(&right_tok, stops);
+            return Node::Delimited { left, right, body };
+        }
+        self.error("unmatched_left", "\\left 缺少对应的 \\right".into(), &tok);
+        Node::Delimited { left, right: ".".into(), body }
+    }
+
+    /// Source text of the `{...}` group that follows, braces excluded.
+    fn read_raw_group(&mut self, owner: &Token) -> Option<String> {
+        let open = self.peek().filter(|t| t.kind == TokenKind::BeginGroup)?.clone();
+        self.next();
+        let mut depth = 1;
+        while let Some(t) = self.next() {
+            match t.kind {
+                TokenKind::BeginGroup => depth += 1,
+                TokenKind::EndGroup => {
+                    depth -= 1;
+                    if depth == 0 { return Some(self.src[end_of(&open)..t.start].to_string()); }
+                }
+                _ => {}
+            }
+        }
+        self.error("unclosed_group", format!("{} 的参数缺少 }}", owner.text), &open);
+        Some(self.src[end_of(&open)..].to_string())
+    }
+
+    /// Skips a `[...]` after `\\` or `\begin{array}`.
+    fn skip_bracket(&mut self) {
+        if !self.peek_is(TokenKind::Symbol, "[") { return; }
+        let save = self.pos;
+        while let Some(t) = self.next() {
+            if t.kind == TokenKind::Symbol && t.text == "]" { return; }
+        }
+        self.pos = save;
+    }
+
+    fn parse_environment(&mut self, tok: Token, stops: Stops) -> Node {
+        let Some(name) = self.read_raw_group(&tok).map(|n| n.trim().to_string()) else {
+            self.error("missing_argument", "\\begin 缺少环境名".into(), &tok);
+            return Node::group(vec![]);
+        };
+        if !ENVIRONMENTS.contains(&name.as_str()) {
+            self.report(Severity::Warning, "unknown_environment", format!("未知环境 {}", name), tok.start, end_of(&tok));
+        }
+        let mut args = Vec::new();
+        if ENVIRONMENTS_WITH_ARG.contains(&name.as_str()) {
+            self.skip_bracket();
+            match self.read_raw_group(&tok) {
+                Some(arg) => args.push(arg),
+                None => self.error("missing_argument", format!("{} 环境缺少参数", name), &tok),
+            }
+        }
+
+        let inner = Stops { env: true, optional: false, ..stops };
+        let mut rows: Vec<Vec<Vec<Node>>> = Vec::new();
+        let mut row: Vec<Vec<Node>> = Vec::new();
+        loop {
+            row.push(self.parse_list(inner));
+            match self.peek().cloned() {
+                Some(t) if t.kind == TokenKind::Align => {
+                    self.next();
+                }
+                Some(t) if t.kind == TokenKind::Command && t.text == "\\\\" => {
+                    self.next();
+                    self.skip_bracket();
+                    rows.push(std::mem::take(&mut row));
+                }
+                Some(t) if t.kind == TokenKind::Command && t.text == "\\end" => {
+                    self.next();
+                    let end_name = self.read_raw_group(&t).map(|n| n.trim().to_string()).unwrap_or_default();
+                    if end_name != name {
+                        self.error("environment_mismatch", format!("\\begin{{{}}} 与 \\end{{{}}} 不匹配", name, end_name), &t);
+                    }
+                    break;
+                }
+                _ => {
+                    self.error("unclosed_environment", format!("\\begin{{{}}} 缺少 \\end{{{}}}", name, name), &tok);
+                    break;
+                }
+            }
+        }
+        // A trailing `\\` before `\end` doesn't start a new row
+        let trailing_empty = row.len() == 1 && row[0].is_empty();
+        if !trailing_empty || rows.is_empty() { rows.push(row); }
+        Node::Environment { name, args, rows }
+    }
+}
+
+/// Parses a formula, with or without `$...$` / `\[...\]` around it.
+pub fn parse(src: &str) -> Parsed {
+    let body = strip_delimiters(src);
+    let base = body.as_ptr() as usize - src.as_ptr() as usize;
+    let mut parser = Parser {
+        src: body,
+        tokens: tokenize(body).into_iter().filter(|t| !t.is_trivia()).collect(),
+        pos: 0,
+        diagnostics: Vec::new(),
+    };
+    let nodes = parser.parse_list(Stops::default());
+
+    let utf16 = |byte: usize| src[..(byte + base).min(src.len())].encode_utf16().count();
+    let diagnostics = parser.diagnostics.into_iter().map(|d| Diagnostic {
+        start: utf16(d.start),
+        end: utf16(d.end),
+        ..d
+    }).collect();
+    Parsed { nodes, diagnostics }
+}
+
+/// Syntax problems in a formula, errors first.
+pub fn check(src: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = parse(src).diagnostics;
+    diagnostics.sort_by_key(|d| (d.severity != Severity::Error, d.start));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter(value: char) -> Node {
+        Node::Letter { value }
+    }
+
+    /// `(code, start, end)` of each diagnostic, in the order they were found.
+    fn spans(parsed: &Parsed) -> Vec<(&'static str, usize, usize)> {
+        parsed.diagnostics.iter().map(|d| (d.code, d.start, d.end)).collect()
+    }
+
+    #[test]
+    fn unclosed_group() {
+        let parsed = parse("\\frac{a}{b");
+        assert_eq!(spans(&parsed), [("unclosed_group", 8, 9)]);
+        assert_eq!(parsed.nodes, [Node::Command {
+            name: "frac".into(),
+            optional: None,
+            args: vec![Node::group(vec![letter('a')]), Node::group(vec![letter('b')])],
+        }]);
+    }
+
+    #[test]
+    fn unexpected_close() {
+        let parsed = parse("a}+b");
+        assert_eq!(spans(&parsed), [("unexpected_close", 1, 2)]);
+        assert_eq!(parsed.nodes, [letter('a'), Node::Symbol { value: '+' }, letter('b')]);
+    }
+
+    #[test]
+    fn left_without_right() {
+        let parsed = parse("\\left( x + 1");
+        assert_eq!(spans(&parsed), [("unmatched_left", 0, 5)]);
+        assert_eq!(parsed.nodes, [Node::Delimited {
+            left: "(".into(),
+            right: ".".into(),
+            body: vec![letter('x'), Node::Symbol { value: '+' }, Node::Number { value: "1".into() }],
+        }]);
+
+        let parsed = parse("x \\right)");
+        assert_eq!(spans(&parsed), [("unmatched_right", 2, 8)]);
+        assert_eq!(parsed.nodes, [letter('x')]);
+    }
+
+    #[test]
+    fn unknown_command() {
+        let parsed = parse("\\foo + x");
+        assert_eq!(spans(&parsed), [("unknown_command", 0, 4)]);
+        assert_eq!(parsed.diagnostics[0].severity, Severity::Warning);
+        assert_eq!(parsed.nodes[0], Node::Command { name: "foo".into(), optional: None, args: vec![] });
+        assert_eq!(parsed.nodes.len(), 3);
+    }
+
+    #[test]
+    fn stray_align() {
+        let parsed = parse("{a & b}");
+        assert_eq!(spans(&parsed), [("stray_align", 3, 4)]);
+        assert_eq!(parsed.nodes, [Node::group(vec![letter('a'), Node::Align, letter('b')])]);
+
+        let parsed = parse("\\left( a & b \\right)");
+        assert_eq!(spans(&parsed), [("stray_align", 9, 10)]);
+
+        // Top-level rows are an aligned block without the environment, not an error
+        let parsed = parse("a & b");
+        assert!(parsed.diagnostics.is_empty());
+        assert_eq!(parsed.nodes, [letter('a'), Node::Align, letter('b')]);
+    }
+
+    #[test]
+    fn unclosed_environment() {
+        let parsed = parse("\\begin{matrix} a");
+        assert_eq!(spans(&parsed), [("unclosed_environment", 0, 6)]);
+        assert_eq!(parsed.nodes, [Node::Environment { name: "matrix".into(), args: vec![], rows: vec![vec![vec![letter('a')]]] }]);
+    }
+
+    #[test]
+    fn spans_are_utf16_offsets_into_the_source() {
+        // Past the `$` that is stripped, counting `α` as one unit
+        let parsed = parse("$\\frac{α}{b$");
+        assert_eq!(spans(&parsed), [("unclosed_group", 9, 10)]);
+    }
+
+    #[test]
+    fn check_puts_errors_first() {
+        let codes: Vec<_> = check("\\foo{a").into_iter().map(|d| d.code).collect();
+        assert_eq!(codes, ["unclosed_group", "unknown_command"]);
+    }
+}
@@ -0,0 +1,172 @@
+//! Formula images: the laid out formula with every glyph written as an SVG path, so the image
+//! displays without the font. PNG is that SVG rasterized.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use fontdb::ID;
+use resvg::{tiny_skia, usvg};
+use ttf_parser::{GlyphId, OutlineBuilder};
+
+use super::layout::{Item, Layout, Typesetter};
+use super::parser::Node;
+
+pub struct RenderOptions {
+    /// Pixels per em
+    pub font_size: f32,
+    pub display: bool,
+    /// CSS color of the formula
+    pub color: String,
+    /// Transparent when `None`
+    pub background: Option<String>,
+}
+
+pub struct Svg {
+    pub markup: String,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Margin around the formula
+const PADDING: f32 = 0.15;
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Shortest decimal form, three places at most.
+fn num(v: f32) -> String {
+    let s = format!("{:.3}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+/// Every glyph becomes a path; repeated glyphs reuse the path data of the first.
+fn write(typesetter: &Typesetter, layout: &Layout, opts: &RenderOptions) -> Svg {
+    let px = opts.font_size;
+    let (asc, desc) = (layout.asc.max(0.0), layout.desc.max(0.0));
+    let width = (layout.w.max(0.0) + 2.0 * PADDING) * px;
+    let height = (asc + desc + 2.0 * PADDING) * px;
+    let (ox, oy) = (PADDING, PADDING + asc);
+    let fill = |color: &Option<String>| color.as_ref().map(|c| format!(" fill=\"{}\"", escape(c))).unwrap_or_default();
+    let mut outlines: HashMap<(ID, GlyphId), String> = HashMap::new();
+
+    let mut body = String::new();
+    if let Some(background) = &opts.background {
+        let _ = write!(body, r#"<rect width="100%" height="100%" fill="{}"/>"#, escape(background));
+    }
+    for item in &layout.items {
+        let _ = match item {
+            Item::Glyph { face, id, x, y, scale_x, scale_y, color } => {
+                let d = outlines.entry((*face, *id)).or_insert_with(|| {
+                    let mut path = PathData(String::new());
+                    typesetter.outline(*face, *id, &mut path);
+                    path.0
+                });
+                if d.is_empty() { continue; }
+                write!(
+                    body, r#"<path{} transform="matrix({} 0 0 {} {} {})" d="{}"/>"#,
+                    fill(color), num(scale_x * px), num(-scale_y * px), num((ox + x) * px), num((oy + y) * px), d,
+                )
+            }
+            Item::Rule { x, y, w, h, color } => write!(
+                body, r#"<rect{} x="{}" y="{}" width="{}" height="{}"/>"#,
+                fill(color), num((ox + x) * px), num((oy + y) * px), num(w * px), num(h * px),
+            ),
+            Item::Line { x1, y1, x2, y2, width, color } => write!(
+                body, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-linecap="round"/>"#,
+                num((ox + x1) * px), num((oy + y1) * px), num((ox + x2) * px), num((oy + y2) * px),
+                escape(color.as_deref().unwrap_or(&opts.color)), num(width * px),
+            ),
+        };
+    }
+    let markup = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}"><g fill="{}">{}</g></svg>"#,
+        escape(&opts.color), body, w = num(width), h = num(height),
+    );
+    Svg { markup, width, height }
+}
+
+/// Glyph outlines as path data in font units, y up.
+struct PathData(String);
+
+impl OutlineBuilder for PathData {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let _ = write!(self.0, "M{} {}", num(x), num(y));
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        let _ = write!(self.0, "L{} {}", num(x), num(y));
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let _ = write!(self.0, "Q{} {} {} {}", num(x1), num(y1), num(x), num(y));
+    }
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let _ = write!(self.0, "C{} {} {} {} {} {}", num(x1), num(y1), num(x2), num(y2), num(x), num(y));
+    }
+    fn close(&mut self) {
+        self.0.push('Z');
+    }
+}
+
+pub fn to_svg(nodes: &[Node], opts: &RenderOptions) -> Result<Svg, String> {
+    let mut typesetter = Typesetter::new()?;
+    let layout = typesetter.formula(nodes, opts.display);
+    Ok(write(&typesetter, &layout, opts))
+}
+
+/// Rasterizes at `scale` device pixels per SVG pixel, 2 for 192 DPI.
+pub fn to_png(svg: &Svg, scale: f32) -> Result<Vec<u8>, String> {
+    let tree = usvg::Tree::from_str(&svg.markup, &usvg::Options::default()).map_err(|e| format!("SVG 解析失败: {}", e))?;
+    let width = (svg.width * scale).ceil().max(1.0) as u32;
+    let height = (svg.height * scale).ceil().max(1.0) as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or_else(|| "图片尺寸无效".to_string())?;
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|e| format!("PNG 编码失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latex::parse;
+
+    fn render(src: &str, font_size: f32) -> Svg {
+        let opts = RenderOptions { font_size, display: true, color: "#000000".into(), background: None };
+        to_svg(&parse(src).nodes, &opts).expect("no font to render with")
+    }
+
+    /// Width and height from a PNG's IHDR chunk.
+    fn png_size(png: &[u8]) -> (u32, u32) {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let be = |i: usize| u32::from_be_bytes(png[i..i + 4].try_into().unwrap());
+        (be(16), be(20))
+    }
+
+    #[test]
+    fn size_matches_view_box() {
+        let svg = render("x+1", 32.0);
+        assert!(svg.width > 2.0 * PADDING * 32.0 && svg.height > 2.0 * PADDING * 32.0);
+        let (w, h) = (num(svg.width), num(svg.height));
+        assert!(
+            svg.markup.starts_with(&format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#)),
+            "{}", svg.markup,
+        );
+        assert_eq!(svg.markup.matches("<path").count(), 3);
+
+        // Everything is in pixels of the font size
+        let double = render("x+1", 64.0);
+        assert!((double.width - 2.0 * svg.width).abs() < 0.01);
+        assert!((double.height - 2.0 * svg.height).abs() < 0.01);
+    }
+
+    #[test]
+    fn png_scales_with_dpi() {
+        let svg = render("\\frac{a}{b}", 32.0);
+        let one = png_size(&to_png(&svg, 1.0).unwrap());
+        assert_eq!(one, (svg.width.ceil() as u32, svg.height.ceil() as u32));
+        for scale in [2.0, 3.0] {
+            let (w, h) = png_size(&to_png(&svg, scale).unwrap());
+            assert_eq!((w, h), ((svg.width * scale).ceil() as u32, (svg.height * scale).ceil() as u32));
+            assert!(w >= one.0 * scale as u32 - 1 && h >= one.1 * scale as u32 - 1);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! What the LaTeX math commands mean: Unicode symbols, operator names, accents, spacing.
+//! Shared by the parser (to spot unknown commands) and the converters.
+
+use serde::Serialize;
+
+/// TeX math class, decides spacing and MathML element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Class {
+    Ordinary,
+    Binary,
+    Relation,
+    /// `\sum`, `\int`, ... take limits
+    LargeOp,
+    Open,
+    Close,
+    Punct,
+}
+
+use Class::*;
+
+/// `(command, unicode, class)`
+const SYMBOLS: &[(&str, &str, Class)] = &[
+    // Greek
+    ("alpha", "α", Ordinary), ("beta", "β", Ordinary), ("gamma", "γ", Ordinary), ("delta", "δ", Ordinary),
+    ("epsilon", "ϵ", Ordinary), ("varepsilon", "ε", Ordinary), ("zeta", "ζ", Ordinary), ("eta", "η", Ordinary),
+    ("theta", "θ", Ordinary), ("vartheta", "ϑ", Ordinary), ("iota", "ι", Ordinary), ("kappa", "κ", Ordinary),
+    ("varkappa", "ϰ", Ordinary), ("lambda", "λ", Ordinary), ("mu", "μ", Ordinary), ("nu", "ν", Ordinary),
+    ("xi", "ξ", Ordinary), ("omicron", "ο", Ordinary), ("pi", "π", Ordinary), ("varpi", "ϖ", Ordinary),
+    ("rho", "ρ", Ordinary), ("varrho", "ϱ", Ordinary), ("sigma", "σ", Ordinary), ("varsigma", "ς", Ordinary),
+    ("tau", "τ", Ordinary), ("upsilon", "υ", Ordinary), ("phi", "ϕ", Ordinary), ("varphi", "φ", Ordinary),
+    ("chi", "χ", Ordinary), ("psi", "ψ", Ordinary), ("omega", "ω", Ordinary),
+    ("Gamma", "Γ", Ordinary), ("Delta", "Δ", Ordinary), ("Theta", "Θ", Ordinary), ("Lambda", "Λ", Ordinary),
+    ("Xi", "Ξ", Ordinary), ("Pi", "Π", Ordinary), ("Sigma", "Σ", Ordinary), ("Upsilon", "Υ", Ordinary),
+    ("Phi", "Φ", Ordinary), ("Psi", "Ψ", Ordinary), ("Omega", "Ω", Ordinary),
+    // Letter-like and misc
+    ("infty", "∞", Ordinary), ("partial", "∂", Ordinary), ("nabla", "∇", Ordinary), ("hbar", "ℏ", Ordinary),
+    ("hslash", "ℏ", Ordinary), ("ell", "ℓ", Ordinary), ("Re", "ℜ", Ordinary), ("Im", "ℑ", Ordinary),
+    ("aleph", "ℵ", Ordinary), ("beth", "ℶ", Ordinary), ("wp", "℘", Ordinary), ("imath", "ı", Ordinary),
+    ("jmath", "ȷ", Ordinary), ("emptyset", "∅", Ordinary), ("varnothing", "∅", Ordinary),
+    ("forall", "∀", Ordinary), ("exists", "∃", Ordinary), ("nexists", "∄", Ordinary), ("neg", "¬", Ordinary),
+    ("lnot", "¬", Ordinary), ("angle", "∠", Ordinary), ("measuredangle", "∡", Ordinary),
+    ("triangle", "△", Ordinary), ("square", "□", Ordinary), ("Box", "□", Ordinary), ("prime", "′", Ordinary),
+    ("top", "⊤", Ordinary), ("bot", "⊥", Ordinary), ("surd", "√", Ordinary), ("degree", "°", Ordinary),
+    ("ldots", "…", Ordinary), ("dots", "…", Ordinary), ("dotsc", "…", Ordinary), ("cdots", "⋯", Ordinary),
+    ("dotsb", "⋯", Ordinary), ("vdots", "⋮", Ordinary), ("ddots", "⋱", Ordinary), ("therefore", "∴", Ordinary),
+    ("because", "∵", Ordinary), ("checkmark", "✓", Ordinary), ("dagger", "†", Binary), ("ddagger", "‡", Binary),
+    ("backslash", "\\", Ordinary), ("#", "#", Ordinary), ("%", "%", Ordinary), ("$", "$", Ordinary),
+    ("&", "&", Ordinary), ("_", "_", Ordinary),
+    // Binary operators
+    ("pm", "±", Binary), ("mp", "∓", Binary), ("times", "×", Binary), ("div", "÷", Binary),
+    ("cdot", "⋅", Binary), ("ast", "∗", Binary), ("star", "⋆", Binary), ("circ", "∘", Binary),
+    ("bullet", "∙", Binary), ("oplus", "⊕", Binary), ("ominus", "⊖", Binary), ("otimes", "⊗", Binary),
+    ("odot", "⊙", Binary), ("oslash", "⊘", Binary), ("cap", "∩", Binary), ("cup", "∪", Binary),
+    ("setminus", "∖", Binary), ("wedge", "∧", Binary), ("land", "∧", Binary), ("vee", "∨", Binary),
+    ("lor", "∨", Binary), ("sqcap", "⊓", Binary), ("sqcup", "⊔", Binary), ("uplus", "⊎", Binary),
+    ("amalg", "⨿", Binary), ("diamond", "⋄", Binary), ("wr", "≀", Binary), ("bmod", "mod", Binary),
+    // Relations
+    ("leq", "≤", Relation), ("le", "≤", Relation), ("geq", "≥", Relation), ("ge", "≥", Relation),
+    ("neq", "≠", Relation), ("ne", "≠", Relation), ("lt", "<", Relation), ("gt", ">", Relation),
+    ("leqslant", "⩽", Relation), ("geqslant", "⩾", Relation), ("leqq", "≦", Relation), ("geqq", "≧", Relation),
+    ("lesssim", "≲", Relation), ("gtrsim", "≳", Relation), ("ll", "≪", Relation), ("gg", "≫", Relation),
+    ("equiv", "≡", Relation), ("approx", "≈", Relation), ("approxeq", "≊", Relation), ("sim", "∼", Relation),
+    ("simeq", "≃", Relation), ("cong", "≅", Relation), ("propto", "∝", Relation), ("asymp", "≍", Relation),
+    ("doteq", "≐", Relation), ("triangleq", "≜", Relation), ("coloneqq", "≔", Relation),
+    ("prec", "≺", Relation), ("succ", "≻", Relation), ("preceq", "⪯", Relation), ("succeq", "⪰", Relation),
+    ("subset", "⊂", Relation), ("supset", "⊃", Relation), ("subseteq", "⊆", Relation), ("supseteq", "⊇", Relation),
+    ("subsetneq", "⊊", Relation), ("supsetneq", "⊋", Relation), ("sqsubseteq", "⊑", Relation),
+    ("sqsupseteq", "⊒", Relation), ("in", "∈", Relation), ("notin", "∉", Relation), ("ni", "∋", Relation),
+    ("mid", "∣", Relation), ("nmid", "∤", Relation), ("parallel", "∥", Relation), ("nparallel", "∦", Relation),
+    ("perp", "⊥", Relation), ("models", "⊨", Relation), ("vdash", "⊢", Relation), ("dashv", "⊣", Relation),
+    ("bowtie", "⋈", Relation),
+    // Arrows
+    ("to", "→", Relation), ("rightarrow", "→", Relation), ("leftarrow", "←", Relation), ("gets", "←", Relation),
+    ("leftrightarrow", "↔", Relation), ("Rightarrow", "⇒", Relation), ("Leftarrow", "⇐", Relation),
+    ("Leftrightarrow", "⇔", Relation), ("implies", "⟹", Relation), ("impliedby", "⟸", Relation),
+    ("iff", "⟺", Relation), ("longrightarrow", "⟶", Relation), ("longleftarrow", "⟵", Relation),
+    ("longleftrightarrow", "⟷", Relation), ("Longrightarrow", "⟹", Relation), ("Longleftarrow", "⟸", Relation),
+    ("Longleftrightarrow", "⟺", Relation), ("mapsto", "↦", Relation), ("longmapsto", "⟼", Relation),
+    ("uparrow", "↑", Relation), ("downarrow", "↓", Relation), ("updownarrow", "↕", Relation),
+    ("Uparrow", "⇑", Relation), ("Downarrow", "⇓", Relation), ("nearrow", "↗", Relation),
+    ("searrow", "↘", Relation), ("swarrow", "↙", Relation), ("nwarrow", "↖", Relation),
+    ("hookrightarrow", "↪", Relation), ("hookleftarrow", "↩", Relation), ("rightleftharpoons", "⇌", Relation),
+    ("leftrightharpoons", "⇋", Relation), ("rightharpoonup", "⇀", Relation), ("leftharpoonup", "↼", Relation),
+    // Large operators
+    ("sum", "∑", LargeOp), ("prod", "∏", LargeOp), ("coprod", "∐", LargeOp), ("int", "∫", LargeOp),
+    ("iint", "∬", LargeOp), ("iiint", "∭", LargeOp), ("oint", "∮", LargeOp), ("bigcup", "⋃", LargeOp),
+    ("bigcap", "⋂", LargeOp), ("bigoplus", "⨁", LargeOp), ("bigotimes", "⨂", LargeOp), ("bigodot", "⨀", LargeOp),
+    ("bigvee", "⋁", LargeOp), ("bigwedge", "⋀", LargeOp), ("bigsqcup", "⨆", LargeOp), ("biguplus", "⨄", LargeOp),
+    // Delimiters
+    ("langle", "⟨", Open), ("rangle", "⟩", Close), ("lceil", "⌈", Open), ("rceil", "⌉", Close),
+    ("lfloor", "⌊", Open), ("rfloor", "⌋", Close), ("lbrace", "{", Open), ("rbrace", "}", Close),
+    ("{", "{", Open), ("}", "}", Close), ("lbrack", "[", Open), ("rbrack", "]", Close),
+    ("lvert", "|", Open), ("rvert", "|", Close), ("lVert", "‖", Open), ("rVert", "‖", Close),
+    ("vert", "|", Ordinary), ("Vert", "‖", Ordinary), ("|", "‖", Ordinary),
+    // Punctuation
+    ("colon", ":", Punct), ("ldotp", ".", Punct), ("cdotp", "⋅", Punct),
+];
+
+/// Upright operator names like `\sin`; the `bool` says whether scripts go above/below (`\lim`).
+const FUNCTIONS: &[(&str, bool)] = &[
+    ("sin", false), ("cos", false), ("tan", false), ("cot", false), ("sec", false), ("csc", false),
+    ("arcsin", false), ("arccos", false), ("arctan", false), ("sinh", false), ("cosh", false),
+    ("tanh", false), ("coth", false), ("log", false), ("ln", false), ("lg", false), ("exp", false),
+    ("arg", false), ("deg", false), ("dim", false), ("hom", false), ("ker", false), ("Pr", true),
+    ("det", true), ("gcd", true), ("lim", true), ("liminf", true), ("limsup", true), ("sup", true),
+    ("inf", true), ("max", true), ("min", true),
+];
+
+/// `(command, accent mark, goes under the base)`
+const ACCENTS: &[(&str, &str, bool)] = &[
+    ("hat", "^", false), ("widehat", "^", false), ("check", "ˇ", false), ("widecheck", "ˇ", false),
+    ("tilde", "~", false), ("widetilde", "~", false), ("acute", "´", false), ("grave", "`", false),
+    ("dot", "˙", false), ("ddot", "¨", false), ("dddot", "⃛", false), ("breve", "˘", false),
+    ("bar", "¯", false), ("vec", "→", false), ("overline", "‾", false), ("underline", "_", true),
+    ("overrightarrow", "→", false), ("overleftarrow", "←", false), ("overleftrightarrow", "↔", false),
+    ("underrightarrow", "→", true), ("underleftarrow", "←", true),
+    ("overbrace", "⏞", false), ("underbrace", "⏟", true),
+];
+
+/// Horizontal spacing commands and their width in em.
+const SPACES: &[(&str, f32)] = &[
+    (",", 0.1667), (":", 0.2222), (">", 0.2222), (";", 0.2778), ("!", -0.1667), (" ", 0.25),
+    ("thinspace", 0.1667), ("medspace", 0.2222), ("thickspace", 0.2778), ("negthinspace", -0.1667),
+    ("enspace", 0.5), ("quad", 1.0), ("qquad", 2.0),
+];
+
+pub fn symbol(name: &str) -> Option<(&'static str, Class)> {
+    SYMBOLS.iter().find(|(n, _, _)| *n == name).map(|&(_, u, c)| (u, c))
+}
+
+/// `Some(limits)` for operator names like `sin` or `lim`.
+pub fn function(name: &str) -> Option<bool> {
+    FUNCTIONS.iter().find(|(n, _)| *n == name).map(|&(_, limits)| limits)
+}
+
+/// `(mark, under)` for accent commands.
+pub fn accent(name: &str) -> Option<(&'static str, bool)> {
+    ACCENTS.iter().find(|(n, _, _)| *n == name).map(|&(_, m, under)| (m, under))
+}
+
+pub fn space(name: &str) -> Option<f32> {
+    SPACES.iter().find(|(n, _)| *n == name).map(|&(_, w)| w)
+}
+
+/// Combining mark for an accent command, as UnicodeMath and OMML put it on their operand.
+pub fn combining(command: &str) -> Option<char> {
+    Some(match command {
+        "hat" | "widehat" => '\u{0302}',
+        "check" | "widecheck" => '\u{030C}',
+        "tilde" | "widetilde" => '\u{0303}',
+        "acute" => '\u{0301}',
+        "grave" => '\u{0300}',
+        "dot" => '\u{0307}',
+        "ddot" => '\u{0308}',
+        "dddot" => '\u{20DB}',
+        "breve" => '\u{0306}',
+        "bar" => '\u{0305}',
+        "vec" | "overrightarrow" => '\u{20D7}',
+        "overleftarrow" => '\u{20D6}',
+        "overleftrightarrow" => '\u{20E1}',
+        _ => return None,
+    })
+}
+
+/// `\not` applied to a relation, using the precomposed character where Unicode has one.
+pub fn negated(op: &str) -> String {
+    match op {
+        "=" => "≠", "<" => "≮", ">" => "≯", "∈" => "∉", "≤" => "≰", "≥" => "≱", "⊂" => "⊄",
+        "⊃" => "⊅", "⊆" => "⊈", "⊇" => "⊉", "≡" => "≢", "∼" => "≁", "≈" => "≉", "≅" => "≇",
+        "∣" => "∤", "∥" => "∦", "∃" => "∄",
+        _ => return format!("{}\u{0338}", op),
+    }.to_string()
+}
+
+/// Unicode for a delimiter as written after `\left`, `\big`, ...; empty for `.`.
+pub fn delimiter(text: &str) -> &str {
+    match text {
+        "." => "",
+        "<" => "⟨",
+        ">" => "⟩",
+        _ => match text.strip_prefix('\\') {
+            Some("backslash") => "\\",
+            Some(name) => symbol(name).map(|(u, _)| u).unwrap_or(text),
+            None => text,
+        },
+    }
+}
+
+/// `c` in a Mathematical Alphanumeric Symbols style; `variant` uses MathML's `mathvariant` names.
+/// Characters without a styled form are returned as is.
+pub fn styled(c: char, variant: &str) -> char {
+    // Letters encoded earlier in Letterlike Symbols, left as holes in the alphanumeric block
+    let holes: &[(char, char)] = match variant {
+        "italic" => &[('h', 'ℎ')],
+        "script" => &[
+            ('B', 'ℬ'), ('E', 'ℰ'), ('F', 'ℱ'), ('H', 'ℋ'), ('I', 'ℐ'), ('L', 'ℒ'), ('M', 'ℳ'), ('R', 'ℛ'),
+            ('e', 'ℯ'), ('g', 'ℊ'), ('o', 'ℴ'),
+        ],
+        "fraktur" => &[('C', 'ℭ'), ('H', 'ℌ'), ('I', 'ℑ'), ('R', 'ℜ'), ('Z', 'ℨ')],
+        "double-struck" => &[('C', 'ℂ'), ('H', 'ℍ'), ('N', 'ℕ'), ('P', 'ℙ'), ('Q', 'ℚ'), ('R', 'ℝ'), ('Z', 'ℤ')],
+        _ => &[],
+    };
+    if let Some(&(_, h)) = holes.iter().find(|(k, _)| *k == c) { return h; }
+    let (letters, digits) = match variant {
+        "bold" => (0x1D400, Some(0x1D7CE)),
+        "italic" => (0x1D434, None),
+        "bold-italic" => (0x1D468, None),
+        "script" => (0x1D49C, None),
+        "fraktur" => (0x1D504, None),
+        "double-struck" => (0x1D538, Some(0x1D7D8)),
+        "sans-serif" => (0x1D5A0, Some(0x1D7E2)),
+        "monospace" => (0x1D670, Some(0x1D7F6)),
+        _ => return c,
+    };
+    let code = match c {
+        'A'..='Z' => letters + (c as u32 - 'A' as u32),
+        'a'..='z' => letters + 26 + (c as u32 - 'a' as u32),
+        '0'..='9' => match digits {
+            Some(d) => d + (c as u32 - '0' as u32),
+            None => return c,
+        },
+        _ => return c,
+    };
+    char::from_u32(code).unwrap_or(c)
+}
@@ -0,0 +1,582 @@
+//! Typst math markup from the LaTeX AST.
+//!
+//! Symbols are written by name where Typst's name has been stable across releases and as the
+//! Unicode character otherwise, which Typst accepts in math directly.
+
+use super::parser::{split_primes, Node};
+use super::symbols;
+
+/// `(unicode, Typst name)`
+const NAMES: &[(&str, &str)] = &[
+    // Greek
+    ("α", "alpha"), ("β", "beta"), ("γ", "gamma"), ("δ", "delta"), ("ϵ", "epsilon.alt"), ("ε", "epsilon"),
+    ("ζ", "zeta"), ("η", "eta"), ("θ", "theta"), ("ϑ", "theta.alt"), ("ι", "iota"), ("κ", "kappa"),
+    ("ϰ", "kappa.alt"), ("λ", "lambda"), ("μ", "mu"), ("ν", "nu"), ("ξ", "xi"), ("ο", "omicron"),
+    ("π", "pi"), ("ϖ", "pi.alt"), ("ρ", "rho"), ("ϱ", "rho.alt"), ("σ", "sigma"), ("ς", "sigma.alt"),
+    ("τ", "tau"), ("υ", "upsilon"), ("ϕ", "phi.alt"), ("φ", "phi"), ("χ", "chi"), ("ψ", "psi"),
+    ("ω", "omega"), ("Γ", "Gamma"), ("Δ", "Delta"), ("Θ", "Theta"), ("Λ", "Lambda"), ("Ξ", "Xi"),
+    ("Π", "Pi"), ("Σ", "Sigma"), ("Υ", "Upsilon"), ("Φ", "Phi"), ("Ψ", "Psi"), ("Ω", "Omega"),
+    // Letter-like and misc
+    ("∞", "infinity"), ("∂", "partial"), ("∇", "nabla"), ("ℓ", "ell"), ("ℵ", "aleph"), ("∅", "emptyset"),
+    ("∀", "forall"), ("∃", "exists"), ("∄", "exists.not"), ("¬", "not"), ("∠", "angle"), ("′", "prime"),
+    ("⊤", "top"), ("°", "degree"), ("…", "dots.h"), ("⋯", "dots.h.c"), ("⋮", "dots.v"), ("⋱", "dots.down"),
+    ("∴", "therefore"), ("∵", "because"), ("†", "dagger"), ("‡", "dagger.double"), ("mod", "mod"),
+    // Binary operators
+    ("±", "plus.minus"), ("∓", "minus.plus"), ("×", "times"), ("÷", "div"), ("⋅", "dot.op"),
+    ("∗", "ast.op"), ("⋆", "star.op"), ("⊕", "plus.circle"), ("⊖", "minus.circle"), ("⊗", "times.circle"),
+    ("⊙", "dot.circle"), ("∪", "union"), ("⊎", "union.plus"), ("∖", "without"), ("∧", "and"), ("∨", "or"),
+    // Relations
+    ("≤", "lt.eq"), ("≥", "gt.eq"), ("≠", "eq.not"), ("⩽", "lt.eq.slant"), ("⩾", "gt.eq.slant"),
+    ("≲", "lt.tilde"), ("≳", "gt.tilde"), ("≪", "lt.double"), ("≫", "gt.double"), ("≮", "lt.not"),
+    ("≯", "gt.not"), ("≰", "lt.eq.not"), ("≱", "gt.eq.not"), ("≡", "equiv"), ("≢", "equiv.not"),
+    ("≈", "approx"), ("≉", "approx.not"), ("∼", "tilde.op"), ("≁", "tilde.not"), ("≃", "tilde.eq"),
+    ("≅", "tilde.equiv"), ("∝", "prop"), ("≔", "colon.eq"), ("≺", "prec"), ("≻", "succ"),
+    ("⪯", "prec.eq"), ("⪰", "succ.eq"), ("⊂", "subset"), ("⊃", "supset"), ("⊆", "subset.eq"),
+    ("⊇", "supset.eq"), ("⊊", "subset.neq"), ("⊋", "supset.neq"), ("⊄", "subset.not"),
+    ("⊈", "subset.eq.not"), ("∈", "in"), ("∉", "in.not"), ("∋", "in.rev"), ("∣", "divides"),
+    ("∤", "divides.not"), ("∥", "parallel"), ("∦", "parallel.not"), ("⊨", "models"), ("⊢", "tack.r"),
+    ("⊣", "tack.l"),
+    // Arrows
+    ("→", "arrow.r"), ("←", "arrow.l"), ("↔", "arrow.l.r"), ("⇒", "arrow.r.double"), ("⇐", "arrow.l.double"),
+    ("⇔", "arrow.l.r.double"), ("⟶", "arrow.r.long"), ("⟵", "arrow.l.long"), ("⟷", "arrow.l.r.long"),
+    ("⟹", "arrow.r.double.long"), ("⟸", "arrow.l.double.long"), ("⟺", "arrow.l.r.double.long"),
+    ("↦", "arrow.r.bar"), ("⟼", "arrow.r.long.bar"), ("↑", "arrow.t"), ("↓", "arrow.b"),
+    ("↕", "arrow.t.b"), ("⇑", "arrow.t.double"), ("⇓", "arrow.b.double"), ("↗", "arrow.tr"),
+    ("↘", "arrow.br"), ("↙", "arrow.bl"), ("↖", "arrow.tl"), ("↪", "arrow.r.hook"), ("↩", "arrow.l.hook"),
+    ("⇀", "harpoon.rt"), ("↼", "harpoon.lt"),
+    // Large operators
+    ("∑", "sum"), ("∏", "product"), ("∐", "product.co"), ("∫", "integral"), ("∬", "integral.double"),
+    ("∭", "integral.triple"), ("∮", "integral.cont"), ("⋃", "union.big"), ("⨁", "plus.circle.big"),
+    ("⨂", "times.circle.big"), ("⨀", "dot.circle.big"), ("⋁", "or.big"), ("⋀", "and.big"),
+    ("⨄", "union.plus.big"),
+];
+
+/// Names for delimiters inside `lr(...)`, where a bare bracket would be matched by the parser.
+const DELIMITER_NAMES: &[(&str, &str)] = &[
+    ("(", "paren.l"), (")", "paren.r"), ("[", "bracket.l"), ("]", "bracket.r"), ("{", "brace.l"),
+    ("}", "brace.r"), ("|", "bar.v"), ("‖", "bar.v.double"), ("⌊", "floor.l"), ("⌋", "floor.r"),
+    ("⌈", "ceil.l"), ("⌉", "ceil.r"),
+];
+
+/// Colors Typst knows by name.
+const COLORS: &[&str] = &[
+    "black", "gray", "silver", "white", "navy", "blue", "aqua", "teal", "eastern", "purple",
+    "fuchsia", "maroon", "red", "orange", "yellow", "olive", "green", "lime",
+];
+
+fn font(command: &str) -> Option<&'static [&'static str]> {
+    Some(match command {
+        "mathbf" => &["upright", "bold"],
+        "mathrm" => &["upright"],
+        "mathit" => &["italic"],
+        "mathbb" => &["bb"],
+        "mathcal" | "mathscr" => &["cal"],
+        "mathfrak" => &["frak"],
+        "mathsf" => &["sans"],
+        "mathtt" => &["mono"],
+        "boldsymbol" | "bm" => &["bold"],
+        _ => return None,
+    })
+}
+
+fn accent(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "hat" | "widehat" => "hat",
+        "check" | "widecheck" => "caron",
+        "tilde" | "widetilde" => "tilde",
+        "acute" => "acute",
+        "grave" => "grave",
+        "dot" => "dot",
+        "ddot" => "dot.double",
+        "dddot" => "dot.triple",
+        "breve" => "breve",
+        "bar" => "macron",
+        "vec" | "overrightarrow" => "arrow",
+        "overleftarrow" => "arrow.l",
+        "overleftrightarrow" => "arrow.l.r",
+        "overline" => "overline",
+        "underline" => "underline",
+        "overbrace" => "overbrace",
+        "underbrace" => "underbrace",
+        _ => return None,
+    })
+}
+
+/// A string literal, rendered upright like `\text`.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn glyph(u: &str) -> String {
+    if let Some(&(_, name)) = NAMES.iter().find(|(c, _)| *c == u) {
+        return name.to_string();
+    }
+    match u {
+        // Markup in Typst math: `/` makes a fraction, `_ ^ &` attach and align
+        "#" | "$" | "\"" | "@" | "/" | "_" | "^" | "&" | "\\" | "`" => format!("\\{}", u),
+        _ => u.to_string(),
+    }
+}
+
+fn symbol(c: char) -> String {
+    match c {
+        '~' => "space".to_string(),
+        c => glyph(c.encode_utf8(&mut [0; 4])),
+    }
+}
+
+fn spacing(command: &str, em: f32) -> String {
+    match command {
+        "," | "thinspace" => "thin".to_string(),
+        ":" | ">" | "medspace" => "med".to_string(),
+        ";" | "thickspace" => "thick".to_string(),
+        " " => "space".to_string(),
+        "quad" => "quad".to_string(),
+        "qquad" => "wide".to_string(),
+        _ => format!("#h({}em)", em),
+    }
+}
+
+fn color(spec: &str) -> Option<String> {
+    let spec = spec.trim();
+    if COLORS.contains(&spec) { return Some(spec.to_string()); }
+    let hex = spec.trim_start_matches('#');
+    if matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(format!("rgb(\"#{}\")", hex));
+    }
+    None
+}
+
+fn colored(spec: Option<&Node>, content: String) -> String {
+    match spec.and_then(|n| color(&n.plain_text())) {
+        Some(c) => format!("#text(fill: {})[${}$]", c, content),
+        None => content,
+    }
+}
+
+/// Whether `s` can take a script without parentheses: a name, number, string or call.
+fn atomic(s: &str) -> bool {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => return false,
+        (Some(c), None) => return c.is_alphanumeric() || !c.is_ascii(),
+        _ => {}
+    }
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') { return true; }
+    let name_end = s.find('(').unwrap_or(s.len());
+    let name = s[..name_end].trim_start_matches('#');
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '.') { return false; }
+    if name_end == s.len() { return !s.ends_with('.'); }
+    // A call: the parenthesis after the name has to close at the very end
+    let mut depth = 0;
+    for (i, c) in s.char_indices().skip_while(|&(i, _)| i < name_end) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 { return i == s.len() - 1; }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+fn spaced(prev: &str, next: &str) -> bool {
+    if prev == "\\" { return true; }
+    if matches!(prev, "(" | "[" | "{") { return false; }
+    if matches!(next, ")" | "]" | "}" | "," | ";" | "\\," | "\\;" | "!" | "'") { return false; }
+    if matches!(next, "(" | "[") {
+        // `f(x)`; a longer name would read as a function call
+        let mut chars = prev.chars();
+        let single = matches!((chars.next(), chars.next()), (Some(c), None) if c.is_alphanumeric());
+        return !(single || prev.ends_with('\''));
+    }
+    true
+}
+
+fn join(pieces: &[String]) -> String {
+    let mut out = String::new();
+    let mut prev: Option<&str> = None;
+    for piece in pieces.iter().filter(|p| !p.is_empty()) {
+        if prev.is_some_and(|p| spaced(p, piece)) { out.push(' '); }
+        out.push_str(piece);
+        prev = Some(piece);
+    }
+    out
+}
+
+/// `arg`: the list is a function argument, where a top-level `,` or `;` would split it.
+fn list(nodes: &[Node], arg: bool) -> String {
+    let mut pieces = Vec::new();
+    let mut depth = 0usize;
+    for (i, n) in nodes.iter().enumerate() {
+        // Switches apply to the rest of the list
+        if let Node::Command { name, args, .. } = n {
+            let rest = &nodes[i + 1..];
+            let style = match name.as_str() {
+                "displaystyle" => Some("display"),
+                "textstyle" => Some("inline"),
+                "scriptstyle" => Some("script"),
+                "scriptscriptstyle" => Some("sscript"),
+                _ => None,
+            };
+            if let Some(f) = style {
+                if !rest.is_empty() { pieces.push(format!("{}({})", f, list(rest, true))); }
+                break;
+            }
+            if name == "color" {
+                pieces.push(colored(args.first(), list(rest, false)));
+                break;
+            }
+        }
+        let piece = match n {
+            Node::Symbol { value: c @ (',' | ';') } if arg && depth == 0 => format!("\\{}", c),
+            n => node(n, arg),
+        };
+        match piece.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        pieces.push(piece);
+    }
+    join(&pieces)
+}
+
+/// A function argument: groups are unwrapped.
+fn argument(n: Option<&Node>) -> String {
+    match n {
+        Some(Node::Group { body }) => list(body, true),
+        Some(n) => node(n, true),
+        None => String::new(),
+    }
+}
+
+/// A sub- or superscript, parenthesized unless it is a single item.
+fn script(n: &Node) -> String {
+    let s = match n {
+        Node::Group { body } => list(body, false),
+        n => node(n, false),
+    };
+    if atomic(&s) { s } else { format!("({})", s) }
+}
+
+fn node(n: &Node, arg: bool) -> String {
+    match n {
+        Node::Letter { value } => value.to_string(),
+        Node::Number { value } => value.clone(),
+        Node::Symbol { value } => symbol(*value),
+        Node::Group { body } => list(body, arg),
+        Node::Command { name, optional, args } => command(name, optional.as_deref(), args),
+        Node::Text { command, value } => text(command, value),
+        Node::Scripts { base, sub, sup } => scripts(base, sub.as_deref(), sup.as_deref()),
+        Node::Delimited { left, right, body } => delimited(left, right, body),
+        Node::Sized { size, delim } => {
+            let d = symbols::delimiter(delim);
+            if d.is_empty() { return String::new(); }
+            if size == "middle" { format!("mid({})", glyph(d)) } else { glyph(d) }
+        }
+        Node::Environment { name, args, rows } => environment(name, args, rows, arg),
+        Node::Align => "&".to_string(),
+        Node::LineBreak => "\\".to_string(),
+    }
+}
+
+fn text(command: &str, value: &str) -> String {
+    let q = quote(value);
+    match command {
+        "tag" | "tag*" | "label" => String::new(),
+        "textbf" => format!("bold({})", q),
+        "textit" => format!("italic({})", q),
+        "textsf" => format!("sans({})", q),
+        "texttt" => format!("mono({})", q),
+        _ => q,
+    }
+}
+
+fn command(name: &str, optional: Option<&Node>, args: &[Node]) -> String {
+    let arg0 = args.first();
+    let arg1 = args.get(1);
+
+    // `\mathrm{max}` reads as one upright word
+    if name == "mathrm" {
+        if let Some(Node::Group { body }) = arg0 {
+            if body.len() > 1 && body.iter().all(|n| matches!(n, Node::Letter { .. })) {
+                return quote(&arg0.map(Node::plain_text).unwrap_or_default());
+            }
+        }
+    }
+    if let Some(wrappers) = font(name) {
+        return wrappers.iter().rev().fold(argument(arg0), |inner, f| format!("{}({})", f, inner));
+    }
+    if let Some(f) = accent(name) {
+        return format!("{}({})", f, argument(arg0));
+    }
+    if let Some(em) = symbols::space(name) {
+        return spacing(name, em);
+    }
+    // Typst predefines every operator name in the table
+    if symbols::function(name).is_some() {
+        return name.to_string();
+    }
+    if let Some((u, _)) = symbols::symbol(name) {
+        return glyph(u);
+    }
+
+    match name {
+        "frac" => format!("frac({}, {})", argument(arg0), argument(arg1)),
+        "dfrac" | "cfrac" => format!("display(frac({}, {}))", argument(arg0), argument(arg1)),
+        "tfrac" => format!("inline(frac({}, {}))", argument(arg0), argument(arg1)),
+        "binom" => format!("binom({}, {})", argument(arg0), argument(arg1)),
+        "dbinom" => format!("display(binom({}, {}))", argument(arg0), argument(arg1)),
+        "tbinom" => format!("inline(binom({}, {}))", argument(arg0), argument(arg1)),
+        "sqrt" => match optional {
+            Some(index) => format!("root({}, {})", argument(Some(index)), argument(arg0)),
+            None => format!("sqrt({})", argument(arg0)),
+        },
+        "overset" | "stackrel" | "underset" => {
+            let (base, over) = (argument(arg1), arg0.map(script).unwrap_or_default());
+            let attach = if name == "underset" { '_' } else { '^' };
+            format!("limits({}){}{}", base, attach, over)
+        }
+        "operatorname" | "operatorname*" => {
+            let text = arg0.map(Node::plain_text).unwrap_or_default();
+            match (name, symbols::function(&text)) {
+                ("operatorname", Some(_)) => text,
+                ("operatorname", None) => format!("op({})", quote(&text)),
+                _ => format!("op({}, limits: #true)", quote(&text)),
+            }
+        }
+        "limits" => format!("limits({})", argument(arg0)),
+        "nolimits" => format!("scripts({})", argument(arg0)),
+        "mathnormal" | "substack" => argument(arg0),
+        "boxed" => format!("#box(stroke: 0.5pt, inset: 0.25em, ${}$)", argument(arg0)),
+        "cancel" => format!("cancel({})", argument(arg0)),
+        "bcancel" => format!("cancel(inverted: #true, {})", argument(arg0)),
+        "xcancel" => format!("cancel(cross: #true, {})", argument(arg0)),
+        "phantom" | "hphantom" | "vphantom" => format!("#hide(${}$)", argument(arg0)),
+        "textcolor" => colored(arg0, argument(arg1)),
+        "pmod" => format!("quad (mod {})", argument(arg0)),
+        "not" => glyph(&symbols::negated(&arg0.map(Node::plain_text).unwrap_or_default())),
+        "xrightarrow" | "xleftarrow" => {
+            let arrow = if name == "xrightarrow" { "arrow.r.long" } else { "arrow.l.long" };
+            let below = optional.map(|n| format!("_{}", script(n))).unwrap_or_default();
+            format!("limits({}){}^{}", arrow, below, arg0.map(script).unwrap_or_default())
+        }
+        "displaystyle" | "textstyle" | "scriptstyle" | "scriptscriptstyle" | "color"
+        | "hline" | "nonumber" | "notag" => String::new(),
+        _ => quote(&format!("\\{}", name)),
+    }
+}
+
+fn scripts(base: &Node, sub: Option<&Node>, sup: Option<&Node>) -> String {
+    // `x'` is written with primes in both languages
+    let (primes, rest) = split_primes(sup);
+    let mut sup = rest.as_ref();
+
+    // Brace annotations become the second argument
+    let mut sub = sub;
+    let base_text = match base {
+        Node::Command { name, args, .. } if name == "overbrace" && sup.is_some() => {
+            format!("overbrace({}, {})", argument(args.first()), argument(sup.take()))
+        }
+        Node::Command { name, args, .. } if name == "underbrace" && sub.is_some() => {
+            format!("underbrace({}, {})", argument(args.first()), argument(sub.take()))
+        }
+        Node::Group { body } if body.is_empty() => "\"\"".to_string(),
+        n => node(n, false),
+    };
+
+    if atomic(&base_text) || matches!(base, Node::Delimited { .. }) {
+        let sub = sub.map(|n| format!("_{}", script(n))).unwrap_or_default();
+        let sup = sup.map(|n| format!("^{}", script(n))).unwrap_or_default();
+        return format!("{}{}{}{}", base_text, "'".repeat(primes), sub, sup);
+    }
+    // `{a+b}^2`: parentheses would show, `attach` does not
+    let mut parts = vec![base_text];
+    if sup.is_some() || primes > 0 {
+        let mut top = vec!["prime".to_string(); primes];
+        top.push(argument(sup));
+        parts.push(format!("t: {}", join(&top)));
+    }
+    if let Some(n) = sub { parts.push(format!("b: {}", argument(Some(n)))); }
+    format!("attach({})", parts.join(", "))
+}
+
+fn delimited(left: &str, right: &str, body: &[Node]) -> String {
+    let (l, r) = (symbols::delimiter(left), symbols::delimiter(right));
+    match (l, r) {
+        ("|", "|") => format!("abs({})", list(body, true)),
+        ("‖", "‖") => format!("norm({})", list(body, true)),
+        ("⌊", "⌋") => format!("floor({})", list(body, true)),
+        ("⌈", "⌉") => format!("ceil({})", list(body, true)),
+        // Typst sizes matched brackets on its own
+        ("(" | "[" | "{", ")" | "]" | "}") => format!("{}{}{}", l, list(body, false), r),
+        _ => {
+            let name = |d: &str| DELIMITER_NAMES.iter().find(|(c, _)| *c == d).map(|(_, n)| n.to_string()).unwrap_or_else(|| glyph(d));
+            let l = if l.is_empty() { String::new() } else { name(l) };
+            let r = if r.is_empty() { String::new() } else { name(r) };
+            format!("lr({})", join(&[l, list(body, true), r]))
+        }
+    }
+}
+
+fn environment(name: &str, args: &[String], rows: &[Vec<Vec<Node>>], arg: bool) -> String {
+    let table = |cell_sep: &str, row_sep: &str, arg: bool| {
+        rows.iter()
+            .map(|row| row.iter()
+                .map(|cell| if cell.is_empty() && arg { "\"\"".to_string() } else { list(cell, arg) })
+                .collect::<Vec<_>>()
+                .join(cell_sep)
+                .trim()
+                .to_string())
+            .collect::<Vec<_>>()
+            .join(row_sep)
+    };
+    let matrix = |delim: Option<&str>, align: Option<&str>| {
+        let mut parts = Vec::new();
+        if let Some(d) = delim { parts.push(format!("delim: {}", d)); }
+        if let Some(a) = align { parts.push(format!("align: #{}", a)); }
+        parts.push(table(", ", "; ", true));
+        format!("mat({})", parts.join(", "))
+    };
+
+    match name {
+        "pmatrix" => matrix(None, None),
+        "bmatrix" => matrix(Some("\"[\""), None),
+        "Bmatrix" => matrix(Some("\"{\""), None),
+        "vmatrix" => matrix(Some("\"|\""), None),
+        "Vmatrix" => matrix(Some("\"‖\""), None),
+        "cases" | "dcases" => format!("cases({})", table(" & ", ", ", true)),
+        "rcases" => format!("cases(reverse: #true, {})", table(" & ", ", ", true)),
+        "array" | "subarray" => {
+            let spec: String = args.first().map(|s| s.chars().filter(|c| "lcr".contains(*c)).collect()).unwrap_or_default();
+            let align = match spec.chars().next() {
+                Some(c) if c != 'c' && spec.chars().all(|x| x == c) => Some(if c == 'l' { "left" } else { "right" }),
+                _ => None,
+            };
+            matrix(Some("#none"), align)
+        }
+        "aligned" | "alignedat" | "align" | "align*" | "alignat" | "alignat*" | "split" | "eqnarray" | "eqnarray*" => {
+            table(" & ", " \\ ", arg)
+        }
+        "gathered" | "gather" | "gather*" | "equation" | "equation*" | "multline" | "multline*" => {
+            table(" ", " \\ ", arg)
+        }
+        // matrix, smallmatrix and anything unknown: a bare grid
+        _ => matrix(Some("#none"), None),
+    }
+}
+
+/// `display`: `$ ... $` on its own line rather than inline `$...$`.
+pub fn to_typst(nodes: &[Node], display: bool) -> String {
+    let body = list(nodes, false);
+    if display { format!("$ {} $", body) } else { format!("${}$", body) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latex::parse;
+
+    fn typst(src: &str) -> String {
+        list(&parse(src).nodes, false)
+    }
+
+    /// `(LaTeX, Typst)` pairs taken from recognition results.
+    const CORPUS: &[(&str, &str)] = &[
+        ("\\frac{a^{2}}{b}", "frac(a^2, b)"),
+        ("\\frac{f(x,y)}{2}", "frac(f(x, y), 2)"),
+        ("\\dfrac{1}{n}", "display(frac(1, n))"),
+        ("\\sqrt{x^2+y^2}", "sqrt(x^2 + y^2)"),
+        ("\\sqrt[3]{x+1}", "root(3, x + 1)"),
+        ("e^{-x^2}", "e^(- x^2)"),
+        ("x_{i,j}", "x_(i, j)"),
+        ("x'^2 + f'(x)", "x'^2 + f'(x)"),
+        ("{a+b}^2", "attach(a + b, t: 2)"),
+        ("{}^{14}C", "\"\"^14 C"),
+        ("\\sum_{i=1}^{n} i^2", "sum_(i = 1)^n i^2"),
+        ("\\int\\limits_0^1 x\\,dx", "limits(integral)_0^1 x thin d x"),
+        ("\\lim_{x\\to 0} \\frac{\\sin x}{x} = 1", "lim_(x arrow.r 0) frac(sin x, x) = 1"),
+        ("\\operatorname*{arg\\,max}_x f(x)", "op(\"argmax\", limits: #true)_x f(x)"),
+        ("\\operatorname{rank} A", "op(\"rank\") A"),
+        ("\\begin{pmatrix}1&2\\\\3&4\\end{pmatrix}", "mat(1, 2; 3, 4)"),
+        ("\\begin{bmatrix}a&b\\end{bmatrix}", "mat(delim: \"[\", a, b)"),
+        ("\\begin{vmatrix}a&b\\\\c&d\\end{vmatrix}", "mat(delim: \"|\", a, b; c, d)"),
+        ("f(x)=\\begin{cases}0 & x<0\\\\ 1 & \\text{otherwise}\\end{cases}", "f(x) = cases(0 & x < 0, 1 & \"otherwise\")"),
+        ("\\begin{aligned}a &= b+c \\\\ &= d\\end{aligned}", "a & = b + c \\ & = d"),
+        ("\\hat{x} + \\bar{y} + \\vec{v} + \\ddot{q}", "hat(x) + macron(y) + arrow(v) + dot.double(q)"),
+        ("\\overline{AB}", "overline(A B)"),
+        ("\\underbrace{a+b}_{n}", "underbrace(a + b, n)"),
+        ("\\alpha\\beta\\Gamma \\epsilon\\varepsilon \\phi\\varphi", "alpha beta Gamma epsilon.alt epsilon phi.alt phi"),
+        ("a \\leq b \\neq c \\pm d \\times e", "a lt.eq b eq.not c plus.minus d times e"),
+        ("x \\in \\mathbb{R}, \\forall \\epsilon > 0", "x in bb(R), forall epsilon.alt > 0"),
+        ("A \\Rightarrow B \\iff C", "A arrow.r.double B arrow.l.r.double.long C"),
+        ("a \\not\\in B", "a in.not B"),
+        ("\\mathbf{v} \\cdot \\mathrm{d}\\mathbf{s}", "upright(bold(v)) dot.op upright(d) upright(bold(s))"),
+        ("\\text{若 } x > 0", "\"若 \" x > 0"),
+        ("\\text{say \"hi\"}", "\"say \\\"hi\\\"\""),
+        ("\\left( \\frac{a}{b} \\right)", "(frac(a, b))"),
+        ("\\left| x \\right| + \\left\\| v \\right\\|", "abs(x) + norm(v)"),
+        ("\\left\\{ x, y \\right.", "lr(brace.l x\\, y)"),
+        ("\\left\\langle u, v \\right\\rangle", "lr(⟨ u\\, v ⟩)"),
+        ("a/b", "a \\/ b"),
+        ("\\sin^2 x + \\cos^2 x = 1", "sin^2 x + cos^2 x = 1"),
+        ("\\binom{n}{k}", "binom(n, k)"),
+        ("\\overset{\\text{def}}{=}", "limits(=)^\"def\""),
+        ("\\frac{\\partial f}{\\partial x}", "frac(partial f, partial x)"),
+        ("\\nabla \\times \\mathbf{E} = -\\frac{\\partial \\mathbf{B}}{\\partial t}", "nabla times upright(bold(E)) = - frac(partial upright(bold(B)), partial t)"),
+    ];
+
+    #[test]
+    fn corpus() {
+        for (latex, expected) in CORPUS {
+            assert_eq!(typst(latex), *expected, "{}", latex);
+        }
+    }
+
+    /// Different spellings of the same formula convert to the same Typst.
+    #[test]
+    fn equivalent_spellings() {
+        for (a, b) in [
+            ("x^{2}", "x^2"),
+            ("\\frac12", "\\frac{1}{2}"),
+            ("\\le", "\\leq"),
+            ("\\to", "\\rightarrow"),
+            ("$x+1$", "x + 1"),
+            ("\\[ a \\]", "a"),
+            ("\\left( x \\right)", "(x)"),
+            ("\\lbrace x \\rbrace", "\\{ x \\}"),
+        ] {
+            assert_eq!(typst(a), typst(b), "{} vs {}", a, b);
+        }
+    }
+
+    /// Converting again from the same LaTeX gives the same output, and no LaTeX command leaks through.
+    #[test]
+    fn output_is_typst() {
+        for (latex, _) in CORPUS {
+            let out = typst(latex);
+            assert_eq!(out, typst(latex));
+            let outside_strings: String = out.split('"').step_by(2).collect();
+            let leaked = outside_strings.char_indices()
+                .any(|(i, c)| c == '\\' && outside_strings[i + 1..].starts_with(|n: char| n.is_ascii_alphabetic()));
+            assert!(!leaked, "{} -> {}", latex, out);
+            let depth = outside_strings.chars().try_fold(0i32, |d, c| match c {
+                '(' => Some(d + 1),
+                ')' if d > 0 => Some(d - 1),
+                ')' => None,
+                _ => Some(d),
+            });
+            assert_eq!(depth, Some(0), "{} -> {}", latex, out);
+        }
+    }
+
+    #[test]
+    fn display_and_inline() {
+        let nodes = parse("x^2").nodes;
+        assert_eq!(to_typst(&nodes, true), "$ x^2 $");
+        assert_eq!(to_typst(&nodes, false), "$x^2$");
+    }
+}
@@ -0,0 +1,414 @@
+//! UnicodeMath, the linear format Word's equation editor builds up, from the LaTeX AST.
+
+use super::parser::{split_primes, split_rows, Node};
+use super::symbols::{self, Class};
+
+/// Function application, invisible: tells Word `sin x` is a call.
+const APPLY_FUNCTION: &str = "\u{2061}";
+
+/// Separates an n-ary operator (with its limits) from its operand: `∑_(i=1)^n▒i`.
+const NARY_OPERAND: &str = "▒";
+
+/// Prefix operators for bars and braces over or under their operand.
+fn prefix(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "overline" => "¯",
+        "underline" => "▁",
+        "overbrace" => "⏞",
+        "underbrace" => "⏟",
+        "underrightarrow" | "underleftarrow" => "▁",
+        _ => return None,
+    })
+}
+
+fn font_variant(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "mathbf" => "bold",
+        "mathit" | "mathnormal" => "italic",
+        "mathbb" => "double-struck",
+        "mathcal" | "mathscr" => "script",
+        "mathfrak" => "fraktur",
+        "mathsf" => "sans-serif",
+        "mathtt" => "monospace",
+        "boldsymbol" | "bm" => "bold-italic",
+        _ => return None,
+    })
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\\\""))
+}
+
+fn glyph(u: &str) -> String {
+    match u {
+        // `/` builds a fraction; U+2215 is the literal slash
+        "/" => "∕".to_string(),
+        "-" => "−".to_string(),
+        "*" => "∗".to_string(),
+        "'" => "′".to_string(),
+        "~" => "\u{00A0}".to_string(),
+        "\\" | "&" | "@" | "\"" | "_" | "^" => format!("\\{}", u),
+        _ => u.to_string(),
+    }
+}
+
+/// Whether `s` is one operand: a character, a run of letters or digits, or a quoted string.
+fn atomic(s: &str) -> bool {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => false,
+        (Some(c), None) => c.is_alphanumeric() || !c.is_ascii(),
+        _ => (s.starts_with('"') && s.ends_with('"')) || s.chars().all(|c| c.is_alphanumeric() || c == '.'),
+    }
+}
+
+fn bracket(s: String) -> String {
+    if atomic(&s) { s } else { format!("({})", s) }
+}
+
+fn is_letter(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(char::is_alphabetic) && chars.next().is_none()
+}
+
+fn spaced(prev: &str, next: &str) -> bool {
+    if matches!(prev, "(" | "[" | "{" | "├") { return false; }
+    // Explicit spaces separate on their own; letters in a row are a product, `dx`
+    if prev.chars().all(char::is_whitespace) || next.chars().all(char::is_whitespace) { return false; }
+    if is_letter(prev) && is_letter(next) { return false; }
+    if matches!(next, ")" | "]" | "}" | "┤" | "," | ";" | "!" | "′") { return false; }
+    if matches!(next, "(" | "[") {
+        return !prev.ends_with(|c: char| c.is_alphanumeric() || c == '′');
+    }
+    true
+}
+
+fn join(pieces: &[String]) -> String {
+    let mut out = String::new();
+    let mut prev: Option<&str> = None;
+    for piece in pieces.iter().filter(|p| !p.is_empty()) {
+        if prev.is_some_and(|p| spaced(p, piece)) { out.push(' '); }
+        out.push_str(piece);
+        prev = Some(piece);
+    }
+    out
+}
+
+fn list(nodes: &[Node], variant: Option<&str>) -> String {
+    if let Some(rows) = split_rows(nodes) {
+        return format!("█({})", table(&rows, variant));
+    }
+    let mut pieces = Vec::new();
+    let mut i = 0;
+    while i < nodes.len() {
+        let n = &nodes[i];
+        i += 1;
+        let Some(op) = nary(n) else {
+            pieces.push(node(n, variant));
+            continue;
+        };
+        let end = i + operand_len(&nodes[i..], op.contains("int"));
+        let operand = list(&nodes[i..end], variant);
+        i = end;
+        pieces.push(match operand.as_str() {
+            "" => node(n, variant),
+            _ if atomic(&operand) => format!("{}{}{}", node(n, variant), NARY_OPERAND, operand),
+            _ => format!("{}{}〖{}〗", node(n, variant), NARY_OPERAND, operand),
+        });
+    }
+    join(&pieces)
+}
+
+/// The operator of `\sum`, `\int`, ... alone, wrapped in `\limits`/`\nolimits`, or with scripts.
+fn nary(n: &Node) -> Option<&str> {
+    match n {
+        Node::Scripts { base, .. } => nary(base),
+        Node::Command { name, args, .. } if matches!(name.as_str(), "limits" | "nolimits") => args.first().and_then(nary),
+        Node::Command { name, .. } => matches!(symbols::symbol(name), Some((_, Class::LargeOp))).then_some(name.as_str()),
+        _ => None,
+    }
+}
+
+/// How many of `nodes` make up the operand of an n-ary operator: up to the next relation or
+/// separator outside brackets, and for sums and products also up to a `+` or `−` after the first
+/// term, so `∫_0^1▒〖x^2+1 dx〗` but `∑_i▒a_i + b`.
+fn operand_len(nodes: &[Node], integral: bool) -> usize {
+    let mut depth = 0usize;
+    for (i, n) in nodes.iter().enumerate() {
+        let ends = match n {
+            Node::Align | Node::LineBreak => true,
+            Node::Symbol { value: '(' | '[' } => {
+                depth += 1;
+                false
+            }
+            Node::Symbol { value: ')' | ']' } => {
+                depth = depth.saturating_sub(1);
+                false
+            }
+            Node::Symbol { value } if depth == 0 => {
+                matches!(value, '=' | '<' | '>' | ',' | ';') || (!integral && i > 0 && matches!(value, '+' | '-'))
+            }
+            Node::Command { name, .. } if depth == 0 => match symbols::symbol(name) {
+                Some((_, Class::Relation | Class::Punct)) => true,
+                Some((_, Class::Binary)) => !integral && i > 0 && matches!(name.as_str(), "pm" | "mp"),
+                _ => name == "not",
+            },
+            _ => false,
+        };
+        if ends { return i; }
+    }
+    nodes.len()
+}
+
+fn argument(n: Option<&Node>, variant: Option<&str>) -> String {
+    match n {
+        Some(Node::Group { body }) => list(body, variant),
+        Some(n) => node(n, variant),
+        None => String::new(),
+    }
+}
+
+fn node(n: &Node, variant: Option<&str>) -> String {
+    let style = |c: char| variant.map_or(c, |v| symbols::styled(c, v));
+    match n {
+        Node::Letter { value } => style(*value).to_string(),
+        Node::Number { value } => value.chars().map(style).collect(),
+        Node::Symbol { value } => glyph(value.encode_utf8(&mut [0; 4])),
+        Node::Group { body } => list(body, variant),
+        Node::Command { name, optional, args } => command(name, optional.as_deref(), args, variant),
+        Node::Text { command, value } => match command.as_str() {
+            "tag" | "tag*" | "label" => String::new(),
+            _ => quote(value),
+        },
+        Node::Scripts { base, sub, sup } => scripts(base, sub.as_deref(), sup.as_deref(), variant),
+        Node::Delimited { left, right, body } => {
+            let l = match symbols::delimiter(left) { "" => "├", d => d };
+            let r = match symbols::delimiter(right) { "" => "┤", d => d };
+            format!("{}{}{}", l, list(body, variant), r)
+        }
+        Node::Sized { delim, .. } => symbols::delimiter(delim).to_string(),
+        Node::Environment { name, rows, .. } => environment(name, rows, variant),
+        Node::Align | Node::LineBreak => String::new(),
+    }
+}
+
+fn command(name: &str, optional: Option<&Node>, args: &[Node], variant: Option<&str>) -> String {
+    let arg0 = args.first();
+    let arg1 = args.get(1);
+
+    if matches!(name, "mathrm" | "operatorname" | "operatorname*") {
+        let text = arg0.map(Node::plain_text).unwrap_or_default();
+        return if name == "mathrm" { quote(&text) } else { format!("{}{}", text, APPLY_FUNCTION) };
+    }
+    if let Some(v) = font_variant(name) {
+        return argument(arg0, Some(v));
+    }
+    if let Some(mark) = symbols::combining(name) {
+        // The mark goes on the character before it, so `AB` needs brackets to take it as a whole
+        let base = argument(arg0, variant);
+        let base = if base.chars().count() == 1 { base } else { format!("({})", base) };
+        return format!("{}{}", base, mark);
+    }
+    if let Some(p) = prefix(name) {
+        return format!("{}({})", p, argument(arg0, variant));
+    }
+    if let Some(em) = symbols::space(name) {
+        return match name {
+            "quad" => "\u{2003}".to_string(),
+            "qquad" => "\u{2003}\u{2003}".to_string(),
+            _ if em >= 0.27 => "\u{2004}".to_string(),
+            _ if em >= 0.2 => "\u{205F}".to_string(),
+            _ if em > 0.0 => "\u{2009}".to_string(),
+            _ => String::new(),
+        };
+    }
+    if symbols::function(name).is_some() {
+        return format!("{}{}", name, APPLY_FUNCTION);
+    }
+    if let Some((u, class)) = symbols::symbol(name) {
+        return match class {
+            Class::Ordinary => u.chars().map(|c| variant.map_or(c, |v| symbols::styled(c, v))).collect(),
+            _ => glyph(u),
+        };
+    }
+
+    match name {
+        "frac" | "dfrac" | "tfrac" | "cfrac" => {
+            format!("{}/{}", bracket(argument(arg0, variant)), bracket(argument(arg1, variant)))
+        }
+        "binom" | "dbinom" | "tbinom" => format!("({}¦{})", argument(arg0, variant), argument(arg1, variant)),
+        "sqrt" => match optional {
+            Some(index) => format!("√({}&{})", argument(Some(index), variant), argument(arg0, variant)),
+            None => format!("√{}", bracket(argument(arg0, variant))),
+        },
+        "overset" | "stackrel" => format!("{}┴{}", bracket(argument(arg1, variant)), bracket(argument(arg0, variant))),
+        "underset" => format!("{}┬{}", bracket(argument(arg1, variant)), bracket(argument(arg0, variant))),
+        "boxed" => format!("▭({})", argument(arg0, variant)),
+        "phantom" | "hphantom" | "vphantom" => format!("⟡({})", argument(arg0, variant)),
+        "textcolor" => argument(arg1, variant),
+        "pmod" => format!("(mod {})", argument(arg0, variant)),
+        "not" => symbols::negated(&arg0.map(Node::plain_text).unwrap_or_default()),
+        "xrightarrow" | "xleftarrow" => {
+            let arrow = if name == "xrightarrow" { "→" } else { "←" };
+            let over = format!("{}┴{}", arrow, bracket(argument(arg0, variant)));
+            match optional {
+                Some(below) => format!("({})┬{}", over, bracket(argument(Some(below), variant))),
+                None => over,
+            }
+        }
+        "limits" | "nolimits" | "substack" | "cancel" | "bcancel" | "xcancel" => argument(arg0, variant),
+        "displaystyle" | "textstyle" | "scriptstyle" | "scriptscriptstyle" | "color"
+        | "hline" | "nonumber" | "notag" => String::new(),
+        _ => quote(&format!("\\{}", name)),
+    }
+}
+
+/// Operator names written with `┬`/`┴` so their scripts go below and above, as Word does for `lim`.
+fn function_name(base: &Node) -> Option<(String, bool)> {
+    let Node::Command { name, args, .. } = base else { return None };
+    match name.as_str() {
+        "operatorname" | "operatorname*" => {
+            Some((args.first().map(Node::plain_text).unwrap_or_default(), name == "operatorname*"))
+        }
+        n => symbols::function(n).map(|limits| (n.to_string(), limits)),
+    }
+}
+
+fn scripts(base: &Node, sub: Option<&Node>, sup: Option<&Node>, variant: Option<&str>) -> String {
+    let script = |n: Option<&Node>| bracket(argument(n, variant));
+    let (primes, rest) = split_primes(sup);
+    let sup = rest.as_ref();
+    if let Some((name, limits)) = function_name(base) {
+        let (below, above) = if limits { ("┬", "┴") } else { ("_", "^") };
+        let mut out = name;
+        if let Some(s) = sub { out = format!("{}{}{}", out, below, script(Some(s))); }
+        if let Some(s) = sup { out = format!("{}{}{}", out, above, script(Some(s))); }
+        return format!("{}{}", out, APPLY_FUNCTION);
+    }
+
+    let base_text = match base {
+        Node::Group { body } if body.is_empty() => "〖〗".to_string(),
+        n => node(n, variant),
+    };
+    // Already one operand: `(x)`, `√x`, `x̂`, and `⏞(a+b)` so that `^n` annotates the brace
+    let operand = match base {
+        Node::Delimited { .. } => true,
+        Node::Command { name, .. } => prefix(name).is_some() || symbols::combining(name).is_some() || name == "sqrt",
+        _ => false,
+    };
+    let base_text = if operand || atomic(&base_text) {
+        base_text
+    } else {
+        format!("〖{}〗", base_text)
+    };
+    // `┬`/`┴` put the limits below and above, as `\limits` asks
+    let (below, above) = match base {
+        Node::Command { name, .. } if name == "limits" => ("┬", "┴"),
+        _ => ("_", "^"),
+    };
+    let sub = sub.map(|n| format!("{}{}", below, script(Some(n)))).unwrap_or_default();
+    let sup = sup.map(|n| format!("{}{}", above, script(Some(n)))).unwrap_or_default();
+    format!("{}{}{}{}", base_text, "′".repeat(primes), sub, sup)
+}
+
+/// Cells separated by `&`, rows by `@`.
+fn table(rows: &[Vec<Vec<Node>>], variant: Option<&str>) -> String {
+    rows.iter()
+        .map(|row| row.iter().map(|cell| list(cell, variant)).collect::<Vec<_>>().join("&"))
+        .collect::<Vec<_>>()
+        .join("@")
+}
+
+fn environment(name: &str, rows: &[Vec<Vec<Node>>], variant: Option<&str>) -> String {
+    let matrix = format!("■({})", table(rows, variant));
+    match name {
+        "pmatrix" => format!("({})", matrix),
+        "bmatrix" => format!("[{}]", matrix),
+        "Bmatrix" => format!("{{{}}}", matrix),
+        "vmatrix" => format!("|{}|", matrix),
+        "Vmatrix" => format!("‖{}‖", matrix),
+        "cases" | "dcases" => format!("{{{}┤", matrix),
+        "rcases" => format!("├{}}}", matrix),
+        "matrix" | "smallmatrix" | "array" | "subarray" => matrix,
+        // aligned, gathered, ...: an equation array, aligned at `&`
+        _ => format!("█({})", table(rows, variant)),
+    }
+}
+
+pub fn to_unicodemath(nodes: &[Node]) -> String {
+    list(nodes, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latex::parse;
+
+    fn unicodemath(src: &str) -> String {
+        to_unicodemath(&parse(src).nodes)
+    }
+
+    /// `(LaTeX, UnicodeMath)` pairs; `\u{2009}` is the thin space of `\,`, `\u{2061}` function application.
+    const CORPUS: &[(&str, &str)] = &[
+        ("\\frac{a^{2}}{b}", "(a^2)/b"),
+        ("\\frac{f(x,y)}{2}", "(f(x, y))/2"),
+        ("\\sqrt{x^2+y^2}", "√(x^2 + y^2)"),
+        ("\\sqrt[3]{x+1}", "√(3&x + 1)"),
+        ("e^{-x^2}", "e^(− x^2)"),
+        ("x_{i,j}", "x_(i, j)"),
+        ("x'^2 + f'(x)", "x′^2 + f′(x)"),
+        ("\\sum_{i=1}^{n} i^2 = \\frac{n(n+1)(2n+1)}{6}", "∑_(i = 1)^n▒〖i^2〗 = (n(n + 1) (2 n + 1))/6"),
+        ("\\sum\\limits_{i=1}^n a_i", "∑┬(i = 1)┴n▒〖a_i〗"),
+        ("\\sum_i a_i + b", "∑_i▒〖a_i〗 + b"),
+        ("\\prod_{k=1}^n k", "∏_(k = 1)^n▒k"),
+        ("\\int_0^1 x^2\\,dx", "∫_0^1▒〖x^2\u{2009}dx〗"),
+        ("\\int\\limits_0^1 x^2\\,dx", "∫┬0┴1▒〖x^2\u{2009}dx〗"),
+        ("\\int_0^1 x^2+1\\,dx = \\frac{4}{3}", "∫_0^1▒〖x^2 + 1\u{2009}dx〗 = 4/3"),
+        ("\\int_{-\\infty}^{\\infty} e^{-x^2}\\,dx = \\sqrt{\\pi}", "∫_(− ∞)^∞▒〖e^(− x^2)\u{2009}dx〗 = √π"),
+        ("\\iint_D f(x,y)\\,dA", "∬_D▒〖f(x, y)\u{2009}dA〗"),
+        ("\\oint_C \\mathbf{F}\\cdot d\\mathbf{r}", "∮_C▒〖𝐅 ⋅ d𝐫〗"),
+        ("\\int \\int f \\, dx\\, dy", "∫▒〖∫▒〖f\u{2009}dx\u{2009}dy〗〗"),
+        ("\\lim_{x\\to 0} \\frac{\\sin x}{x} = 1", "lim┬(x → 0)\u{2061} (sin\u{2061} x)/x = 1"),
+        ("\\operatorname{rank} A", "rank\u{2061} A"),
+        ("\\begin{pmatrix}1&2\\\\3&4\\end{pmatrix}", "(■(1&2@3&4))"),
+        ("\\begin{bmatrix}a&b\\\\c&d\\end{bmatrix}", "[■(a&b@c&d)]"),
+        ("\\begin{vmatrix}a&b\\\\c&d\\end{vmatrix}", "|■(a&b@c&d)|"),
+        ("\\begin{matrix}a&b\\end{matrix}", "■(a&b)"),
+        ("f(x)=\\begin{cases}0 & x<0\\\\ 1 & x\\ge 0\\end{cases}", "f(x) = {■(0&x < 0@1&x ≥ 0)┤"),
+        ("\\begin{aligned}a &= b+c \\\\ &= d\\end{aligned}", "█(a&= b + c@&= d)"),
+        ("\\hat{x} + \\bar{y} + \\vec{v} + \\dot{q}", "x\u{0302} + y\u{0305} + v\u{20D7} + q\u{0307}"),
+        ("\\tilde{a} + \\ddot{x}", "a\u{0303} + x\u{0308}"),
+        ("\\hat{AB}", "(AB)\u{0302}"),
+        ("\\overline{AB}", "¯(AB)"),
+        ("\\underbrace{a+b}_{n}", "⏟(a + b)_n"),
+        ("\\alpha\\beta\\Gamma", "αβΓ"),
+        ("a \\leq b \\neq c \\pm d \\times e", "a ≤ b ≠ c ± d × e"),
+        ("x \\in \\mathbb{R}", "x ∈ ℝ"),
+        ("\\mathbf{v}", "𝐯"),
+        ("\\left( \\frac{a}{b} \\right)", "(a/b)"),
+        ("\\left| x \\right|", "|x|"),
+        ("\\binom{n}{k}", "(n¦k)"),
+        ("\\text{if } x > 0", "\"if \" x > 0"),
+        ("\\sin^2 x + \\cos^2 x = 1", "sin^2\u{2061} x + cos^2\u{2061} x = 1"),
+        ("\\frac{\\partial f}{\\partial x}", "(∂ f)/(∂ x)"),
+    ];
+
+    #[test]
+    fn corpus() {
+        for (latex, expected) in CORPUS {
+            assert_eq!(unicodemath(latex), *expected, "{}", latex);
+        }
+    }
+
+    /// Every n-ary operator takes its operand through `▒`, and `〖〗` stay balanced.
+    #[test]
+    fn nary_operands() {
+        for (latex, _) in CORPUS.iter().filter(|(l, _)| l.contains("\\int") || l.contains("\\sum") || l.contains("\\prod")) {
+            let out = unicodemath(latex);
+            let operators = out.chars().filter(|c| "∑∏∫∬∮".contains(*c)).count();
+            assert_eq!(out.matches(NARY_OPERAND).count(), operators, "{} -> {}", latex, out);
+            assert_eq!(out.matches('〖').count(), out.matches('〗').count(), "{} -> {}", latex, out);
+        }
+        assert_eq!(unicodemath("\\int"), "∫");
+    }
+}
@@ -1,4 +1,15 @@
+mod batch;
+mod cache;
 mod commands;
+mod error;
+mod export;
+mod history;
+mod latex;
+mod pdf;
+mod pipeline;
+mod preprocess;
+mod providers;
+mod store;
 
 use tauri_plugin_store::StoreExt;
 
@@ -6,6 +17,7 @@ use tauri_plugin_store::StoreExt;
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::default().build())
+        .plugin(tauri_plugin_dialog::init())
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -25,6 +37,7 @@ pub fn run() {
             if store.get("voucher_models").is_none() {
                 store.set("voucher_models", serde_json::json!([]));
             }
+            batch::restore(app.handle());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -32,9 +45,38 @@ pub fn run() {
             commands::save_settings,
             commands::test_simpletex,
             commands::test_siliconflow,
+            commands::test_provider,
+            commands::get_tesseract_languages,
             commands::get_available_models,
             commands::get_sf_balance,
             commands::recognize,
+            commands::recognize_regions,
+            commands::pick_pdf,
+            commands::get_pdf_info,
+            commands::recognize_pdf,
+            commands::pick_batch_images,
+            commands::create_batch,
+            commands::list_batches,
+            commands::get_batch,
+            commands::pause_batch,
+            commands::resume_batch,
+            commands::cancel_batch,
+            commands::delete_batch,
+            commands::export_batch,
+            commands::clear_recognition_cache,
+            commands::check_latex,
+            commands::parse_latex,
+            commands::convert_latex,
+            commands::render_latex,
+            commands::export_result,
+            commands::add_history,
+            commands::list_history,
+            commands::search_history,
+            commands::get_history,
+            commands::get_history_image,
+            commands::set_history_favorite,
+            commands::delete_history,
+            commands::clear_history,
             commands::open_external_url,
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,248 @@
+//! PDF input: the selected pages are rasterized with PDFium and recognized one after another in
+//! document mode, with a `pdf-progress` event per step, and the page results joined into one
+//! Markdown document.
+//!
+//! PDFium is loaded at runtime from `pdfium_path` (the library file or the directory holding it),
+//! otherwise from next to the executable or the system library path, so the installer stays small.
+
+use base64::Engine;
+use pdfium_render::prelude::*;
+use serde::Serialize;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::error::RecognizeError;
+use crate::pipeline;
+use crate::store::get_store_string;
+
+// ── Constants ──
+
+pub const DEFAULT_DPI: u32 = 150;
+const MIN_DPI: u32 = 72;
+const MAX_DPI: u32 = 300;
+const POINTS_PER_INCH: f32 = 72.0;
+
+/// PDFium keeps global state; one document is open at a time.
+static LOCK: Mutex<()> = Mutex::new(());
+
+// ── Types ──
+
+#[derive(Serialize)]
+pub struct PdfInfo {
+    pub path: String,
+    pub pages: u32,
+}
+
+/// Sent as `pdf-progress` while a PDF is recognized.
+#[derive(Serialize, Clone)]
+pub struct PdfProgress {
+    /// Page number in the document, from 1
+    pub page: u32,
+    /// Position among the selected pages, from 1
+    pub index: usize,
+    pub total: usize,
+    /// `rendering`, `recognizing`, `done` or `failed`
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RecognizeError>,
+}
+
+#[derive(Serialize)]
+pub struct PdfPageResult {
+    pub page: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RecognizeError>,
+}
+
+#[derive(Serialize)]
+pub struct PdfRecognition {
+    /// The pages attempted, in order; fewer than selected when a failure stopped the run
+    pub pages: Vec<PdfPageResult>,
+    /// Every page's Markdown, each after a `<!-- 第 N 页 -->` comment
+    pub markdown: String,
+}
+
+// ── PDFium ──
+
+fn bind(configured: &str) -> Result<Pdfium, RecognizeError> {
+    let bindings = if configured.is_empty() {
+        let beside_exe = std::env::current_exe().ok()
+            .and_then(|exe| exe.parent().map(Pdfium::pdfium_platform_library_name_at_path))
+            .filter(|p| p.is_file());
+        match beside_exe {
+            Some(lib) => Pdfium::bind_to_library(lib),
+            None => Pdfium::bind_to_system_library(),
+        }
+    } else {
+        let path = Path::new(configured);
+        let lib: PathBuf = if path.is_dir() { Pdfium::pdfium_platform_library_name_at_path(path) } else { path.to_path_buf() };
+        Pdfium::bind_to_library(lib)
+    };
+    bindings.map(Pdfium::new)
+        .map_err(|e| RecognizeError::NotConfigured(format!("无法加载 PDFium，请在设置中填写 PDFium 库路径: {}", e)))
+}
+
+fn open<'a>(pdfium: &'a Pdfium, path: &str) -> Result<PdfDocument<'a>, RecognizeError> {
+    pdfium.load_pdf_from_file(path, None)
+        .map_err(|e| RecognizeError::InvalidInput(format!("无法打开 PDF: {}", e)))
+}
+
+pub fn info(app: &AppHandle, path: &str) -> Result<PdfInfo, RecognizeError> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let pdfium = bind(&get_store_string(app, "pdfium_path"))?;
+    let pages = open(&pdfium, path)?.pages().len() as u32;
+    Ok(PdfInfo { path: path.to_string(), pages })
+}
+
+/// One page as a PNG data URL, at `dpi` pixels per inch.
+fn render_page(document: &PdfDocument, index: u16, dpi: u32) -> Result<String, RecognizeError> {
+    let page = document.pages().get(index)
+        .map_err(|e| RecognizeError::InvalidInput(format!("无法读取第 {} 页: {}", index + 1, e)))?;
+    let config = PdfRenderConfig::new().scale_page_by_factor(dpi as f32 / POINTS_PER_INCH);
+    let bitmap = page.render_with_config(&config)
+        .map_err(|e| RecognizeError::Engine(format!("渲染第 {} 页失败: {}", index + 1, e)))?;
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgb8(bitmap.as_image().to_rgb8())
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| RecognizeError::Engine(format!("图片编码失败: {}", e)))?;
+    Ok(format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(png)))
+}
+
+/// Renders pages on request from a single blocking task, so PDFium is bound and the document
+/// opened once per run. Dropping it closes the document.
+struct Renderer {
+    requests: mpsc::Sender<u16>,
+    rendered: mpsc::Receiver<Result<String, RecognizeError>>,
+}
+
+impl Renderer {
+    /// Opens `path` and returns the renderer with the document's page count.
+    async fn open(pdfium_path: String, path: String, dpi: u32) -> Result<(Renderer, u32), RecognizeError> {
+        let (requests, mut pending) = mpsc::channel::<u16>(1);
+        let (done, rendered) = mpsc::channel(1);
+        let (ready_tx, ready) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let pdfium = match bind(&pdfium_path) {
+                Ok(pdfium) => pdfium,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let document = match open(&pdfium, &path) {
+                Ok(document) => document,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(document.pages().len() as u32));
+            while let Some(index) = pending.blocking_recv() {
+                if done.blocking_send(render_page(&document, index, dpi)).is_err() { break; }
+            }
+        });
+        let count = ready.await.map_err(|_| RecognizeError::Engine("PDF 渲染线程异常退出".into()))??;
+        Ok((Renderer { requests, rendered }, count))
+    }
+
+    async fn render(&mut self, index: u16) -> Result<String, RecognizeError> {
+        let lost = || RecognizeError::Engine("PDF 渲染线程异常退出".into());
+        self.requests.send(index).await.map_err(|_| lost())?;
+        self.rendered.recv().await.ok_or_else(lost)?
+    }
+}
+
+// ── Page selection ──
+
+/// Zero-based page indices for a spec such as `1-3, 5, 8-`: page numbers from 1, ranges
+/// inclusive, an open end running to the last page. Empty selects every page.
+pub fn parse_pages(spec: &str, count: u32) -> Result<Vec<u16>, String> {
+    let invalid = || format!("页码范围无效: {}（示例：1-3, 5, 8-）", spec.trim());
+    let mut pages: Vec<u32> = Vec::new();
+    for part in spec.split([',', '，']).map(str::trim).filter(|p| !p.is_empty()) {
+        let (from, to) = match part.split_once('-') {
+            Some((from, to)) => (
+                from.trim().parse::<u32>().map_err(|_| invalid())?,
+                if to.trim().is_empty() { count } else { to.trim().parse::<u32>().map_err(|_| invalid())? },
+            ),
+            None => {
+                let n = part.parse::<u32>().map_err(|_| invalid())?;
+                (n, n)
+            }
+        };
+        if from > to { return Err(invalid()); }
+        if from == 0 || to > count {
+            return Err(format!("页码超出范围：文档共 {} 页", count));
+        }
+        pages.extend(from..=to);
+    }
+    if pages.is_empty() { pages.extend(1..=count); }
+    pages.sort_unstable();
+    pages.dedup();
+    Ok(pages.into_iter().map(|n| (n - 1) as u16).collect())
+}
+
+// ── Recognition ──
+
+fn emit(app: &AppHandle, progress: PdfProgress) {
+    if let Err(e) = app.emit("pdf-progress", progress) {
+        log::warn!("pdf-progress: {}", e);
+    }
+}
+
+/// Renders and recognizes the pages of `spec` one by one. A page that fails keeps its error and the
+/// next one goes on, unless the failure would repeat for every page (missing credentials, quota
+/// used up), which ends the run early.
+pub async fn recognize(app: &AppHandle, path: &str, spec: &str, dpi: u32, model_id: &str) -> Result<PdfRecognition, RecognizeError> {
+    let dpi = dpi.clamp(MIN_DPI, MAX_DPI);
+    let (mut renderer, count) = Renderer::open(get_store_string(app, "pdfium_path"), path.to_string(), dpi).await?;
+    if count == 0 {
+        return Err(RecognizeError::InvalidInput("PDF 没有页面".into()));
+    }
+    let indices = parse_pages(spec, count).map_err(RecognizeError::InvalidInput)?;
+    let total = indices.len();
+
+    let mut pages: Vec<PdfPageResult> = Vec::new();
+    for (i, index) in indices.into_iter().enumerate() {
+        let page = index as u32 + 1;
+        let progress = |status, error| PdfProgress { page, index: i + 1, total, status, error };
+        emit(app, progress("rendering", None));
+        let result = match renderer.render(index).await {
+            Ok(image) => {
+                emit(app, progress("recognizing", None));
+                pipeline::recognize(app, image, "document", model_id, false).await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(res) => {
+                emit(app, progress("done", None));
+                pages.push(PdfPageResult { page, text: Some(res.text), model: Some(res.model), error: None });
+            }
+            Err(e) => {
+                emit(app, progress("failed", Some(e.clone())));
+                let stop = e.affects_all();
+                pages.push(PdfPageResult { page, text: None, model: None, error: Some(e) });
+                if stop { break; }
+            }
+        }
+    }
+
+    if pages.iter().all(|p| p.text.is_none()) {
+        return Err(pages.swap_remove(0).error.unwrap_or_else(|| RecognizeError::Parse("识别结果为空".into())));
+    }
+    let markdown = pages.iter().map(|p| match (&p.text, &p.error) {
+        (Some(text), _) => format!("<!-- 第 {} 页 -->\n\n{}", p.page, text.trim()),
+        (None, Some(e)) => format!("<!-- 第 {} 页 -->\n\n> 第 {} 页识别失败：{}", p.page, p.page, e),
+        (None, None) => format!("<!-- 第 {} 页 -->", p.page),
+    }).collect::<Vec<_>>().join("\n\n");
+    Ok(PdfRecognition { pages, markdown })
+}
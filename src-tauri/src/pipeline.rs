@@ -0,0 +1,366 @@
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::cache;
+use crate::commands::{ConsensusCandidate, ConsensusReport, FailedAttempt, RecognizeResponse, SecondOpinion};
+use crate::error::RecognizeError;
+use crate::latex;
+use crate::preprocess;
+use crate::providers::{self, RecognizeRequest, SIMPLETEX_MODELS};
+use crate::store::{get_store_f64, get_store_string, get_store_u64, get_store_vec};
+
+// ── Constants ──
+
+/// Pseudo model id: pick the best model still inside its free quota.
+pub const AUTO_MODEL_ID: &str = "auto";
+
+/// Pseudo model id: ask every model in `consensus_models` and vote.
+pub const CONSENSUS_MODEL_ID: &str = "consensus";
+
+const DEFAULT_MAX_RETRIES: u32 = 2;
+const BACKOFF_BASE_MS: u64 = 500;
+const DEFAULT_CONFIDENCE_THRESHOLD: f64 = 0.8;
+
+// ── Model resolution ──
+
+/// Full `provider:model` id for a request. Ids without a prefix fall back to a sensible default.
+pub fn resolve_model_id(app: &AppHandle, mode: &str, model_id: &str) -> String {
+    if providers::split_model_id(model_id).is_some() {
+        return model_id.to_string();
+    }
+    let st_token = get_store_string(app, "simpletex_token");
+    if !st_token.is_empty() && mode == "formula" {
+        "simpletex:latex_ocr".to_string()
+    } else {
+        "siliconflow:".to_string()
+    }
+}
+
+/// `primary` followed by the configured `fallback_chain`, without duplicates.
+pub fn fallback_chain(app: &AppHandle, primary: &str) -> Vec<String> {
+    let mut chain = vec![primary.to_string()];
+    for id in get_store_vec(app, "fallback_chain") {
+        if !chain.contains(&id) {
+            chain.push(id);
+        }
+    }
+    chain
+}
+
+/// Candidates for [`AUTO_MODEL_ID`], best first: models with daily free quota left (in registry
+/// order, so the SimpleTex standard model precedes turbo), then models that are always free.
+/// Paid models are never included. SiliconFlow's prices come from its cached model list, so this
+/// makes no network calls while that is fresh.
+pub async fn auto_chain(app: &AppHandle, mode: &str) -> Vec<String> {
+    let mut quota = Vec::new();
+    let mut free = Vec::new();
+    for provider in providers::registry() {
+        if !provider.offers_free() || !provider.is_configured(app) { continue; }
+        for m in provider.list_models(app).await {
+            if !m.available || !m.modes.iter().any(|x| x == mode) { continue; }
+            match (m.free_per_day, m.free) {
+                (Some(limit), _) if m.usage_today.unwrap_or(0) < limit => quota.push(m.id),
+                (None, Some(true)) => free.push(m.id),
+                _ => {}
+            }
+        }
+    }
+    quota.extend(free);
+    quota
+}
+
+/// The ordered list of models to try for a request.
+pub async fn build_chain(app: &AppHandle, mode: &str, model_id: &str) -> Result<Vec<String>, RecognizeError> {
+    if model_id == AUTO_MODEL_ID {
+        let chain = auto_chain(app, mode).await;
+        if chain.is_empty() {
+            return Err(RecognizeError::QuotaExhausted("今日免费额度已用完，且没有可用的免费模型".into()));
+        }
+        return Ok(chain);
+    }
+    let primary = resolve_model_id(app, mode, model_id);
+    Ok(fallback_chain(app, &primary))
+}
+
+/// Models polled by [`CONSENSUS_MODEL_ID`], in priority order.
+pub fn consensus_models(app: &AppHandle) -> Vec<String> {
+    get_store_vec(app, "consensus_models")
+}
+
+pub fn max_retries(app: &AppHandle) -> u32 {
+    get_store_u64(app, "max_retries").map(|n| n as u32).unwrap_or(DEFAULT_MAX_RETRIES)
+}
+
+pub fn confidence_threshold(app: &AppHandle) -> f64 {
+    get_store_f64(app, "confidence_threshold").unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD)
+}
+
+/// Who double-checks `model_id`: the configured `second_opinion_model`, otherwise another
+/// SimpleTex model for the same mode.
+fn second_opinion_model(app: &AppHandle, mode: &str, model_id: &str) -> Option<String> {
+    let configured = get_store_string(app, "second_opinion_model");
+    if !configured.is_empty() && configured != model_id {
+        return Some(configured);
+    }
+    let (provider, model) = providers::split_model_id(model_id)?;
+    if provider != "simpletex" { return None; }
+    SIMPLETEX_MODELS.iter()
+        .find(|&&(id, _, _, st_mode)| id != model && st_mode == mode)
+        .map(|&(id, _, _, _)| format!("simpletex:{}", id))
+}
+
+// ── Recognition ──
+
+/// The image ready for the provider, or the answer already in the cache.
+enum Prepared {
+    Cached(RecognizeResponse),
+    Fresh(preprocess::Preprocessed),
+}
+
+/// Recognizes one image with everything around the provider call: the cache, preprocessing, the
+/// fallback chain or consensus vote, the second opinion and the syntax check. A repeat image is
+/// answered from the cache unless `force`; a forced result replaces the cached one.
+pub async fn recognize(app: &AppHandle, image: String, mode: &str, model_id: &str, force: bool) -> Result<RecognizeResponse, RecognizeError> {
+    let settings = preprocess::settings(app);
+    let perceptual = cache::perceptual(app);
+    // Hashing and preprocessing decode the image, and the cache is a file: all off the async runtime
+    let (handle, cache_mode, cache_model) = (app.clone(), mode.to_string(), model_id.to_string());
+    let (fingerprint, prepared) = tokio::task::spawn_blocking(move || {
+        let fingerprint = cache::fingerprint(&image, perceptual);
+        if let Some(fp) = fingerprint.as_ref().filter(|_| !force) {
+            if let Some(res) = cache::lookup(&handle, fp, &cache_mode, &cache_model, &settings) {
+                return Ok((fingerprint, Prepared::Cached(res)));
+            }
+        }
+        preprocess::run(&image, &settings).map(|prepared| (fingerprint, Prepared::Fresh(prepared)))
+    })
+    .await
+    .map_err(|e| RecognizeError::Engine(e.to_string()))??;
+    let prepared = match prepared {
+        Prepared::Fresh(prepared) => prepared,
+        Prepared::Cached(mut res) => {
+            if mode == "formula" {
+                res.syntax_issues = latex::check(&res.text);
+            }
+            return Ok(res);
+        }
+    };
+    let image = prepared.image;
+
+    let mut res = if model_id == CONSENSUS_MODEL_ID {
+        recognize_consensus(app, &image, mode).await?
+    } else {
+        let chain = build_chain(app, mode, model_id).await?;
+        recognize_chain(app, &image, mode, &chain).await?
+    };
+    second_opinion(app, &image, mode, &mut res).await;
+    if mode == "formula" {
+        res.syntax_issues = latex::check(&res.text);
+    }
+    res.preprocess = prepared.report;
+    if let Some(fp) = fingerprint {
+        if let Err(e) = cache::insert(app, fp, mode, model_id, &settings, &res) {
+            log::warn!("{}", e);
+        }
+    }
+    Ok(res)
+}
+
+/// Runs one model, retrying transient failures with exponential backoff.
+/// Returns the result or the last error, plus the number of retries spent.
+async fn recognize_with_retry(
+    app: &AppHandle,
+    image: &str,
+    mode: &str,
+    model_id: &str,
+    max_retries: u32,
+) -> (Result<RecognizeResponse, RecognizeError>, u32) {
+    let (provider_id, model) = providers::split_model_id(model_id).unwrap_or((model_id, ""));
+    let Some(provider) = providers::find(provider_id) else {
+        return (Err(RecognizeError::NotConfigured(format!("未知的服务: {}", provider_id))), 0);
+    };
+
+    let mut retries = 0;
+    loop {
+        let result = provider.recognize(app, RecognizeRequest { image, mode, model }).await;
+        match result {
+            Err(e) if e.is_transient() && retries < max_retries => {
+                let delay = BACKOFF_BASE_MS * 2u64.pow(retries);
+                log::warn!("{} failed ({}), retrying in {}ms", model_id, e.code(), delay);
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                retries += 1;
+            }
+            other => return (other, retries),
+        }
+    }
+}
+
+/// Tries each model in `chain` until one succeeds. Bad input aborts immediately since no model can fix it.
+pub async fn recognize_chain(
+    app: &AppHandle,
+    image: &str,
+    mode: &str,
+    chain: &[String],
+) -> Result<RecognizeResponse, RecognizeError> {
+    let max_retries = max_retries(app);
+    let mut failed: Vec<FailedAttempt> = Vec::new();
+
+    for model_id in chain {
+        let (result, retries) = recognize_with_retry(app, image, mode, model_id, max_retries).await;
+        match result {
+            Ok(mut res) => {
+                res.model_id = Some(model_id.clone());
+                res.failed_attempts = failed;
+                return Ok(res);
+            }
+            Err(e @ RecognizeError::InvalidInput(_)) => return Err(e),
+            Err(e) => {
+                log::warn!("{} failed: {}", model_id, e);
+                failed.push(FailedAttempt { model_id: model_id.clone(), error: e, retries });
+            }
+        }
+    }
+
+    Err(failed.pop().map(|a| a.error).unwrap_or_else(|| RecognizeError::NotConfigured("未选择模型".into())))
+}
+
+/// Flags results whose confidence is below the threshold and asks a second model.
+/// Chat models verify the existing result (formula mode); other providers recognize from scratch.
+/// A differing verification is adopted, a differing recognition only if it is more confident.
+pub async fn second_opinion(app: &AppHandle, image: &str, mode: &str, res: &mut RecognizeResponse) {
+    // Consensus results were already cross-checked
+    if res.consensus.is_some() { return; }
+    let Some(conf) = res.confidence else { return };
+    if conf >= confidence_threshold(app) {
+        res.needs_review = Some(false);
+        return;
+    }
+    res.needs_review = Some(true);
+
+    let Some(model_id) = res.model_id.as_deref().and_then(|id| second_opinion_model(app, mode, id)) else { return };
+    let (provider_id, model) = providers::split_model_id(&model_id).unwrap_or((&model_id, ""));
+    let Some(provider) = providers::find(provider_id) else { return };
+    if !provider.is_configured(app) { return; }
+
+    let req = RecognizeRequest { image, mode, model };
+    let verified = if mode == "formula" { provider.verify(app, req, &res.text).await } else { None };
+    let is_verification = verified.is_some();
+    let opinion = match verified {
+        Some(r) => r.map(|text| (text, None)),
+        None => provider.recognize(app, req).await.map(|r| (r.text, r.confidence)),
+    };
+    let (text, confidence) = match opinion {
+        Ok(o) if !o.0.is_empty() => o,
+        Ok(_) => return,
+        Err(e) => {
+            log::warn!("second opinion from {} failed: {}", model_id, e);
+            return;
+        }
+    };
+
+    let agrees = providers::same_latex(&res.text, &text);
+    let adopted = !agrees && (is_verification || confidence.is_some_and(|c| c > conf));
+    res.needs_review = Some(!agrees);
+    if adopted {
+        let original = std::mem::replace(&mut res.text, text.clone());
+        res.diff = Some(latex::diff(&original, &text));
+        res.original_text = Some(original);
+        res.corrected = Some(true);
+    }
+    res.second_opinion = Some(SecondOpinion { model_id, text, confidence, agrees, adopted });
+}
+
+// ── Consensus ──
+
+/// Sends the image to every model in `consensus_models` at once and returns the result most of
+/// them agree on after [`vote_key`] normalization. Ties go to the group whose first member comes first
+/// in the list. Disagreement marks the result for review.
+pub async fn recognize_consensus(app: &AppHandle, image: &str, mode: &str) -> Result<RecognizeResponse, RecognizeError> {
+    let models = consensus_models(app);
+    if models.len() < 2 {
+        return Err(RecognizeError::NotConfigured("多模型投票至少需要在设置中选择两个模型".into()));
+    }
+    let max_retries = max_retries(app);
+
+    let handles: Vec<_> = models.iter().map(|model_id| {
+        let (app, image, mode, model_id) = (app.clone(), image.to_string(), mode.to_string(), model_id.clone());
+        tokio::spawn(async move { recognize_with_retry(&app, &image, &mode, &model_id, max_retries).await })
+    }).collect();
+    let aborts: Vec<_> = handles.iter().map(|h| h.abort_handle()).collect();
+
+    let mut results: Vec<(String, RecognizeResponse)> = Vec::new();
+    let mut failed: Vec<FailedAttempt> = Vec::new();
+    for (model_id, handle) in models.into_iter().zip(handles) {
+        match handle.await {
+            Ok((Ok(res), _)) if !res.text.trim().is_empty() => results.push((model_id, res)),
+            Ok((Ok(_), retries)) => {
+                failed.push(FailedAttempt { model_id, error: RecognizeError::Parse("识别结果为空".into()), retries });
+            }
+            Ok((Err(e @ RecognizeError::InvalidInput(_)), _)) => {
+                // Every other model would reject the image too; stop spending their quota
+                aborts.iter().for_each(|a| a.abort());
+                return Err(e);
+            }
+            Ok((Err(e), retries)) => {
+                log::warn!("{} failed: {}", model_id, e);
+                failed.push(FailedAttempt { model_id, error: e, retries });
+            }
+            Err(e) => {
+                failed.push(FailedAttempt { model_id, error: RecognizeError::Engine(e.to_string()), retries: 0 });
+            }
+        }
+    }
+    if results.is_empty() {
+        return Err(failed.pop().map(|a| a.error).unwrap_or_else(|| RecognizeError::NotConfigured("未选择模型".into())));
+    }
+
+    let normalized: Vec<String> = results.iter().map(|(_, r)| vote_key(mode, &r.text)).collect();
+    let votes_of = |key: &str| normalized.iter().filter(|n| *n == key).count() as u32;
+    // First index with the most votes
+    let winner = (0..results.len())
+        .max_by_key(|&i| (votes_of(&normalized[i]), std::cmp::Reverse(i)))
+        .unwrap_or(0);
+
+    let candidates: Vec<ConsensusCandidate> = results.iter().zip(&normalized).map(|((model_id, r), n)| ConsensusCandidate {
+        model_id: model_id.clone(),
+        model: r.model.clone(),
+        text: r.text.clone(),
+        normalized: n.clone(),
+        confidence: r.confidence,
+        votes: votes_of(n),
+        agrees: *n == normalized[winner],
+    }).collect();
+    let votes = votes_of(&normalized[winner]);
+    let responded = results.len() as u32;
+    let dissenters = candidates.iter().filter(|c| !c.agrees).map(|c| c.model_id.clone()).collect();
+
+    let (model_id, mut res) = results.swap_remove(winner);
+    res.model_id = Some(model_id);
+    res.failed_attempts = failed;
+    res.needs_review = Some(votes < responded);
+    res.consensus = Some(ConsensusReport { candidates, votes, responded, unanimous: votes == responded, dissenters });
+    Ok(res)
+}
+
+/// What two results must share to count as the same vote: normalized LaTeX in formula mode,
+/// otherwise the text with whitespace runs collapsed (`%` and spacing carry meaning in prose).
+fn vote_key(mode: &str, text: &str) -> String {
+    if mode == "formula" {
+        latex::normalize(text)
+    } else {
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vote_key_by_mode() {
+        assert_eq!(vote_key("formula", "x^{2}"), vote_key("formula", "x^2"));
+        assert_eq!(vote_key("ocr", "  a  b\nc "), "a b c");
+        assert_ne!(vote_key("ocr", "50% off"), vote_key("ocr", "50"));
+        assert_ne!(vote_key("document", "ab"), vote_key("document", "a b"));
+    }
+}
@@ -0,0 +1,310 @@
+//! Straightening photographed pages. Everything is measured on the ink of a reduced copy, found
+//! with a local threshold so uneven lighting and chalk on a dark board work as well as print:
+//!
+//! - skew: the angle at which projecting the ink onto one axis is sharpest, i.e. the text lines
+//!   fall into the fewest rows;
+//! - quarter turns: the projection is much sharper across columns, in bands long enough to be lines;
+//! - upside down: Latin text has more ascenders and capitals above the x-height than descenders
+//!   below it. Text without that asymmetry (CJK, most formulas) is left as it is.
+
+use image::{Rgb, RgbImage};
+
+/// Longest side of the copy the measurements are taken on.
+const ANALYSIS_SIDE: u32 = 1000;
+const MAX_POINTS: usize = 100_000;
+/// Largest skew searched for, in degrees either way.
+const MAX_SKEW: f64 = 10.0;
+/// Smaller skews are left alone; resampling would cost more sharpness than it gains.
+pub const MIN_SKEW: f64 = 0.3;
+/// How much sharper the projection at the found skew must be than at 0°. Tilted text gains far
+/// more; single formulas, whose few rows have no lines to align, gain a little at random angles.
+const MIN_SKEW_GAIN: f64 = 1.15;
+/// How much sharper the column projection must be before the text is taken to run vertically.
+const VERTICAL_MARGIN: f64 = 1.3;
+/// Ink below the x-height over ink above it at which a page counts as upside down.
+const FLIP_RATIO: f64 = 1.5;
+
+/// What [`analyze`] found.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Straighten {
+    /// Clockwise quarter turns that bring the text upright
+    pub quarter_turns: u32,
+    /// Degrees the text lines are tilted clockwise once upright
+    pub skew: f64,
+}
+
+type Point = (f64, f64);
+
+/// Ink pixels by Bradley's adaptive threshold, relative to the image center and thinned out to
+/// at most [`MAX_POINTS`].
+fn ink(img: &RgbImage) -> Vec<Point> {
+    let gray = image::imageops::grayscale(img);
+    let (w, h) = gray.dimensions();
+    let (wu, hu) = (w as usize, h as usize);
+    let mut integral = vec![0u64; (wu + 1) * (hu + 1)];
+    for y in 0..hu {
+        let mut row = 0u64;
+        for x in 0..wu {
+            row += gray.get_pixel(x as u32, y as u32)[0] as u64;
+            integral[(y + 1) * (wu + 1) + x + 1] = integral[y * (wu + 1) + x + 1] + row;
+        }
+    }
+
+    // Dark boards with light chalk are the mirror case
+    let mean_luma = integral[(wu + 1) * (hu + 1) - 1] as f64 / (wu * hu) as f64;
+    let dark_background = mean_luma < 110.0;
+    let r = (w.max(h) as usize / 32).max(4);
+    let mut points = Vec::new();
+    for y in 0..hu {
+        let (y0, y1) = (y.saturating_sub(r), (y + r + 1).min(hu));
+        for x in 0..wu {
+            let (x0, x1) = (x.saturating_sub(r), (x + r + 1).min(wu));
+            let sum = integral[y1 * (wu + 1) + x1] + integral[y0 * (wu + 1) + x0]
+                - integral[y0 * (wu + 1) + x1] - integral[y1 * (wu + 1) + x0];
+            let mean = sum as f64 / ((x1 - x0) * (y1 - y0)) as f64;
+            let p = gray.get_pixel(x as u32, y as u32)[0] as f64;
+            let is_ink = if dark_background {
+                p - mean > ((255.0 - mean) * 0.15).max(8.0)
+            } else {
+                mean - p > (mean * 0.15).max(8.0)
+            };
+            if is_ink { points.push((x as f64 - w as f64 / 2.0, y as f64 - h as f64 / 2.0)); }
+        }
+    }
+    let step = points.len() / MAX_POINTS + 1;
+    points.into_iter().step_by(step).collect()
+}
+
+/// Offset of a point across lines tilted by `angle` degrees: rows for horizontal lines,
+/// columns for vertical ones.
+fn across(p: Point, angle: f64, vertical: bool) -> f64 {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    if vertical { p.0 * cos + p.1 * sin } else { p.1 * cos - p.0 * sin }
+}
+
+/// Offset of a point along those lines, up to sign.
+fn along(p: Point, angle: f64, vertical: bool) -> f64 {
+    across(p, angle, !vertical)
+}
+
+/// Ink per one-pixel bin across the lines, and the offset of bin 0.
+fn profile(points: &[Point], angle: f64, vertical: bool) -> (Vec<u32>, f64) {
+    let radius = points.iter().map(|&(x, y)| x.hypot(y)).fold(0.0, f64::max).ceil() + 1.0;
+    let mut bins = vec![0u32; (2.0 * radius) as usize + 1];
+    for &p in points {
+        bins[(across(p, angle, vertical) + radius) as usize] += 1;
+    }
+    (bins, radius)
+}
+
+/// Sum of squared bin counts: highest when the ink piles up in few bins.
+fn sharpness(points: &[Point], angle: f64, vertical: bool) -> f64 {
+    profile(points, angle, vertical).0.iter().map(|&n| (n as f64).powi(2)).sum()
+}
+
+/// The sharpest angle in ±[`MAX_SKEW`], by a coarse search refined around the best step.
+fn best_angle(points: &[Point], vertical: bool) -> (f64, f64) {
+    let search = |from: f64, to: f64, step: f64| {
+        let steps = ((to - from) / step).round() as i32;
+        (0..=steps).map(|i| from + i as f64 * step)
+            .map(|a| (a, sharpness(points, a, vertical)))
+            .fold((0.0, f64::MIN), |best, cur| if cur.1 > best.1 { cur } else { best })
+    };
+    let (coarse, _) = search(-MAX_SKEW, MAX_SKEW, 0.5);
+    search(coarse - 0.5, coarse + 0.5, 0.1)
+}
+
+/// Runs of bins holding at least 5% of the fullest one, as `start..end` bin ranges.
+fn bands(bins: &[u32]) -> Vec<(usize, usize)> {
+    let threshold = (bins.iter().copied().max().unwrap_or(0) / 20).max(1);
+    let mut out = Vec::new();
+    let mut start = None;
+    for (i, &n) in bins.iter().chain(std::iter::once(&0)).enumerate() {
+        match (n >= threshold, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                if i - s >= 2 { out.push((s, i)); }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// At least two bands, typically several times longer than thick: lines of text rather than
+/// the columns of separate symbols a single formula line gives.
+fn looks_like_lines(points: &[Point], angle: f64, vertical: bool) -> bool {
+    let (bins, radius) = profile(points, angle, vertical);
+    let bands = bands(&bins);
+    if bands.len() < 2 { return false; }
+    let mut extents: Vec<Vec<f64>> = vec![Vec::new(); bands.len()];
+    for &p in points {
+        let bin = (across(p, angle, vertical) + radius) as usize;
+        if let Some(i) = bands.iter().position(|&(s, e)| (s..e).contains(&bin)) {
+            extents[i].push(along(p, angle, vertical));
+        }
+    }
+    let mut ratios: Vec<f64> = bands.iter().zip(&mut extents).filter(|(_, e)| e.len() >= 10).map(|(&(s, e), offsets)| {
+        offsets.sort_by(f64::total_cmp);
+        let length = offsets[offsets.len() * 19 / 20] - offsets[offsets.len() / 20];
+        length / (e - s) as f64
+    }).collect();
+    if ratios.len() < 2 { return false; }
+    ratios.sort_by(f64::total_cmp);
+    ratios[ratios.len() / 2] >= 4.0
+}
+
+/// Upright text lines carry more ink above their x-height band than below it; flipped ones the
+/// reverse. The band is taken loosely, rows with a third of the fullest one, so the blurred edge
+/// of a resampled baseline stays in it. `points` must be level. False when there are too few
+/// lines or the difference is too small to tell.
+fn upside_down(points: &[Point]) -> bool {
+    let (bins, _) = profile(points, 0.0, false);
+    let (mut above, mut below, mut lines) = (0u64, 0u64, 0);
+    for (s, e) in bands(&bins) {
+        if e - s < 8 { continue; }
+        let line = &bins[s..e];
+        let peak = line.iter().copied().max().unwrap_or(0);
+        let Some(core_start) = line.iter().position(|&n| n * 3 >= peak) else { continue };
+        let core_end = line.iter().rposition(|&n| n * 3 >= peak).unwrap_or(core_start);
+        above += line[..core_start].iter().map(|&n| n as u64).sum::<u64>();
+        below += line[core_end + 1..].iter().map(|&n| n as u64).sum::<u64>();
+        lines += 1;
+    }
+    lines >= 2 && below as f64 > above as f64 * FLIP_RATIO
+}
+
+/// Finds how far `img` is turned and tilted. Quarter turns are only looked for with `turns`.
+pub fn analyze(img: &RgbImage, turns: bool) -> Straighten {
+    let (w, h) = img.dimensions();
+    let scale = (ANALYSIS_SIDE as f64 / w.max(h) as f64).min(1.0);
+    let (sw, sh) = (((w as f64 * scale) as u32).max(1), ((h as f64 * scale) as u32).max(1));
+    let mut small = image::imageops::resize(img, sw, sh, image::imageops::FilterType::Triangle);
+    let mut points = ink(&small);
+    if points.len() < 50 { return Straighten::default(); }
+
+    let (mut skew, horizontal) = best_angle(&points, false);
+    let mut quarter_turns = 0;
+    if turns {
+        let (vertical_skew, vertical) = best_angle(&points, true);
+        if vertical > horizontal * VERTICAL_MARGIN && looks_like_lines(&points, vertical_skew, true) {
+            // Turned a quarter clockwise so the lines run across, then measured again
+            small = image::imageops::rotate90(&small);
+            points = ink(&small);
+            skew = best_angle(&points, false).0;
+            quarter_turns = 1;
+        }
+    }
+    // A skew is only trusted for lines of text that clearly line up better at it
+    let gain = sharpness(&points, skew, false) / sharpness(&points, 0.0, false);
+    if gain < MIN_SKEW_GAIN || !looks_like_lines(&points, skew, false) { skew = 0.0; }
+
+    // Measured level: a tilted projection aliases the pixel grid and blurs the x-height band
+    if turns {
+        let level = if skew == 0.0 { points } else { ink(&rotate(&small, skew, super::background(&small))) };
+        if upside_down(&level) { quarter_turns += 2; }
+    }
+    Straighten { quarter_turns, skew }
+}
+
+/// Rotates `img` counterclockwise by `degrees`, undoing a clockwise tilt of that much. The canvas
+/// grows to hold the corners, filled with `background`.
+pub fn rotate(img: &RgbImage, degrees: f64, background: Rgb<u8>) -> RgbImage {
+    let (w, h) = img.dimensions();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let nw = (w as f64 * cos.abs() + h as f64 * sin.abs()).ceil() as u32;
+    let nh = (w as f64 * sin.abs() + h as f64 * cos.abs()).ceil() as u32;
+    let (cx, cy) = (w as f64 / 2.0, h as f64 / 2.0);
+    let (ncx, ncy) = (nw as f64 / 2.0, nh as f64 / 2.0);
+    RgbImage::from_fn(nw, nh, |x, y| {
+        let (u, v) = (x as f64 + 0.5 - ncx, y as f64 + 0.5 - ncy);
+        let sx = cx + u * cos - v * sin - 0.5;
+        let sy = cy + u * sin + v * cos - 0.5;
+        bilinear(img, sx, sy).unwrap_or(background)
+    })
+}
+
+fn bilinear(img: &RgbImage, x: f64, y: f64) -> Option<Rgb<u8>> {
+    let (w, h) = img.dimensions();
+    if x < -0.5 || y < -0.5 || x > w as f64 - 0.5 || y > h as f64 - 0.5 { return None; }
+    let (x, y) = (x.clamp(0.0, w as f64 - 1.0), y.clamp(0.0, h as f64 - 1.0));
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let (a, b, c, d) = (img.get_pixel(x0, y0), img.get_pixel(x1, y0), img.get_pixel(x0, y1), img.get_pixel(x1, y1));
+    let mix = |i: usize| {
+        let top = a[i] as f64 * (1.0 - fx) + b[i] as f64 * fx;
+        let bottom = c[i] as f64 * (1.0 - fx) + d[i] as f64 * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    };
+    Some(Rgb([mix(0), mix(1), mix(2)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAPER: Rgb<u8> = Rgb([245, 245, 240]);
+
+    /// A page of `lines` lines of Latin-like text: x-height letters, every third with an ascender
+    /// and every seventh with a descender, in words of five.
+    fn text_page(lines: u32) -> RgbImage {
+        let (w, h) = (800, 80 + lines * 50);
+        RgbImage::from_fn(w, h, |x, y| {
+            let (line, dy) = ((y as i32 - 60) / 50, (y as i32 - 60) % 50);
+            let (letter, dx) = ((x as i32 - 40) / 12, (x as i32 - 40) % 12);
+            let in_text = y >= 60 && line < lines as i32 && x >= 40 && x < w - 40 && letter % 6 != 5;
+            let ink = in_text && match dx {
+                0..=7 if (10..22).contains(&dy) => true,
+                0..=1 if letter % 3 == 0 && (0..10).contains(&dy) => true,
+                6..=7 if letter % 7 == 0 && (22..28).contains(&dy) => true,
+                _ => false,
+            };
+            if ink { Rgb([25, 25, 30]) } else { PAPER }
+        })
+    }
+
+    /// `img` tilted clockwise by `degrees`.
+    fn tilt(img: &RgbImage, degrees: f64) -> RgbImage {
+        rotate(img, -degrees, PAPER)
+    }
+
+    #[test]
+    fn level_page_is_left_alone() {
+        let found = analyze(&text_page(8), true);
+        assert_eq!(found.quarter_turns, 0);
+        assert!(found.skew.abs() < MIN_SKEW, "{}", found.skew);
+    }
+
+    #[test]
+    fn measures_skew_either_way() {
+        for degrees in [-7.0, -2.5, 1.5, 4.0] {
+            let found = analyze(&tilt(&text_page(8), degrees), false);
+            assert!((found.skew - degrees).abs() <= 0.3, "{} found as {}", degrees, found.skew);
+        }
+    }
+
+    #[test]
+    fn finds_quarter_turns_and_flips() {
+        let page = text_page(8);
+        // Turned a quarter counterclockwise: one clockwise turn puts it back
+        let found = analyze(&image::imageops::rotate270(&page), true);
+        assert_eq!(found.quarter_turns, 1);
+        let found = analyze(&image::imageops::rotate180(&page), true);
+        assert_eq!(found.quarter_turns, 2);
+        // Upside down and tilted
+        let found = analyze(&tilt(&image::imageops::rotate180(&page), 3.0), true);
+        assert_eq!(found.quarter_turns, 2);
+        assert!((found.skew - 3.0).abs() <= 0.3, "{}", found.skew);
+        // Without `turns` only the skew is measured
+        assert_eq!(analyze(&image::imageops::rotate180(&page), false).quarter_turns, 0);
+    }
+
+    #[test]
+    fn single_line_keeps_its_angle() {
+        // One line of symbols gives nothing to align, as with a lone formula
+        let found = analyze(&tilt(&text_page(1), 3.0), true);
+        assert_eq!(found.skew, 0.0);
+    }
+}
@@ -0,0 +1,179 @@
+//! Splitting a screenshot into the formulas and paragraphs it shows, by the projection profile of
+//! its ink onto the vertical axis: regions are the runs of rows with ink, separated by gaps half as
+//! tall again as a typical glyph. The spacing inside a formula (between a fraction and its bar, a
+//! sum and its limits, the rows of a matrix) stays below that; the glyph size is the median height
+//! of the connected components of the ink.
+//!
+//! Only horizontal cuts are made. Cutting across columns would split off equation numbers and the
+//! columns of aligned environments far more often than it would separate formulas set side by side.
+
+use image::GrayImage;
+use serde::Serialize;
+
+/// Gaps narrower than this never separate regions, however small the print.
+const MIN_GAP: u32 = 6;
+/// Gap, relative to the median glyph height, at which two runs belong to separate regions.
+const GAP_RATIO: f64 = 1.5;
+/// Regions with less ink than this are specks of dust or compression noise.
+const MIN_INK: u32 = 24;
+/// Regions thinner than this are rules and underlines rather than content.
+const MIN_HEIGHT: u32 = 4;
+
+/// A region's bounding box, in pixels of the image as received (with its EXIF orientation applied,
+/// not rotated or deskewed).
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Runs of rows holding ink, as `start..end` row ranges.
+fn runs(rows: &[u32]) -> Vec<(u32, u32)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    for (y, &n) in rows.iter().chain(std::iter::once(&0)).enumerate() {
+        match (n > 0, start) {
+            (true, None) => start = Some(y as u32),
+            (false, Some(s)) => {
+                out.push((s, y as u32));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn find(parent: &mut [u32], mut i: u32) -> u32 {
+    while parent[i as usize] != i {
+        parent[i as usize] = parent[parent[i as usize] as usize];
+        i = parent[i as usize];
+    }
+    i
+}
+
+/// Median height of the 8-connected components of the ink, roughly the height of a glyph.
+fn glyph_height(ink: &[bool], w: usize) -> Option<u32> {
+    // Union-find over pixel indices, joining each ink pixel to its ink neighbours above and to the left
+    let mut parent: Vec<u32> = (0..ink.len() as u32).collect();
+    for i in 0..ink.len() {
+        if !ink[i] { continue; }
+        let (x, y) = (i % w, i / w);
+        let above = if y == 0 { [None; 3] } else {
+            [x.checked_sub(1).map(|x| (y - 1) * w + x), Some((y - 1) * w + x), (x + 1 < w).then(|| (y - 1) * w + x + 1)]
+        };
+        let left = x.checked_sub(1).map(|x| y * w + x);
+        for j in above.into_iter().chain([left]).flatten() {
+            if ink[j] {
+                let (a, b) = (find(&mut parent, i as u32), find(&mut parent, j as u32));
+                parent[a.max(b) as usize] = a.min(b);
+            }
+        }
+    }
+    let mut extents: std::collections::HashMap<u32, (u32, u32)> = std::collections::HashMap::new();
+    for i in (0..ink.len()).filter(|&i| ink[i]) {
+        let y = (i / w) as u32;
+        let e = extents.entry(find(&mut parent, i as u32)).or_insert((y, y));
+        e.0 = e.0.min(y);
+        e.1 = e.1.max(y);
+    }
+    let mut heights: Vec<u32> = extents.values().map(|&(top, bottom)| bottom - top + 1).collect();
+    heights.sort_unstable();
+    heights.get(heights.len() / 2).copied()
+}
+
+/// The regions of `gray`, top to bottom. `is_ink` tells content pixels from the background.
+pub fn detect(gray: &GrayImage, is_ink: impl Fn(u8) -> bool) -> Vec<Region> {
+    let (w, _) = gray.dimensions();
+    let ink: Vec<bool> = gray.pixels().map(|p| is_ink(p[0])).collect();
+    let rows: Vec<u32> = ink.chunks(w as usize).map(|row| row.iter().filter(|&&i| i).count() as u32).collect();
+    let runs = runs(&rows);
+    let Some(glyph) = glyph_height(&ink, w as usize) else { return Vec::new() };
+    let gap = MIN_GAP.max((glyph as f64 * GAP_RATIO).round() as u32);
+
+    let mut groups: Vec<(u32, u32)> = Vec::new();
+    for (s, e) in runs {
+        match groups.last_mut() {
+            Some(last) if s - last.1 < gap => last.1 = e,
+            _ => groups.push((s, e)),
+        }
+    }
+
+    groups.into_iter().filter_map(|(y0, y1)| {
+        let (mut x0, mut x1, mut count) = (w, 0, 0);
+        for y in y0..y1 {
+            for x in 0..w {
+                if ink[(y * w + x) as usize] {
+                    x0 = x0.min(x);
+                    x1 = x1.max(x);
+                    count += 1;
+                }
+            }
+        }
+        (count >= MIN_INK && y1 - y0 >= MIN_HEIGHT).then_some(Region { x: x0, y: y0, width: x1 - x0 + 1, height: y1 - y0 })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    fn canvas(w: u32, h: u32) -> GrayImage {
+        GrayImage::from_pixel(w, h, Luma([255]))
+    }
+
+    fn fill(img: &mut GrayImage, x: u32, y: u32, w: u32, h: u32) {
+        for yy in y..y + h {
+            for xx in x..x + w {
+                img.put_pixel(xx, yy, Luma([0]));
+            }
+        }
+    }
+
+    /// `n` glyphs 10×14 with 3 px between them.
+    fn glyphs(img: &mut GrayImage, x: u32, y: u32, n: u32) {
+        for i in 0..n { fill(img, x + i * 13, y, 10, 14); }
+    }
+
+    fn detect_dark(img: &GrayImage) -> Vec<Region> {
+        detect(img, |p| p < 128)
+    }
+
+    #[test]
+    fn two_stacked_formulas() {
+        let mut img = canvas(300, 200);
+        // A fraction: its numerator, bar and denominator sit closer than a glyph apart
+        glyphs(&mut img, 40, 20, 5);
+        fill(&mut img, 30, 38, 80, 2);
+        glyphs(&mut img, 50, 44, 3);
+        // A line of text well below it
+        glyphs(&mut img, 20, 100, 12);
+        assert_eq!(detect_dark(&img), [
+            Region { x: 30, y: 20, width: 80, height: 38 },
+            Region { x: 20, y: 100, width: 12 * 13 - 3, height: 14 },
+        ]);
+    }
+
+    #[test]
+    fn close_lines_stay_together() {
+        let mut img = canvas(200, 100);
+        glyphs(&mut img, 10, 10, 6);
+        // 20 px apart is less than one and a half glyphs of 14 px
+        glyphs(&mut img, 10, 44, 6);
+        assert_eq!(detect_dark(&img), [Region { x: 10, y: 10, width: 6 * 13 - 3, height: 48 }]);
+    }
+
+    #[test]
+    fn drops_specks_and_rules() {
+        let mut img = canvas(200, 160);
+        glyphs(&mut img, 10, 10, 4);
+        // Dust, then a long underline far from everything
+        fill(&mut img, 100, 80, 2, 2);
+        fill(&mut img, 10, 130, 150, 2);
+        assert_eq!(detect_dark(&img), [Region { x: 10, y: 10, width: 4 * 13 - 3, height: 14 }]);
+        assert!(detect_dark(&canvas(50, 50)).is_empty());
+    }
+}
@@ -0,0 +1,415 @@
+//! Cleans up an image before it goes to a provider: the real format is sniffed from the bytes,
+//! photos are turned upright and straightened, whitespace borders are cropped, tiny crops are
+//! enlarged to what the vision models accept, huge screenshots are shrunk, and the result is
+//! optionally reduced to gray or black and white.
+//!
+//! Every step can be switched off in settings (`preprocess` in `config.json`). An image no step
+//! touched is passed through byte for byte.
+//!
+//! [`split`] cuts a screenshot holding several formulas into one image per formula.
+
+mod deskew;
+mod layout;
+
+use base64::Engine;
+use image::metadata::Orientation;
+use image::{DynamicImage, GrayImage, ImageDecoder, ImageFormat, ImageReader, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+use crate::error::RecognizeError;
+
+pub use layout::Region;
+
+// ── Constants ──
+
+/// Smallest side sent to a provider; SiliconFlow rejects anything under 28 pixels.
+const MIN_SIDE: u32 = 32;
+/// Crops whose shorter side is below this are enlarged; the models read small print badly.
+const UPSCALE_BELOW: u32 = 64;
+const MAX_UPSCALE: u32 = 4;
+/// Longest side after downscaling; more only costs upload time and tokens.
+const MAX_SIDE: u32 = 2048;
+/// Luma distance from the background at which a pixel counts as content.
+const CONTENT_THRESHOLD: u8 = 48;
+/// Kept around the content when cropping, so glyphs don't touch the edge.
+const CROP_MARGIN: u32 = 8;
+
+// ── Settings ──
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct PreprocessSettings {
+    /// Turn pages photographed sideways or upside down upright
+    pub rotate: bool,
+    /// Straighten text lines tilted by up to 10°
+    pub deskew: bool,
+    /// Cut uniform borders down to the content plus a small margin
+    pub crop: bool,
+    /// Enlarge and pad tiny images to a size the models accept
+    pub upscale: bool,
+    /// Shrink images whose longest side exceeds the limit
+    pub downscale: bool,
+    pub grayscale: bool,
+    /// Black and white by Otsu's threshold; implies grayscale
+    pub binarize: bool,
+}
+
+impl Default for PreprocessSettings {
+    fn default() -> Self {
+        PreprocessSettings { rotate: true, deskew: true, crop: true, upscale: true, downscale: true, grayscale: false, binarize: false }
+    }
+}
+
+pub fn settings(app: &AppHandle) -> PreprocessSettings {
+    app.store("config.json").ok()
+        .and_then(|store| store.get("preprocess"))
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
+}
+
+// ── Types ──
+
+/// What preprocessing did to the image, for the response; kept with cached results.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PreprocessReport {
+    /// Format sniffed from the bytes, whatever the data URL claimed
+    pub format: String,
+    pub original_width: u32,
+    pub original_height: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Steps that changed the image, in order: `orient` (the EXIF orientation), `rotate`, `deskew`,
+    /// `crop`, `downscale`, `upscale`, `pad`, `grayscale`, `binarize`
+    pub steps: Vec<String>,
+    /// Degrees the image was turned clockwise, by its EXIF orientation and `rotate` together
+    pub rotation: u32,
+    /// Degrees the text was tilted clockwise, and the image turned back by `deskew`
+    pub skew: f64,
+    /// The image was encoded again as PNG rather than sent as received
+    pub reencoded: bool,
+}
+
+/// An image cut into its regions, top to bottom.
+pub struct Split {
+    pub width: u32,
+    pub height: u32,
+    /// Each region with its crop as a PNG data URL
+    pub regions: Vec<(Region, String)>,
+}
+
+pub struct Preprocessed {
+    /// Data URL with the real MIME type
+    pub image: String,
+    /// `None` when the image couldn't be decoded and went through as received
+    pub report: Option<PreprocessReport>,
+}
+
+// ── Steps ──
+
+/// Alpha composited onto white: transparent clipboard images would otherwise turn black.
+fn flatten(img: &DynamicImage) -> RgbImage {
+    if !img.color().has_alpha() { return img.to_rgb8(); }
+    let rgba = img.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let p = rgba.get_pixel(x, y).0;
+        let a = p[3] as u32;
+        let blend = |c: u8| ((c as u32 * a + 255 * (255 - a)) / 255) as u8;
+        Rgb([blend(p[0]), blend(p[1]), blend(p[2])])
+    })
+}
+
+/// Median luma of the outermost pixels, taken as the background.
+fn border_luma(gray: &GrayImage) -> u8 {
+    let (w, h) = gray.dimensions();
+    let mut border: Vec<u8> = (0..w).flat_map(|x| [gray.get_pixel(x, 0)[0], gray.get_pixel(x, h - 1)[0]])
+        .chain((0..h).flat_map(|y| [gray.get_pixel(0, y)[0], gray.get_pixel(w - 1, y)[0]]))
+        .collect();
+    border.sort_unstable();
+    border[border.len() / 2]
+}
+
+/// Bounds of the content with a margin, or `None` when there is nothing to cut (or nothing at all).
+fn content_bounds(img: &RgbImage) -> Option<(u32, u32, u32, u32)> {
+    let gray = DynamicImage::ImageRgb8(img.clone()).to_luma8();
+    let (w, h) = gray.dimensions();
+    let bg = border_luma(&gray);
+    let (mut x0, mut y0, mut x1, mut y1) = (w, h, 0, 0);
+    for (x, y, p) in gray.enumerate_pixels() {
+        if p[0].abs_diff(bg) > CONTENT_THRESHOLD {
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+        }
+    }
+    if x0 > x1 { return None; }
+    let (x0, y0) = (x0.saturating_sub(CROP_MARGIN), y0.saturating_sub(CROP_MARGIN));
+    let (x1, y1) = ((x1 + CROP_MARGIN).min(w - 1), (y1 + CROP_MARGIN).min(h - 1));
+    let (cw, ch) = (x1 - x0 + 1, y1 - y0 + 1);
+    (cw < w || ch < h).then_some((x0, y0, cw, ch))
+}
+
+/// Background colour for padding: the average of the corners.
+fn background(img: &RgbImage) -> Rgb<u8> {
+    let (w, h) = img.dimensions();
+    let corners = [img.get_pixel(0, 0), img.get_pixel(w - 1, 0), img.get_pixel(0, h - 1), img.get_pixel(w - 1, h - 1)];
+    let avg = |i: usize| (corners.iter().map(|p| p[i] as u32).sum::<u32>() / 4) as u8;
+    Rgb([avg(0), avg(1), avg(2)])
+}
+
+/// Centers the image on a canvas at least `MIN_SIDE` in both directions.
+fn pad(img: &RgbImage) -> RgbImage {
+    let (w, h) = img.dimensions();
+    let (nw, nh) = (w.max(MIN_SIDE), h.max(MIN_SIDE));
+    let mut canvas = RgbImage::from_pixel(nw, nh, background(img));
+    image::imageops::replace(&mut canvas, img, ((nw - w) / 2) as i64, ((nh - h) / 2) as i64);
+    canvas
+}
+
+/// Threshold between the two luma classes that maximizes the variance between them.
+fn otsu_threshold(gray: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for p in gray.pixels() { histogram[p[0] as usize] += 1; }
+    let total = gray.pixels().len() as f64;
+    let sum: f64 = histogram.iter().enumerate().map(|(i, &n)| i as f64 * n as f64).sum();
+    let (mut weight_bg, mut sum_bg) = (0.0, 0.0);
+    let (mut best, mut best_variance) = (127u8, 0.0);
+    for (t, &n) in histogram.iter().enumerate() {
+        weight_bg += n as f64;
+        if weight_bg == 0.0 { continue; }
+        let weight_fg = total - weight_bg;
+        if weight_fg == 0.0 { break; }
+        sum_bg += t as f64 * n as f64;
+        let mean_bg = sum_bg / weight_bg;
+        let mean_fg = (sum - sum_bg) / weight_fg;
+        let variance = weight_bg * weight_fg * (mean_bg - mean_fg).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = t as u8;
+        }
+    }
+    best
+}
+
+/// Decodes the image the way viewers show it, with the EXIF orientation of phone photos applied.
+/// Returns the clockwise degrees that orientation turned it by.
+fn decode(bytes: &[u8], format: ImageFormat) -> Option<(DynamicImage, Option<u32>)> {
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format).into_decoder().ok()?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder).ok()?;
+    if orientation == Orientation::NoTransforms { return Some((img, None)); }
+    img.apply_orientation(orientation);
+    let degrees = match orientation {
+        Orientation::Rotate90 | Orientation::Rotate90FlipH => 90,
+        Orientation::Rotate180 | Orientation::FlipVertical => 180,
+        Orientation::Rotate270 | Orientation::Rotate270FlipH => 270,
+        _ => 0,
+    };
+    Some((img, Some(degrees)))
+}
+
+fn format_name(format: ImageFormat) -> String {
+    format.extensions_str().first().copied().unwrap_or("unknown").to_string()
+}
+
+// ── Pipeline ──
+
+/// Runs the enabled steps over a data URL or bare base64 image.
+///
+/// Images the `image` crate can't decode go through unchanged; the provider may still read them.
+pub fn run(image: &str, settings: &PreprocessSettings) -> Result<Preprocessed, RecognizeError> {
+    let data = image.split(',').next_back().unwrap_or(image).trim();
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data)
+        .map_err(|e| RecognizeError::InvalidInput(format!("Base64 解码失败: {}", e)))?;
+    let format = image::guess_format(&bytes).ok();
+    let decoded = format.and_then(|f| decode(&bytes, f));
+    let (Some(format), Some((decoded, exif_rotation))) = (format, decoded) else {
+        log::warn!("preprocess: unrecognized image format, sending as is");
+        return Ok(Preprocessed { image: image.to_string(), report: None });
+    };
+
+    let mut report = PreprocessReport {
+        format: format_name(format),
+        original_width: decoded.width(),
+        original_height: decoded.height(),
+        ..Default::default()
+    };
+    let mut steps: Vec<&str> = Vec::new();
+    let mut img = flatten(&decoded);
+    if let Some(degrees) = exif_rotation {
+        report.rotation = degrees;
+        steps.push("orient");
+    }
+
+    if settings.rotate || settings.deskew {
+        let found = deskew::analyze(&img, settings.rotate);
+        if found.quarter_turns > 0 {
+            img = match found.quarter_turns {
+                1 => image::imageops::rotate90(&img),
+                2 => image::imageops::rotate180(&img),
+                _ => image::imageops::rotate270(&img),
+            };
+            report.rotation = (report.rotation + found.quarter_turns * 90) % 360;
+            steps.push("rotate");
+        }
+        if settings.deskew && found.skew.abs() >= deskew::MIN_SKEW {
+            img = deskew::rotate(&img, found.skew, background(&img));
+            report.skew = (found.skew * 10.0).round() / 10.0;
+            steps.push("deskew");
+        }
+    }
+
+    if settings.crop {
+        if let Some((x, y, w, h)) = content_bounds(&img) {
+            img = image::imageops::crop_imm(&img, x, y, w, h).to_image();
+            steps.push("crop");
+        }
+    }
+    // Before enlarging, so a long thin line shrunk to fit is still padded to the minimum height
+    if settings.downscale {
+        let (w, h) = img.dimensions();
+        if w.max(h) > MAX_SIDE {
+            let scale = MAX_SIDE as f64 / w.max(h) as f64;
+            let (nw, nh) = (((w as f64 * scale).round() as u32).max(1), ((h as f64 * scale).round() as u32).max(1));
+            img = image::imageops::resize(&img, nw, nh, image::imageops::FilterType::Lanczos3);
+            steps.push("downscale");
+        }
+    }
+    if settings.upscale {
+        let (w, h) = img.dimensions();
+        let factor = UPSCALE_BELOW.div_ceil(w.min(h).max(1)).min(MAX_UPSCALE).min(MAX_SIDE / w.max(h).max(1));
+        if w.min(h) < UPSCALE_BELOW && factor > 1 {
+            img = image::imageops::resize(&img, w * factor, h * factor, image::imageops::FilterType::CatmullRom);
+            steps.push("upscale");
+        }
+        if img.width() < MIN_SIDE || img.height() < MIN_SIDE {
+            img = pad(&img);
+            steps.push("pad");
+        }
+    }
+
+    let mut out = DynamicImage::ImageRgb8(img);
+    if settings.grayscale || settings.binarize {
+        let mut gray = out.to_luma8();
+        if settings.binarize {
+            let t = otsu_threshold(&gray);
+            for p in gray.pixels_mut() { p[0] = if p[0] > t { 255 } else { 0 }; }
+            steps.push("binarize");
+        } else {
+            steps.push("grayscale");
+        }
+        out = DynamicImage::ImageLuma8(gray);
+    }
+
+    report.width = out.width();
+    report.height = out.height();
+    report.steps = steps.iter().map(|s| s.to_string()).collect();
+
+    // Untouched PNG and JPEG go as received; everything else is re-encoded as PNG, which every
+    // provider accepts
+    let passthrough = steps.is_empty() && matches!(format, ImageFormat::Png | ImageFormat::Jpeg) && !decoded.color().has_alpha();
+    let (mime, data) = if passthrough {
+        (format.to_mime_type(), data.to_string())
+    } else {
+        let mut png = Vec::new();
+        out.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| RecognizeError::InvalidInput(format!("图片编码失败: {}", e)))?;
+        report.reencoded = true;
+        ("image/png", base64::engine::general_purpose::STANDARD.encode(png))
+    };
+    Ok(Preprocessed { image: format!("data:{};base64,{}", mime, data), report: Some(report) })
+}
+
+/// Finds the formulas and paragraphs of a data URL or bare base64 image and crops each out, with
+/// a margin of background so the glyphs don't touch the edge.
+pub fn split(image: &str) -> Result<Split, RecognizeError> {
+    let data = image.split(',').next_back().unwrap_or(image).trim();
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data)
+        .map_err(|e| RecognizeError::InvalidInput(format!("Base64 解码失败: {}", e)))?;
+    let (decoded, _) = image::guess_format(&bytes).ok()
+        .and_then(|f| decode(&bytes, f))
+        .ok_or_else(|| RecognizeError::InvalidInput("无法解析图片，请使用 PNG、JPG 或 WebP 格式".into()))?;
+    let img = flatten(&decoded);
+    let gray = DynamicImage::ImageRgb8(img.clone()).to_luma8();
+    let bg = border_luma(&gray);
+    let fill = background(&img);
+
+    let regions = layout::detect(&gray, |p| p.abs_diff(bg) > CONTENT_THRESHOLD).into_iter().map(|r| {
+        let mut canvas = RgbImage::from_pixel(r.width + 2 * CROP_MARGIN, r.height + 2 * CROP_MARGIN, fill);
+        let crop = image::imageops::crop_imm(&img, r.x, r.y, r.width, r.height).to_image();
+        image::imageops::replace(&mut canvas, &crop, CROP_MARGIN as i64, CROP_MARGIN as i64);
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(canvas).write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| RecognizeError::InvalidInput(format!("图片编码失败: {}", e)))?;
+        Ok((r, format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(png))))
+    }).collect::<Result<_, RecognizeError>>()?;
+    Ok(Split { width: img.width(), height: img.height(), regions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// White canvas with a dark rectangle covering `x0..x1` × `y0..y1`.
+    fn page(w: u32, h: u32, (x0, y0, x1, y1): (u32, u32, u32, u32)) -> RgbImage {
+        RgbImage::from_fn(w, h, |x, y| {
+            if (x0..x1).contains(&x) && (y0..y1).contains(&y) { Rgb([20, 20, 20]) } else { Rgb([250, 250, 250]) }
+        })
+    }
+
+    #[test]
+    fn otsu_splits_two_classes() {
+        let gray = GrayImage::from_fn(100, 10, |x, _| Luma([if x < 30 { 40 } else { 210 }]));
+        let t = otsu_threshold(&gray);
+        assert!((40..210).contains(&t), "{}", t);
+        // Noise around each level doesn't move it outside the gap
+        let noisy = GrayImage::from_fn(100, 10, |x, y| Luma([if x < 30 { 30 + ((x + y) % 20) as u8 } else { 200 + ((x * y) % 30) as u8 }]));
+        let t = otsu_threshold(&noisy);
+        assert!((49..200).contains(&t), "{}", t);
+        // A flat image has nothing to separate
+        assert_eq!(otsu_threshold(&GrayImage::from_pixel(10, 10, Luma([90]))), 127);
+    }
+
+    #[test]
+    fn content_bounds_keep_a_margin() {
+        let img = page(200, 100, (50, 30, 120, 60));
+        assert_eq!(content_bounds(&img), Some((50 - CROP_MARGIN, 30 - CROP_MARGIN, 70 + 2 * CROP_MARGIN, 30 + 2 * CROP_MARGIN)));
+    }
+
+    #[test]
+    fn content_bounds_clamp_to_the_image() {
+        // Content up to the left edge: the margin stops at 0
+        let img = page(200, 100, (0, 30, 120, 60));
+        assert_eq!(content_bounds(&img), Some((0, 30 - CROP_MARGIN, 120 + CROP_MARGIN, 30 + 2 * CROP_MARGIN)));
+        // Nothing to cut when the content plus margin already fills the image
+        assert_eq!(content_bounds(&page(40, 40, (4, 4, 36, 36))), None);
+        // Or when there is no content at all
+        assert_eq!(content_bounds(&RgbImage::from_pixel(50, 50, Rgb([250, 250, 250]))), None);
+    }
+
+    #[test]
+    fn content_bounds_follow_a_dark_background() {
+        let img = RgbImage::from_fn(100, 100, |x, y| {
+            if (40..60).contains(&x) && (45..55).contains(&y) { Rgb([240, 240, 240]) } else { Rgb([10, 10, 10]) }
+        });
+        assert_eq!(content_bounds(&img), Some((40 - CROP_MARGIN, 45 - CROP_MARGIN, 20 + 2 * CROP_MARGIN, 10 + 2 * CROP_MARGIN)));
+    }
+
+    #[test]
+    fn pad_centers_on_the_background() {
+        let img = page(10, 20, (2, 2, 8, 18));
+        let padded = pad(&img);
+        assert_eq!(padded.dimensions(), (MIN_SIDE, MIN_SIDE));
+        let (dx, dy) = ((MIN_SIDE - 10) / 2, (MIN_SIDE - 20) / 2);
+        assert_eq!(padded.get_pixel(dx + 5, dy + 10), img.get_pixel(5, 10));
+        assert_eq!(*padded.get_pixel(0, 0), Rgb([250, 250, 250]));
+        assert_eq!(*padded.get_pixel(MIN_SIDE - 1, MIN_SIDE - 1), Rgb([250, 250, 250]));
+        // Large enough already: unchanged
+        let big = page(40, 50, (5, 5, 30, 30));
+        assert_eq!(pad(&big), big);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::commands::RecognizeResponse;
+use crate::error::RecognizeError;
+use crate::latex;
+
+use super::get_prompt;
+
+/// Maps an error response to a [`RecognizeError`], using the provider's message where there is one.
+fn classify_error(status: u16, err_text: &str) -> RecognizeError {
+    let msg = serde_json::from_str::<serde_json::Value>(err_text).ok()
+        .and_then(|err_json| err_json["message"].as_str()
+            .or_else(|| err_json["error"]["message"].as_str())
+            .map(|s| s.to_string()))
+        .unwrap_or_default();
+    let lower = msg.to_lowercase();
+    if lower.contains("height") && lower.contains("width") && lower.contains("must be larger") {
+        return RecognizeError::ImageTooSmall { min_width: 28, min_height: 28 };
+    }
+    if lower.contains("insufficient") && lower.contains("balance") {
+        return RecognizeError::QuotaExhausted("账户余额不足".into());
+    }
+    RecognizeError::from_status("API", status, msg)
+}
+
+fn image_url(image: &str) -> String {
+    if image.starts_with("data:") {
+        image.to_string()
+    } else {
+        format!("data:image/png;base64,{}", image)
+    }
+}
+
+/// Equal up to whitespace.
+pub fn same_latex(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
+}
+
+/// An OpenAI-style `/chat/completions` endpoint. `base_url` is the API root, e.g. `https://api.siliconflow.cn/v1`.
+pub struct ChatEndpoint<'a> {
+    pub base_url: &'a str,
+    pub api_key: &'a str,
+}
+
+impl ChatEndpoint<'_> {
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    fn authorize(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        // Self-hosted servers often run without auth
+        if self.api_key.is_empty() { req } else { req.header("Authorization", format!("Bearer {}", self.api_key)) }
+    }
+
+    pub async fn get(&self, path: &str) -> reqwest::Result<reqwest::Response> {
+        let client = reqwest::Client::new();
+        self.authorize(client.get(self.url(path))).send().await
+    }
+
+    /// Model ids from `/models`, or `None` if the endpoint is unreachable.
+    pub async fn list_model_ids(&self, query: &str) -> Option<Vec<String>> {
+        let res = self.get(&format!("models{}", query)).await.ok()?;
+        if !res.status().is_success() { return None; }
+        let data: serde_json::Value = res.json().await.unwrap_or_default();
+        Some(data["data"].as_array()
+            .map(|arr| arr.iter().filter_map(|m| m["id"].as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default())
+    }
+
+    async fn send(&self, client: &reqwest::Client, model: &str, image_url: &str, prompt: &str) -> reqwest::Result<reqwest::Response> {
+        let body = serde_json::json!({
+            "model": model,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "image_url", "image_url": { "url": image_url, "detail": "high" } },
+                    { "type": "text", "text": prompt }
+                ]
+            }],
+            "max_tokens": 4096
+        });
+
+        self.authorize(client.post(self.url("chat/completions")))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send().await
+    }
+
+    /// Asks `model` to check `text` against `image` and return the (possibly corrected) LaTeX.
+    async fn check(&self, client: &reqwest::Client, model: &str, image_url: &str, text: &str) -> Result<String, RecognizeError> {
+        let verify_prompt = format!(
+            "请对照图片检查以下LaTeX公式是否正确。如果正确，原样返回该公式；如果有错误，返回修正后的公式。只返回最终的纯LaTeX代码，不要解释。\n\n识别结果：{}",
+            text
+        );
+
+        let res = self.send(client, model, image_url, &verify_prompt).await?;
+        if !res.status().is_success() {
+            let status = res.status().as_u16();
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(classify_error(status, &err_text));
+        }
+        let data: serde_json::Value = res.json().await.map_err(|e| RecognizeError::Parse(e.to_string()))?;
+        Ok(data["choices"][0]["message"]["content"].as_str().unwrap_or("").trim().to_string())
+    }
+
+    /// Verification pass on another model's result, used as a second opinion.
+    pub async fn verify(&self, model: &str, image: &str, text: &str) -> Result<String, RecognizeError> {
+        let client = reqwest::Client::new();
+        self.check(&client, model, &image_url(image), text).await
+    }
+
+    /// Recognizes `image` with the mode prompt; formula results get a second verification pass.
+    pub async fn recognize(&self, model: &str, image: &str, mode: &str) -> Result<RecognizeResponse, RecognizeError> {
+        let image_url = image_url(image);
+
+        let prompt = get_prompt(mode);
+
+        let client = reqwest::Client::new();
+
+        // Step 1: Recognize
+        let res = self.send(&client, model, &image_url, prompt).await?;
+
+        if !res.status().is_success() {
+            let status = res.status().as_u16();
+            let err_text = res.text().await.unwrap_or_default();
+            return Err(classify_error(status, &err_text));
+        }
+
+        let data1: serde_json::Value = res.json().await.map_err(|e| RecognizeError::Parse(e.to_string()))?;
+        let text1 = data1["choices"][0]["message"]["content"].as_str().unwrap_or("").trim().to_string();
+
+        if text1.is_empty() {
+            return Ok(RecognizeResponse {
+                text: String::new(),
+                model: model.to_string(),
+                verified: Some(false),
+                ..Default::default()
+            });
+        }
+
+        // Step 2: Verify — only for formula mode
+        if mode == "formula" {
+            if let Ok(text2) = self.check(&client, model, &image_url, &text1).await {
+                if !text2.is_empty() {
+                    let verified = same_latex(&text1, &text2);
+                    let corrected = !verified;
+                    let final_text = if corrected { text2.clone() } else { text1.clone() };
+                    return Ok(RecognizeResponse {
+                        text: final_text,
+                        model: model.to_string(),
+                        verified: Some(verified),
+                        corrected: Some(corrected),
+                        diff: if corrected { Some(latex::diff(&text1, &text2)) } else { None },
+                        original_text: if corrected { Some(text1) } else { None },
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        // Non-formula mode or verify failed — return first result
+        Ok(RecognizeResponse {
+            text: text1,
+            model: model.to_string(),
+            verified: if mode == "formula" { Some(false) } else { None },
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,303 @@
+//! Offline formula recognition with a pix2tex-style encoder/decoder exported to ONNX.
+//!
+//! The model directory (setting `local_model_dir`) must contain:
+//! - `encoder.onnx`: grayscale image `[1, 1, H, W]` (f32, normalized) → memory `[1, N, D]`
+//! - `decoder.onnx`: inputs `(tokens [1, T] i64, memory)` → logits `[1, T, V]`
+//! - `tokenizer.json`: HuggingFace tokenizer file, only `model.vocab` is read
+//! - `config.json` (optional): overrides for [`LocalModelConfig`]
+//!
+//! ONNX Runtime is loaded at runtime from `onnxruntime_path` (or `ORT_DYLIB_PATH`) so the installer stays small.
+
+use async_trait::async_trait;
+use base64::Engine;
+use image::imageops::FilterType;
+use ort::session::Session;
+use ort::value::Tensor;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::AppHandle;
+
+use super::{RecognitionProvider, RecognizeRequest};
+use crate::commands::{AvailableModel, RecognizeResponse, TestResult};
+use crate::error::RecognizeError;
+use crate::store::get_store_string;
+
+// ── Model files ──
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+struct LocalModelConfig {
+    max_width: u32,
+    max_height: u32,
+    bos_token: i64,
+    eos_token: i64,
+    max_seq_len: usize,
+    mean: f32,
+    std: f32,
+}
+
+impl Default for LocalModelConfig {
+    // pix2tex defaults
+    fn default() -> Self {
+        LocalModelConfig {
+            max_width: 672,
+            max_height: 192,
+            bos_token: 1,
+            eos_token: 2,
+            max_seq_len: 512,
+            mean: 0.7931,
+            std: 0.1738,
+        }
+    }
+}
+
+struct LocalModel {
+    dir: PathBuf,
+    encoder: Session,
+    decoder: Session,
+    vocab: Vec<String>,
+    config: LocalModelConfig,
+}
+
+static MODEL: OnceLock<Mutex<Option<LocalModel>>> = OnceLock::new();
+static RUNTIME: Mutex<Option<RuntimeState>> = Mutex::new(None);
+
+/// The dylib path handed to `ort` and whether committing the environment succeeded.
+/// `ort` latches the first path for the life of the process, so only failures on that same path can be retried.
+struct RuntimeState {
+    path: String,
+    ready: bool,
+}
+
+fn init_runtime(dylib_path: &str) -> Result<(), String> {
+    let mut state = RUNTIME.lock().unwrap_or_else(|e| e.into_inner());
+    match state.as_ref() {
+        Some(s) if s.path == dylib_path && s.ready => return Ok(()),
+        Some(s) if s.path != dylib_path => {
+            return Err("ONNX Runtime 路径已更改，请重启应用后生效".into());
+        }
+        _ => {}
+    }
+    // Check before handing the path to ort, which would otherwise latch a wrong path until restart.
+    if !dylib_path.is_empty() && !Path::new(dylib_path).is_file() {
+        return Err(format!("找不到 ONNX Runtime 动态库: {}", dylib_path));
+    }
+
+    *state = Some(RuntimeState { path: dylib_path.to_string(), ready: false });
+    let builder = if dylib_path.is_empty() { ort::init() } else { ort::init_from(dylib_path) };
+    // ort panics when the library exists but cannot be loaded
+    let committed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| builder.with_name("formula-ocr").commit()))
+        .map_err(|_| "无法加载 ONNX Runtime: 动态库无效".to_string())?;
+    committed.map_err(|e| format!("无法加载 ONNX Runtime: {}", e))?;
+    if let Some(s) = state.as_mut() {
+        s.ready = true;
+    }
+    Ok(())
+}
+
+fn load_vocab(path: &Path) -> Result<Vec<String>, String> {
+    let data = std::fs::read_to_string(path).map_err(|e| format!("读取 tokenizer.json 失败: {}", e))?;
+    let val: serde_json::Value = serde_json::from_str(&data).map_err(|e| format!("解析 tokenizer.json 失败: {}", e))?;
+    let map = val["model"]["vocab"].as_object().ok_or("tokenizer.json 缺少 model.vocab")?;
+    let mut vocab = vec![String::new(); map.len()];
+    for (token, id) in map {
+        let id = id.as_u64().unwrap_or(0) as usize;
+        if id >= vocab.len() { vocab.resize(id + 1, String::new()); }
+        vocab[id] = token.clone();
+    }
+    Ok(vocab)
+}
+
+fn load_model(dir: &Path) -> Result<LocalModel, String> {
+    let session = |name: &str| -> Result<Session, String> {
+        let path = dir.join(name);
+        if !path.exists() {
+            return Err(format!("模型目录缺少 {}", name));
+        }
+        Session::builder()
+            .and_then(|b| b.with_intra_threads(num_threads()))
+            .and_then(|b| b.commit_from_file(&path))
+            .map_err(|e| format!("加载 {} 失败: {}", name, e))
+    };
+    let config = match std::fs::read_to_string(dir.join("config.json")) {
+        Ok(data) => serde_json::from_str(&data).map_err(|e| format!("解析 config.json 失败: {}", e))?,
+        Err(_) => LocalModelConfig::default(),
+    };
+    Ok(LocalModel {
+        dir: dir.to_path_buf(),
+        encoder: session("encoder.onnx")?,
+        decoder: session("decoder.onnx")?,
+        vocab: load_vocab(&dir.join("tokenizer.json"))?,
+        config,
+    })
+}
+
+fn num_threads() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4)
+}
+
+/// Loads (or reuses) the model in `dir` and runs `f` on it.
+fn with_model<T>(dylib_path: &str, dir: &Path, f: impl FnOnce(&mut LocalModel) -> Result<T, String>) -> Result<T, String> {
+    init_runtime(dylib_path)?;
+    let mut guard = MODEL.get_or_init(|| Mutex::new(None)).lock().map_err(|_| "本地模型状态异常".to_string())?;
+    if guard.as_ref().map_or(true, |m| m.dir != dir) {
+        *guard = Some(load_model(dir)?);
+    }
+    f(guard.as_mut().unwrap())
+}
+
+// ── Inference ──
+
+/// Grayscale, fit within the model's max size, pad to a multiple of 32 and normalize.
+fn preprocess(img: &image::DynamicImage, config: &LocalModelConfig) -> (Vec<f32>, usize, usize) {
+    let gray = img.to_luma8();
+    let (w, h) = gray.dimensions();
+    let scale = (config.max_width as f32 / w as f32).min(config.max_height as f32 / h as f32).min(1.0);
+    let nw = ((w as f32 * scale).round() as u32).max(1);
+    let nh = ((h as f32 * scale).round() as u32).max(1);
+    let resized = image::imageops::resize(&gray, nw, nh, FilterType::Lanczos3);
+
+    let pw = nw.div_ceil(32).max(1) as usize * 32;
+    let ph = nh.div_ceil(32).max(1) as usize * 32;
+    let white = (1.0 - config.mean) / config.std;
+    let mut data = vec![white; pw * ph];
+    for (x, y, p) in resized.enumerate_pixels() {
+        data[y as usize * pw + x as usize] = (p[0] as f32 / 255.0 - config.mean) / config.std;
+    }
+    (data, ph, pw)
+}
+
+fn run_model(model: &mut LocalModel, img: &image::DynamicImage) -> Result<String, String> {
+    let config = model.config.clone();
+    let (pixels, h, w) = preprocess(img, &config);
+    let input = Tensor::from_array(([1usize, 1, h, w], pixels)).map_err(|e| e.to_string())?;
+
+    let encoded = model.encoder.run(ort::inputs![input]).map_err(|e| format!("编码器推理失败: {}", e))?;
+    let (shape, data) = encoded[0].try_extract_tensor::<f32>().map_err(|e| e.to_string())?;
+    let memory = Tensor::from_array((shape.clone(), data.to_vec())).map_err(|e| e.to_string())?;
+    drop(encoded);
+
+    // Greedy decoding
+    let mut tokens: Vec<i64> = vec![config.bos_token];
+    while tokens.len() < config.max_seq_len {
+        let input = Tensor::from_array(([1usize, tokens.len()], tokens.clone())).map_err(|e| e.to_string())?;
+        let out = model.decoder.run(ort::inputs![input, &memory]).map_err(|e| format!("解码器推理失败: {}", e))?;
+        let (shape, logits) = out[0].try_extract_tensor::<f32>().map_err(|e| e.to_string())?;
+        let vocab_size = *shape.last().unwrap_or(&0) as usize;
+        if vocab_size == 0 || logits.len() < vocab_size {
+            return Err("解码器输出格式不正确".into());
+        }
+        let last = &logits[logits.len() - vocab_size..];
+        let next = last.iter().enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i as i64)
+            .unwrap_or(config.eos_token);
+        if next == config.eos_token { break; }
+        tokens.push(next);
+    }
+
+    Ok(detokenize(&model.vocab, &tokens[1..]))
+}
+
+fn detokenize(vocab: &[String], ids: &[i64]) -> String {
+    let raw: String = ids.iter()
+        .filter_map(|&id| vocab.get(id as usize))
+        .filter(|t| !matches!(t.as_str(), "[PAD]" | "[BOS]" | "[EOS]"))
+        .map(|t| t.replace('Ġ', " "))
+        .collect();
+    post_process(raw.trim())
+}
+
+/// pix2tex-style cleanup: a space survives only between two letters or after `\`.
+fn post_process(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            let mut j = i;
+            while j < chars.len() && chars[j].is_whitespace() { j += 1; }
+            let prev = out.chars().last();
+            let next = chars.get(j).copied();
+            let keep = prev == Some('\\')
+                || (prev.is_some_and(|c| c.is_ascii_alphabetic()) && next.is_some_and(|c| c.is_ascii_alphabetic()));
+            if keep { out.push(' '); }
+            i = j;
+        } else {
+            out.push(chars[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+// ── Provider ──
+
+pub struct LocalOnnx;
+
+#[async_trait]
+impl RecognitionProvider for LocalOnnx {
+    fn id(&self) -> &'static str { "local" }
+
+    fn name(&self) -> &'static str { "本地模型" }
+
+    fn credential_key(&self) -> &'static str { "local_model_dir" }
+
+    fn credential_label(&self) -> &'static str { "模型目录" }
+
+    async fn list_models(&self, app: &AppHandle) -> Vec<AvailableModel> {
+        vec![AvailableModel {
+            id: "local:pix2tex".to_string(),
+            name: "本地公式模型 (离线)".to_string(),
+            provider: self.name().to_string(),
+            modes: vec!["formula".into()],
+            available: self.is_configured(app),
+            free_per_day: None,
+            usage_today: None,
+            pricing: Some("离线免费".to_string()),
+            free: Some(true),
+            voucher: None,
+            charge_balance: None,
+            total_balance: None,
+        }]
+    }
+
+    async fn recognize(&self, app: &AppHandle, req: RecognizeRequest<'_>) -> Result<RecognizeResponse, RecognizeError> {
+        let dir = self.credential(app);
+        if dir.is_empty() {
+            return Err(RecognizeError::NotConfigured("请先在设置中配置本地模型目录".into()));
+        }
+        let dylib = get_store_string(app, "onnxruntime_path");
+
+        let base64_data = req.image.split(',').next_back().unwrap_or(req.image);
+        let bytes = base64::engine::general_purpose::STANDARD.decode(base64_data)
+            .map_err(|e| RecognizeError::InvalidInput(format!("Base64 解码失败: {}", e)))?;
+        let img = image::load_from_memory(&bytes)
+            .map_err(|e| RecognizeError::InvalidInput(format!("图片解码失败: {}", e)))?;
+
+        let text = tokio::task::spawn_blocking(move || {
+            with_model(&dylib, Path::new(&dir), |model| run_model(model, &img))
+        }).await
+            .map_err(|e| RecognizeError::Engine(format!("本地识别失败: {}", e)))?
+            .map_err(RecognizeError::Engine)?;
+
+        Ok(RecognizeResponse {
+            text,
+            model: "本地模型 (pix2tex)".to_string(),
+            ..Default::default()
+        })
+    }
+
+    async fn test_credentials(&self, app: &AppHandle, dir: &str) -> TestResult {
+        let dylib = get_store_string(app, "onnxruntime_path");
+        let dir = dir.to_string();
+        let loaded = tokio::task::spawn_blocking(move || {
+            with_model(&dylib, Path::new(&dir), |_| Ok(()))
+        }).await.unwrap_or_else(|e| Err(e.to_string()));
+        match loaded {
+            Ok(()) => TestResult { ok: true, error: None, balance: None },
+            Err(e) => TestResult { ok: false, error: Some(e), balance: None },
+        }
+    }
+}
@@ -0,0 +1,117 @@
+mod chat;
+mod local_onnx;
+mod openai_compat;
+mod siliconflow;
+mod simpletex;
+mod tesseract;
+
+pub use chat::same_latex;
+pub use simpletex::SIMPLETEX_MODELS;
+pub use tesseract::list_languages as tesseract_languages;
+
+use async_trait::async_trait;
+use std::sync::OnceLock;
+use tauri::AppHandle;
+
+use crate::commands::{AvailableModel, RecognizeResponse, TestResult};
+use crate::error::RecognizeError;
+use crate::store::get_store_string;
+
+// ── Types ──
+
+/// A single recognition call, with the provider prefix already stripped from `model`.
+#[derive(Clone, Copy)]
+pub struct RecognizeRequest<'a> {
+    pub image: &'a str,
+    pub mode: &'a str,
+    pub model: &'a str,
+}
+
+#[derive(Clone, Debug)]
+pub struct Balance {
+    pub charge_balance: String,
+    pub total_balance: String,
+}
+
+/// A recognition backend. Model ids exposed to the frontend are `"{id}:{model}"`.
+#[async_trait]
+pub trait RecognitionProvider: Send + Sync {
+    /// Model id prefix, e.g. `simpletex`.
+    fn id(&self) -> &'static str;
+
+    /// Human-readable provider name shown in the model list.
+    fn name(&self) -> &'static str;
+
+    /// Key in `config.json` holding this provider's token / API key.
+    fn credential_key(&self) -> &'static str;
+
+    /// What the credential is called in messages, e.g. `Token` or `API Key`.
+    fn credential_label(&self) -> &'static str;
+
+    fn credential(&self, app: &AppHandle) -> String {
+        get_store_string(app, self.credential_key())
+    }
+
+    fn is_configured(&self, app: &AppHandle) -> bool {
+        !self.credential(app).is_empty()
+    }
+
+    async fn list_models(&self, app: &AppHandle) -> Vec<AvailableModel>;
+
+    /// Whether any of its models can be free or have a free quota; `auto` only lists those providers.
+    fn offers_free(&self) -> bool {
+        true
+    }
+
+    async fn recognize(&self, app: &AppHandle, req: RecognizeRequest<'_>) -> Result<RecognizeResponse, RecognizeError>;
+
+    /// Checks another model's `text` against the image and returns the (possibly corrected) result.
+    /// `None` if this provider can only recognize from scratch.
+    async fn verify(&self, _app: &AppHandle, _req: RecognizeRequest<'_>, _text: &str) -> Option<Result<String, RecognizeError>> {
+        None
+    }
+
+    async fn test_credentials(&self, app: &AppHandle, credential: &str) -> TestResult;
+
+    /// Check used by `save_settings` before persisting a credential.
+    async fn validate_credentials(&self, app: &AppHandle, credential: &str) -> bool {
+        self.test_credentials(app, credential).await.ok
+    }
+
+    async fn balance(&self, _app: &AppHandle) -> Option<Balance> {
+        None
+    }
+}
+
+// ── Registry ──
+
+pub fn registry() -> &'static [Box<dyn RecognitionProvider>] {
+    static REGISTRY: OnceLock<Vec<Box<dyn RecognitionProvider>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| vec![
+        Box::new(simpletex::SimpleTex),
+        Box::new(siliconflow::SiliconFlow),
+        Box::new(openai_compat::OpenAiCompat),
+        Box::new(local_onnx::LocalOnnx),
+        Box::new(tesseract::Tesseract),
+    ])
+}
+
+pub fn find(id: &str) -> Option<&'static dyn RecognitionProvider> {
+    registry().iter().find(|p| p.id() == id).map(|p| p.as_ref())
+}
+
+/// Splits `"provider:model"` into its parts. Ids without a prefix yield `None`.
+pub fn split_model_id(model_id: &str) -> Option<(&str, &str)> {
+    model_id.split_once(':')
+}
+
+// ── Prompts ──
+
+pub fn get_prompt(mode: &str) -> &'static str {
+    match mode {
+        "formula" => "请识别图片中的数学公式，只返回纯LaTeX代码，不要用markdown代码块包裹，不要加$符号，不要解释。",
+        "ocr" => "请识别图片中的所有文字内容，保持原始排版格式。只返回识别到的文字，不要解释。",
+        "document" => "请识别图片中的所有内容（包括文字、公式、表格等），以Markdown格式返回。公式用$...$（行内）或$$...$$（块级）包裹，表格用Markdown表格语法，保持原始排版结构。不要解释。",
+        _ => "请识别图片中的数学公式，只返回纯LaTeX代码，不要用markdown代码块包裹，不要加$符号，不要解释。",
+    }
+}
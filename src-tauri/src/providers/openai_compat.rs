@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use tauri::AppHandle;
+
+use super::chat::ChatEndpoint;
+use super::{RecognitionProvider, RecognizeRequest};
+use crate::commands::{AvailableModel, RecognizeResponse, TestResult};
+use crate::error::RecognizeError;
+use crate::store::{get_store_string, get_store_vec};
+
+// ── Helpers ──
+
+fn base_url(app: &AppHandle) -> String {
+    get_store_string(app, "openai_base_url")
+}
+
+/// Short display name for ids like `Qwen/Qwen2-VL-7B-Instruct` or `llava:13b`.
+fn model_display_name(id: &str) -> String {
+    id.rsplit('/').next().unwrap_or(id).to_string()
+}
+
+// ── Provider ──
+
+/// Any server speaking the OpenAI chat completions API (vLLM, Ollama, internal gateways, ...).
+pub struct OpenAiCompat;
+
+#[async_trait]
+impl RecognitionProvider for OpenAiCompat {
+    fn id(&self) -> &'static str { "openai" }
+
+    fn name(&self) -> &'static str { "OpenAI 兼容" }
+
+    fn credential_key(&self) -> &'static str { "openai_key" }
+
+    fn credential_label(&self) -> &'static str { "API Key" }
+
+    // The key is optional for self-hosted servers; the base URL is what enables the provider
+    fn is_configured(&self, app: &AppHandle) -> bool {
+        !base_url(app).is_empty()
+    }
+
+    // Pricing is up to the server, so `auto` never picks these
+    fn offers_free(&self) -> bool {
+        false
+    }
+
+    async fn list_models(&self, app: &AppHandle) -> Vec<AvailableModel> {
+        let url = base_url(app);
+        if url.is_empty() { return vec![]; }
+
+        // Prefer the user's list; otherwise ask the server
+        let mut ids = get_store_vec(app, "openai_models");
+        if ids.is_empty() {
+            let key = self.credential(app);
+            ids = ChatEndpoint { base_url: &url, api_key: &key }
+                .list_model_ids("").await
+                .unwrap_or_default();
+        }
+
+        ids.iter().map(|id| AvailableModel {
+            id: format!("openai:{}", id),
+            name: model_display_name(id),
+            provider: self.name().to_string(),
+            modes: vec!["formula".into(), "ocr".into()],
+            available: true,
+            free_per_day: None,
+            usage_today: None,
+            pricing: None,
+            free: None,
+            voucher: None,
+            charge_balance: None,
+            total_balance: None,
+        }).collect()
+    }
+
+    async fn recognize(&self, app: &AppHandle, req: RecognizeRequest<'_>) -> Result<RecognizeResponse, RecognizeError> {
+        let url = base_url(app);
+        if url.is_empty() {
+            return Err(RecognizeError::NotConfigured("请先在设置中配置 OpenAI 兼容服务地址".into()));
+        }
+        if req.model.is_empty() {
+            return Err(RecognizeError::NotConfigured("未选择模型".into()));
+        }
+
+        let key = self.credential(app);
+        ChatEndpoint { base_url: &url, api_key: &key }.recognize(req.model, req.image, req.mode).await
+    }
+
+    async fn verify(&self, app: &AppHandle, req: RecognizeRequest<'_>, text: &str) -> Option<Result<String, RecognizeError>> {
+        let url = base_url(app);
+        if url.is_empty() || req.model.is_empty() { return None; }
+        let key = self.credential(app);
+        Some(ChatEndpoint { base_url: &url, api_key: &key }.verify(req.model, req.image, text).await)
+    }
+
+    async fn test_credentials(&self, app: &AppHandle, api_key: &str) -> TestResult {
+        let url = base_url(app);
+        if url.is_empty() {
+            return TestResult { ok: false, error: Some("未填写服务地址".into()), balance: None };
+        }
+
+        let res = match (ChatEndpoint { base_url: &url, api_key }).get("models").await {
+            Ok(r) => r,
+            Err(e) => return TestResult { ok: false, error: Some(format!("网络错误: {}", e)), balance: None },
+        };
+
+        if res.status().as_u16() == 401 {
+            return TestResult { ok: false, error: Some("API Key 无效".into()), balance: None };
+        }
+        if !res.status().is_success() {
+            return TestResult { ok: false, error: Some(format!("HTTP {}", res.status())), balance: None };
+        }
+
+        TestResult { ok: true, error: None, balance: None }
+    }
+}
@@ -0,0 +1,250 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+use super::chat::ChatEndpoint;
+use super::{Balance, RecognitionProvider, RecognizeRequest};
+use crate::commands::{AvailableModel, RecognizeResponse, SfModel, TestResult};
+use crate::error::RecognizeError;
+use crate::store::get_store_vec;
+
+// ── Constants ──
+
+const SILICONFLOW_BASE_URL: &str = "https://api.siliconflow.cn/v1";
+/// How long the vision model list and its prices are reused; `auto` lists models before every
+/// recognition.
+const CATALOG_TTL: Duration = Duration::from_secs(60 * 60);
+const PRICE_UNKNOWN: &str = "价格未知";
+/// Retry sooner when the pricing page could not be read.
+const PRICING_RETRY: Duration = Duration::from_secs(5 * 60);
+
+/// Held while fetching, so concurrent batch workers wait for one fetch instead of each making their own.
+static CATALOG: Mutex<Option<Catalog>> = Mutex::const_new(None);
+
+struct Catalog {
+    api_key: String,
+    models: Vec<SfModel>,
+    refresh_at: Instant,
+}
+
+// ── Vision model detection ──
+
+fn is_vision_model(id: &str) -> bool {
+    let upper = id.to_uppercase();
+    upper.contains("VL") || upper.contains("OCR") || upper.contains("PADDLEOCR")
+        || upper.contains("OMNI") || upper.contains("CAPTIONER") || id.contains("vl2")
+        || id.contains("Kimi-K2.5")
+        || {
+            // GLM-x.xV pattern
+            let parts: Vec<&str> = id.split('/').collect();
+            let last = parts.last().unwrap_or(&"");
+            last.contains("GLM-") && last.ends_with('V')
+        }
+}
+
+fn is_ocr_only_model(id: &str) -> bool {
+    let upper = id.to_uppercase();
+    upper.contains("PADDLEOCR") || upper.contains("DEEPSEEK-OCR") || upper.contains("CAPTIONER")
+}
+
+fn model_id_to_name(id: &str) -> String {
+    let is_pro = id.starts_with("Pro/");
+    let stripped = if is_pro { &id[4..] } else { id };
+    let parts: Vec<&str> = stripped.split('/').collect();
+    let name = parts.last().unwrap_or(&id)
+        .trim_end_matches("-Instruct");
+    if is_pro {
+        format!("{} (Pro)", name)
+    } else {
+        name.to_string()
+    }
+}
+
+// ── API helpers ──
+
+fn endpoint(api_key: &str) -> ChatEndpoint<'_> {
+    ChatEndpoint { base_url: SILICONFLOW_BASE_URL, api_key }
+}
+
+async fn fetch_sf_balance(api_key: &str) -> Option<Balance> {
+    if api_key.is_empty() { return None; }
+    let res = endpoint(api_key).get("user/info").await.ok()?;
+    if !res.status().is_success() { return None; }
+    let data: serde_json::Value = res.json().await.ok()?;
+    let charge = data["data"]["chargeBalance"].as_str().unwrap_or("0").to_string();
+    let total = data["data"]["totalBalance"].as_str()
+        .or_else(|| data["data"]["balance"].as_str())
+        .unwrap_or("0").to_string();
+    Some(Balance { charge_balance: charge, total_balance: total })
+}
+
+/// The vision models with their prices, from [`CATALOG`] while it is fresh. When the pricing page
+/// can't be read, the prices already known are kept so free models stay free.
+async fn fetch_sf_vision_models(api_key: &str) -> Vec<SfModel> {
+    if api_key.is_empty() { return vec![]; }
+    let mut catalog = CATALOG.lock().await;
+    let previous = catalog.as_ref().filter(|c| c.api_key == api_key);
+    if let Some(c) = previous.filter(|c| Instant::now() < c.refresh_at) {
+        return c.models.clone();
+    }
+
+    let Some(ids) = endpoint(api_key).list_model_ids("?sub_type=chat").await else {
+        return previous.map(|c| c.models.clone()).unwrap_or_default();
+    };
+    let mut pricing_map = fetch_pricing_map().await;
+    let priced = !pricing_map.is_empty();
+    if !priced {
+        for m in previous.map(|c| c.models.as_slice()).unwrap_or_default() {
+            if m.pricing != PRICE_UNKNOWN { pricing_map.insert(m.id.clone(), (m.input_price, m.output_price)); }
+        }
+    }
+    let models = vision_models(&ids, &pricing_map);
+    let ttl = if priced { CATALOG_TTL } else { PRICING_RETRY };
+    *catalog = Some(Catalog { api_key: api_key.to_string(), models: models.clone(), refresh_at: Instant::now() + ttl });
+    models
+}
+
+/// Vision models among `all_models`, free ones first, then by input price.
+fn vision_models(all_models: &[String], pricing_map: &HashMap<String, (f64, f64)>) -> Vec<SfModel> {
+    let mut result: Vec<SfModel> = all_models.iter()
+        .filter(|id| is_vision_model(id))
+        .map(|id| {
+            let price = pricing_map.get(id.as_str());
+            let input_price = price.map(|p| p.0).unwrap_or(-1.0);
+            let output_price = price.map(|p| p.1).unwrap_or(-1.0);
+            let is_free = input_price == 0.0 && output_price == 0.0;
+            let is_ocr_only = is_ocr_only_model(id);
+            let pricing = if input_price < 0.0 {
+                PRICE_UNKNOWN.to_string()
+            } else if is_free {
+                "免费".to_string()
+            } else {
+                format!("入¥{}/出¥{}", input_price, output_price)
+            };
+            SfModel {
+                id: id.clone(),
+                name: model_id_to_name(id),
+                pricing,
+                modes: if is_ocr_only { vec!["ocr".into()] } else { vec!["formula".into(), "ocr".into()] },
+                input_price: input_price.max(0.0),
+                output_price: output_price.max(0.0),
+                free: is_free,
+            }
+        })
+        .collect();
+
+    result.sort_by(|a, b| {
+        if a.free && !b.free { return std::cmp::Ordering::Less; }
+        if !a.free && b.free { return std::cmp::Ordering::Greater; }
+        a.input_price.partial_cmp(&b.input_price).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    result
+}
+
+async fn fetch_pricing_map() -> HashMap<String, (f64, f64)> {
+    let mut map = HashMap::new();
+    let client = reqwest::Client::new();
+    let res = match client.get("https://siliconflow.cn/pricing").send().await {
+        Ok(r) if r.status().is_success() => r,
+        _ => return map,
+    };
+    let html = res.text().await.unwrap_or_default();
+    let re = regex_lite::Regex::new(
+        r#"href="[^"]*?target=([^"]+)"[^>]*>([^<]+)</a></div><div[^>]*>(免费|[\d.]+)</div><div[^>]*>(免费|[\d.]+)</div>"#
+    );
+    if let Ok(re) = re {
+        for cap in re.captures_iter(&html) {
+            let id = cap[2].trim().to_string();
+            let inp = if &cap[3] == "免费" { 0.0 } else { cap[3].parse().unwrap_or(-1.0) };
+            let out = if &cap[4] == "免费" { 0.0 } else { cap[4].parse().unwrap_or(-1.0) };
+            if inp >= 0.0 && out >= 0.0 {
+                map.insert(id, (inp, out));
+            }
+        }
+    }
+    map
+}
+
+// ── Provider ──
+
+pub struct SiliconFlow;
+
+#[async_trait]
+impl RecognitionProvider for SiliconFlow {
+    fn id(&self) -> &'static str { "siliconflow" }
+
+    fn name(&self) -> &'static str { "硅基流动" }
+
+    fn credential_key(&self) -> &'static str { "siliconflow_key" }
+
+    fn credential_label(&self) -> &'static str { "API Key" }
+
+    async fn list_models(&self, app: &AppHandle) -> Vec<AvailableModel> {
+        let sf_key = self.credential(app);
+        let sf_valid = !sf_key.is_empty();
+        let voucher_models = get_store_vec(app, "voucher_models");
+        let sf_models = if sf_valid { fetch_sf_vision_models(&sf_key).await } else { vec![] };
+
+        sf_models.iter().map(|m| AvailableModel {
+            id: format!("siliconflow:{}", m.id),
+            name: m.name.clone(),
+            provider: self.name().to_string(),
+            modes: m.modes.clone(),
+            available: sf_valid,
+            free_per_day: None,
+            usage_today: None,
+            pricing: Some(m.pricing.clone()),
+            free: Some(m.free),
+            voucher: Some(voucher_models.contains(&m.id)),
+            charge_balance: None,
+            total_balance: None,
+        }).collect()
+    }
+
+    async fn recognize(&self, app: &AppHandle, req: RecognizeRequest<'_>) -> Result<RecognizeResponse, RecognizeError> {
+        let sf_key = self.credential(app);
+        if sf_key.is_empty() {
+            return Err(RecognizeError::NotConfigured("请先在设置中配置硅基流动 API Key".into()));
+        }
+
+        if req.model.is_empty() {
+            // No model specified, this shouldn't happen normally
+            return Err(RecognizeError::NotConfigured("未选择模型".into()));
+        }
+
+        endpoint(&sf_key).recognize(req.model, req.image, req.mode).await
+    }
+
+    async fn verify(&self, app: &AppHandle, req: RecognizeRequest<'_>, text: &str) -> Option<Result<String, RecognizeError>> {
+        let sf_key = self.credential(app);
+        if sf_key.is_empty() || req.model.is_empty() { return None; }
+        Some(endpoint(&sf_key).verify(req.model, req.image, text).await)
+    }
+
+    async fn test_credentials(&self, _app: &AppHandle, api_key: &str) -> TestResult {
+        let res = match endpoint(api_key).get("user/info").await {
+            Ok(r) => r,
+            Err(e) => return TestResult { ok: false, error: Some(format!("网络错误: {}", e)), balance: None },
+        };
+
+        if res.status().as_u16() == 401 {
+            return TestResult { ok: false, error: Some("API Key 无效".into()), balance: None };
+        }
+        if !res.status().is_success() {
+            return TestResult { ok: false, error: Some(format!("HTTP {}", res.status())), balance: None };
+        }
+
+        let data: serde_json::Value = res.json().await.unwrap_or_default();
+        let balance = data["data"]["totalBalance"].as_str()
+            .or_else(|| data["data"]["balance"].as_str())
+            .map(|s| s.to_string());
+
+        TestResult { ok: true, error: None, balance }
+    }
+
+    async fn balance(&self, app: &AppHandle) -> Option<Balance> {
+        fetch_sf_balance(&self.credential(app)).await
+    }
+}
@@ -0,0 +1,230 @@
+use async_trait::async_trait;
+use base64::Engine;
+use reqwest::multipart;
+use tauri::AppHandle;
+
+use super::{RecognitionProvider, RecognizeRequest};
+use crate::commands::{AvailableModel, RecognizeResponse, TestResult};
+use crate::error::RecognizeError;
+use crate::store::{get_model_usage_today, increment_model_usage};
+
+// ── Constants ──
+
+pub const SIMPLETEX_MODELS: &[(&str, &str, u32, &str)] = &[
+    ("latex_ocr", "SimpleTex 标准模型", 500, "formula"),
+    ("latex_ocr_turbo", "SimpleTex 轻量模型", 2000, "formula"),
+    ("simpletex_ocr", "SimpleTex 通用识别", 50, "document"),
+];
+
+// 50x50 PNG (white bg, black square) used to probe a token against latex_ocr_turbo
+const TEST_PNG_BASE64: &str = "iVBORw0KGgoAAAANSUhEUgAAADIAAAAyCAIAAACRXR/mAAAASklEQVR4nO3OsQ3AIBAAsd9/abIAzSkFCNkTeNaV5nRgT6vQKrQKrUKr0CqeaM0/WlpaWlpaWlpaWlpaR2gVWoVWoVVoFVrFpa0PK6QKSH2kFl4AAAAASUVORK5CYII=";
+
+fn err_type_of(val: &serde_json::Value) -> Option<&str> {
+    val["res"]["errType"].as_str()
+        .or_else(|| val["err_info"]["err_type"].as_str())
+        .or_else(|| val["errType"].as_str())
+}
+
+async fn send_test_image(token: &str) -> reqwest::Result<reqwest::Response> {
+    let png_bytes = base64::engine::general_purpose::STANDARD.decode(TEST_PNG_BASE64).unwrap();
+    let part = multipart::Part::bytes(png_bytes).file_name("test.png").mime_str("image/png").unwrap();
+    let form = multipart::Form::new().part("file", part);
+    let client = reqwest::Client::new();
+    client.post("https://server.simpletex.net/api/latex_ocr_turbo")
+        .header("token", token)
+        .multipart(form)
+        .send().await
+}
+
+async fn recognize_simpletex(token: &str, image_base64: &str, model_id: &str, rec_mode: Option<&str>) -> Result<(String, Option<f64>), RecognizeError> {
+    let (header, base64_data) = image_base64.split_once(',').unwrap_or(("", image_base64));
+    let image_bytes = base64::engine::general_purpose::STANDARD.decode(base64_data)
+        .map_err(|e| RecognizeError::InvalidInput(format!("Base64 解码失败: {}", e)))?;
+
+    // Labelled with the type the data URL declares; preprocessing makes that the real one
+    let (mime, ext) = match header.trim_start_matches("data:").split(';').next().unwrap_or("") {
+        "image/jpeg" => ("image/jpeg", "jpg"),
+        "image/webp" => ("image/webp", "webp"),
+        "image/gif" => ("image/gif", "gif"),
+        "image/bmp" => ("image/bmp", "bmp"),
+        _ => ("image/png", "png"),
+    };
+    let part = multipart::Part::bytes(image_bytes)
+        .file_name(format!("image.{}", ext))
+        .mime_str(mime).unwrap();
+    let mut form = multipart::Form::new().part("file", part);
+    if let Some(rm) = rec_mode {
+        form = form.text("rec_mode", rm.to_string());
+    }
+
+    let client = reqwest::Client::new();
+    let res = client.post(format!("https://server.simpletex.net/api/{}", model_id))
+        .header("token", token)
+        .multipart(form)
+        .send().await?;
+
+    let status = res.status().as_u16();
+    let body = res.text().await?;
+    let data: Option<serde_json::Value> = serde_json::from_str(&body).ok();
+
+    // Error details may come with either a 200 or an error status
+    if let Some(err_type) = data.as_ref().filter(|d| !d["status"].as_bool().unwrap_or(false)).and_then(err_type_of) {
+        if err_type == "req_unauthorized" {
+            return Err(RecognizeError::Unauthorized("SimpleTex Token 无效或已过期".into()));
+        }
+        if err_type == "resource_no_valid" {
+            return Err(RecognizeError::QuotaExhausted("SimpleTex 额度已用完".into()));
+        }
+    }
+    if !(200..300).contains(&status) {
+        return Err(RecognizeError::from_status("SimpleTex", status, body));
+    }
+
+    let data = data.ok_or_else(|| RecognizeError::Parse("SimpleTex 返回了无效的 JSON".into()))?;
+    if !data["status"].as_bool().unwrap_or(false) {
+        let err_type = err_type_of(&data).unwrap_or("unknown");
+        return Err(RecognizeError::ProviderError { status, body: format!("SimpleTex 识别失败: {}", err_type) });
+    }
+
+    let res_obj = &data["res"];
+    let text = if let Some(s) = res_obj["info"].as_str() {
+        s.to_string()
+    } else if res_obj["info"].is_object() {
+        res_obj["info"]["markdown"].as_str()
+            .or_else(|| res_obj["info"]["text"].as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| res_obj["info"].to_string())
+    } else {
+        res_obj["markdown"].as_str()
+            .or_else(|| res_obj["latex"].as_str())
+            .unwrap_or("").to_string()
+    };
+
+    let conf = res_obj["conf"].as_f64();
+    Ok((text, conf))
+}
+
+// ── Provider ──
+
+pub struct SimpleTex;
+
+#[async_trait]
+impl RecognitionProvider for SimpleTex {
+    fn id(&self) -> &'static str { "simpletex" }
+
+    fn name(&self) -> &'static str { "SimpleTex" }
+
+    fn credential_key(&self) -> &'static str { "simpletex_token" }
+
+    fn credential_label(&self) -> &'static str { "Token" }
+
+    async fn list_models(&self, app: &AppHandle) -> Vec<AvailableModel> {
+        let st_valid = self.is_configured(app);
+        SIMPLETEX_MODELS.iter().map(|&(id, name, free_per_day, st_mode)| {
+            let modes = if st_mode == "document" {
+                vec!["formula".into(), "ocr".into(), "document".into()]
+            } else {
+                vec![st_mode.to_string()]
+            };
+            AvailableModel {
+                id: format!("simpletex:{}", id),
+                name: name.to_string(),
+                provider: self.name().to_string(),
+                modes,
+                available: st_valid,
+                free_per_day: Some(free_per_day),
+                usage_today: Some(get_model_usage_today(app, id)),
+                pricing: Some(format!("每日免费 {} 次", free_per_day)),
+                free: None,
+                voucher: None,
+                charge_balance: None,
+                total_balance: None,
+            }
+        }).collect()
+    }
+
+    async fn recognize(&self, app: &AppHandle, req: RecognizeRequest<'_>) -> Result<RecognizeResponse, RecognizeError> {
+        let token = self.credential(app);
+        if token.is_empty() {
+            return Err(RecognizeError::NotConfigured("SimpleTex Token 未配置".into()));
+        }
+
+        let rec_mode = if req.model == "simpletex_ocr" {
+            Some(if req.mode == "formula" { "formula" } else { "document" })
+        } else {
+            None
+        };
+
+        let (text, conf) = recognize_simpletex(&token, req.image, req.model, rec_mode).await?;
+        increment_model_usage(app, req.model);
+
+        let model_name = SIMPLETEX_MODELS.iter()
+            .find(|&&(id, _, _, _)| id == req.model)
+            .map(|&(_, name, _, _)| name)
+            .unwrap_or(req.model);
+
+        Ok(RecognizeResponse {
+            text,
+            model: format!("SimpleTex ({})", model_name),
+            confidence: conf,
+            ..Default::default()
+        })
+    }
+
+    async fn test_credentials(&self, _app: &AppHandle, token: &str) -> TestResult {
+        let res = match send_test_image(token).await {
+            Ok(r) => r,
+            Err(e) => return TestResult { ok: false, error: Some(format!("网络错误: {}", e)), balance: None },
+        };
+
+        if res.status().as_u16() == 401 {
+            return TestResult { ok: false, error: Some("Token 无效或已过期".into()), balance: None };
+        }
+        if !res.status().is_success() {
+            let status = res.status().as_u16();
+            let body = res.text().await.unwrap_or_default();
+            // Check for specific error types in both res.errType and err_info.err_type paths
+            if let Ok(val) = serde_json::from_str::<serde_json::Value>(&body) {
+                let err_type = err_type_of(&val).unwrap_or("");
+                if err_type == "req_unauthorized" {
+                    return TestResult { ok: false, error: Some("Token 无效或已过期".into()), balance: None };
+                }
+                if err_type == "resource_no_valid" {
+                    return TestResult { ok: false, error: Some("无可用资源（额度已用完）".into()), balance: None };
+                }
+            }
+            return TestResult { ok: false, error: Some(format!("服务器错误 (HTTP {})", status)), balance: None };
+        }
+
+        let data: serde_json::Value = res.json().await.unwrap_or_default();
+        if !data["status"].as_bool().unwrap_or(false) {
+            let err_type = err_type_of(&data).unwrap_or("未知错误");
+            if err_type == "req_unauthorized" {
+                return TestResult { ok: false, error: Some("Token 无效或已过期".into()), balance: None };
+            }
+            if err_type == "resource_no_valid" {
+                return TestResult { ok: false, error: Some("无可用资源（额度已用完）".into()), balance: None };
+            }
+            return TestResult { ok: false, error: Some(err_type.to_string()), balance: None };
+        }
+
+        TestResult { ok: true, error: None, balance: None }
+    }
+
+    async fn validate_credentials(&self, _app: &AppHandle, token: &str) -> bool {
+        match send_test_image(token).await {
+            Ok(r) => {
+                if r.status().as_u16() == 401 { return false; }
+                let body = r.text().await.unwrap_or_default();
+                if let Ok(val) = serde_json::from_str::<serde_json::Value>(&body) {
+                    let err_type = val["res"]["errType"].as_str()
+                        .or_else(|| val["err_info"]["err_type"].as_str())
+                        .unwrap_or("");
+                    if err_type == "req_unauthorized" { return false; }
+                }
+                true // 200 or server error (not auth error) = token valid
+            }
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use async_trait::async_trait;
+use base64::Engine;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tauri::AppHandle;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::{RecognitionProvider, RecognizeRequest};
+use crate::commands::{AvailableModel, RecognizeResponse, TestResult};
+use crate::error::RecognizeError;
+use crate::store::get_store_vec;
+
+// ── Helpers ──
+
+const DEFAULT_LANGS: &[&str] = &["chi_sim", "eng"];
+
+/// Finds the tesseract executable: an explicit path, or `tesseract` on `PATH`.
+fn resolve_binary(configured: &str) -> Option<PathBuf> {
+    if !configured.is_empty() {
+        let path = PathBuf::from(configured);
+        return path.is_file().then_some(path);
+    }
+    let exe = if cfg!(windows) { "tesseract.exe" } else { "tesseract" };
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).map(|dir| dir.join(exe)).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+fn command(binary: &Path) -> Command {
+    #[allow(unused_mut)]
+    let mut cmd = Command::new(binary);
+    #[cfg(windows)]
+    {
+        // CREATE_NO_WINDOW — don't flash a console for every call
+        cmd.creation_flags(0x0800_0000);
+    }
+    cmd
+}
+
+fn langs(app: &AppHandle) -> Vec<String> {
+    let langs = get_store_vec(app, "tesseract_langs");
+    if langs.is_empty() { DEFAULT_LANGS.iter().map(|s| s.to_string()).collect() } else { langs }
+}
+
+/// Installed language packs as reported by `tesseract --list-langs`.
+pub async fn list_languages(configured: &str) -> Result<Vec<String>, String> {
+    let binary = resolve_binary(configured).ok_or("未找到 Tesseract，请安装或填写可执行文件路径")?;
+    let output = command(&binary).arg("--list-langs").output().await
+        .map_err(|e| format!("无法运行 Tesseract: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    // First line is "List of available languages in ...:"
+    Ok(String::from_utf8_lossy(&output.stdout).lines()
+        .skip(1)
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3000}'..='\u{303F}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{FF00}'..='\u{FFEF}')
+}
+
+/// Tesseract separates every CJK glyph with a space; drop those, keep the rest of the layout.
+fn clean_output(text: &str) -> String {
+    text.lines().map(|line| {
+        let chars: Vec<char> = line.trim_end().chars().collect();
+        chars.iter().enumerate()
+            .filter(|&(i, &c)| {
+                !(c == ' ' && i > 0 && i + 1 < chars.len() && is_cjk(chars[i - 1]) && is_cjk(chars[i + 1]))
+            })
+            .map(|(_, &c)| c)
+            .collect::<String>()
+    }).collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
+// ── Provider ──
+
+/// Local plain-text OCR through an installed Tesseract CLI.
+pub struct Tesseract;
+
+#[async_trait]
+impl RecognitionProvider for Tesseract {
+    fn id(&self) -> &'static str { "tesseract" }
+
+    fn name(&self) -> &'static str { "Tesseract" }
+
+    fn credential_key(&self) -> &'static str { "tesseract_path" }
+
+    fn credential_label(&self) -> &'static str { "程序路径" }
+
+    // An empty path means "use tesseract from PATH"
+    fn is_configured(&self, app: &AppHandle) -> bool {
+        resolve_binary(&self.credential(app)).is_some()
+    }
+
+    async fn list_models(&self, app: &AppHandle) -> Vec<AvailableModel> {
+        vec![AvailableModel {
+            id: "tesseract:ocr".to_string(),
+            name: format!("Tesseract 本地 OCR ({})", langs(app).join("+")),
+            provider: self.name().to_string(),
+            modes: vec!["ocr".into()],
+            available: self.is_configured(app),
+            free_per_day: None,
+            usage_today: None,
+            pricing: Some("离线免费".to_string()),
+            free: Some(true),
+            voucher: None,
+            charge_balance: None,
+            total_balance: None,
+        }]
+    }
+
+    async fn recognize(&self, app: &AppHandle, req: RecognizeRequest<'_>) -> Result<RecognizeResponse, RecognizeError> {
+        let binary = resolve_binary(&self.credential(app))
+            .ok_or_else(|| RecognizeError::NotConfigured("未找到 Tesseract，请安装或填写可执行文件路径".into()))?;
+
+        let base64_data = req.image.split(',').next_back().unwrap_or(req.image);
+        let bytes = base64::engine::general_purpose::STANDARD.decode(base64_data)
+            .map_err(|e| RecognizeError::InvalidInput(format!("Base64 解码失败: {}", e)))?;
+
+        let lang = langs(app).join("+");
+        let mut child = command(&binary)
+            .args(["stdin", "stdout", "-l", &lang, "--psm", "3"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| RecognizeError::Engine(format!("无法运行 Tesseract: {}", e)))?;
+
+        let mut stdin = child.stdin.take().ok_or_else(|| RecognizeError::Engine("无法写入 Tesseract".into()))?;
+        stdin.write_all(&bytes).await.map_err(|e| RecognizeError::Engine(format!("无法写入 Tesseract: {}", e)))?;
+        drop(stdin);
+
+        let output = child.wait_with_output().await
+            .map_err(|e| RecognizeError::Engine(format!("Tesseract 运行失败: {}", e)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(RecognizeError::Engine(format!("Tesseract 识别失败: {}", stderr.trim())));
+        }
+
+        Ok(RecognizeResponse {
+            text: clean_output(&String::from_utf8_lossy(&output.stdout)),
+            model: format!("Tesseract ({})", lang),
+            ..Default::default()
+        })
+    }
+
+    async fn test_credentials(&self, app: &AppHandle, path: &str) -> TestResult {
+        let installed = match list_languages(path).await {
+            Ok(l) => l,
+            Err(e) => return TestResult { ok: false, error: Some(e), balance: None },
+        };
+        let missing: Vec<String> = langs(app).into_iter().filter(|l| !installed.contains(l)).collect();
+        if !missing.is_empty() {
+            return TestResult { ok: false, error: Some(format!("缺少语言包: {}", missing.join(", "))), balance: None };
+        }
+        TestResult { ok: true, error: None, balance: None }
+    }
+
+    async fn validate_credentials(&self, _app: &AppHandle, path: &str) -> bool {
+        resolve_binary(path).is_some()
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::Local;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+// ── Store helpers ──
+
+pub fn get_store_string(app: &AppHandle, key: &str) -> String {
+    let store = app.store("config.json").unwrap();
+    store.get(key)
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+pub fn get_store_vec(app: &AppHandle, key: &str) -> Vec<String> {
+    let store = app.store("config.json").unwrap();
+    store.get(key)
+        .and_then(|v| serde_json::from_value::<Vec<String>>(v.clone()).ok())
+        .unwrap_or_default()
+}
+
+pub fn get_store_u64(app: &AppHandle, key: &str) -> Option<u64> {
+    let store = app.store("config.json").unwrap();
+    store.get(key).and_then(|v| v.as_u64())
+}
+
+pub fn get_store_f64(app: &AppHandle, key: &str) -> Option<f64> {
+    let store = app.store("config.json").unwrap();
+    store.get(key).and_then(|v| v.as_f64())
+}
+
+// ── Usage tracking ──
+
+/// Serializes increments, which batch jobs make from several recognitions at once.
+static USAGE_LOCK: Mutex<()> = Mutex::new(());
+
+fn get_usage_path(app: &AppHandle) -> std::path::PathBuf {
+    let dir = app.path().app_data_dir().unwrap();
+    std::fs::create_dir_all(&dir).ok();
+    dir.join("usage.json")
+}
+
+fn load_usage(app: &AppHandle) -> (String, HashMap<String, u32>) {
+    let path = get_usage_path(app);
+    if let Ok(data) = std::fs::read_to_string(&path) {
+        if let Ok(val) = serde_json::from_str::<serde_json::Value>(&data) {
+            let date = val["date"].as_str().unwrap_or("").to_string();
+            let models: HashMap<String, u32> = val["models"].as_object()
+                .map(|m| m.iter().map(|(k, v)| (k.clone(), v.as_u64().unwrap_or(0) as u32)).collect())
+                .unwrap_or_default();
+            return (date, models);
+        }
+    }
+    (String::new(), HashMap::new())
+}
+
+pub fn get_model_usage_today(app: &AppHandle, model_id: &str) -> u32 {
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let (date, models) = load_usage(app);
+    if date != today { return 0; }
+    *models.get(model_id).unwrap_or(&0)
+}
+
+pub fn increment_model_usage(app: &AppHandle, model_id: &str) {
+    let _guard = USAGE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let (date, mut models) = load_usage(app);
+    if date != today {
+        models.clear();
+    }
+    let count = models.entry(model_id.to_string()).or_insert(0);
+    *count += 1;
+    let val = serde_json::json!({ "date": today, "models": models });
+    let path = get_usage_path(app);
+    std::fs::write(path, serde_json::to_string_pretty(&val).unwrap()).ok();
+}
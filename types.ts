@@ -5,4 +5,9 @@ export interface HistoryItem {
   isFavorite: boolean;
   dateLabel: string;
   mode: string;
+  needsReview?: boolean;
+  /** Full result; `formula` is only the preview */
+  content: string;
+  model?: string;
+  hasImage: boolean;
 }